@@ -7,7 +7,11 @@
 use std::marker::PhantomData;
 
 use hotshot_types::{
-    traits::{node_implementation::NodeType, signature_key::BuilderSignatureKey, BlockPayload},
+    traits::{
+        node_implementation::NodeType,
+        signature_key::{BuilderSignatureKey, SignatureKey},
+        BlockPayload,
+    },
     utils::BuilderCommitment,
     vid::{VidCommitment, VidPrecomputeData},
 };
@@ -74,3 +78,25 @@ impl<TYPES: NodeType> AvailableBlockHeaderInput<TYPES> {
             )
     }
 }
+
+/// A request for the block candidates on top of a parent block, sent by the client over the stream
+/// of available blocks. It takes the same parameters as `available_blocks`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(bound = "")]
+pub struct AvailableBlocksRequest<TYPES: NodeType> {
+    pub parent_hash: VidCommitment,
+    pub view_number: u64,
+    pub sender: TYPES::SignatureKey,
+    // signature over parent_hash
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+/// A block candidate pushed by the builder over the stream of available blocks, with the request
+/// it answers
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(bound = "")]
+pub struct AvailableBlocksUpdate<TYPES: NodeType> {
+    pub parent_hash: VidCommitment,
+    pub view_number: u64,
+    pub block: AvailableBlockInfo<TYPES>,
+}
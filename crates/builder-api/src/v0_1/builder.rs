@@ -4,25 +4,44 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::path::PathBuf;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use clap::Args;
 use committable::Committable;
 use derive_more::From;
-use futures::FutureExt;
+use futures::{
+    future::{self, BoxFuture, Either},
+    FutureExt, SinkExt, StreamExt,
+};
 use hotshot_types::{traits::node_implementation::NodeType, utils::BuilderCommitment};
 use serde::{Deserialize, Serialize};
 use tagged_base64::TaggedBase64;
 use thiserror::Error;
-use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, RequestParams, StatusCode};
+use tide_disco::{
+    api::ApiError, method::ReadState, socket::Connection, Api, RequestError, RequestParams,
+    StatusCode,
+};
 use vbs::version::StaticVersionType;
 
 use super::{
+    block_info::{AvailableBlocksRequest, AvailableBlocksUpdate},
     data_source::{AcceptsTxnSubmits, BuilderDataSource},
     Version,
 };
 use crate::api::load_api;
 
+/// How often the stream of available blocks looks for new blocks, for the data sources which don't
+/// wake it up themselves
+pub const AVAILABLE_BLOCKS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long the stream of available blocks keeps looking for new blocks on top of a parent after
+/// it last found one, until the client asks for another parent
+pub const AVAILABLE_BLOCKS_MAX_WAIT: Duration = Duration::from_secs(30);
+
 #[derive(Args, Default)]
 pub struct Options {
     #[arg(long = "builder-api-path", env = "HOTSHOT_BUILDER_API_PATH")]
@@ -137,7 +156,7 @@ where
         include_str!("../../api/v0_1/builder.toml"),
         options.extensions.clone(),
     )?;
-    api.with_version("0.1.0".parse().unwrap())
+    api.with_version("0.1.1".parse().unwrap())
         .get("available_blocks", |req, state| {
             async move {
                 let hash = req.blob_param("parent_hash")?;
@@ -211,7 +230,89 @@ where
         })?
         .get("builder_address", |_req, state| {
             async move { state.builder_address().await.map_err(|e| e.into()) }.boxed()
-        })?;
+        })?
+        .socket(
+            "available_blocks_stream",
+            |_req,
+             mut conn: Connection<
+                AvailableBlocksUpdate<Types>,
+                AvailableBlocksRequest<Types>,
+                Error,
+                Version,
+            >,
+             state| {
+                async move {
+                    // The request we push blocks for, the blocks we pushed for it, and until when
+                    // we look for more
+                    let mut request: Option<AvailableBlocksRequest<Types>> = None;
+                    let mut known = HashSet::new();
+                    let mut deadline = Instant::now();
+                    loop {
+                        let mut changed: BoxFuture<'static, ()> = future::pending().boxed();
+                        if let Some(current) = request.clone().filter(|_| Instant::now() < deadline)
+                        {
+                            let (parent_hash, view_number) =
+                                (current.parent_hash, current.view_number);
+                            let known_blocks = known.clone();
+                            // Only read the state to look for blocks, so that it isn't held while
+                            // we wait. We watch for changes first, so that none are missed.
+                            let (next_change, blocks) = state
+                                .read(|state| {
+                                    async move {
+                                        let changed = state.available_blocks_changed(&parent_hash);
+                                        let blocks = state
+                                            .next_available_blocks(
+                                                &parent_hash,
+                                                view_number,
+                                                current.sender,
+                                                &current.signature,
+                                                &known_blocks,
+                                            )
+                                            .await;
+                                        (changed, blocks)
+                                    }
+                                    .boxed()
+                                })
+                                .await;
+                            let blocks = blocks.map_err(|source| Error::BlockAvailable {
+                                source,
+                                resource: parent_hash.to_string(),
+                            })?;
+
+                            if !blocks.is_empty() {
+                                deadline = Instant::now() + AVAILABLE_BLOCKS_MAX_WAIT;
+                            }
+                            for block in blocks {
+                                known.insert(block.block_hash.clone());
+                                let update = AvailableBlocksUpdate {
+                                    parent_hash,
+                                    view_number,
+                                    block,
+                                };
+                                if conn.send(&update).await.is_err() {
+                                    // The client is gone
+                                    return Ok(());
+                                }
+                            }
+                            changed = next_change;
+                        }
+
+                        // Wait for new blocks, or for the client to ask for another parent
+                        match future::select(changed, conn.next()).await {
+                            Either::Left(_) => {}
+                            Either::Right((Some(Ok(next)), _)) => {
+                                request = Some(next);
+                                known.clear();
+                                deadline = Instant::now() + AVAILABLE_BLOCKS_MAX_WAIT;
+                            }
+                            // The client is gone
+                            Either::Right(_) => return Ok(()),
+                        }
+                    }
+                }
+                .boxed()
+            },
+        )?;
     Ok(api)
 }
 
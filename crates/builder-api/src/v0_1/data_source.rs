@@ -4,8 +4,12 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
+use std::collections::HashSet;
+
 use async_trait::async_trait;
 use committable::Commitment;
+use futures::{future::BoxFuture, FutureExt};
+use futures_timer::Delay;
 use hotshot_types::{
     traits::{node_implementation::NodeType, signature_key::SignatureKey},
     utils::BuilderCommitment,
@@ -14,7 +18,7 @@ use hotshot_types::{
 
 use super::{
     block_info::{AvailableBlockData, AvailableBlockHeaderInput, AvailableBlockInfo},
-    builder::{BuildError, TransactionStatus},
+    builder::{BuildError, TransactionStatus, AVAILABLE_BLOCKS_POLL_INTERVAL},
 };
 
 #[async_trait]
@@ -28,6 +32,44 @@ pub trait BuilderDataSource<TYPES: NodeType> {
         signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuildError>;
 
+    /// To get the blocks based on `for_parent` which are available now and not in `known`.
+    ///
+    /// The stream of available blocks calls this each time [`Self::available_blocks_changed`]
+    /// resolves, without holding on to the state in between. The default implementation filters
+    /// `available_blocks`.
+    async fn next_available_blocks(
+        &self,
+        for_parent: &VidCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        known: &HashSet<BuilderCommitment>,
+    ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuildError> {
+        match self
+            .available_blocks(for_parent, view_number, sender, signature)
+            .await
+        {
+            Ok(blocks) => Ok(blocks
+                .into_iter()
+                .filter(|block| !known.contains(&block.block_hash))
+                .collect()),
+            // The builder may not have seen the parent yet
+            Err(BuildError::NotFound | BuildError::Missing) => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// A future which resolves once the blocks available on top of `for_parent` may have changed,
+    /// for the stream of available blocks to look for new ones. It is created before the stream
+    /// looks, so it must resolve for any change after it was created, and it must not borrow the
+    /// state, which the stream releases while it waits.
+    ///
+    /// The default implementation resolves after [`AVAILABLE_BLOCKS_POLL_INTERVAL`], so builders
+    /// which don't override it are polled.
+    fn available_blocks_changed(&self, _for_parent: &VidCommitment) -> BoxFuture<'static, ()> {
+        Delay::new(AVAILABLE_BLOCKS_POLL_INTERVAL).boxed()
+    }
+
     /// To claim a block from the list of provided available blocks
     async fn claim_block(
         &self,
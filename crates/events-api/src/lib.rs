@@ -0,0 +1,11 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+mod api;
+pub mod v0_1;
+
+/// The module name the events API is usually registered under
+pub const EVENTS_API_MODULE: &str = "hotshot-events";
@@ -0,0 +1,293 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::BTreeSet,
+    time::{Duration, Instant},
+};
+
+use futures::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use hotshot_types::{
+    event::{Event, EventKind},
+    traits::node_implementation::NodeType,
+};
+use surf_disco::{client::HealthStatus, Client, Url};
+use tokio::time::sleep;
+
+use super::{
+    data_source::EventsFrom,
+    events::{decided_height, kind_name, Error},
+    Version,
+};
+use crate::EVENTS_API_MODULE;
+
+/// Client for the events API
+pub struct EventsClient<TYPES: NodeType> {
+    /// Underlying surf_disco::Client
+    client: Client<Error, Version>,
+    /// Marker for [`NodeType`] used here
+    _marker: std::marker::PhantomData<TYPES>,
+}
+
+impl<TYPES: NodeType> Clone for EventsClient<TYPES> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+/// The path of a route, with the optional parameters present
+fn route(route: &str, from: Option<EventsFrom>, kinds: Option<&BTreeSet<EventKind>>) -> String {
+    let mut path = format!("{EVENTS_API_MODULE}/{route}");
+    match from {
+        Some(EventsFrom::View(view)) => path = format!("{path}/from/{view}"),
+        Some(EventsFrom::Decide(height)) => path = format!("{path}/after/{height}"),
+        None => {}
+    }
+    if let Some(kinds) = kinds {
+        let names: Vec<_> = kinds.iter().filter_map(|kind| kind_name(*kind)).collect();
+        path = format!("{path}/kinds/{}", names.join(","));
+    }
+    path
+}
+
+impl<TYPES: NodeType> EventsClient<TYPES> {
+    /// Construct a new client from base url
+    pub fn new(base_url: impl Into<Url>) -> Self {
+        Self {
+            client: Client::new(base_url.into()),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Wait for server to become available
+    /// Returns `false` if server doesn't respond
+    /// with OK healthcheck before `timeout`
+    pub async fn connect(&self, timeout: Duration) -> bool {
+        let timeout = Instant::now() + timeout;
+        let mut backoff = Duration::from_millis(50);
+        while Instant::now() < timeout {
+            if matches!(
+                self.client.healthcheck::<HealthStatus>().await,
+                Ok(HealthStatus::Available)
+            ) {
+                return true;
+            }
+            sleep(backoff).await;
+            backoff *= 2;
+        }
+        false
+    }
+
+    /// The oldest view events can be requested from, or `None` if the server has them all
+    ///
+    /// # Errors
+    /// If the server can't be reached
+    pub async fn oldest_view(&self) -> Result<Option<u64>, Error> {
+        self.client
+            .get(&format!("{EVENTS_API_MODULE}/oldestview"))
+            .send()
+            .await
+    }
+
+    /// The events of `kinds`, or of every kind, from `from_view` onwards which the server
+    /// retained
+    ///
+    /// # Errors
+    /// If the server can't be reached, or no longer has the events from `from_view`
+    pub async fn history(
+        &self,
+        from_view: u64,
+        kinds: Option<&BTreeSet<EventKind>>,
+    ) -> Result<Vec<Event<TYPES>>, Error> {
+        self.client
+            .get(&route(&format!("history/{from_view}"), None, kinds))
+            .send()
+            .await
+    }
+
+    /// Subscribe to the events of `kinds`, or of every kind. With `from_view`, the retained
+    /// events from that view onwards come first.
+    ///
+    /// The stream ends when the connection is closed, e.g. because the subscriber fell behind.
+    ///
+    /// # Errors
+    /// If the server can't be reached, or no longer has the events from `from_view`
+    pub async fn subscribe(
+        &self,
+        from_view: Option<u64>,
+        kinds: Option<&BTreeSet<EventKind>>,
+    ) -> Result<BoxStream<'static, Result<Event<TYPES>, Error>>, Error> {
+        self.subscribe_from(from_view.map(EventsFrom::View), kinds)
+            .await
+    }
+
+    /// Subscribe to the events of `kinds`, or of every kind, starting with the retained events
+    /// right after the `Decide` of the leaf at `height`
+    ///
+    /// The stream ends when the connection is closed, e.g. because the subscriber fell behind.
+    ///
+    /// # Errors
+    /// If the server can't be reached, or no longer has the `Decide` of `height`
+    pub async fn subscribe_after_decide(
+        &self,
+        height: u64,
+        kinds: Option<&BTreeSet<EventKind>>,
+    ) -> Result<BoxStream<'static, Result<Event<TYPES>, Error>>, Error> {
+        self.subscribe_from(Some(EventsFrom::Decide(height)), kinds)
+            .await
+    }
+
+    /// Subscribe to the events of `kinds`, or of every kind, from `from` onwards
+    async fn subscribe_from(
+        &self,
+        from: Option<EventsFrom>,
+        kinds: Option<&BTreeSet<EventKind>>,
+    ) -> Result<BoxStream<'static, Result<Event<TYPES>, Error>>, Error> {
+        let connection = self
+            .client
+            .socket(&route("events", from, kinds))
+            .subscribe::<Event<TYPES>>()
+            .await?;
+        Ok(connection.boxed())
+    }
+
+    /// Stream the events of `kinds`, or of every kind, from `from_view` onwards like
+    /// [`Self::subscribe`], but resubscribe when the connection drops and carry on without
+    /// missing or repeating events.
+    ///
+    /// A resubscription starts right after the last `Decide` received, so `Decide` events are
+    /// always subscribed to, even if `kinds` leaves them out; they are then not yielded.
+    ///
+    /// The stream yields an error and ends if resubscribing fails, or the connection drops
+    /// before any new event arrived.
+    pub fn events(
+        &self,
+        from_view: u64,
+        kinds: Option<BTreeSet<EventKind>>,
+    ) -> impl Stream<Item = Result<Event<TYPES>, Error>> {
+        let subscribed = kinds.clone().map(|mut kinds| {
+            kinds.insert(EventKind::Decide);
+            kinds
+        });
+        let state = Resumption {
+            client: self.clone(),
+            kinds,
+            subscribed,
+            from_view,
+            connection: None,
+            decided_height: None,
+            received_since: 0,
+            skip: 0,
+            received: false,
+        };
+
+        stream::unfold(Some(state), |state| async move {
+            let mut state = state?;
+            match state.next().await {
+                Ok(Some(event)) => Some((Ok(event), Some(state))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+}
+
+/// The state of a resuming event stream
+struct Resumption<TYPES: NodeType> {
+    /// The client to resubscribe with
+    client: EventsClient<TYPES>,
+    /// The kinds of events yielded
+    kinds: Option<BTreeSet<EventKind>>,
+    /// The kinds of events subscribed to, which always include `Decide`
+    subscribed: Option<BTreeSet<EventKind>>,
+    /// The view the stream started from
+    from_view: u64,
+    /// The current subscription
+    connection: Option<BoxStream<'static, Result<Event<TYPES>, Error>>>,
+    /// The height of the newest leaf in the last `Decide` received
+    decided_height: Option<u64>,
+    /// How many events were received after the last `Decide`, or since the start before any
+    received_since: usize,
+    /// How many replayed events the current subscription still has to skip
+    skip: usize,
+    /// Whether the current subscription delivered a new event
+    received: bool,
+}
+
+impl<TYPES: NodeType> Resumption<TYPES> {
+    /// Where to resubscribe from
+    ///
+    /// Everything received from there onwards is a prefix of what the server replays, as both
+    /// are in the order the events were emitted.
+    fn resume_from(&self) -> EventsFrom {
+        self.decided_height
+            .map_or(EventsFrom::View(self.from_view), EventsFrom::Decide)
+    }
+
+    /// The next new event, or `None` once the stream ends
+    async fn next(&mut self) -> Result<Option<Event<TYPES>>, Error> {
+        loop {
+            let mut connection = match self.connection.take() {
+                Some(connection) => connection,
+                None => {
+                    let connection = self
+                        .client
+                        .subscribe_from(Some(self.resume_from()), self.subscribed.as_ref())
+                        .await?;
+                    // The replay starts with the events we already have from there
+                    self.skip = self.received_since;
+                    self.received = false;
+                    connection
+                }
+            };
+
+            match connection.next().await {
+                Some(Ok(event)) => {
+                    self.connection = Some(connection);
+                    if self.skip > 0 {
+                        self.skip -= 1;
+                        continue;
+                    }
+                    self.record(&event);
+                    self.received = true;
+                    if self
+                        .kinds
+                        .as_ref()
+                        .is_some_and(|kinds| !kinds.contains(&event.event.kind()))
+                    {
+                        continue;
+                    }
+                    return Ok(Some(event));
+                }
+                // Resubscribing can only help if the subscription got somewhere
+                Some(Err(err)) if !self.received => return Err(err),
+                None if !self.received => return Ok(None),
+                Some(Err(err)) => {
+                    tracing::warn!("Error receiving events, resubscribing: {err}");
+                }
+                None => {
+                    tracing::info!("Event subscription closed, resubscribing");
+                }
+            }
+        }
+    }
+
+    /// Count a received event, starting over after each `Decide`
+    fn record(&mut self, event: &Event<TYPES>) {
+        if let Some(height) = decided_height(event) {
+            self.decided_height = Some(height);
+            self.received_since = 0;
+        } else {
+            self.received_since += 1;
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use hotshot_types::{
+    event::{Event, EventKind},
+    traits::node_implementation::NodeType,
+};
+
+use super::events::EventsError;
+
+/// Where in the retained events a subscription starts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventsFrom {
+    /// With the first event of this view or a later one
+    View(u64),
+    /// Right after the `Decide` event which decided the leaf at this height
+    Decide(u64),
+}
+
+#[async_trait]
+pub trait EventsSource<TYPES: NodeType> {
+    /// To subscribe to events of `kinds`, or of every streamed kind if `None`. The retained
+    /// events from `from` onwards come first, followed by new events as they arrive.
+    async fn subscribe_events(
+        &self,
+        from: Option<EventsFrom>,
+        kinds: Option<BTreeSet<EventKind>>,
+    ) -> Result<BoxStream<'static, Event<TYPES>>, EventsError>;
+
+    /// To get the retained events of `kinds` from the first one of `from_view` or a later view
+    /// onwards
+    async fn retained_events(
+        &self,
+        from_view: u64,
+        kinds: Option<BTreeSet<EventKind>>,
+    ) -> Result<Vec<Event<TYPES>>, EventsError>;
+
+    /// To get the oldest view events can be requested from, or `None` if none were dropped yet
+    async fn oldest_view(&self) -> Option<u64>;
+}
@@ -0,0 +1,190 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeSet, path::PathBuf};
+
+use clap::Args;
+use futures::{FutureExt, StreamExt, TryFutureExt};
+use hotshot_types::{
+    event::{Event, EventKind, EventType},
+    traits::node_implementation::NodeType,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, RequestParams, StatusCode};
+
+use super::{
+    data_source::{EventsFrom, EventsSource},
+    Version,
+};
+use crate::api::load_api;
+
+/// The kinds of events the API streams
+pub const STREAMED_EVENT_KINDS: [EventKind; 5] = [
+    EventKind::Decide,
+    EventKind::DecideGap,
+    EventKind::QuorumProposal,
+    EventKind::DaProposal,
+    EventKind::ExternalMessageReceived,
+];
+
+#[derive(Args, Default)]
+pub struct Options {
+    #[arg(long = "events-api-path", env = "HOTSHOT_EVENTS_API_PATH")]
+    pub api_path: Option<PathBuf>,
+
+    /// Additional API specification files to merge with `events-api-path`.
+    ///
+    /// These optional files may contain route definitions for application-specific routes that have
+    /// been added as extensions to the basic events API.
+    #[arg(
+        long = "events-extension",
+        env = "HOTSHOT_EVENTS_EXTENSIONS",
+        value_delimiter = ','
+    )]
+    pub extensions: Vec<toml::Value>,
+}
+
+#[derive(Clone, Debug, Error, Deserialize, Serialize)]
+pub enum EventsError {
+    #[error("Events before view {oldest_view} are no longer retained")]
+    Pruned { oldest_view: u64 },
+    #[error("The decide of the leaf at height {height} is no longer retained")]
+    DecideNotRetained { height: u64 },
+    #[error("Unknown event kind {0}")]
+    UnknownKind(String),
+}
+
+#[derive(Clone, Debug, Error, Deserialize, Serialize)]
+pub enum Error {
+    #[error("Error processing request: {0}")]
+    Request(#[from] RequestError),
+    #[error("Error fetching events: {0}")]
+    Events(#[from] EventsError),
+    #[error("Custom error {status}: {message}")]
+    Custom { message: String, status: StatusCode },
+}
+
+impl tide_disco::error::Error for Error {
+    fn catch_all(status: StatusCode, msg: String) -> Self {
+        Error::Custom {
+            message: msg,
+            status,
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Error::Request { .. } => StatusCode::BAD_REQUEST,
+            Error::Events(source) => match source {
+                EventsError::Pruned { .. } | EventsError::DecideNotRetained { .. } => {
+                    StatusCode::NOT_FOUND
+                }
+                EventsError::UnknownKind(_) => StatusCode::BAD_REQUEST,
+            },
+            Error::Custom { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// The name of `kind` in the `:kinds` route parameter
+pub fn kind_name(kind: EventKind) -> Option<&'static str> {
+    match kind {
+        EventKind::Decide => Some("decide"),
+        EventKind::DecideGap => Some("decide_gap"),
+        EventKind::QuorumProposal => Some("quorum_proposal"),
+        EventKind::DaProposal => Some("da_proposal"),
+        EventKind::ExternalMessageReceived => Some("external_message_received"),
+        _ => None,
+    }
+}
+
+/// The height of the newest leaf decided by `event`, if it is a `Decide`
+pub fn decided_height<TYPES: NodeType>(event: &Event<TYPES>) -> Option<u64> {
+    match &event.event {
+        EventType::Decide { leaf_chain, .. } => {
+            leaf_chain.iter().map(|info| info.leaf.height()).max()
+        }
+        _ => None,
+    }
+}
+
+/// Parse a comma separated list of event kind names
+pub fn parse_kinds(kinds: &str) -> Result<BTreeSet<EventKind>, EventsError> {
+    kinds
+        .split(',')
+        .map(|name| {
+            STREAMED_EVENT_KINDS
+                .into_iter()
+                .find(|kind| kind_name(*kind) == Some(name))
+                .ok_or_else(|| EventsError::UnknownKind(name.to_string()))
+        })
+        .collect()
+}
+
+fn kinds_param(req: &RequestParams) -> Result<Option<BTreeSet<EventKind>>, Error> {
+    req.opt_string_param("kinds")?
+        .map(|kinds| parse_kinds(&kinds).map_err(Error::from))
+        .transpose()
+}
+
+fn from_param(req: &RequestParams) -> Result<Option<EventsFrom>, Error> {
+    if let Some(height) = req.opt_integer_param("height")? {
+        return Ok(Some(EventsFrom::Decide(height)));
+    }
+    Ok(req.opt_integer_param("view")?.map(EventsFrom::View))
+}
+
+pub fn define_api<State, Types: NodeType>(
+    options: &Options,
+) -> Result<Api<State, Error, Version>, ApiError>
+where
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + EventsSource<Types>,
+{
+    let mut api = load_api::<State, Error, Version>(
+        options.api_path.as_ref(),
+        include_str!("../../api/v0_1/events.toml"),
+        options.extensions.clone(),
+    )?;
+    api.with_version("0.1.0".parse().unwrap())
+        .stream("events", |req, state| {
+            async move {
+                let from = from_param(&req)?;
+                let kinds = kinds_param(&req)?;
+                tracing::info!("Client subscribed to events from {from:?}");
+                state
+                    .read(|state| {
+                        async move {
+                            state
+                                .subscribe_events(from, kinds)
+                                .await
+                                .map(|events| events.map(Ok))
+                                .map_err(Error::from)
+                        }
+                        .boxed()
+                    })
+                    .await
+            }
+            .try_flatten_stream()
+            .boxed()
+        })?
+        .get("history", |req, state| {
+            async move {
+                let from_view = req.integer_param("view")?;
+                let kinds = kinds_param(&req)?;
+                state
+                    .retained_events(from_view, kinds)
+                    .await
+                    .map_err(Error::from)
+            }
+            .boxed()
+        })?
+        .get("oldest_view", |_req, state| {
+            async move { Ok(state.oldest_view().await) }.boxed()
+        })?;
+    Ok(api)
+}
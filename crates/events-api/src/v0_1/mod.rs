@@ -0,0 +1,7 @@
+pub mod client;
+pub mod data_source;
+pub mod events;
+pub mod sse;
+pub mod streamer;
+
+pub type Version = vbs::version::StaticVersion<0, 1>;
@@ -0,0 +1,250 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Server-sent events for clients which can't use the WebSocket `events` route.
+//!
+//! tide-disco only serves request/response routes and WebSockets, and can't hold a response open
+//! to stream into it, so this is a small HTTP server of its own which runs next to the tide-disco
+//! app.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use futures::StreamExt;
+use hotshot_types::{event::EventKind, traits::node_implementation::NodeType};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use super::{
+    data_source::{EventsFrom, EventsSource},
+    events::{decided_height, kind_name, parse_kinds, EventsError},
+};
+use crate::EVENTS_API_MODULE;
+
+/// The most bytes of a request line and headers read before the request is refused
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+/// A request for a stream of events
+#[derive(Debug)]
+struct SseRequest {
+    /// Where the stream starts, if it includes retained events
+    from: Option<EventsFrom>,
+    /// The kinds of events to send, or all of them if `None`
+    kinds: Option<BTreeSet<EventKind>>,
+    /// How many events after `from` the client already has
+    skip: usize,
+}
+
+/// A request which can't be served, as the status line and body of the response
+type Refusal = (&'static str, String);
+
+/// Serve the events of `source` as server-sent events to the connections accepted by `listener`.
+///
+/// `GET /hotshot-events/events` takes the same `from/:view`, `after/:height` and `kinds/:kinds`
+/// path segments as the WebSocket `events` route. Each event is sent with its kind, as named in
+/// `:kinds`, in the `event` field and its JSON encoding in the `data` field. From the first
+/// `Decide` onwards, events have an `id` of `<height>:<count>`: the height of the newest leaf in
+/// the last `Decide` sent, and how many events were sent after it. A client which reconnects with
+/// that id in the `Last-Event-ID` header carries on right after the last event it received.
+pub async fn serve_sse<TYPES, S>(source: S, listener: TcpListener)
+where
+    TYPES: NodeType,
+    S: EventsSource<TYPES> + Send + Sync + 'static,
+{
+    let source = Arc::new(source);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                tracing::warn!("Failed to accept a server-sent events connection: {err}");
+                continue;
+            }
+        };
+        let source = Arc::clone(&source);
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(source.as_ref(), stream).await {
+                tracing::debug!("Server-sent events connection from {peer} failed: {err}");
+            }
+        });
+    }
+}
+
+/// Answer the request on `stream`, streaming events until the client goes away
+async fn serve_connection<TYPES: NodeType>(
+    source: &impl EventsSource<TYPES>,
+    stream: TcpStream,
+) -> std::io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let request = match read_request(&mut stream).await? {
+        Ok(request) => request,
+        Err((status, message)) => return refuse(stream.get_mut(), status, &message).await,
+    };
+
+    // A `Decide` is needed to resume after it, so it is subscribed to even if not sent
+    let subscribed = request.kinds.clone().map(|mut kinds| {
+        kinds.insert(EventKind::Decide);
+        kinds
+    });
+    let mut events = match source.subscribe_events(request.from, subscribed).await {
+        Ok(events) => events.skip(request.skip),
+        Err(err) => return refuse(stream.get_mut(), events_status(&err), &err.to_string()).await,
+    };
+
+    let stream = stream.get_mut();
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+              Connection: close\r\n\r\n",
+        )
+        .await?;
+    stream.flush().await?;
+
+    let mut decided = match request.from {
+        Some(EventsFrom::Decide(height)) => Some((height, request.skip)),
+        _ => None,
+    };
+    while let Some(event) = events.next().await {
+        decided = match (decided_height(&event), decided) {
+            (Some(height), _) => Some((height, 0)),
+            (None, Some((height, count))) => Some((height, count + 1)),
+            (None, None) => None,
+        };
+        let kind = event.event.kind();
+        if request
+            .kinds
+            .as_ref()
+            .is_some_and(|kinds| !kinds.contains(&kind))
+        {
+            continue;
+        }
+
+        let data = serde_json::to_string(&event)?;
+        let mut message = format!("event: {}\n", kind_name(kind).unwrap_or("message"));
+        if let Some((height, count)) = decided {
+            message.push_str(&format!("id: {height}:{count}\n"));
+        }
+        message.push_str(&format!("data: {data}\n\n"));
+        stream.write_all(message.as_bytes()).await?;
+        stream.flush().await?;
+    }
+    Ok(())
+}
+
+/// Read the request head from `stream`
+async fn read_request(
+    stream: &mut BufReader<TcpStream>,
+) -> std::io::Result<Result<SseRequest, Refusal>> {
+    let mut head = (&mut *stream).take(MAX_REQUEST_HEAD);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line).await?;
+
+    let mut last_event_id = None;
+    loop {
+        let mut line = String::new();
+        if head.read_line(&mut line).await? == 0 {
+            return Ok(Err((
+                "400 Bad Request",
+                "Incomplete or oversized request".to_string(),
+            )));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("last-event-id") {
+                last_event_id = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(Err((
+            "400 Bad Request",
+            "Malformed request line".to_string(),
+        )));
+    };
+    if method != "GET" {
+        return Ok(Err((
+            "405 Method Not Allowed",
+            format!("Unsupported method {method}"),
+        )));
+    }
+    Ok(parse_request(target, last_event_id.as_deref()))
+}
+
+/// Parse the request for `target`, resuming after `last_event_id` if the client sent one
+fn parse_request(target: &str, last_event_id: Option<&str>) -> Result<SseRequest, Refusal> {
+    let not_found = || ("404 Not Found", format!("No route for {target}"));
+    let path = target.split('?').next().unwrap_or_default();
+    let mut segments = path.trim_matches('/').split('/');
+    if segments.next() != Some(EVENTS_API_MODULE) || segments.next() != Some("events") {
+        return Err(not_found());
+    }
+
+    let mut request = SseRequest {
+        from: None,
+        kinds: None,
+        skip: 0,
+    };
+    let segments: Vec<_> = segments.collect();
+    let mut segments = segments.as_slice();
+    if let [position @ ("from" | "after"), value, rest @ ..] = segments {
+        let value = value.parse().map_err(|_| {
+            (
+                "400 Bad Request",
+                format!("Invalid {position} parameter {value}"),
+            )
+        })?;
+        request.from = Some(if *position == "from" {
+            EventsFrom::View(value)
+        } else {
+            EventsFrom::Decide(value)
+        });
+        segments = rest;
+    }
+    if let ["kinds", kinds, rest @ ..] = segments {
+        request.kinds =
+            Some(parse_kinds(kinds).map_err(|err| ("400 Bad Request", err.to_string()))?);
+        segments = rest;
+    }
+    if !segments.is_empty() {
+        return Err(not_found());
+    }
+
+    if let Some(id) = last_event_id {
+        let parsed = id
+            .split_once(':')
+            .and_then(|(height, count)| Some((height.parse().ok()?, count.parse().ok()?)));
+        let Some((height, count)) = parsed else {
+            return Err(("400 Bad Request", format!("Invalid Last-Event-ID {id}")));
+        };
+        request.from = Some(EventsFrom::Decide(height));
+        request.skip = count;
+    }
+    Ok(request)
+}
+
+/// The status line for a request failing with `err`
+fn events_status(err: &EventsError) -> &'static str {
+    match err {
+        EventsError::Pruned { .. } | EventsError::DecideNotRetained { .. } => "404 Not Found",
+        EventsError::UnknownKind(_) => "400 Bad Request",
+    }
+}
+
+/// Answer with `status` and a plain text `message`
+async fn refuse(stream: &mut TcpStream, status: &str, message: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{message}",
+        message.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await
+}
@@ -0,0 +1,278 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    sync::Arc,
+};
+
+use async_broadcast::{broadcast, InactiveReceiver, RecvError, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use futures::{
+    future::{ready, BoxFuture},
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use hotshot_types::{
+    event::{Event, EventFilter, EventKind},
+    traits::node_implementation::NodeType,
+};
+use tide_disco::method::ReadState;
+
+use super::{
+    data_source::{EventsFrom, EventsSource},
+    events::{decided_height, EventsError, STREAMED_EVENT_KINDS},
+};
+
+/// The default number of views of events kept for subscribers resuming from the past
+pub const DEFAULT_RETAINED_VIEWS: u64 = 1_000;
+
+/// The default number of events kept for subscribers resuming from the past, whatever their views
+pub const DEFAULT_MAX_RETAINED_EVENTS: usize = 10_000;
+
+/// The default number of events a subscriber may fall behind by before it is disconnected
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 1_000;
+
+/// The retained events of an [`EventsStreamer`]
+struct History<TYPES: NodeType> {
+    /// The retained events, in the order they were emitted. Events are only ever dropped from the
+    /// front, so that a subscriber resuming from any retained event gets everything after it.
+    events: VecDeque<Event<TYPES>>,
+
+    /// The latest view of any event
+    latest_view: Option<u64>,
+
+    /// The oldest view events are still retained from, once some were dropped
+    oldest_view: Option<u64>,
+}
+
+/// Keeps the recent events of a node and streams them to the subscribers of the events API.
+///
+/// Feed it with [`run_events_streamer`], and serve it with the `define_api` of this version.
+/// Clones share their events.
+pub struct EventsStreamer<TYPES: NodeType> {
+    /// The retained events
+    history: Arc<RwLock<History<TYPES>>>,
+
+    /// How many views of events to retain, counting back from the latest one
+    retained_views: u64,
+
+    /// The most events to retain
+    max_retained_events: usize,
+
+    /// Channel to the live subscribers
+    sender: Sender<Event<TYPES>>,
+
+    /// Kept so that the channel stays open without subscribers
+    receiver: InactiveReceiver<Event<TYPES>>,
+}
+
+impl<TYPES: NodeType> Clone for EventsStreamer<TYPES> {
+    fn clone(&self) -> Self {
+        Self {
+            history: Arc::clone(&self.history),
+            retained_views: self.retained_views,
+            max_retained_events: self.max_retained_events,
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> EventsStreamer<TYPES> {
+    /// Create a streamer which retains `retained_views` views of events, but no more than
+    /// `max_retained_events` events, and disconnects subscribers which fall more than
+    /// `subscriber_capacity` events behind
+    pub fn new(
+        retained_views: u64,
+        max_retained_events: usize,
+        subscriber_capacity: usize,
+    ) -> Self {
+        let (mut sender, receiver) = broadcast(subscriber_capacity);
+        // A slow subscriber must not hold up the others
+        sender.set_overflow(true);
+        sender.set_await_active(false);
+
+        Self {
+            history: Arc::new(RwLock::new(History {
+                events: VecDeque::new(),
+                latest_view: None,
+                oldest_view: None,
+            })),
+            retained_views,
+            max_retained_events,
+            sender,
+            receiver: receiver.deactivate(),
+        }
+    }
+
+    /// Record an event, and send it to the subscribers. Events of kinds the API doesn't stream
+    /// are ignored.
+    pub async fn handle_event(&self, event: Event<TYPES>) {
+        if !STREAMED_EVENT_KINDS.contains(&event.event.kind()) {
+            return;
+        }
+
+        let mut history = self.history.write().await;
+        let view = *event.view_number;
+        let latest_view = history.latest_view.map_or(view, |latest| latest.max(view));
+        history.latest_view = Some(latest_view);
+
+        history.events.push_back(event.clone());
+        let oldest_retained = latest_view.saturating_sub(self.retained_views);
+        while let Some(oldest) = history.events.front() {
+            let oldest = *oldest.view_number;
+            if oldest >= oldest_retained && history.events.len() <= self.max_retained_events {
+                break;
+            }
+            history.events.pop_front();
+            history.oldest_view = Some(
+                history
+                    .oldest_view
+                    .map_or(oldest + 1, |view| view.max(oldest + 1)),
+            );
+        }
+
+        // Sent while holding the lock, so that subscribers joining concurrently get every event
+        // exactly once. This fails only when nobody is subscribed.
+        let _ = self.sender.try_broadcast(event);
+    }
+}
+
+impl<TYPES: NodeType> History<TYPES> {
+    /// The index of the first retained event from `from` onwards
+    fn position(&self, from: EventsFrom) -> Result<usize, EventsError> {
+        match from {
+            EventsFrom::View(view) => {
+                if let Some(oldest_view) = self.oldest_view.filter(|oldest| view < *oldest) {
+                    return Err(EventsError::Pruned { oldest_view });
+                }
+                Ok(self
+                    .events
+                    .iter()
+                    .position(|event| *event.view_number >= view)
+                    .unwrap_or(self.events.len()))
+            }
+            EventsFrom::Decide(height) => self
+                .events
+                .iter()
+                .position(|event| decided_height(event) == Some(height))
+                .map(|index| index + 1)
+                .ok_or(EventsError::DecideNotRetained { height }),
+        }
+    }
+
+    /// The retained events of `kinds` from `from` onwards
+    fn retained(
+        &self,
+        from: EventsFrom,
+        kinds: Option<BTreeSet<EventKind>>,
+    ) -> Result<Vec<Event<TYPES>>, EventsError> {
+        let filter = kinds_filter(kinds);
+        Ok(self
+            .events
+            .iter()
+            .skip(self.position(from)?)
+            .filter(|event| filter.matches(event))
+            .cloned()
+            .collect())
+    }
+}
+
+/// The filter for events of `kinds`, or of any kind
+fn kinds_filter<TYPES: NodeType>(kinds: Option<BTreeSet<EventKind>>) -> EventFilter<TYPES> {
+    let filter = EventFilter::default();
+    match kinds {
+        Some(kinds) => filter.kinds(kinds),
+        None => filter,
+    }
+}
+
+impl<TYPES: NodeType> Default for EventsStreamer<TYPES> {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_RETAINED_VIEWS,
+            DEFAULT_MAX_RETAINED_EVENTS,
+            DEFAULT_SUBSCRIBER_CAPACITY,
+        )
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> ReadState for EventsStreamer<TYPES> {
+    type State = Self;
+
+    async fn read<T>(
+        &self,
+        op: impl Send + for<'a> FnOnce(&'a Self::State) -> BoxFuture<'a, T> + 'async_trait,
+    ) -> T {
+        op(self).await
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> EventsSource<TYPES> for EventsStreamer<TYPES> {
+    async fn subscribe_events(
+        &self,
+        from: Option<EventsFrom>,
+        kinds: Option<BTreeSet<EventKind>>,
+    ) -> Result<BoxStream<'static, Event<TYPES>>, EventsError> {
+        // Nothing is recorded while we hold the lock, so the live events start right after the
+        // retained ones
+        let history = self.history.read().await;
+        let retained = match from {
+            Some(from) => history.retained(from, kinds.clone())?,
+            None => Vec::new(),
+        };
+        let receiver = self.receiver.activate_cloned();
+        drop(history);
+
+        let filter = kinds_filter(kinds);
+        let live = stream::unfold(receiver, |mut receiver| async move {
+            match receiver.recv().await {
+                Ok(event) => Some((event, receiver)),
+                Err(RecvError::Overflowed(missed)) => {
+                    // Ending the stream closes the connection, and the subscriber can resume
+                    // from where it left off
+                    tracing::warn!("Disconnecting a subscriber which missed {missed} events");
+                    None
+                }
+                Err(RecvError::Closed) => None,
+            }
+        })
+        .filter(move |event| ready(filter.matches(event)));
+
+        Ok(stream::iter(retained).chain(live).boxed())
+    }
+
+    async fn retained_events(
+        &self,
+        from_view: u64,
+        kinds: Option<BTreeSet<EventKind>>,
+    ) -> Result<Vec<Event<TYPES>>, EventsError> {
+        self.history
+            .read()
+            .await
+            .retained(EventsFrom::View(from_view), kinds)
+    }
+
+    async fn oldest_view(&self) -> Option<u64> {
+        self.history.read().await.oldest_view
+    }
+}
+
+/// Record every event of `events`, such as the event stream of a `SystemContextHandle`, in
+/// `streamer`
+pub async fn run_events_streamer<TYPES: NodeType>(
+    streamer: EventsStreamer<TYPES>,
+    events: impl Stream<Item = Event<TYPES>>,
+) {
+    let mut events = Box::pin(events);
+    while let Some(event) = events.next().await {
+        streamer.handle_event(event).await;
+    }
+}
@@ -6,6 +6,7 @@
 
 use anyhow::{bail, Result};
 use async_trait::async_trait;
+use hotshot_task_impls::solver::SolverClient;
 use hotshot_types::traits::{
     auction_results_provider::AuctionResultsProvider,
     node_implementation::{HasUrls, NodeType},
@@ -38,10 +39,10 @@ pub struct TestAuctionResultsProvider<TYPES: NodeType> {
     /// error.
     pub should_return_err: bool,
 
-    /// The broadcast URL that the solver is running on. This type allows for the url to be
-    /// optional, where `None` means to just return whatever `solver_results` contains, and `Some`
-    /// means that we have a `FakeSolver` instance available to query.
-    pub broadcast_url: Option<Url>,
+    /// The client for the Solver, if there is one. `None` means to just return whatever
+    /// `solver_results` contains, and `Some` means that we have a Solver, for instance a
+    /// `FakeSolver`, to fetch the results from.
+    pub solver: Option<SolverClient<TYPES>>,
 }
 
 #[async_trait]
@@ -49,14 +50,8 @@ impl<TYPES: NodeType> AuctionResultsProvider<TYPES> for TestAuctionResultsProvid
     /// Mock fetching the auction results, with optional error injection to simulate failure cases
     /// in the solver.
     async fn fetch_auction_result(&self, view_number: TYPES::View) -> Result<TYPES::AuctionResult> {
-        if let Some(url) = &self.broadcast_url {
-            let resp =
-                reqwest::get(url.join(&format!("/v0/api/auction_results/{}", *view_number))?)
-                    .await?
-                    .json::<TYPES::AuctionResult>()
-                    .await?;
-
-            Ok(resp)
+        if let Some(solver) = &self.solver {
+            solver.fetch_auction_result(view_number).await
         } else {
             if self.should_return_err {
                 bail!("Something went wrong")
@@ -66,4 +61,10 @@ impl<TYPES: NodeType> AuctionResultsProvider<TYPES> for TestAuctionResultsProvid
             Ok(self.solver_results.clone())
         }
     }
+
+    fn prefetch_auction_result(&self, view_number: TYPES::View) {
+        if let Some(solver) = &self.solver {
+            solver.prefetch_auction_result(view_number);
+        }
+    }
 }
@@ -0,0 +1,482 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut, RangeInclusive},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{bail, Result};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::traits::{RecoverableStorage, UndecidedState};
+use hotshot_types::{
+    consensus::CommitmentMap,
+    data::{DaProposal, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare},
+    drb::DrbOutput,
+    event::{HotShotAction, LeafInfo},
+    message::Proposal,
+    simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
+    slashing_protection::{SigningHistory, SigningRoot},
+    traits::{node_implementation::NodeType, storage::Storage},
+    utils::View,
+    vid::VidSchemeType,
+    vote::HasViewNumber,
+    PeerConfig,
+};
+use jf_vid::VidScheme;
+
+/// A [`Storage`] method into which faults can be injected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StorageMethod {
+    AppendVid,
+    AppendDa,
+    AppendProposal,
+    AppendProposal2,
+    RecordAction,
+    RecordSignedAction,
+    UpdateHighQc,
+    UpdateHighQc2,
+    UpdateUndecidedState,
+    UpdateUndecidedState2,
+    UpdateDecidedLeaf,
+    Gc,
+    UpdateDecidedUpgradeCertificate,
+    MigrateConsensus,
+    ImportSigningHistory,
+    AddDrbResult,
+    AddStakeTable,
+}
+
+/// How a faulty [`Storage`] call misbehaves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    /// Return an error without writing anything.
+    Fail,
+    /// Return `Ok` without writing anything.
+    DropWrite,
+    /// Write a corrupted value and return `Ok`. High QCs are stored without their signatures;
+    /// every other method fails instead.
+    Corrupt,
+}
+
+/// A scripted fault, injected into every call to `method` for a view in `views`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageFault {
+    /// The method to inject the fault into
+    pub method: StorageMethod,
+    /// How the method misbehaves
+    pub kind: FaultKind,
+    /// The views in which the method misbehaves, or `None` for every call. Calls which are not
+    /// tied to a single view, like `update_undecided_state`, only match faults for every call.
+    pub views: Option<RangeInclusive<u64>>,
+}
+
+impl StorageFault {
+    /// A fault injected into every call to `method`.
+    #[must_use]
+    pub fn new(method: StorageMethod, kind: FaultKind) -> Self {
+        Self {
+            method,
+            kind,
+            views: None,
+        }
+    }
+
+    /// Only inject the fault in `view`.
+    #[must_use]
+    pub fn in_view(self, view: u64) -> Self {
+        self.in_views(view..=view)
+    }
+
+    /// Only inject the fault in `views`.
+    #[must_use]
+    pub fn in_views(mut self, views: RangeInclusive<u64>) -> Self {
+        self.views = Some(views);
+        self
+    }
+
+    /// Whether the fault applies to a call to `method` in `view`.
+    fn matches(&self, method: StorageMethod, view: Option<u64>) -> bool {
+        self.method == method
+            && match (&self.views, view) {
+                (None, _) => true,
+                (Some(views), Some(view)) => views.contains(&view),
+                (Some(_), None) => false,
+            }
+    }
+}
+
+/// A [`Storage`] wrapper which injects scripted faults into the storage it wraps.
+///
+/// Clones share the same script, so faults can be added or cleared while a node is running.
+#[derive(Clone, Debug)]
+pub struct FaultyStorage<S> {
+    /// The wrapped storage
+    inner: S,
+    /// The faults to inject
+    faults: Arc<RwLock<Vec<StorageFault>>>,
+    /// Number of faults injected so far
+    injected: Arc<AtomicUsize>,
+}
+
+impl<S: Default> Default for FaultyStorage<S> {
+    fn default() -> Self {
+        Self::new(S::default(), Vec::new())
+    }
+}
+
+impl<S> Deref for FaultyStorage<S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<S> DerefMut for FaultyStorage<S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<S> FaultyStorage<S> {
+    /// Wrap `inner`, injecting `faults` into it.
+    pub fn new(inner: S, faults: Vec<StorageFault>) -> Self {
+        Self {
+            inner,
+            faults: Arc::new(RwLock::new(faults)),
+            injected: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Start injecting `fault`.
+    pub async fn add_fault(&self, fault: StorageFault) {
+        self.faults.write().await.push(fault);
+    }
+
+    /// Stop injecting any faults.
+    pub async fn clear_faults(&self) {
+        self.faults.write().await.clear();
+    }
+
+    /// The number of faults injected so far.
+    pub fn injected_faults(&self) -> usize {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    /// The fault to inject into a call to `method` in `view`, if any.
+    async fn fault(&self, method: StorageMethod, view: Option<u64>) -> Option<FaultKind> {
+        let kind = self
+            .faults
+            .read()
+            .await
+            .iter()
+            .find(|fault| fault.matches(method, view))
+            .map(|fault| fault.kind)?;
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        Some(kind)
+    }
+
+    /// Inject a fault which does not depend on the written value into a call to `method` in
+    /// `view`. Returns whether the write should go through.
+    async fn should_write(&self, method: StorageMethod, view: Option<u64>) -> Result<bool> {
+        match self.fault(method, view).await {
+            None => Ok(true),
+            Some(FaultKind::DropWrite) => Ok(false),
+            Some(kind) => bail!("Injected {kind:?} fault into {method:?}"),
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, S: Storage<TYPES>> Storage<TYPES> for FaultyStorage<S> {
+    async fn append_vid(&self, proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::AppendVid, Some(*proposal.data.view_number))
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.append_vid(proposal).await
+    }
+
+    async fn append_da(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal<TYPES>>,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
+    ) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::AppendDa, Some(*proposal.data.view_number))
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.append_da(proposal, vid_commit).await
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    ) -> Result<()> {
+        if !self
+            .should_write(
+                StorageMethod::AppendProposal,
+                Some(*proposal.data.view_number),
+            )
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.append_proposal(proposal).await
+    }
+
+    async fn append_proposal2(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        if !self
+            .should_write(
+                StorageMethod::AppendProposal2,
+                Some(*proposal.data.view_number),
+            )
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.append_proposal2(proposal).await
+    }
+
+    async fn record_action(&self, view: TYPES::View, action: HotShotAction) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::RecordAction, Some(*view))
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.record_action(view, action).await
+    }
+
+    async fn record_signed_action(
+        &self,
+        view: TYPES::View,
+        action: HotShotAction,
+        signing_root: SigningRoot,
+    ) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::RecordSignedAction, Some(*view))
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner
+            .record_signed_action(view, action, signing_root)
+            .await
+    }
+
+    async fn load_signing_history(&self) -> Result<SigningHistory<TYPES>> {
+        self.inner.load_signing_history().await
+    }
+
+    async fn import_signing_history(&self, history: SigningHistory<TYPES>) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::ImportSigningHistory, None)
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.import_signing_history(history).await
+    }
+
+    async fn update_high_qc(&self, mut high_qc: QuorumCertificate<TYPES>) -> Result<()> {
+        match self
+            .fault(StorageMethod::UpdateHighQc, Some(*high_qc.view_number()))
+            .await
+        {
+            None => {}
+            Some(FaultKind::DropWrite) => return Ok(()),
+            Some(FaultKind::Corrupt) => high_qc.signatures = None,
+            Some(kind) => bail!("Injected {kind:?} fault into update_high_qc"),
+        }
+        self.inner.update_high_qc(high_qc).await
+    }
+
+    async fn update_high_qc2(&self, mut high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
+        match self
+            .fault(StorageMethod::UpdateHighQc2, Some(*high_qc.view_number()))
+            .await
+        {
+            None => {}
+            Some(FaultKind::DropWrite) => return Ok(()),
+            Some(FaultKind::Corrupt) => high_qc.signatures = None,
+            Some(kind) => bail!("Injected {kind:?} fault into update_high_qc2"),
+        }
+        self.inner.update_high_qc2(high_qc).await
+    }
+
+    async fn update_undecided_state(
+        &self,
+        leafs: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::UpdateUndecidedState, None)
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.update_undecided_state(leafs, state).await
+    }
+
+    async fn update_undecided_state2(
+        &self,
+        leafs: CommitmentMap<Leaf2<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::UpdateUndecidedState2, None)
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.update_undecided_state2(leafs, state).await
+    }
+
+    async fn update_decided_leaf(&self, leaf_info: &LeafInfo<TYPES>) -> Result<()> {
+        if !self
+            .should_write(
+                StorageMethod::UpdateDecidedLeaf,
+                Some(*leaf_info.leaf.view_number()),
+            )
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.update_decided_leaf(leaf_info).await
+    }
+
+    async fn gc(&self, decided_view: TYPES::View) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::Gc, Some(*decided_view))
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.gc(decided_view).await
+    }
+
+    async fn load_leaf_at_height(&self, height: u64) -> Result<Option<Leaf2<TYPES>>> {
+        self.inner.load_leaf_at_height(height).await
+    }
+
+    async fn load_payload(&self, view: TYPES::View) -> Result<Option<TYPES::BlockPayload>> {
+        self.inner.load_payload(view).await
+    }
+
+    async fn load_quorum_certificate(
+        &self,
+        view: TYPES::View,
+    ) -> Result<Option<QuorumCertificate2<TYPES>>> {
+        self.inner.load_quorum_certificate(view).await
+    }
+
+    async fn update_decided_upgrade_certificate(
+        &self,
+        decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
+    ) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::UpdateDecidedUpgradeCertificate, None)
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner
+            .update_decided_upgrade_certificate(decided_upgrade_certificate)
+            .await
+    }
+
+    async fn add_drb_result(&self, epoch: TYPES::Epoch, drb_output: &DrbOutput) -> Result<()> {
+        if !self.should_write(StorageMethod::AddDrbResult, None).await? {
+            return Ok(());
+        }
+        self.inner.add_drb_result(epoch, drb_output).await
+    }
+
+    async fn add_stake_table(
+        &self,
+        epoch: TYPES::Epoch,
+        stake_table: &[PeerConfig<TYPES::SignatureKey>],
+    ) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::AddStakeTable, None)
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner.add_stake_table(epoch, stake_table).await
+    }
+
+    async fn migrate_consensus(
+        &self,
+        convert_leaf: fn(Leaf<TYPES>) -> Leaf2<TYPES>,
+        convert_proposal: fn(
+            Proposal<TYPES, QuorumProposal<TYPES>>,
+        ) -> Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        if !self
+            .should_write(StorageMethod::MigrateConsensus, None)
+            .await?
+        {
+            return Ok(());
+        }
+        self.inner
+            .migrate_consensus(convert_leaf, convert_proposal)
+            .await
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, S: RecoverableStorage<TYPES>> RecoverableStorage<TYPES> for FaultyStorage<S> {
+    async fn load_decided_leaf(&self) -> Result<Option<LeafInfo<TYPES>>> {
+        self.inner.load_decided_leaf().await
+    }
+
+    async fn load_high_qc2(&self) -> Result<Option<QuorumCertificate2<TYPES>>> {
+        self.inner.load_high_qc2().await
+    }
+
+    async fn load_proposals2(
+        &self,
+    ) -> Result<BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>> {
+        self.inner.load_proposals2().await
+    }
+
+    async fn load_undecided_state2(&self) -> Result<Option<UndecidedState<TYPES, Leaf2<TYPES>>>> {
+        self.inner.load_undecided_state2().await
+    }
+
+    async fn load_decided_upgrade_certificate(&self) -> Result<Option<UpgradeCertificate<TYPES>>> {
+        self.inner.load_decided_upgrade_certificate().await
+    }
+
+    async fn load_last_actioned_view(&self) -> Result<TYPES::View> {
+        self.inner.load_last_actioned_view().await
+    }
+
+    async fn load_drb_results(&self) -> Result<BTreeMap<TYPES::Epoch, DrbOutput>> {
+        self.inner.load_drb_results().await
+    }
+
+    async fn load_stake_tables(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Epoch, Vec<PeerConfig<TYPES::SignatureKey>>>> {
+        self.inner.load_stake_tables().await
+    }
+}
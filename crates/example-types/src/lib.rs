@@ -16,6 +16,9 @@ pub mod node_types;
 /// storage types for hotshot storage
 pub mod storage_types;
 
+/// storage wrapper which injects faults, for testing
+pub mod faulty_storage;
+
 /// auction types for solver-to-hotshot interactions
 pub mod auction_results_provider_types;
 
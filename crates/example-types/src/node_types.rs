@@ -6,6 +6,7 @@
 
 use hotshot::traits::{
     election::{
+        dynamic::DynamicCommittee, epoch_committee::EpochCommittee,
         randomized_committee::RandomizedCommittee, static_committee::StaticCommittee,
         static_committee_leader_two_views::StaticCommitteeLeaderForTwoViews,
     },
@@ -23,6 +24,7 @@ use vbs::version::StaticVersion;
 use crate::{
     auction_results_provider_types::{TestAuctionResult, TestAuctionResultsProvider},
     block_types::{TestBlockHeader, TestBlockPayload, TestTransaction},
+    faulty_storage::FaultyStorage,
     state_types::{TestInstanceState, TestValidatedState},
     storage_types::TestStorage,
 };
@@ -117,6 +119,66 @@ impl NodeType for TestConsecutiveLeaderTypes {
     type BuilderSignatureKey = BuilderKey;
 }
 
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+/// filler struct to implement node type and allow us
+/// to select our traits
+pub struct TestEpochCommitteeTypes;
+impl NodeType for TestEpochCommitteeTypes {
+    type AuctionResult = TestAuctionResult;
+    type View = ViewNumber;
+    type Epoch = EpochNumber;
+    type BlockHeader = TestBlockHeader;
+    type BlockPayload = TestBlockPayload;
+    type SignatureKey = BLSPubKey;
+    type Transaction = TestTransaction;
+    type ValidatedState = TestValidatedState;
+    type InstanceState = TestInstanceState;
+    type Membership = EpochCommittee<TestEpochCommitteeTypes>;
+    type BuilderSignatureKey = BuilderKey;
+}
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+/// filler struct to implement node type and allow us
+/// to select our traits
+pub struct TestDynamicCommitteeTypes;
+impl NodeType for TestDynamicCommitteeTypes {
+    type AuctionResult = TestAuctionResult;
+    type View = ViewNumber;
+    type Epoch = EpochNumber;
+    type BlockHeader = TestBlockHeader;
+    type BlockPayload = TestBlockPayload;
+    type SignatureKey = BLSPubKey;
+    type Transaction = TestTransaction;
+    type ValidatedState = TestValidatedState;
+    type InstanceState = TestInstanceState;
+    type Membership = DynamicCommittee<TestDynamicCommitteeTypes>;
+    type BuilderSignatureKey = BuilderKey;
+}
+
 /// The Push CDN implementation
 #[derive(Clone, Debug, Deserialize, Serialize, Hash, Eq, PartialEq)]
 pub struct PushCdnImpl;
@@ -142,25 +204,25 @@ pub type StaticMembership = StaticCommittee<TestTypes>;
 
 impl<TYPES: NodeType> NodeImplementation<TYPES> for PushCdnImpl {
     type Network = PushCdnNetwork<TYPES::SignatureKey>;
-    type Storage = TestStorage<TYPES>;
+    type Storage = FaultyStorage<TestStorage<TYPES>>;
     type AuctionResultsProvider = TestAuctionResultsProvider<TYPES>;
 }
 
 impl<TYPES: NodeType> NodeImplementation<TYPES> for MemoryImpl {
     type Network = MemoryNetwork<TYPES::SignatureKey>;
-    type Storage = TestStorage<TYPES>;
+    type Storage = FaultyStorage<TestStorage<TYPES>>;
     type AuctionResultsProvider = TestAuctionResultsProvider<TYPES>;
 }
 
 impl<TYPES: NodeType> NodeImplementation<TYPES> for CombinedImpl {
     type Network = CombinedNetworks<TYPES>;
-    type Storage = TestStorage<TYPES>;
+    type Storage = FaultyStorage<TestStorage<TYPES>>;
     type AuctionResultsProvider = TestAuctionResultsProvider<TYPES>;
 }
 
 impl<TYPES: NodeType> NodeImplementation<TYPES> for Libp2pImpl {
     type Network = Libp2pNetwork<TYPES>;
-    type Storage = TestStorage<TYPES>;
+    type Storage = FaultyStorage<TestStorage<TYPES>>;
     type AuctionResultsProvider = TestAuctionResultsProvider<TYPES>;
 }
 
@@ -178,6 +240,8 @@ impl Versions for TestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type Compression = StaticVersion<0, 2>;
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -194,6 +258,8 @@ impl Versions for MarketplaceUpgradeTestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type Compression = StaticVersion<0, 2>;
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -210,6 +276,8 @@ impl Versions for MarketplaceTestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type Compression = StaticVersion<0, 2>;
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -226,6 +294,8 @@ impl Versions for EpochsTestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type Compression = StaticVersion<0, 2>;
 }
 
 #[cfg(test)]
@@ -5,7 +5,7 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! Implementations for examples and tests only
-use std::fmt::Debug;
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
 
 use async_trait::async_trait;
 use committable::{Commitment, Committable};
@@ -18,6 +18,7 @@ use hotshot_types::{
         BlockPayload,
     },
     vid::VidCommon,
+    PeerConfig, ValidatorConfig,
 };
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -29,17 +30,32 @@ use crate::{
     testable_delay::{DelayConfig, SupportedTraitTypesForAsyncDelay, TestableDelay},
 };
 
+/// The stake tables set by the blocks at given heights, as lists of node indices and stakes
+pub type StakeTableUpdates = BTreeMap<u64, Vec<(u64, u64)>>;
+
 /// Instance-level state implementation for testing purposes.
 #[derive(Clone, Debug, Default)]
 pub struct TestInstanceState {
     pub delay_config: DelayConfig,
+    /// The stake tables the state sets, keyed by block height
+    pub stake_table_updates: Arc<StakeTableUpdates>,
 }
 
 impl InstanceState for TestInstanceState {}
 
 impl TestInstanceState {
     pub fn new(delay_config: DelayConfig) -> Self {
-        TestInstanceState { delay_config }
+        TestInstanceState {
+            delay_config,
+            stake_table_updates: Arc::default(),
+        }
+    }
+
+    /// Have the states of the blocks at the given heights set the given stake tables
+    #[must_use]
+    pub fn with_stake_table_updates(mut self, stake_table_updates: StakeTableUpdates) -> Self {
+        self.stake_table_updates = Arc::new(stake_table_updates);
+        self
     }
 }
 
@@ -128,6 +144,24 @@ impl<TYPES: NodeType> ValidatedState<TYPES> for TestValidatedState {
 
     fn on_commit(&self) {}
 
+    fn stake_table_update(
+        &self,
+        instance: &Self::Instance,
+    ) -> Option<Vec<PeerConfig<TYPES::SignatureKey>>> {
+        let nodes = instance.stake_table_updates.get(&self.block_height)?;
+        Some(
+            nodes
+                .iter()
+                .map(|(node_id, stake)| {
+                    ValidatorConfig::<TYPES::SignatureKey>::generated_from_seed_indexed(
+                        [0u8; 32], *node_id, *stake, false,
+                    )
+                    .public_config()
+                })
+                .collect(),
+        )
+    }
+
     fn genesis(_instance: &Self::Instance) -> (Self, Self::Delta) {
         (Self::default(), TestStateDelta {})
     }
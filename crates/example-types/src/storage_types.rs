@@ -12,12 +12,17 @@ use std::{
 use anyhow::{bail, Result};
 use async_lock::RwLock;
 use async_trait::async_trait;
+use hotshot::traits::{
+    implementations::FileSystemStorage, RecoverableStorage, RetentionPolicy, UndecidedState,
+};
 use hotshot_types::{
     consensus::CommitmentMap,
     data::{DaProposal, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare},
-    event::HotShotAction,
+    drb::DrbOutput,
+    event::{HotShotAction, LeafInfo},
     message::Proposal,
     simple_certificate::{QuorumCertificate2, UpgradeCertificate},
+    slashing_protection::{SigningHistory, SigningRoot},
     traits::{
         node_implementation::{ConsensusTime, NodeType},
         storage::Storage,
@@ -25,8 +30,10 @@ use hotshot_types::{
     utils::View,
     vid::VidSchemeType,
     vote::HasViewNumber,
+    PeerConfig,
 };
 use jf_vid::VidScheme;
+use tempfile::TempDir;
 
 use crate::testable_delay::{DelayConfig, SupportedTraitTypesForAsyncDelay, TestableDelay};
 
@@ -42,6 +49,18 @@ pub struct TestStorageState<TYPES: NodeType> {
     proposals2: BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>,
     high_qc: Option<hotshot_types::simple_certificate::QuorumCertificate<TYPES>>,
     high_qc2: Option<hotshot_types::simple_certificate::QuorumCertificate2<TYPES>>,
+    decided_leaf: Option<LeafInfo<TYPES>>,
+    /// The decided leaves, by height, which are garbage collected unless the retention policy
+    /// archives them
+    decided_leaves: BTreeMap<u64, Leaf2<TYPES>>,
+    /// The quorum certificates in the decided leaves, by the view they were formed in
+    decided_qcs: BTreeMap<TYPES::View, QuorumCertificate2<TYPES>>,
+    undecided_state2: Option<UndecidedState<TYPES, Leaf2<TYPES>>>,
+    /// The DRB results we computed, by the epoch whose leaders they seed
+    drb_results: BTreeMap<TYPES::Epoch, DrbOutput>,
+    /// The stake tables set by decided state, by the epoch from which they take effect
+    stake_tables: BTreeMap<TYPES::Epoch, Vec<PeerConfig<TYPES::SignatureKey>>>,
+    signing_history: SigningHistory<TYPES>,
     action: TYPES::View,
     epoch: TYPES::Epoch,
 }
@@ -55,15 +74,33 @@ impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
             proposals2: BTreeMap::new(),
             high_qc: None,
             high_qc2: None,
+            decided_leaf: None,
+            decided_leaves: BTreeMap::new(),
+            decided_qcs: BTreeMap::new(),
+            undecided_state2: None,
+            drb_results: BTreeMap::new(),
+            stake_tables: BTreeMap::new(),
+            signing_history: SigningHistory::default(),
             action: TYPES::View::genesis(),
             epoch: TYPES::Epoch::genesis(),
         }
     }
 }
 
+/// A [`FileSystemStorage`] in a temporary directory which outlives the storage using it.
+#[derive(Clone, Debug)]
+struct DiskBackend<TYPES: NodeType> {
+    storage: FileSystemStorage<TYPES>,
+    dir: Arc<TempDir>,
+}
+
 #[derive(Clone, Debug)]
 pub struct TestStorage<TYPES: NodeType> {
     inner: Arc<RwLock<TestStorageState<TYPES>>>,
+    /// If set, every write is also persisted to disk, and reads are served from disk.
+    disk: Option<DiskBackend<TYPES>>,
+    /// What is kept around once views are decided
+    retention_policy: RetentionPolicy,
     /// `should_return_err` is a testing utility to validate negative cases.
     pub should_return_err: bool,
     pub delay_config: DelayConfig,
@@ -74,6 +111,8 @@ impl<TYPES: NodeType> Default for TestStorage<TYPES> {
     fn default() -> Self {
         Self {
             inner: Arc::new(RwLock::new(TestStorageState::default())),
+            disk: None,
+            retention_policy: RetentionPolicy::default(),
             should_return_err: false,
             delay_config: DelayConfig::default(),
             decided_upgrade_certificate: Arc::new(RwLock::new(None)),
@@ -92,6 +131,61 @@ impl<TYPES: NodeType> TestableDelay for TestStorage<TYPES> {
 }
 
 impl<TYPES: NodeType> TestStorage<TYPES> {
+    /// Create a storage which persists everything it is given to a [`FileSystemStorage`] in a
+    /// fresh temporary directory.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created.
+    pub fn on_disk() -> Result<Self> {
+        let dir = Arc::new(tempfile::tempdir()?);
+        Ok(Self {
+            disk: Some(DiskBackend {
+                storage: FileSystemStorage::new(dir.path())?,
+                dir,
+            }),
+            ..Self::default()
+        })
+    }
+
+    /// Reopen the directory of a storage created by [`TestStorage::on_disk`], as a restarted node
+    /// would, so that nothing held in memory by `self` is visible to the new storage.
+    ///
+    /// # Errors
+    /// Returns an error if `self` is not backed by disk, or if the directory cannot be opened.
+    pub fn reopen(&self) -> Result<Self> {
+        let Some(disk) = &self.disk else {
+            bail!("Only storage on disk can be reopened");
+        };
+        let storage = Self {
+            disk: Some(DiskBackend {
+                storage: FileSystemStorage::new(disk.dir.path())?,
+                dir: Arc::clone(&disk.dir),
+            }),
+            should_return_err: self.should_return_err,
+            delay_config: self.delay_config.clone(),
+            ..Self::default()
+        };
+        Ok(storage.with_retention_policy(self.retention_policy))
+    }
+
+    /// Replace the default [`RetentionPolicy`] of this storage, and of its storage on disk.
+    #[must_use]
+    pub fn with_retention_policy(mut self, retention_policy: RetentionPolicy) -> Self {
+        self.retention_policy = retention_policy;
+        if let Some(disk) = self.disk.take() {
+            self.disk = Some(DiskBackend {
+                storage: disk.storage.with_retention_policy(retention_policy),
+                dir: disk.dir,
+            });
+        }
+        self
+    }
+
+    /// Whether writes are also persisted to disk.
+    pub fn is_on_disk(&self) -> bool {
+        self.disk.is_some()
+    }
+
     pub async fn proposals_cloned(
         &self,
     ) -> BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>> {
@@ -124,13 +218,17 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
             .entry(proposal.data.view_number)
             .or_default()
             .insert(proposal.data.recipient_key.clone(), proposal.clone());
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.append_vid(proposal).await?;
+        }
         Ok(())
     }
 
     async fn append_da(
         &self,
         proposal: &Proposal<TYPES, DaProposal<TYPES>>,
-        _vid_commit: <VidSchemeType as VidScheme>::Commit,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
     ) -> Result<()> {
         if self.should_return_err {
             bail!("Failed to append VID proposal to storage");
@@ -140,6 +238,10 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         inner
             .das
             .insert(proposal.data.view_number, proposal.clone());
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.append_da(proposal, vid_commit).await?;
+        }
         Ok(())
     }
     async fn append_proposal(
@@ -154,6 +256,10 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         inner
             .proposals
             .insert(proposal.data.view_number, proposal.clone());
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.append_proposal(proposal).await?;
+        }
         Ok(())
     }
     async fn append_proposal2(
@@ -168,6 +274,10 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         inner
             .proposals2
             .insert(proposal.data.view_number, proposal.clone());
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.append_proposal2(proposal).await?;
+        }
         Ok(())
     }
 
@@ -183,7 +293,52 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if view > inner.action && matches!(action, HotShotAction::Vote | HotShotAction::Propose) {
             inner.action = view;
         }
+        drop(inner);
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        if let Some(disk) = &self.disk {
+            disk.storage.record_action(view, action).await?;
+        }
+        Ok(())
+    }
+
+    async fn record_signed_action(
+        &self,
+        view: <TYPES as NodeType>::View,
+        action: HotShotAction,
+        signing_root: SigningRoot,
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to record signed action in storage");
+        }
         Self::run_delay_settings_from_config(&self.delay_config).await;
+        self.inner
+            .write()
+            .await
+            .signing_history
+            .check_and_insert(view, action, signing_root)?;
+        if let Some(disk) = &self.disk {
+            disk.storage
+                .record_signed_action(view, action, signing_root)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn load_signing_history(&self) -> Result<SigningHistory<TYPES>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_signing_history().await;
+        }
+        Ok(self.inner.read().await.signing_history.clone())
+    }
+
+    async fn import_signing_history(&self, history: SigningHistory<TYPES>) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to import signing history to storage");
+        }
+        if let Some(disk) = &self.disk {
+            disk.storage.import_signing_history(history.clone()).await?;
+        }
+        self.inner.write().await.signing_history.merge(history);
         Ok(())
     }
 
@@ -201,7 +356,11 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
                 inner.high_qc = Some(new_high_qc);
             }
         } else {
-            inner.high_qc = Some(new_high_qc);
+            inner.high_qc = Some(new_high_qc.clone());
+        }
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.update_high_qc(new_high_qc).await?;
         }
         Ok(())
     }
@@ -220,44 +379,182 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
                 inner.high_qc2 = Some(new_high_qc);
             }
         } else {
-            inner.high_qc2 = Some(new_high_qc);
+            inner.high_qc2 = Some(new_high_qc.clone());
+        }
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.update_high_qc2(new_high_qc).await?;
         }
         Ok(())
     }
     async fn update_undecided_state(
         &self,
-        _leafs: CommitmentMap<Leaf<TYPES>>,
-        _state: BTreeMap<TYPES::View, View<TYPES>>,
+        leafs: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
     ) -> Result<()> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
         Self::run_delay_settings_from_config(&self.delay_config).await;
+        if let Some(disk) = &self.disk {
+            disk.storage.update_undecided_state(leafs, state).await?;
+        }
         Ok(())
     }
     async fn update_undecided_state2(
         &self,
-        _leafs: CommitmentMap<Leaf2<TYPES>>,
-        _state: BTreeMap<TYPES::View, View<TYPES>>,
+        leafs: CommitmentMap<Leaf2<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
     ) -> Result<()> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
         Self::run_delay_settings_from_config(&self.delay_config).await;
+        if let Some(disk) = &self.disk {
+            disk.storage
+                .update_undecided_state2(leafs.clone(), state.clone())
+                .await?;
+        }
+        self.inner.write().await.undecided_state2 = Some((leafs, state));
+        Ok(())
+    }
+    async fn update_decided_leaf(&self, leaf_info: &LeafInfo<TYPES>) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to update decided leaf in storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        let justify_qc = leaf_info.leaf.justify_qc();
+        inner
+            .decided_qcs
+            .insert(justify_qc.view_number(), justify_qc);
+        inner
+            .decided_leaves
+            .insert(leaf_info.leaf.height(), leaf_info.leaf.clone());
+        if inner.decided_leaf.as_ref().map_or(true, |current| {
+            leaf_info.leaf.view_number() > current.leaf.view_number()
+        }) {
+            inner.decided_leaf = Some(leaf_info.clone());
+        }
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.update_decided_leaf(leaf_info).await?;
+        }
+        Ok(())
+    }
+    async fn gc(&self, decided_view: TYPES::View) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to garbage collect storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let policy = self.retention_policy;
+        let cutoff = TYPES::View::new(decided_view.u64().saturating_sub(policy.views_to_keep));
+        let vid_cutoff = if policy.prune_vid_on_decide {
+            TYPES::View::new(decided_view.u64() + 1)
+        } else {
+            cutoff
+        };
+        let mut inner = self.inner.write().await;
+        inner.vids.retain(|view, _| *view >= vid_cutoff);
+        inner.das.retain(|view, _| *view >= cutoff);
+        inner.proposals = inner.proposals.split_off(&cutoff);
+        inner.proposals2 = inner.proposals2.split_off(&cutoff);
+        if !policy.archive_decided_leaves {
+            inner
+                .decided_leaves
+                .retain(|_, leaf| leaf.view_number() >= cutoff);
+            inner.decided_qcs = inner.decided_qcs.split_off(&cutoff);
+        }
+        inner.signing_history.prune(cutoff);
+        drop(inner);
+        if let Some(disk) = &self.disk {
+            disk.storage.gc(decided_view).await?;
+        }
         Ok(())
     }
+    async fn load_leaf_at_height(&self, height: u64) -> Result<Option<Leaf2<TYPES>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_leaf_at_height(height).await;
+        }
+        Ok(self.inner.read().await.decided_leaves.get(&height).cloned())
+    }
+    async fn load_payload(&self, view: TYPES::View) -> Result<Option<TYPES::BlockPayload>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_payload(view).await;
+        }
+        Ok(self
+            .inner
+            .read()
+            .await
+            .decided_leaves
+            .values()
+            .find(|leaf| leaf.view_number() == view)
+            .and_then(|leaf| leaf.block_payload()))
+    }
+    async fn load_quorum_certificate(
+        &self,
+        view: TYPES::View,
+    ) -> Result<Option<QuorumCertificate2<TYPES>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_quorum_certificate(view).await;
+        }
+        Ok(self.inner.read().await.decided_qcs.get(&view).cloned())
+    }
     async fn update_decided_upgrade_certificate(
         &self,
         decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
     ) -> Result<()> {
+        if let Some(disk) = &self.disk {
+            disk.storage
+                .update_decided_upgrade_certificate(decided_upgrade_certificate.clone())
+                .await?;
+        }
         *self.decided_upgrade_certificate.write().await = decided_upgrade_certificate;
 
         Ok(())
     }
 
+    async fn add_drb_result(&self, epoch: TYPES::Epoch, drb_output: &DrbOutput) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to add DRB result to storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        if let Some(disk) = &self.disk {
+            disk.storage.add_drb_result(epoch, drb_output).await?;
+        }
+        self.inner
+            .write()
+            .await
+            .drb_results
+            .insert(epoch, drb_output.clone());
+
+        Ok(())
+    }
+
+    async fn add_stake_table(
+        &self,
+        epoch: TYPES::Epoch,
+        stake_table: &[PeerConfig<TYPES::SignatureKey>],
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to add stake table to storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        if let Some(disk) = &self.disk {
+            disk.storage.add_stake_table(epoch, stake_table).await?;
+        }
+        self.inner
+            .write()
+            .await
+            .stake_tables
+            .insert(epoch, stake_table.to_vec());
+
+        Ok(())
+    }
+
     async fn migrate_consensus(
         &self,
-        _convert_leaf: fn(Leaf<TYPES>) -> Leaf2<TYPES>,
+        convert_leaf: fn(Leaf<TYPES>) -> Leaf2<TYPES>,
         convert_proposal: fn(
             Proposal<TYPES, QuorumProposal<TYPES>>,
         ) -> Proposal<TYPES, QuorumProposal2<TYPES>>,
@@ -269,7 +566,77 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
                 .proposals2
                 .insert(*view, convert_proposal(proposal.clone()));
         }
+        drop(storage_writer);
+
+        if let Some(disk) = &self.disk {
+            disk.storage
+                .migrate_consensus(convert_leaf, convert_proposal)
+                .await?;
+        }
 
         Ok(())
     }
 }
+
+#[async_trait]
+impl<TYPES: NodeType> RecoverableStorage<TYPES> for TestStorage<TYPES> {
+    async fn load_decided_leaf(&self) -> Result<Option<LeafInfo<TYPES>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_decided_leaf().await;
+        }
+        Ok(self.inner.read().await.decided_leaf.clone())
+    }
+
+    async fn load_high_qc2(&self) -> Result<Option<QuorumCertificate2<TYPES>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_high_qc2().await;
+        }
+        Ok(self.high_qc_cloned().await)
+    }
+
+    async fn load_proposals2(
+        &self,
+    ) -> Result<BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_proposals2().await;
+        }
+        Ok(self.proposals_cloned().await)
+    }
+
+    async fn load_undecided_state2(&self) -> Result<Option<UndecidedState<TYPES, Leaf2<TYPES>>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_undecided_state2().await;
+        }
+        Ok(self.inner.read().await.undecided_state2.clone())
+    }
+
+    async fn load_decided_upgrade_certificate(&self) -> Result<Option<UpgradeCertificate<TYPES>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_decided_upgrade_certificate().await;
+        }
+        Ok(self.decided_upgrade_certificate().await)
+    }
+
+    async fn load_last_actioned_view(&self) -> Result<TYPES::View> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_last_actioned_view().await;
+        }
+        Ok(self.last_actioned_view().await)
+    }
+
+    async fn load_drb_results(&self) -> Result<BTreeMap<TYPES::Epoch, DrbOutput>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_drb_results().await;
+        }
+        Ok(self.inner.read().await.drb_results.clone())
+    }
+
+    async fn load_stake_tables(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Epoch, Vec<PeerConfig<TYPES::SignatureKey>>>> {
+        if let Some(disk) = &self.disk {
+            return disk.storage.load_stake_tables().await;
+        }
+        Ok(self.inner.read().await.stake_tables.clone())
+    }
+}
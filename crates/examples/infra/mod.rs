@@ -23,14 +23,14 @@ use futures::StreamExt;
 use hotshot::{
     traits::{
         implementations::{
-            derive_libp2p_multiaddr, derive_libp2p_peer_id, CdnMetricsValue, CdnTopic,
-            CombinedNetworks, Libp2pMetricsValue, Libp2pNetwork, PushCdnNetwork,
-            WrappedSignatureKey,
+            derive_libp2p_multiaddr, derive_libp2p_multiaddr_with_transport, derive_libp2p_peer_id,
+            CdnMetricsValue, CdnTopic, CombinedNetworks, Libp2pMetricsValue, Libp2pNetwork,
+            PushCdnNetwork, WrappedSignatureKey,
         },
         BlockPayload, NodeImplementation,
     },
     types::SystemContextHandle,
-    MarketplaceConfig, Memberships, SystemContext,
+    LocalBuilder, MarketplaceConfig, Memberships, SolverClient, SystemContext,
 };
 use hotshot_example_types::{
     auction_results_provider_types::TestAuctionResultsProvider,
@@ -57,6 +57,7 @@ use hotshot_types::{
         election::Membership,
         network::{ConnectedNetwork, Topic},
         node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::BuilderSignatureKey,
         states::TestableState,
     },
     HotShotConfig, PeerConfig, ValidatorConfig,
@@ -399,10 +400,24 @@ pub trait RunDa<
         };
 
         let marketplace_config = MarketplaceConfig {
-            auction_results_provider: TestAuctionResultsProvider::<TYPES>::default().into(),
+            auction_results_provider: TestAuctionResultsProvider::<TYPES> {
+                solver: config.solver_url.clone().map(SolverClient::new),
+                ..TestAuctionResultsProvider::default()
+            }
+            .into(),
             // TODO: we need to pass a valid fallback builder url here somehow
             fallback_builder_url: config.config.builder_urls.first().clone(),
+            local_builder: config.local_builder.as_ref().map(|local_builder| {
+                let (public_key, private_key) =
+                    TYPES::BuilderSignatureKey::generated_from_seed_indexed(
+                        local_builder.seed,
+                        config.node_index,
+                    );
+                LocalBuilder::new(public_key, private_key, local_builder.max_block_size)
+            }),
         };
+        let storage =
+            TestStorage::<TYPES>::default().with_retention_policy(config.config.retention_policy);
 
         SystemContext::init(
             pk,
@@ -413,7 +428,7 @@ pub trait RunDa<
             Arc::from(network),
             initializer,
             ConsensusMetricsValue::default(),
-            TestStorage::<TYPES>::default(),
+            storage,
             marketplace_config,
         )
         .await
@@ -754,9 +769,14 @@ where
         let all_nodes = config.config.known_nodes_with_stake.clone();
         let quorum_membership = TYPES::Membership::new(all_nodes.clone(), all_nodes, Topic::Global);
 
-        // Derive the bind address
-        let bind_address =
-            derive_libp2p_multiaddr(&bind_address).expect("failed to derive bind address");
+        // Derive the bind address for the configured transport
+        let transport = config
+            .libp2p_config
+            .as_ref()
+            .map(|libp2p_config| libp2p_config.transport)
+            .unwrap_or_default();
+        let bind_address = derive_libp2p_multiaddr_with_transport(&bind_address, transport)
+            .expect("failed to derive bind address");
 
         // Create the Libp2p network
         let libp2p_network = Libp2pNetwork::from_config(
@@ -1094,6 +1114,7 @@ where
             let builder_task =
                 <RandomBuilderImplementation as TestBuilderImplementation<TYPES>>::start(
                     run_config.config.num_nodes_with_stake.into(),
+                    run_config.config.vid_code_rate,
                     bind_address,
                     run_config.random_builder.clone().unwrap_or_default(),
                     HashMap::new(),
@@ -1110,6 +1131,7 @@ where
             let builder_task =
                 <SimpleBuilderImplementation as TestBuilderImplementation<TYPES>>::start(
                     run_config.config.num_nodes_with_stake.into(),
+                    run_config.config.vid_code_rate,
                     bind_address,
                     (),
                     HashMap::new(),
@@ -1,5 +1,9 @@
 use std::{
     io::{self, ErrorKind},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time,
 };
 
@@ -20,6 +24,7 @@ use vbs::version::{StaticVersion, StaticVersionType};
 const SOLVER_MAX_TIMEOUT_S: time::Duration = time::Duration::from_secs(1);
 
 /// The type of fake solver error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FakeSolverFaultType {
     /// A 500 error
     InternalServerFault,
@@ -36,6 +41,12 @@ pub struct FakeSolverState {
 
     /// The available builder list
     pub available_builders: Vec<Url>,
+
+    /// A fault to inject into every response, on top of the random ones
+    pub fault: Option<FakeSolverFaultType>,
+
+    /// The number of auction results requested so far, shared with the clones of this state
+    pub num_requests: Arc<AtomicUsize>,
 }
 
 impl FakeSolverState {
@@ -45,9 +56,18 @@ impl FakeSolverState {
         Self {
             error_pct: error_pct.unwrap_or(0.0),
             available_builders,
+            fault: None,
+            num_requests: Arc::default(),
         }
     }
 
+    /// Inject `fault` into every response, or only the random faults if `None`
+    #[must_use]
+    pub fn with_fault(mut self, fault: Option<FakeSolverFaultType>) -> Self {
+        self.fault = fault;
+        self
+    }
+
     /// Runs the fake solver
     /// # Errors
     /// This errors if tide disco runs into an issue during serving
@@ -63,9 +83,13 @@ impl FakeSolverState {
         app.serve(url, StaticVersion::<0, 1> {}).await
     }
 
-    /// If a random fault event happens, what fault should we send?
+    /// If the injected fault or a random fault event happens, what fault should we send?
     #[must_use]
     fn should_fault(&self) -> Option<FakeSolverFaultType> {
+        if self.fault.is_some() {
+            return self.fault;
+        }
+
         if rand::random::<f32>() < self.error_pct {
             // Spin a random number over the fault types
             if rand::random::<f32>() < 0.5 {
@@ -84,6 +108,8 @@ impl FakeSolverState {
     /// # Errors
     /// Returns an error if the `should_fault` method is `Some`.
     async fn dump_builders(&self) -> Result<TestAuctionResult, ServerError> {
+        self.num_requests.fetch_add(1, Ordering::Relaxed);
+
         if let Some(fault) = self.should_fault() {
             match fault {
                 FakeSolverFaultType::InternalServerFault => {
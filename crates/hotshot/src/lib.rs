@@ -42,14 +42,25 @@ use async_lock::RwLock;
 use async_trait::async_trait;
 use futures::join;
 use hotshot_task::task::{ConsensusTaskRegistry, NetworkTaskRegistry};
-use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
+use hotshot_task_impls::{
+    builder_reputation::BuilderReputation,
+    events::HotShotEvent,
+    helpers::broadcast_event,
+    mempool::{Mempool, TransactionSource},
+    request_scheduler::RequestScheduler,
+};
 // Internal
+/// Reexport the local builder, which nodes configure through [`MarketplaceConfig`]
+pub use hotshot_task_impls::local_builder::LocalBuilder;
+/// Reexport the client for the Solver, which fetches the auction results of the marketplace
+pub use hotshot_task_impls::solver::SolverClient;
 /// Reexport error type
 pub use hotshot_types::error::HotShotError;
 use hotshot_types::{
     consensus::{Consensus, ConsensusMetricsValue, OuterConsensus, View, ViewInner},
     constants::{EVENT_CHANNEL_SIZE, EXTERNAL_EVENT_CHANNEL_SIZE},
     data::{Leaf, Leaf2, QuorumProposal, QuorumProposal2},
+    drb::DrbOutput,
     event::{EventType, LeafInfo},
     message::{convert_proposal, DataMessage, Message, MessageKind, Proposal},
     simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
@@ -63,14 +74,14 @@ use hotshot_types::{
         storage::Storage,
         EncodeBytes,
     },
-    HotShotConfig,
+    HotShotConfig, PeerConfig,
 };
 // -- Rexports
 // External
 /// Reexport rand crate
 pub use rand;
 use tokio::{spawn, time::sleep};
-use tracing::{debug, instrument, trace};
+use tracing::{debug, instrument, trace, warn};
 
 use crate::{
     tasks::{add_consensus_tasks, add_network_tasks},
@@ -84,12 +95,16 @@ pub const H_512: usize = 64;
 pub const H_256: usize = 32;
 
 #[derive(Clone)]
-/// Wrapper for all marketplace config that needs to be passed when creating a new instance of HotShot
+/// Wrapper for all marketplace and block building config that needs to be passed when creating a
+/// new instance of HotShot
 pub struct MarketplaceConfig<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// auction results provider
     pub auction_results_provider: Arc<I::AuctionResultsProvider>,
     /// fallback builder
     pub fallback_builder_url: Url,
+    /// builder for blocks of the transactions in our mempool when no builder delivers one, if
+    /// the node should build blocks itself
+    pub local_builder: Option<LocalBuilder<TYPES>>,
 }
 
 /// Bundle of all the memberships a consensus instance uses
@@ -160,6 +175,15 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
 
     /// Marketplace config for this instance of HotShot
     pub marketplace_config: MarketplaceConfig<TYPES, I>,
+
+    /// Picks the peers to request data from, shared by all the tasks which make requests
+    pub request_scheduler: RequestScheduler<TYPES::SignatureKey>,
+
+    /// Pool of the transactions waiting to be included in a block, if the config asks for one
+    pub mempool: Option<Mempool<TYPES>>,
+
+    /// Scores of the builders we get blocks from when we lead
+    pub builder_reputation: BuilderReputation,
 }
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
     for SystemContext<TYPES, I, V>
@@ -185,6 +209,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
             storage: Arc::clone(&self.storage),
             upgrade_lock: self.upgrade_lock.clone(),
             marketplace_config: self.marketplace_config.clone(),
+            request_scheduler: self.request_scheduler.clone(),
+            mempool: self.mempool.clone(),
+            builder_reputation: self.builder_reputation.clone(),
         }
     }
 }
@@ -336,7 +363,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         } else {
             TYPES::Epoch::new(anchored_leaf.height() / config.epoch_height + 1)
         };
-        let consensus = Consensus::new(
+        let mut consensus = Consensus::new(
             validated_state_map,
             anchored_leaf.view_number(),
             anchored_epoch,
@@ -351,7 +378,34 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             config.epoch_height,
         );
 
+        // Restore the stake tables set by decided state, which the anchor leaf alone can't tell us
+        for (epoch, stake_table) in initializer.stake_tables {
+            memberships
+                .da_membership
+                .update_stake_table(epoch, stake_table.clone());
+            memberships
+                .quorum_membership
+                .update_stake_table(epoch, stake_table);
+        }
+
+        // Restore the leader schedules we computed before a restart, since the seeds for the
+        // current and next epochs are no longer available to recompute them from.
+        for (epoch, drb_output) in initializer.drb_results {
+            memberships
+                .quorum_membership
+                .add_drb_result(epoch, drb_output.result);
+            memberships
+                .da_membership
+                .add_drb_result(epoch, drb_output.result);
+            if let Err(e) = consensus.update_drb_result(epoch, drb_output) {
+                tracing::error!("Failed to restore the DRB result for epoch {epoch}: {e}");
+            }
+        }
+
         let consensus = Arc::new(RwLock::new(consensus));
+        let mempool = config
+            .mempool
+            .map(|mempool_config| Mempool::new(mempool_config, Arc::clone(&consensus_metrics)));
 
         // This makes it so we won't block on broadcasting if there is not a receiver
         // Our own copy of the receiver is inactive so it doesn't count.
@@ -376,6 +430,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             storage: Arc::new(RwLock::new(storage)),
             upgrade_lock,
             marketplace_config,
+            request_scheduler: RequestScheduler::new(Arc::clone(&consensus_metrics)),
+            mempool,
+            builder_reputation: BuilderReputation::new(Arc::clone(&consensus_metrics)),
         });
 
         inner
@@ -483,7 +540,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     ///
     /// # Errors
     ///
-    /// Always returns Ok; does not return an error if the transaction couldn't be published to the network
+    /// Returns an error if the mempool rejects the transaction; does not return an error if the
+    /// transaction couldn't be published to the network
     #[instrument(skip(self), err, target = "SystemContext", fields(id = self.id))]
     pub async fn publish_transaction_async(
         &self,
@@ -491,6 +549,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     ) -> Result<(), HotShotError<TYPES>> {
         trace!("Adding transaction to our own queue");
 
+        if let Some(mempool) = &self.mempool {
+            mempool
+                .insert(transaction.clone(), TransactionSource::Local)
+                .map_err(|err| HotShotError::TransactionRejected(err.to_string()))?;
+        }
+
         let api = self.clone();
         let view_number = api.consensus.read().await.cur_view();
 
@@ -597,7 +661,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     /// To construct a [`SystemContext`] without setting up tasks, use `fn new` instead.
     /// # Errors
     ///
-    /// Can throw an error if `Self::new` fails.
+    /// If the VID code rate of the config doesn't give a recovery threshold for the nodes with
+    /// stake, or if `Self::new` fails.
     #[allow(clippy::too_many_arguments)]
     pub async fn init(
         public_key: TYPES::SignatureKey,
@@ -618,6 +683,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         ),
         HotShotError<TYPES>,
     > {
+        let vid_code_rate = config.vid_code_rate;
+        let num_storage_nodes = config.num_nodes_with_stake.get();
+        let recovery_threshold = vid_code_rate
+            .recovery_threshold(num_storage_nodes)
+            .map_err(|err| {
+                HotShotError::InvalidConfig(format!("VID code rate {vid_code_rate}: {err}"))
+            })?;
+        // `recovery_threshold` checked the rate and the number of storage nodes
+        let exact_threshold = vid_code_rate
+            .exact_recovery_threshold(num_storage_nodes)
+            .unwrap_or(recovery_threshold);
+        if recovery_threshold != exact_threshold {
+            warn!(
+                "VID code rate {vid_code_rate} asks for {exact_threshold} of {num_storage_nodes} \
+                 shares to recover a payload, but the VID scheme only takes a power of two, so \
+                 the effective rate is {recovery_threshold}/{num_storage_nodes}"
+            );
+        }
+
         let hotshot = Self::new(
             public_key,
             private_key,
@@ -1010,6 +1094,10 @@ pub struct HotShotInitializer<TYPES: NodeType> {
     undecided_state: BTreeMap<TYPES::View, View<TYPES>>,
     /// Proposals we have sent out to provide to others for catchup
     saved_proposals: BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>,
+    /// DRB results computed before a restart, by the epoch whose leaders they seed
+    drb_results: BTreeMap<TYPES::Epoch, DrbOutput>,
+    /// Stake tables set by state decided before a restart, by the epoch from which they take effect
+    stake_tables: BTreeMap<TYPES::Epoch, Vec<PeerConfig<TYPES::SignatureKey>>>,
 }
 
 impl<TYPES: NodeType> HotShotInitializer<TYPES> {
@@ -1038,6 +1126,8 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
             decided_upgrade_certificate: None,
             undecided_leafs: Vec::new(),
             undecided_state: BTreeMap::new(),
+            drb_results: BTreeMap::new(),
+            stake_tables: BTreeMap::new(),
             instance_state,
         })
     }
@@ -1049,6 +1139,9 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
     ///     after restart.
     /// * `validated_state` - Optional validated state that if given, will be used to construct the
     ///     `SystemContext`.
+    /// * `drb_results` - DRB results computed before the restart, which choose the leaders of the
+    ///     epochs after the anchor leaf.
+    /// * `stake_tables` - Stake tables set by state decided before the restart.
     #[allow(clippy::too_many_arguments)]
     pub fn from_reload(
         anchor_leaf: Leaf2<TYPES>,
@@ -1062,6 +1155,8 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
         decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
         undecided_leafs: Vec<Leaf2<TYPES>>,
         undecided_state: BTreeMap<TYPES::View, View<TYPES>>,
+        drb_results: BTreeMap<TYPES::Epoch, DrbOutput>,
+        stake_tables: BTreeMap<TYPES::Epoch, Vec<PeerConfig<TYPES::SignatureKey>>>,
     ) -> Self {
         Self {
             inner: anchor_leaf,
@@ -1076,6 +1171,8 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
             decided_upgrade_certificate,
             undecided_leafs,
             undecided_state,
+            drb_results,
+            stake_tables,
         }
     }
 }
@@ -80,18 +80,22 @@ pub async fn add_request_network_task<
 pub fn add_response_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
     handle: &mut SystemContextHandle<TYPES, I, V>,
 ) {
-    let state = NetworkResponseState::<TYPES>::new(
+    let state = NetworkResponseState::<TYPES, I>::new(
         handle.hotshot.consensus(),
+        Arc::clone(&handle.storage),
         handle.hotshot.memberships.quorum_membership.clone().into(),
         handle.public_key().clone(),
         handle.private_key().clone(),
         handle.hotshot.id,
+        handle.hotshot.config.vid_code_rate,
     );
-    handle.network_registry.register(run_response_task::<TYPES>(
-        state,
-        handle.internal_event_stream.1.activate_cloned(),
-        handle.internal_event_stream.0.clone(),
-    ));
+    handle
+        .network_registry
+        .register(run_response_task::<TYPES, I>(
+            state,
+            handle.internal_event_stream.1.activate_cloned(),
+            handle.internal_event_stream.0.clone(),
+        ));
 }
 
 /// Add a task which updates our queue length metric at a set interval
@@ -128,11 +132,12 @@ pub fn add_network_message_task<
     handle: &mut SystemContextHandle<TYPES, I, V>,
     channel: &Arc<NET>,
 ) {
-    let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
+    let network_state: NetworkMessageTaskState<_, _> = NetworkMessageTaskState {
         internal_event_stream: handle.internal_event_stream.0.clone(),
         external_event_stream: handle.output_event_stream.0.clone(),
         public_key: handle.public_key().clone(),
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
+        network: Arc::clone(channel),
     };
 
     let upgrade_lock = handle.hotshot.upgrade_lock.clone();
@@ -153,9 +158,9 @@ pub fn add_network_message_task<
                 }
 
                 // Wait for a message from the network
-                message = network.recv_message().fuse() => {
+                message = network.recv_message_from().fuse() => {
                     // Make sure the message did not fail
-                    let message = match message {
+                    let (message, peer) = match message {
                         Ok(message) => message,
                         Err(e) => {
                             tracing::error!("Failed to receive message: {:?}", e);
@@ -173,7 +178,7 @@ pub fn add_network_message_task<
                     };
 
                     // Handle the message
-                    state.handle_message(deserialized_message).await;
+                    state.handle_message(deserialized_message, peer).await;
                 }
             }
         }
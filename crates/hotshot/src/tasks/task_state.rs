@@ -51,6 +51,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             consensus: OuterConsensus::new(handle.hotshot.consensus()),
             view: handle.cur_view().await,
             delay: handle.hotshot.config.data_request_delay,
+            scheduler: handle.hotshot.request_scheduler.clone(),
             da_membership: handle.hotshot.memberships.da_membership.clone(),
             public_key: handle.public_key().clone(),
             private_key: handle.private_key().clone(),
@@ -125,6 +126,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             public_key: handle.public_key().clone(),
             private_key: handle.private_key().clone(),
             id: handle.hotshot.id,
+            vid_code_rate: handle.hotshot.config.vid_code_rate,
         }
     }
 }
@@ -148,6 +150,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             id: handle.hotshot.id,
             storage: Arc::clone(&handle.storage),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            vid_code_rate: handle.hotshot.config.vid_code_rate,
         }
     }
 }
@@ -212,6 +215,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
                 .marketplace_config
                 .fallback_builder_url
                 .clone(),
+            mempool: handle.hotshot.mempool.clone(),
+            builder_reputation: handle.hotshot.builder_reputation.clone(),
+            local_builder: handle.hotshot.marketplace_config.local_builder.clone(),
+            vid_code_rate: handle.hotshot.config.vid_code_rate,
         }
     }
 }
@@ -223,7 +230,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
         let consensus = handle.hotshot.consensus();
 
-        Self {
+        let mut state = Self {
             public_key: handle.public_key().clone(),
             private_key: handle.private_key().clone(),
             consensus: OuterConsensus::new(consensus),
@@ -238,7 +245,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             storage: Arc::clone(&handle.storage),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
-        }
+            drb_config: handle.hotshot.config.drb,
+            drb_computations: BTreeMap::new(),
+            decide_catchup: None,
+            request_scheduler: handle.hotshot.request_scheduler.clone(),
+            vid_code_rate: handle.hotshot.config.vid_code_rate,
+        };
+        state.resume_drb_computations().await;
+
+        state
     }
 }
 
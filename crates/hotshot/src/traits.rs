@@ -8,24 +8,29 @@
 pub mod election;
 mod networking;
 mod node_implementation;
+mod storage;
 
 pub use hotshot_types::traits::{BlockPayload, ValidatedState};
 pub use libp2p_networking::network::NetworkNodeConfigBuilder;
 pub use networking::{NetworkError, NetworkReliability};
 pub use node_implementation::{NodeImplementation, TestableNodeImplementation};
+pub use storage::{RecoverableStorage, RetentionPolicy, UndecidedState};
 
 /// Module for publicly usable implementations of the traits
 pub mod implementations {
     pub use super::networking::{
         combined_network::{CombinedNetworks, UnderlyingCombinedNetworks},
         libp2p_network::{
-            derive_libp2p_keypair, derive_libp2p_multiaddr, derive_libp2p_peer_id, GossipConfig,
-            Libp2pMetricsValue, Libp2pNetwork, PeerInfoVec, RequestResponseConfig,
+            derive_libp2p_keypair, derive_libp2p_multiaddr, derive_libp2p_multiaddr_with_transport,
+            derive_libp2p_peer_id, GossipConfig, Libp2pMetricsValue, Libp2pNetwork, PeerInfoVec,
+            RequestResponseConfig,
         },
         memory_network::{MasterMap, MemoryNetwork},
+        misbehaviour::MisbehaviourTracker,
         push_cdn_network::{
             CdnMetricsValue, KeyPair, ProductionDef, PushCdnNetwork, TestingDef, Topic as CdnTopic,
             WrappedSignatureKey,
         },
     };
+    pub use super::storage::file_system_storage::FileSystemStorage;
 }
@@ -8,6 +8,8 @@
 
 /// Dynamic leader election with epochs.
 pub mod dynamic;
+/// committee whose stake table changes from epoch to epoch
+pub mod epoch_committee;
 /// leader completely randomized every view
 pub mod randomized_committee;
 /// static (round robin) committee election
@@ -4,73 +4,249 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::{
+    cmp::max,
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
-use hotshot_types::traits::{node_implementation::NodeType, signature_key::SignatureKey};
+use hotshot_types::{
+    drb::{DrbResult, INITIAL_DRB_RESULT, INITIAL_DRB_RESULT_EPOCHS},
+    traits::{
+        election::Membership,
+        network::Topic,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+    PeerConfig,
+};
+use parking_lot::RwLock;
+use primitive_types::U256;
 use sha2::{Digest, Sha256};
+use utils::anytrace::*;
 
-// TODO: Add the following consts once we bench the hash time.
-// <https://github.com/EspressoSystems/HotShot/issues/3880>
-// /// Highest number of hashes that a hardware can complete in a second.
-// const `HASHES_PER_SECOND`
-// /// Time a DRB calculation will take, in terms of number of views.
-// const `DRB_CALCULATION_NUM_VIEW`: u64 = 300;
-
-// TODO: Replace this with an accurate number calculated by `fn difficulty_level()` once we bench
-// the hash time.
-// <https://github.com/EspressoSystems/HotShot/issues/3880>
-/// Arbitrary number of times the hash function will be repeatedly called.
-const DIFFICULTY_LEVEL: u64 = 10;
-
-// TODO: Use `HASHES_PER_SECOND` * `VIEW_TIMEOUT` * `DRB_CALCULATION_NUM_VIEW` to calculate this
-// once we bench the hash time.
-// <https://github.com/EspressoSystems/HotShot/issues/3880>
-/// Difficulty level of the DRB calculation.
+/// Use the DRB result to get the leader.
 ///
-/// Represents the number of times the hash function will be repeatedly called.
-#[must_use]
-pub fn difficulty_level() -> u64 {
-    unimplemented!("Use an arbitrary `DIFFICULTY_LEVEL` for now before we bench the hash time.");
-}
-
-/// Compute the DRB result for the leader rotation.
+/// The DRB result is the result of [`compute_drb_result`](hotshot_types::drb::compute_drb_result).
 ///
-/// This is to be started two epochs in advance and spawned in a non-blocking thread.
+/// The leader is chosen with probability proportional to its stake. To keep the choice identical
+/// on every platform, it is specified as follows:
+/// 1. Compute `h = SHA-256(drb_result || view_number)`, with `view_number` encoded as 8
+///    little-endian bytes.
+/// 2. Interpret `h` as a big-endian 256-bit unsigned integer, and let `target = h mod total_stake`,
+///    where `total_stake` is the sum of the stakes in `stake_table`.
+/// 3. The leader is the first entry of `stake_table` whose cumulative stake, including its own,
+///    exceeds `target`.
 ///
-/// # Arguments
-/// * `drb_seed_input` - Serialized QC signature.
+/// Since `total_stake` is far below `2^256`, the bias introduced by the modulo is negligible.
+///
+/// Returns `None` if the stake table has no stake.
 #[must_use]
-pub fn compute_drb_result<TYPES: NodeType>(drb_seed_input: [u8; 32]) -> [u8; 32] {
-    let mut hash = drb_seed_input.to_vec();
-    for _iter in 0..DIFFICULTY_LEVEL {
-        // TODO: This may be optimized to avoid memcopies after we bench the hash time.
-        // <https://github.com/EspressoSystems/HotShot/issues/3880>
-        hash = Sha256::digest(hash).to_vec();
+pub fn leader<TYPES: NodeType>(
+    view_number: u64,
+    stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+    drb_result: DrbResult,
+) -> Option<TYPES::SignatureKey> {
+    let total_stake = stake_table.iter().fold(U256::zero(), |total, entry| {
+        total.saturating_add(entry.stake())
+    });
+    if total_stake.is_zero() {
+        return None;
     }
 
-    // Convert the hash to the DRB result.
-    let mut drb_result = [0u8; 32];
-    drb_result.copy_from_slice(&hash);
-    drb_result
+    let mut hasher = Sha256::new();
+    hasher.update(drb_result);
+    hasher.update(view_number.to_le_bytes());
+    let target = U256::from_big_endian(&hasher.finalize()) % total_stake;
+
+    let mut cumulative_stake = U256::zero();
+    stake_table
+        .iter()
+        .find(|entry| {
+            cumulative_stake = cumulative_stake.saturating_add(entry.stake());
+            cumulative_stake > target
+        })
+        .map(TYPES::SignatureKey::public_key)
 }
 
-/// Use the DRB result to get the leader.
-///
-/// The DRB result is the output of a spawned `compute_drb_result` call.
-#[must_use]
-pub fn leader<TYPES: NodeType>(
-    view_number: usize,
-    stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
-    drb_result: [u8; 32],
-) -> TYPES::SignatureKey {
-    let mut hasher = DefaultHasher::new();
-    drb_result.hash(&mut hasher);
-    view_number.hash(&mut hasher);
-    #[allow(clippy::cast_possible_truncation)]
-    // TODO: Use the total stake rather than `len()` and update the indexing after switching to
-    // a weighted stake table.
-    // <https://github.com/EspressoSystems/HotShot/issues/3898>
-    let index = (hasher.finish() as usize) % stake_table.len();
-    let entry = stake_table[index].clone();
-    TYPES::SignatureKey::public_key(&entry)
+/// Committee election with stake-weighted leaders, chosen by [`leader`] from the DRB result of
+/// each epoch.
+#[derive(Clone, Debug)]
+pub struct DynamicCommittee<T: NodeType> {
+    /// The nodes eligible for leadership.
+    /// NOTE: This is currently a hack because the DA leader needs to be the quorum
+    /// leader but without voting rights.
+    eligible_leaders: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The nodes on the committee and their stake
+    stake_table: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The nodes on the committee and their stake, indexed by public key
+    indexed_stake_table:
+        BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The DRB result of each epoch, shared between all clones of the committee
+    drb_results: Arc<RwLock<BTreeMap<T::Epoch, DrbResult>>>,
+
+    /// The network topic of the committee
+    committee_topic: Topic,
+}
+
+impl<T: NodeType> DynamicCommittee<T> {
+    /// The DRB result used to choose the leaders of `epoch`. The first epochs, which come before
+    /// any DRB result could be computed, use [`INITIAL_DRB_RESULT`].
+    ///
+    /// # Errors
+    /// If `epoch` comes after the first epochs and its DRB result has not been recorded
+    pub fn drb_result(&self, epoch: T::Epoch) -> Result<DrbResult> {
+        if let Some(drb_result) = self.drb_results.read().get(&epoch) {
+            return Ok(*drb_result);
+        }
+        ensure!(
+            epoch.u64() <= INITIAL_DRB_RESULT_EPOCHS,
+            error!("No DRB result for epoch {epoch}")
+        );
+        Ok(INITIAL_DRB_RESULT)
+    }
+}
+
+impl<TYPES: NodeType> Membership<TYPES> for DynamicCommittee<TYPES> {
+    type Error = utils::anytrace::Error;
+
+    /// Create a new election
+    fn new(
+        eligible_leaders: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+        committee_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+        committee_topic: Topic,
+    ) -> Self {
+        // For each eligible leader, get the stake table entry
+        let eligible_leaders: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> =
+            eligible_leaders
+                .iter()
+                .map(|member| member.stake_table_entry.clone())
+                .filter(|entry| entry.stake() > U256::zero())
+                .collect();
+
+        // For each member, get the stake table entry
+        let members: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> =
+            committee_members
+                .iter()
+                .map(|member| member.stake_table_entry.clone())
+                .filter(|entry| entry.stake() > U256::zero())
+                .collect();
+
+        // Index the stake table by public key
+        let indexed_stake_table: BTreeMap<
+            TYPES::SignatureKey,
+            <TYPES::SignatureKey as SignatureKey>::StakeTableEntry,
+        > = members
+            .iter()
+            .map(|entry| (TYPES::SignatureKey::public_key(entry), entry.clone()))
+            .collect();
+
+        Self {
+            eligible_leaders,
+            stake_table: members,
+            indexed_stake_table,
+            drb_results: Arc::default(),
+            committee_topic,
+        }
+    }
+
+    /// Get the stake table for the current view
+    fn stake_table(
+        &self,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.stake_table.clone()
+    }
+
+    /// Get all members of the committee for the current view
+    fn committee_members(
+        &self,
+        _view_number: <TYPES as NodeType>::View,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.stake_table
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .collect()
+    }
+
+    /// Get all eligible leaders of the committee for the current view
+    fn committee_leaders(
+        &self,
+        _view_number: <TYPES as NodeType>::View,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.eligible_leaders
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .collect()
+    }
+
+    /// Get the stake table entry for a public key
+    fn stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.indexed_stake_table.get(pub_key).cloned()
+    }
+
+    /// Check if a node has stake in the committee
+    fn has_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> bool {
+        self.indexed_stake_table
+            .get(pub_key)
+            .is_some_and(|x| x.stake() > U256::zero())
+    }
+
+    /// Get the network topic for the committee
+    fn committee_topic(&self) -> Topic {
+        self.committee_topic.clone()
+    }
+
+    /// Choose the leader of the view, weighted by stake, from the DRB result of the epoch
+    fn lookup_leader(
+        &self,
+        view_number: TYPES::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Result<TYPES::SignatureKey> {
+        leader::<TYPES>(
+            *view_number,
+            &self.eligible_leaders,
+            self.drb_result(epoch)?,
+        )
+        .context(error!("No eligible leader has any stake"))
+    }
+
+    /// Get the total number of nodes in the committee
+    fn total_nodes(&self, _epoch: <TYPES as NodeType>::Epoch) -> usize {
+        self.stake_table.len()
+    }
+
+    /// Get the voting success threshold for the committee
+    fn success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        ((self.total_stake(epoch) * 2) / 3) + 1
+    }
+
+    /// Get the voting failure threshold for the committee
+    fn failure_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        (self.total_stake(epoch) / 3) + 1
+    }
+
+    /// Get the voting upgrade threshold for the committee
+    fn upgrade_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        let total_stake = self.total_stake(epoch);
+        max((total_stake * 9) / 10, ((total_stake * 2) / 3) + 1)
+    }
+
+    /// Record the DRB result used to choose the leaders of `epoch`
+    fn add_drb_result(&self, epoch: TYPES::Epoch, drb_result: DrbResult) {
+        self.drb_results.write().insert(epoch, drb_result);
+    }
 }
@@ -0,0 +1,273 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    cmp::{max, Reverse},
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use hotshot_types::{
+    traits::{
+        election::Membership,
+        network::Topic,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+    PeerConfig,
+};
+use parking_lot::RwLock;
+use primitive_types::U256;
+use utils::anytrace::*;
+
+/// The committee of a single epoch
+#[derive(Debug)]
+struct EpochStakeTable<T: NodeType> {
+    /// The nodes eligible for leadership.
+    /// NOTE: This is currently a hack because the DA leader needs to be the quorum
+    /// leader but without voting rights.
+    eligible_leaders: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The nodes on the committee and their stake
+    stake_table: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The nodes on the committee and their stake, indexed by public key
+    indexed_stake_table:
+        BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+}
+
+impl<T: NodeType> EpochStakeTable<T> {
+    /// Build the committee of an epoch, leaving out the nodes without stake
+    fn new(
+        eligible_leaders: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+        members: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+    ) -> Self {
+        let eligible_leaders: Vec<_> = eligible_leaders
+            .into_iter()
+            .filter(|entry| entry.stake() > U256::zero())
+            .collect();
+        let stake_table: Vec<_> = members
+            .into_iter()
+            .filter(|entry| entry.stake() > U256::zero())
+            .collect();
+        let indexed_stake_table = stake_table
+            .iter()
+            .map(|entry| (T::SignatureKey::public_key(entry), entry.clone()))
+            .collect();
+
+        Self {
+            eligible_leaders,
+            stake_table,
+            indexed_stake_table,
+        }
+    }
+}
+
+/// A committee whose stake table changes from epoch to epoch.
+///
+/// The stake table set for an epoch stays in effect for the following epochs, until another one
+/// is set with [`Membership::update_stake_table`]. Leaders are chosen round robin among the
+/// eligible leaders of the epoch.
+///
+/// A DA committee (one with [`Topic::Da`]) is drawn from each new stake table as well: it keeps the
+/// size of its genesis committee, and is made up of the validators with the most stake. Its
+/// eligible leaders are all the validators, since the DA leader must be the quorum leader.
+#[derive(Clone, Debug)]
+pub struct EpochCommittee<T: NodeType> {
+    /// The committee of each epoch in which it changed, shared between all clones
+    stake_tables: Arc<RwLock<BTreeMap<T::Epoch, Arc<EpochStakeTable<T>>>>>,
+
+    /// The network topic of the committee
+    committee_topic: Topic,
+
+    /// The number of members of the genesis committee, which a DA committee keeps
+    da_committee_size: usize,
+}
+
+impl<T: NodeType> EpochCommittee<T> {
+    /// The committee in effect in `epoch`
+    fn stake_table_for(&self, epoch: T::Epoch) -> Arc<EpochStakeTable<T>> {
+        let stake_tables = self.stake_tables.read();
+        let (_, stake_table) = stake_tables
+            .range(..=epoch)
+            .next_back()
+            .or_else(|| stake_tables.first_key_value())
+            .expect("The committee always has a genesis stake table");
+        Arc::clone(stake_table)
+    }
+
+    /// The epochs in which the stake table changed, oldest first
+    #[must_use]
+    pub fn stake_table_epochs(&self) -> Vec<T::Epoch> {
+        self.stake_tables.read().keys().copied().collect()
+    }
+}
+
+impl<TYPES: NodeType> Membership<TYPES> for EpochCommittee<TYPES> {
+    type Error = utils::anytrace::Error;
+
+    /// Create a new election, with the given committee from genesis onwards
+    fn new(
+        eligible_leaders: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+        committee_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+        committee_topic: Topic,
+    ) -> Self {
+        let genesis = EpochStakeTable::new(
+            eligible_leaders
+                .into_iter()
+                .map(|member| member.stake_table_entry)
+                .collect(),
+            committee_members
+                .into_iter()
+                .map(|member| member.stake_table_entry)
+                .collect(),
+        );
+        let da_committee_size = genesis.stake_table.len();
+
+        Self {
+            stake_tables: Arc::new(RwLock::new(BTreeMap::from([(
+                TYPES::Epoch::genesis(),
+                Arc::new(genesis),
+            )]))),
+            committee_topic,
+            da_committee_size,
+        }
+    }
+
+    /// Get the stake table for the epoch
+    fn stake_table(
+        &self,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.stake_table_for(epoch).stake_table.clone()
+    }
+
+    /// Get all members of the committee for the epoch
+    fn committee_members(
+        &self,
+        _view_number: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.stake_table_for(epoch)
+            .stake_table
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .collect()
+    }
+
+    /// Get all eligible leaders of the committee for the epoch
+    fn committee_leaders(
+        &self,
+        _view_number: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.stake_table_for(epoch)
+            .eligible_leaders
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .collect()
+    }
+
+    /// Get the stake table entry for a public key in the epoch
+    fn stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.stake_table_for(epoch)
+            .indexed_stake_table
+            .get(pub_key)
+            .cloned()
+    }
+
+    /// Check if a node has stake in the committee in the epoch
+    fn has_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> bool {
+        self.stake_table_for(epoch)
+            .indexed_stake_table
+            .get(pub_key)
+            .is_some_and(|x| x.stake() > U256::zero())
+    }
+
+    /// Get the network topic for the committee
+    fn committee_topic(&self) -> Topic {
+        self.committee_topic.clone()
+    }
+
+    /// Index the eligible leaders of the epoch with the current view number
+    fn lookup_leader(
+        &self,
+        view_number: TYPES::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Result<TYPES::SignatureKey> {
+        let stake_table = self.stake_table_for(epoch);
+        ensure!(
+            !stake_table.eligible_leaders.is_empty(),
+            "No eligible leaders in epoch {}",
+            epoch
+        );
+        #[allow(clippy::cast_possible_truncation)]
+        let index = *view_number as usize % stake_table.eligible_leaders.len();
+        Ok(TYPES::SignatureKey::public_key(
+            &stake_table.eligible_leaders[index],
+        ))
+    }
+
+    /// Get the total number of nodes in the committee in the epoch
+    fn total_nodes(&self, epoch: <TYPES as NodeType>::Epoch) -> usize {
+        self.stake_table_for(epoch).stake_table.len()
+    }
+
+    /// Get the voting success threshold for the committee
+    fn success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        ((self.total_stake(epoch) * 2) / 3) + 1
+    }
+
+    /// Get the voting failure threshold for the committee
+    fn failure_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        (self.total_stake(epoch) / 3) + 1
+    }
+
+    /// Get the voting upgrade threshold for the committee
+    fn upgrade_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        let total_stake = self.total_stake(epoch);
+        max((total_stake * 9) / 10, ((total_stake * 2) / 3) + 1)
+    }
+
+    /// Set the committee from `epoch` onwards
+    fn update_stake_table(
+        &self,
+        epoch: <TYPES as NodeType>::Epoch,
+        stake_table: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+    ) {
+        let eligible_leaders: Vec<_> = stake_table
+            .into_iter()
+            .map(|member| member.stake_table_entry)
+            .collect();
+        let members = if self.committee_topic == Topic::Da {
+            // Ties go to the earlier entry of the stake table, so every node picks the same members
+            let mut members = eligible_leaders.clone();
+            members.sort_by_key(|entry| Reverse(entry.stake()));
+            members.truncate(self.da_committee_size);
+            members
+        } else {
+            eligible_leaders.clone()
+        };
+
+        tracing::info!(
+            "Updating the {:?} committee from epoch {}",
+            self.committee_topic,
+            epoch
+        );
+        self.stake_tables.write().insert(
+            epoch,
+            Arc::new(EpochStakeTable::new(eligible_leaders, members)),
+        );
+    }
+}
@@ -4,7 +4,7 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{cmp::max, collections::BTreeMap, num::NonZeroU64};
+use std::{cmp::max, collections::BTreeMap};
 
 use hotshot_types::{
     traits::{
@@ -156,21 +156,18 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
     }
 
     /// Get the voting success threshold for the committee
-    fn success_threshold(&self) -> NonZeroU64 {
-        NonZeroU64::new(((self.stake_table.len() as u64 * 2) / 3) + 1).unwrap()
+    fn success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        ((self.total_stake(epoch) * 2) / 3) + 1
     }
 
     /// Get the voting failure threshold for the committee
-    fn failure_threshold(&self) -> NonZeroU64 {
-        NonZeroU64::new(((self.stake_table.len() as u64) / 3) + 1).unwrap()
+    fn failure_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        (self.total_stake(epoch) / 3) + 1
     }
 
     /// Get the voting upgrade threshold for the committee
-    fn upgrade_threshold(&self) -> NonZeroU64 {
-        NonZeroU64::new(max(
-            (self.stake_table.len() as u64 * 9) / 10,
-            ((self.stake_table.len() as u64 * 2) / 3) + 1,
-        ))
-        .unwrap()
+    fn upgrade_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        let total_stake = self.total_stake(epoch);
+        max((total_stake * 9) / 10, ((total_stake * 2) / 3) + 1)
     }
 }
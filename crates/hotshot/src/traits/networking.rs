@@ -14,6 +14,8 @@
 pub mod combined_network;
 pub mod libp2p_network;
 pub mod memory_network;
+/// Tracking of misbehaving peers, shared by the network implementations
+pub mod misbehaviour;
 /// The Push CDN network
 pub mod push_cdn_network;
 
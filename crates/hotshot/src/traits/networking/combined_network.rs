@@ -34,7 +34,7 @@ use hotshot_types::{
     },
     data::ViewNumber,
     traits::{
-        network::{BroadcastDelay, ConnectedNetwork, Topic},
+        network::{AuthenticatedPeer, BroadcastDelay, ConnectedNetwork, Misbehaviour, Topic},
         node_implementation::NodeType,
     },
     BoxSyncFuture,
@@ -440,18 +440,21 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         self.networks.0.vid_broadcast_message(messages).await
     }
 
-    /// Receive one or many messages from the underlying network.
+    /// Receive one or many messages from the underlying network, along with the peer which
+    /// delivered it on whichever network it arrived first.
     ///
     /// # Errors
     /// Does not error
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+    async fn recv_message_from(
+        &self,
+    ) -> Result<(Vec<u8>, Option<AuthenticatedPeer<TYPES::SignatureKey>>), NetworkError> {
         loop {
             // Receive from both networks
-            let mut primary_fut = self.primary().recv_message().fuse();
-            let mut secondary_fut = self.secondary().recv_message().fuse();
+            let mut primary_fut = self.primary().recv_message_from().fuse();
+            let mut secondary_fut = self.secondary().recv_message_from().fuse();
 
             // Wait for one to return a message
-            let message = select! {
+            let (message, peer) = select! {
                 p = primary_fut => p?,
                 s = secondary_fut => s?,
             };
@@ -461,7 +464,7 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
 
             // Check if the hash is in the cache and update the cache
             if self.message_cache.write().put(message_hash, ()).is_none() {
-                break Ok(message);
+                break Ok((message, peer));
             }
         }
     }
@@ -475,6 +478,20 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         self.secondary().queue_node_lookup(view_number, pk)
     }
 
+    /// Each network ignores peers it did not hand out itself
+    fn report_peer(
+        &self,
+        peer: &AuthenticatedPeer<TYPES::SignatureKey>,
+        misbehaviour: Misbehaviour,
+    ) {
+        self.primary().report_peer(peer, misbehaviour);
+        self.secondary().report_peer(peer, misbehaviour);
+    }
+
+    fn is_peer_banned(&self, peer: &AuthenticatedPeer<TYPES::SignatureKey>) -> bool {
+        self.primary().is_peer_banned(peer) || self.secondary().is_peer_banned(peer)
+    }
+
     async fn update_view<'a, T>(&'a self, view: u64, epoch: u64, membership: &T::Membership)
     where
         T: NodeType<SignatureKey = TYPES::SignatureKey> + 'a,
@@ -505,4 +522,9 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
     fn is_primary_down(&self) -> bool {
         self.primary_down.load(Ordering::Relaxed)
     }
+
+    /// Ready once both networks are, like [`wait_for_ready`](Self::wait_for_ready)
+    fn is_ready(&self) -> bool {
+        self.primary().is_ready() && self.secondary().is_ready()
+    }
 }
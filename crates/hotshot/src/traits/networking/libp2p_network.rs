@@ -35,11 +35,14 @@ use hotshot_types::{
     boxed_sync,
     constants::LOOK_AHEAD,
     data::ViewNumber,
-    network::NetworkConfig,
+    network::{Libp2pTransport, NetworkConfig},
     traits::{
         election::Membership,
         metrics::{Counter, Gauge, Metrics, NoMetrics},
-        network::{ConnectedNetwork, NetworkError, Topic},
+        network::{
+            AuthenticatedPeer, ConnectedNetwork, Misbehaviour, NetworkError, Topic,
+            MISBEHAVIOUR_BAN_THRESHOLD,
+        },
         node_implementation::{ConsensusTime, NodeType},
         signature_key::{PrivateSignatureKey, SignatureKey},
     },
@@ -55,6 +58,7 @@ use libp2p_networking::{
         behaviours::dht::record::{Namespace, RecordKey, RecordValue},
         spawn_network_node,
         transport::construct_auth_message,
+        BandwidthMetricsValue,
         NetworkEvent::{self, DirectRequest, DirectResponse, GossipMsg},
         NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeHandle, NetworkNodeReceiver,
         DEFAULT_REPLICATION_FACTOR,
@@ -71,8 +75,9 @@ use tokio::{
     },
     time::sleep,
 };
-use tracing::{error, info, instrument, trace, warn};
+use tracing::{debug, error, info, instrument, trace, warn};
 
+use super::misbehaviour::MisbehaviourTracker;
 use crate::BroadcastDelay;
 
 /// Libp2p-specific metrics
@@ -84,6 +89,8 @@ pub struct Libp2pMetricsValue {
     pub num_failed_messages: Box<dyn Counter>,
     /// Whether or not the network is considered ready
     pub is_ready: Box<dyn Gauge>,
+    /// Per-peer traffic and rate limiting metrics
+    pub bandwidth: BandwidthMetricsValue,
 }
 
 impl Libp2pMetricsValue {
@@ -97,6 +104,7 @@ impl Libp2pMetricsValue {
             num_connected_peers: subgroup.create_gauge("num_connected_peers".into(), None),
             num_failed_messages: subgroup.create_counter("num_failed_messages".into(), None),
             is_ready: subgroup.create_gauge("is_ready".into(), None),
+            bandwidth: BandwidthMetricsValue::new(&*subgroup),
         }
     }
 }
@@ -140,6 +148,10 @@ impl<T: NodeType> Debug for Libp2pNetwork<T> {
 pub type PeerInfoVec = Arc<RwLock<Vec<(PeerId, Multiaddr)>>>;
 
 /// The underlying state of the libp2p network
+/// A received message, along with the peer which authored it (gossip) or sent it (direct
+/// messages). The peer is `None` for messages we sent to ourselves, and for unsigned gossip.
+type ReceivedMessage = (Vec<u8>, Option<PeerId>);
+
 #[derive(Debug)]
 struct Libp2pNetworkInner<T: NodeType> {
     /// this node's public key
@@ -147,9 +159,9 @@ struct Libp2pNetworkInner<T: NodeType> {
     /// handle to control the network
     handle: Arc<NetworkNodeHandle<T>>,
     /// Message Receiver
-    receiver: Mutex<Receiver<Vec<u8>>>,
+    receiver: Mutex<Receiver<ReceivedMessage>>,
     /// Sender for broadcast messages
-    sender: Sender<Vec<u8>>,
+    sender: Sender<ReceivedMessage>,
     /// Sender for node lookup (relevant view number, key of node) (None for shutdown)
     node_lookup_send: Sender<Option<(ViewNumber, T::SignatureKey)>>,
     /// this is really cheating to enable local tests
@@ -174,6 +186,8 @@ struct Libp2pNetworkInner<T: NodeType> {
     reliability_config: Option<Box<dyn NetworkReliability>>,
     /// Killswitch sender
     kill_switch: Sender<()>,
+    /// Peers which have been reported for misbehaving
+    misbehaviour: MisbehaviourTracker<PeerId>,
 }
 
 /// Networking implementation that uses libp2p
@@ -330,7 +344,7 @@ pub fn derive_libp2p_peer_id<K: SignatureKey>(
 }
 
 /// Parse a Libp2p Multiaddr from a string. The input string should be in the format
-/// `hostname:port` or `ip:port`. This function derives a `Multiaddr` from the input string.
+/// `hostname:port` or `ip:port`. This function derives a QUIC `Multiaddr` from the input string.
 ///
 /// This borrows from Rust's implementation of `to_socket_addrs` but will only warn if the domain
 /// does not yet resolve.
@@ -338,6 +352,18 @@ pub fn derive_libp2p_peer_id<K: SignatureKey>(
 /// # Errors
 /// - If the input string is not in the correct format
 pub fn derive_libp2p_multiaddr(addr: &String) -> anyhow::Result<Multiaddr> {
+    derive_libp2p_multiaddr_with_transport(addr, Libp2pTransport::Quic)
+}
+
+/// Parse a Libp2p Multiaddr from a string, like [`derive_libp2p_multiaddr`], but for the given
+/// `transport`.
+///
+/// # Errors
+/// - If the input string is not in the correct format
+pub fn derive_libp2p_multiaddr_with_transport(
+    addr: &String,
+    transport: Libp2pTransport,
+) -> anyhow::Result<Multiaddr> {
     // Split the address into the host and port parts
     let (host, port) = match addr.rfind(':') {
         Some(idx) => (&addr[..idx], &addr[idx + 1..]),
@@ -347,10 +373,16 @@ pub fn derive_libp2p_multiaddr(addr: &String) -> anyhow::Result<Multiaddr> {
     // Try parsing the host as an IP address
     let ip = host.parse::<IpAddr>();
 
+    // The transport-specific part of the address
+    let suffix = match transport {
+        Libp2pTransport::Quic => format!("udp/{port}/quic-v1"),
+        Libp2pTransport::Tcp => format!("tcp/{port}"),
+    };
+
     // Conditionally build the multiaddr string
     let multiaddr_string = match ip {
-        Ok(IpAddr::V4(ip)) => format!("/ip4/{ip}/udp/{port}/quic-v1"),
-        Ok(IpAddr::V6(ip)) => format!("/ip6/{ip}/udp/{port}/quic-v1"),
+        Ok(IpAddr::V4(ip)) => format!("/ip4/{ip}/{suffix}"),
+        Ok(IpAddr::V6(ip)) => format!("/ip6/{ip}/{suffix}"),
         Err(_) => {
             // Try resolving the host. If it fails, continue but warn the user
             let lookup_result = addr.to_socket_addrs();
@@ -368,7 +400,7 @@ pub fn derive_libp2p_multiaddr(addr: &String) -> anyhow::Result<Multiaddr> {
                 );
             }
 
-            format!("/dns/{host}/udp/{port}/quic-v1")
+            format!("/dns/{host}/{suffix}")
         }
     };
 
@@ -414,6 +446,9 @@ impl<T: NodeType> Libp2pNetwork<T> {
         config_builder.gossip_config(gossip_config.clone());
         config_builder.request_response_config(request_response_config);
 
+        // Set the transport, which the bind address and bootstrap nodes must match
+        config_builder.transport(libp2p_config.transport);
+
         // Construct the auth message
         let auth_message =
             construct_auth_message(pub_key, &keypair.public().to_peer_id(), priv_key)
@@ -511,13 +546,16 @@ impl<T: NodeType> Libp2pNetwork<T> {
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
         metrics: Libp2pMetricsValue,
-        config: NetworkNodeConfig<T>,
+        mut config: NetworkNodeConfig<T>,
         pk: T::SignatureKey,
         lookup_record_value: RecordValue<T::SignatureKey>,
         bootstrap_addrs: BootstrapAddrs,
         id: usize,
         #[cfg(feature = "hotshot-testing")] reliability_config: Option<Box<dyn NetworkReliability>>,
     ) -> Result<Libp2pNetwork<T>, NetworkError> {
+        // Report per-peer traffic alongside the rest of our metrics
+        config.bandwidth_metrics = metrics.bandwidth.clone();
+
         let (mut rx, network_handle) = spawn_network_node::<T>(config.clone(), id)
             .await
             .map_err(|e| NetworkError::ConfigError(format!("failed to spawn network node: {e}")))?;
@@ -561,6 +599,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
                 #[cfg(feature = "hotshot-testing")]
                 reliability_config,
                 kill_switch: kill_tx,
+                misbehaviour: MisbehaviourTracker::default(),
             }),
         };
 
@@ -664,16 +703,16 @@ impl<T: NodeType> Libp2pNetwork<T> {
     fn handle_recvd_events(
         &self,
         msg: NetworkEvent,
-        sender: &Sender<Vec<u8>>,
+        sender: &Sender<ReceivedMessage>,
     ) -> Result<(), NetworkError> {
         match msg {
-            GossipMsg(msg) => {
-                sender.try_send(msg).map_err(|err| {
+            GossipMsg(msg, source) => {
+                sender.try_send((msg, source)).map_err(|err| {
                     NetworkError::ChannelSendError(format!("failed to send gossip message: {err}"))
                 })?;
             }
-            DirectRequest(msg, _pid, chan) => {
-                sender.try_send(msg).map_err(|err| {
+            DirectRequest(msg, pid, chan) => {
+                sender.try_send((msg, Some(pid))).map_err(|err| {
                     NetworkError::ChannelSendError(format!(
                         "failed to send direct request message: {err}"
                     ))
@@ -705,7 +744,11 @@ impl<T: NodeType> Libp2pNetwork<T> {
 
     /// task to propagate messages to handlers
     /// terminates on shut down of network
-    fn handle_event_generator(&self, sender: Sender<Vec<u8>>, mut network_rx: NetworkNodeReceiver) {
+    fn handle_event_generator(
+        &self,
+        sender: Sender<ReceivedMessage>,
+        mut network_rx: NetworkNodeReceiver,
+    ) {
         let handle = self.clone();
         let is_bootstrapped = Arc::clone(&self.inner.is_bootstrapped);
         spawn(async move {
@@ -728,7 +771,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
                             NetworkEvent::IsBootstrapped => {
                                 is_bootstrapped.store(true, Ordering::Relaxed);
                             }
-                            GossipMsg(_) | DirectRequest(_, _, _) | DirectResponse(_, _) => {
+                            GossipMsg(_, _) | DirectRequest(_, _, _) | DirectResponse(_, _) => {
                                 let _ = handle.handle_recvd_events(message, &sender);
                             }
                             NetworkEvent::ConnectedPeersUpdate(num_peers) => {
@@ -793,10 +836,13 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
         let topic = topic.to_string();
         if self.inner.subscribed_topics.contains(&topic) {
             // Short-circuit-send the message to ourselves
-            self.inner.sender.try_send(message.clone()).map_err(|_| {
-                self.inner.metrics.num_failed_messages.add(1);
-                NetworkError::ShutDown
-            })?;
+            self.inner
+                .sender
+                .try_send((message.clone(), None))
+                .map_err(|_| {
+                    self.inner.metrics.num_failed_messages.add(1);
+                    NetworkError::ShutDown
+                })?;
         }
 
         // NOTE: metrics is threadsafe, so clone is fine (and lightweight)
@@ -881,7 +927,7 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
         // short circuit if we're dming ourselves
         if recipient == self.inner.pk {
             // panic if we already shut down?
-            self.inner.sender.try_send(message).map_err(|_x| {
+            self.inner.sender.try_send((message, None)).map_err(|_x| {
                 self.inner.metrics.num_failed_messages.add(1);
                 NetworkError::ShutDown
             })?;
@@ -903,6 +949,14 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             }
         };
 
+        // Don't talk to banned peers
+        if self.inner.misbehaviour.is_banned(&pid) {
+            self.inner.metrics.num_failed_messages.add(1);
+            return Err(NetworkError::MessageSendError(
+                "recipient is banned".to_string(),
+            ));
+        }
+
         #[cfg(feature = "hotshot-testing")]
         {
             let metrics = self.inner.metrics.clone();
@@ -940,9 +994,11 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
     ///
     /// # Errors
     /// If there is a network-related failure.
-    #[instrument(name = "Libp2pNetwork::recv_message", skip_all)]
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
-        let result = self
+    #[instrument(name = "Libp2pNetwork::recv_message_from", skip_all)]
+    async fn recv_message_from(
+        &self,
+    ) -> Result<(Vec<u8>, Option<AuthenticatedPeer<T::SignatureKey>>), NetworkError> {
+        let (message, source) = self
             .inner
             .receiver
             .lock()
@@ -951,7 +1007,7 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             .await
             .ok_or(NetworkError::ShutDown)?;
 
-        Ok(result)
+        Ok((message, source.map(AuthenticatedPeer::Libp2p)))
     }
 
     #[instrument(name = "Libp2pNetwork::queue_node_lookup", skip_all)]
@@ -966,6 +1022,47 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             .try_send(Some((view_number, pk)))
     }
 
+    /// Lower the peer's gossipsub score in proportion to its penalty, and ban it at the libp2p
+    /// level once the penalty reaches [`MISBEHAVIOUR_BAN_THRESHOLD`]
+    fn report_peer(&self, peer: &AuthenticatedPeer<T::SignatureKey>, misbehaviour: Misbehaviour) {
+        // We only ever hand out libp2p peers from `recv_message_from`
+        let AuthenticatedPeer::Libp2p(pid) = peer else {
+            return debug!(?peer, "Ignoring report of a peer not known to libp2p");
+        };
+
+        let report = self.inner.misbehaviour.report(pid, misbehaviour);
+        debug!(
+            ?pid,
+            ?misbehaviour,
+            penalty = report.penalty,
+            "Peer misbehaved"
+        );
+
+        // With the default application score weight, a peer about to be banned drops below
+        // the gossip threshold
+        let score = -report.penalty / MISBEHAVIOUR_BAN_THRESHOLD;
+        if let Err(err) = self.inner.handle.set_peer_score(*pid, score) {
+            warn!("Failed to set score of peer {:?}: {}", pid, err);
+        }
+        if let Some(duration) = report.new_ban {
+            warn!(?pid, ?duration, "Banning misbehaving peer");
+            if let Err(err) = self.inner.handle.ban_peer(*pid, duration) {
+                warn!("Failed to ban peer {:?}: {}", pid, err);
+            }
+        }
+    }
+
+    fn is_peer_banned(&self, peer: &AuthenticatedPeer<T::SignatureKey>) -> bool {
+        match peer {
+            AuthenticatedPeer::Libp2p(pid) => self.inner.misbehaviour.is_banned(pid),
+            AuthenticatedPeer::Key(_) => false,
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        Libp2pNetwork::is_ready(self)
+    }
+
     /// The libp2p view update is a special operation intrinsic to its internal behavior.
     ///
     /// Libp2p needs to do a lookup because a libp2p address is not releated to
@@ -25,8 +25,8 @@ use hotshot_types::{
     boxed_sync,
     traits::{
         network::{
-            AsyncGenerator, BroadcastDelay, ConnectedNetwork, TestableNetworkingImplementation,
-            Topic,
+            AsyncGenerator, AuthenticatedPeer, BroadcastDelay, ConnectedNetwork, Misbehaviour,
+            TestableNetworkingImplementation, Topic,
         },
         node_implementation::NodeType,
         signature_key::SignatureKey,
@@ -39,7 +39,7 @@ use tokio::{
 };
 use tracing::{debug, error, info, info_span, instrument, trace, warn, Instrument};
 
-use super::{NetworkError, NetworkReliability};
+use super::{misbehaviour::MisbehaviourTracker, NetworkError, NetworkReliability};
 
 /// Shared state for in-memory mock networking.
 ///
@@ -69,10 +69,12 @@ impl<K: SignatureKey> MasterMap<K> {
 /// Internal state for a `MemoryNetwork` instance
 #[derive(Debug)]
 struct MemoryNetworkInner<K: SignatureKey> {
-    /// Input for messages
-    input: RwLock<Option<Sender<Vec<u8>>>>,
-    /// Output for messages
-    output: Mutex<Receiver<Vec<u8>>>,
+    /// Our public key, which the nodes we send messages to see as the sender
+    pub_key: K,
+    /// Input for messages, along with their sender
+    input: RwLock<Option<Sender<(Vec<u8>, K)>>>,
+    /// Output for messages, along with their sender
+    output: Mutex<Receiver<(Vec<u8>, K)>>,
     /// The master map
     master_map: Arc<MasterMap<K>>,
 
@@ -81,6 +83,10 @@ struct MemoryNetworkInner<K: SignatureKey> {
 
     /// config to introduce unreliability to the network
     reliability_config: Option<Box<dyn NetworkReliability>>,
+
+    /// Peers which have been reported for misbehaving, and which we no longer send messages to
+    /// while they are banned
+    misbehaviour: MisbehaviourTracker<K>,
 }
 
 /// In memory only network simulator.
@@ -140,11 +146,13 @@ impl<K: SignatureKey> MemoryNetwork<K> {
         trace!("Task spawned, creating MemoryNetwork");
         let mn = MemoryNetwork {
             inner: Arc::new(MemoryNetworkInner {
+                pub_key: pub_key.clone(),
                 input: RwLock::new(Some(input)),
                 output: Mutex::new(output),
                 master_map: Arc::clone(master_map),
                 in_flight_message_count,
                 reliability_config,
+                misbehaviour: MisbehaviourTracker::default(),
             }),
         };
         // Insert our public key into the master map
@@ -161,16 +169,16 @@ impl<K: SignatureKey> MemoryNetwork<K> {
         mn
     }
 
-    /// Send a [`Vec<u8>`] message to the inner `input`
-    async fn input(&self, message: Vec<u8>) -> Result<(), SendError<Vec<u8>>> {
+    /// Send a [`Vec<u8>`] message from `sender` to the inner `input`
+    async fn input(&self, message: Vec<u8>, sender: K) -> Result<(), SendError<(Vec<u8>, K)>> {
         self.inner
             .in_flight_message_count
             .fetch_add(1, Ordering::Relaxed);
         let input = self.inner.input.read().await;
         if let Some(input) = &*input {
-            input.send(message).await
+            input.send((message, sender)).await
         } else {
-            Err(SendError(message))
+            Err(SendError((message, sender)))
         }
     }
 }
@@ -222,6 +230,11 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
     #[instrument(name = "MemoryNetwork::ready_blocking")]
     async fn wait_for_ready(&self) {}
 
+    /// The memory network is ready as soon as it is created
+    fn is_ready(&self) -> bool {
+        true
+    }
+
     fn pause(&self) {
         unimplemented!("Pausing not implemented for the Memory network");
     }
@@ -260,16 +273,22 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         {
             // TODO delay/drop etc here
             let (key, node) = node;
+            if self.inner.misbehaviour.is_banned(key) {
+                trace!(?key, "Not sending message to banned node");
+                continue;
+            }
             trace!(?key, "Sending message to node");
             if let Some(ref config) = &self.inner.reliability_config {
                 {
                     let node2 = node.clone();
+                    let sender = self.inner.pub_key.clone();
                     let fut = config.chaos_send_msg(
                         message.clone(),
                         Arc::new(move |msg: Vec<u8>| {
                             let node3 = (node2).clone();
+                            let sender2 = sender.clone();
                             boxed_sync(async move {
-                                let _res = node3.input(msg).await;
+                                let _res = node3.input(msg, sender2).await;
                                 // NOTE we're dropping metrics here but this is only for testing
                                 // purposes. I think that should be okay
                             })
@@ -278,7 +297,9 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                     spawn(fut);
                 }
             } else {
-                let res = node.input(message.clone()).await;
+                let res = node
+                    .input(message.clone(), self.inner.pub_key.clone())
+                    .await;
                 match res {
                     Ok(()) => {
                         trace!(?key, "Delivered message to remote");
@@ -320,16 +341,23 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         // debug!(?message, ?recipient, "Sending direct message");
         // Bincode the message
         trace!("Message bincoded, finding recipient");
+        if self.inner.misbehaviour.is_banned(&recipient) {
+            return Err(NetworkError::MessageSendError(
+                "recipient is banned".to_string(),
+            ));
+        }
         if let Some(node) = self.inner.master_map.map.get(&recipient) {
             let node = node.value().clone();
             if let Some(ref config) = &self.inner.reliability_config {
                 {
+                    let sender = self.inner.pub_key.clone();
                     let fut = config.chaos_send_msg(
                         message.clone(),
                         Arc::new(move |msg: Vec<u8>| {
                             let node2 = node.clone();
+                            let sender2 = sender.clone();
                             boxed_sync(async move {
-                                let _res = node2.input(msg).await;
+                                let _res = node2.input(msg, sender2).await;
                                 // NOTE we're dropping metrics here but this is only for testing
                                 // purposes. I think that should be okay
                             })
@@ -339,7 +367,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                 }
                 Ok(())
             } else {
-                let res = node.input(message).await;
+                let res = node.input(message, self.inner.pub_key.clone()).await;
                 match res {
                     Ok(()) => {
                         trace!(?recipient, "Delivered message to remote");
@@ -357,13 +385,42 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         }
     }
 
-    /// Receive one or many messages from the underlying network.
+    fn report_peer(&self, peer: &AuthenticatedPeer<K>, misbehaviour: Misbehaviour) {
+        // We only ever hand out keys from `recv_message_from`
+        let AuthenticatedPeer::Key(key) = peer else {
+            return debug!(?peer, "Ignoring report of a peer not in the memory network");
+        };
+
+        let report = self.inner.misbehaviour.report(key, misbehaviour);
+        if let Some(duration) = report.new_ban {
+            warn!(?key, ?misbehaviour, ?duration, "Banning misbehaving peer");
+        } else {
+            debug!(
+                ?key,
+                ?misbehaviour,
+                penalty = report.penalty,
+                "Peer misbehaved"
+            );
+        }
+    }
+
+    fn is_peer_banned(&self, peer: &AuthenticatedPeer<K>) -> bool {
+        match peer {
+            AuthenticatedPeer::Key(key) => self.inner.misbehaviour.is_banned(key),
+            AuthenticatedPeer::Libp2p(_) => false,
+        }
+    }
+
+    /// Receive one or many messages from the underlying network, along with the node which sent
+    /// them.
     ///
     /// # Errors
     /// If the other side of the channel is closed
-    #[instrument(name = "MemoryNetwork::recv_messages", skip_all)]
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
-        let ret = self
+    #[instrument(name = "MemoryNetwork::recv_message_from", skip_all)]
+    async fn recv_message_from(
+        &self,
+    ) -> Result<(Vec<u8>, Option<AuthenticatedPeer<K>>), NetworkError> {
+        let (message, sender) = self
             .inner
             .output
             .lock()
@@ -374,6 +431,6 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         self.inner
             .in_flight_message_count
             .fetch_sub(1, Ordering::Relaxed);
-        Ok(ret)
+        Ok((message, Some(AuthenticatedPeer::Key(sender))))
     }
 }
@@ -0,0 +1,178 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Tracking of misbehaving peers
+//!
+//! This module provides [`MisbehaviourTracker`], which the networks use to turn reports from
+//! [`ConnectedNetwork::report_peer`](hotshot_types::traits::network::ConnectedNetwork::report_peer)
+//! into temporary bans.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hotshot_types::traits::network::{Misbehaviour, MISBEHAVIOUR_BAN_THRESHOLD};
+use parking_lot::Mutex;
+
+/// How long it takes for a peer's penalty to halve
+const PENALTY_HALF_LIFE: Duration = Duration::from_secs(5 * 60);
+
+/// How long a peer stays banned
+const BAN_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// The standing of a single peer
+#[derive(Debug, Clone, Copy)]
+struct Standing {
+    /// The total penalty of the peer, as of `updated`
+    penalty: f64,
+    /// When `penalty` was last updated
+    updated: Instant,
+    /// When the current ban of the peer ends, if it is banned
+    banned_until: Option<Instant>,
+}
+
+impl Standing {
+    /// The penalty of the peer at `now`, after decay
+    fn penalty_at(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.penalty * 0.5_f64.powf(elapsed / PENALTY_HALF_LIFE.as_secs_f64())
+    }
+}
+
+/// The result of reporting a peer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Report {
+    /// The total penalty of the peer, including this report
+    pub penalty: f64,
+    /// If this report got the peer banned, how long the ban lasts
+    pub new_ban: Option<Duration>,
+}
+
+/// Keeps track of the penalties of misbehaving peers, and bans peers whose penalties add up to
+/// [`MISBEHAVIOUR_BAN_THRESHOLD`]. Penalties decay over time, and bans are lifted after a while.
+#[derive(Debug)]
+pub struct MisbehaviourTracker<K> {
+    /// The standing of each peer which has been reported
+    standings: Arc<Mutex<HashMap<K, Standing>>>,
+}
+
+impl<K> Clone for MisbehaviourTracker<K> {
+    fn clone(&self) -> Self {
+        Self {
+            standings: Arc::clone(&self.standings),
+        }
+    }
+}
+
+impl<K> Default for MisbehaviourTracker<K> {
+    fn default() -> Self {
+        Self {
+            standings: Arc::default(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> MisbehaviourTracker<K> {
+    /// Record that `key` misbehaved
+    pub fn report(&self, key: &K, misbehaviour: Misbehaviour) -> Report {
+        self.report_at(key, misbehaviour, Instant::now())
+    }
+
+    /// Whether `key` is currently banned
+    pub fn is_banned(&self, key: &K) -> bool {
+        self.is_banned_at(key, Instant::now())
+    }
+
+    /// [`Self::report`] at a given time
+    fn report_at(&self, key: &K, misbehaviour: Misbehaviour, now: Instant) -> Report {
+        let mut standings = self.standings.lock();
+
+        // Forget about peers which have redeemed themselves, so the map doesn't grow forever
+        standings.retain(|_, standing| {
+            standing.banned_until.is_some_and(|until| until > now)
+                || standing.penalty_at(now) >= 1.0
+        });
+
+        let standing = standings.entry(key.clone()).or_insert(Standing {
+            penalty: 0.0,
+            updated: now,
+            banned_until: None,
+        });
+        standing.penalty = standing.penalty_at(now) + misbehaviour.penalty();
+        standing.updated = now;
+
+        let already_banned = standing.banned_until.is_some_and(|until| until > now);
+        let new_ban = if !already_banned && standing.penalty >= MISBEHAVIOUR_BAN_THRESHOLD {
+            standing.banned_until = Some(now + BAN_DURATION);
+            // Start afresh once the ban is over
+            standing.penalty = 0.0;
+            Some(BAN_DURATION)
+        } else {
+            None
+        };
+
+        Report {
+            penalty: standing.penalty,
+            new_ban,
+        }
+    }
+
+    /// [`Self::is_banned`] at a given time
+    fn is_banned_at(&self, key: &K, now: Instant) -> bool {
+        self.standings
+            .lock()
+            .get(key)
+            .and_then(|standing| standing.banned_until)
+            .is_some_and(|until| until > now)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bans_after_repeated_misbehaviour() {
+        let tracker = MisbehaviourTracker::default();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            let report = tracker.report_at(&1, Misbehaviour::InvalidProposal, now);
+            assert!(report.new_ban.is_none());
+            assert!(!tracker.is_banned_at(&1, now));
+        }
+        let report = tracker.report_at(&1, Misbehaviour::InvalidProposal, now);
+        assert_eq!(report.new_ban, Some(BAN_DURATION));
+        assert!(tracker.is_banned_at(&1, now));
+
+        // Other peers are unaffected
+        assert!(!tracker.is_banned_at(&2, now));
+
+        // The ban is lifted eventually
+        assert!(tracker.is_banned_at(&1, now + BAN_DURATION - Duration::from_secs(1)));
+        assert!(!tracker.is_banned_at(&1, now + BAN_DURATION));
+    }
+
+    #[test]
+    fn penalties_decay() {
+        let tracker = MisbehaviourTracker::default();
+        let now = Instant::now();
+
+        // Occasional misbehaviour never adds up to a ban
+        for i in 0..20 {
+            let report = tracker.report_at(
+                &1,
+                Misbehaviour::InvalidProposal,
+                now + PENALTY_HALF_LIFE * i,
+            );
+            assert!(report.new_ban.is_none());
+            assert!(report.penalty < MISBEHAVIOUR_BAN_THRESHOLD);
+        }
+    }
+}
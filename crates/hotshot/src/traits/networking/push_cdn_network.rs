@@ -4,9 +4,13 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-#[cfg(feature = "hotshot-testing")]
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 #[cfg(feature = "hotshot-testing")]
 use std::{path::Path, time::Duration};
 
@@ -38,7 +42,10 @@ use hotshot_types::{
     data::ViewNumber,
     traits::{
         metrics::{Counter, Metrics, NoMetrics},
-        network::{BroadcastDelay, ConnectedNetwork, Topic as HotShotTopic},
+        network::{
+            AuthenticatedPeer, BroadcastDelay, ConnectedNetwork, Misbehaviour,
+            Topic as HotShotTopic,
+        },
         node_implementation::NodeType,
         signature_key::SignatureKey,
     },
@@ -49,9 +56,9 @@ use num_enum::{IntoPrimitive, TryFromPrimitive};
 #[cfg(feature = "hotshot-testing")]
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use tokio::{spawn, sync::mpsc::error::TrySendError, time::sleep};
-use tracing::error;
+use tracing::{debug, error, warn};
 
-use super::NetworkError;
+use super::{misbehaviour::MisbehaviourTracker, NetworkError};
 
 /// CDN-specific metrics
 #[derive(Clone)]
@@ -191,6 +198,10 @@ pub struct PushCdnNetwork<K: SignatureKey + 'static> {
     client: Client<ClientDef<K>>,
     /// The CDN-specific metrics
     metrics: Arc<CdnMetricsValue>,
+    /// Whether we have connected to the CDN
+    is_ready: Arc<AtomicBool>,
+    /// Penalties and bans of the keys reported to us
+    misbehaviour: MisbehaviourTracker<K>,
     /// Whether or not the underlying network is supposed to be paused
     #[cfg(feature = "hotshot-testing")]
     is_paused: Arc<AtomicBool>,
@@ -239,6 +250,8 @@ impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
         Ok(Self {
             client,
             metrics: Arc::from(metrics),
+            is_ready: Arc::default(),
+            misbehaviour: MisbehaviourTracker::default(),
             // Start unpaused
             #[cfg(feature = "hotshot-testing")]
             is_paused: Arc::from(AtomicBool::new(false)),
@@ -434,6 +447,8 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
                     Arc::new(PushCdnNetwork {
                         client: Client::new(client_config),
                         metrics: Arc::new(CdnMetricsValue::default()),
+                        is_ready: Arc::default(),
+                        misbehaviour: MisbehaviourTracker::default(),
                         #[cfg(feature = "hotshot-testing")]
                         is_paused: Arc::from(AtomicBool::new(false)),
                     })
@@ -465,6 +480,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
     /// Wait for the client to initialize the connection
     async fn wait_for_ready(&self) {
         let _ = self.client.ensure_initialized().await;
+        self.is_ready.store(true, Ordering::Relaxed);
     }
 
     /// TODO: shut down the networks. Unneeded for testing.
@@ -533,6 +549,12 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
             return Ok(());
         }
 
+        if self.misbehaviour.is_banned(&recipient) {
+            return Err(NetworkError::MessageSendError(
+                "recipient is banned".to_string(),
+            ));
+        }
+
         // Send the message
         if let Err(e) = self
             .client
@@ -551,9 +573,13 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
     /// Receive a message. Is agnostic over `transmit_type`, which has an issue
     /// to be removed anyway.
     ///
+    /// The CDN does not tell us who sent a message, so there is never a peer to attribute it to.
+    ///
     /// # Errors
     /// - If we fail to receive messages. Will trigger a retry automatically.
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+    async fn recv_message_from(
+        &self,
+    ) -> Result<(Vec<u8>, Option<AuthenticatedPeer<K>>), NetworkError> {
         // Receive a message
         let message = self.client.receive_message().await;
 
@@ -561,7 +587,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         #[cfg(feature = "hotshot-testing")]
         if self.is_paused.load(Ordering::Relaxed) {
             sleep(Duration::from_millis(100)).await;
-            return Ok(vec![]);
+            return Ok((vec![], None));
         }
 
         // If it was an error, wait a bit and retry
@@ -574,6 +600,9 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
             }
         };
 
+        // Getting a message means we're connected, even if nobody waited for us to be
+        self.is_ready.store(true, Ordering::Relaxed);
+
         // Extract the underlying message
         let (PushCdnMessage::Broadcast(Broadcast { message, topics: _ })
         | PushCdnMessage::Direct(Direct {
@@ -581,10 +610,47 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
             recipient: _,
         })) = message
         else {
-            return Ok(vec![]);
+            return Ok((vec![], None));
         };
 
-        Ok(message)
+        Ok((message, None))
+    }
+
+    /// Penalise the key of the peer, and ban it locally once its penalty reaches
+    /// [`MISBEHAVIOUR_BAN_THRESHOLD`](hotshot_types::traits::network::MISBEHAVIOUR_BAN_THRESHOLD).
+    ///
+    /// The broker never tells us who sent a message, so the ban can't be enforced when receiving
+    /// here: it's up to the receiver to check [`is_peer_banned`](Self::is_peer_banned) against
+    /// the key a message names as its sender. We stop sending direct messages to the key.
+    fn report_peer(&self, peer: &AuthenticatedPeer<K>, misbehaviour: Misbehaviour) {
+        let AuthenticatedPeer::Key(key) = peer else {
+            return debug!(?peer, "Ignoring report of a peer not known to the CDN");
+        };
+
+        let report = self.misbehaviour.report(key, misbehaviour);
+        if let Some(duration) = report.new_ban {
+            warn!(?key, ?misbehaviour, ?duration, "Banning misbehaving peer");
+        } else {
+            debug!(
+                ?key,
+                ?misbehaviour,
+                penalty = report.penalty,
+                "Peer misbehaved"
+            );
+        }
+    }
+
+    fn is_peer_banned(&self, peer: &AuthenticatedPeer<K>) -> bool {
+        match peer {
+            AuthenticatedPeer::Key(key) => self.misbehaviour.is_banned(key),
+            AuthenticatedPeer::Libp2p(_) => false,
+        }
+    }
+
+    /// Whether we have connected to the CDN, either in [`wait_for_ready`](Self::wait_for_ready)
+    /// or by receiving a message
+    fn is_ready(&self) -> bool {
+        self.is_ready.load(Ordering::Relaxed)
     }
 
     /// Do nothing here, as we don't need to look up nodes.
@@ -0,0 +1,140 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Persistent storage
+//!
+//! This module contains the [`RecoverableStorage`] trait, which allows a node to be restarted
+//! from whatever consensus persisted through [`Storage`], as well as implementations of
+//! [`Storage`]. Currently this includes
+//! - [`FileSystemStorage`](file_system_storage::FileSystemStorage), a production-ready
+//!   implementation which keeps every record in its own file and survives crashes in the middle
+//!   of a write.
+
+pub mod file_system_storage;
+
+use std::{cmp::max, collections::BTreeMap};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use hotshot_types::{
+    consensus::CommitmentMap,
+    data::{Leaf2, QuorumProposal2},
+    drb::DrbOutput,
+    event::LeafInfo,
+    message::Proposal,
+    simple_certificate::{QuorumCertificate2, UpgradeCertificate},
+    traits::{
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        storage::Storage,
+    },
+    utils::{epoch_from_block_number, View},
+    PeerConfig,
+};
+
+pub use hotshot_types::traits::storage::RetentionPolicy;
+
+use crate::HotShotInitializer;
+
+/// Undecided leaves and the validated state map they belong to.
+pub type UndecidedState<TYPES, LEAF> = (
+    CommitmentMap<LEAF>,
+    BTreeMap<<TYPES as NodeType>::View, View<TYPES>>,
+);
+
+/// A [`Storage`] which can read back what consensus persisted through it, and therefore rebuild a
+/// [`HotShotInitializer`] after a restart.
+#[async_trait]
+pub trait RecoverableStorage<TYPES: NodeType>: Storage<TYPES> {
+    /// Load the most recently decided leaf, along with its state.
+    async fn load_decided_leaf(&self) -> Result<Option<LeafInfo<TYPES>>>;
+
+    /// Load the highest QC we have seen.
+    async fn load_high_qc2(&self) -> Result<Option<QuorumCertificate2<TYPES>>>;
+
+    /// Load every stored quorum proposal, keyed by view.
+    async fn load_proposals2(
+        &self,
+    ) -> Result<BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>>;
+
+    /// Load the undecided leaves and state.
+    async fn load_undecided_state2(&self) -> Result<Option<UndecidedState<TYPES, Leaf2<TYPES>>>>;
+
+    /// Load the decided upgrade certificate, if any.
+    async fn load_decided_upgrade_certificate(&self) -> Result<Option<UpgradeCertificate<TYPES>>>;
+
+    /// Load the last view in which we voted or proposed.
+    async fn load_last_actioned_view(&self) -> Result<TYPES::View>;
+
+    /// Load every stored DRB result, keyed by the epoch whose leaders it seeds.
+    async fn load_drb_results(&self) -> Result<BTreeMap<TYPES::Epoch, DrbOutput>>;
+
+    /// Load every stored stake table, keyed by the epoch from which it takes effect.
+    async fn load_stake_tables(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Epoch, Vec<PeerConfig<TYPES::SignatureKey>>>>;
+
+    /// Rebuild a [`HotShotInitializer`] from the persisted consensus state.
+    ///
+    /// If nothing has been decided yet, the node starts from genesis. Otherwise, it is anchored
+    /// on the last decided leaf and resumes no earlier than the last view it acted in, so it
+    /// cannot vote or propose twice in the same view.
+    ///
+    /// # Errors
+    /// Returns an error if any of the persisted records cannot be read.
+    async fn load_initializer<V: Versions>(
+        &self,
+        instance_state: TYPES::InstanceState,
+        epoch_height: u64,
+    ) -> Result<HotShotInitializer<TYPES>> {
+        let Some(anchor) = self.load_decided_leaf().await? else {
+            tracing::info!("No decided leaf in storage, starting from genesis");
+            return HotShotInitializer::from_genesis::<V>(instance_state)
+                .await
+                .map_err(|e| anyhow!("Failed to initialize from genesis: {e}"));
+        };
+        let anchor_view = anchor.leaf.view_number();
+
+        let high_qc = match self.load_high_qc2().await? {
+            Some(high_qc) => high_qc,
+            None => anchor.leaf.justify_qc(),
+        };
+        let actioned_view = self.load_last_actioned_view().await?;
+        let start_view = max(actioned_view, anchor_view);
+        let start_epoch =
+            TYPES::Epoch::new(epoch_from_block_number(anchor.leaf.height(), epoch_height));
+
+        let decided_upgrade_certificate = self.load_decided_upgrade_certificate().await?;
+        let drb_results = self.load_drb_results().await?;
+        let stake_tables = self.load_stake_tables().await?;
+        let saved_proposals = self.load_proposals2().await?.split_off(&anchor_view);
+        let (undecided_leafs, undecided_state) = match self.load_undecided_state2().await? {
+            Some((leafs, mut state)) => (
+                leafs
+                    .into_values()
+                    .filter(|leaf| leaf.view_number() > anchor_view)
+                    .collect(),
+                state.split_off(&(anchor_view + 1)),
+            ),
+            None => (Vec::new(), BTreeMap::new()),
+        };
+
+        Ok(HotShotInitializer::from_reload(
+            anchor.leaf,
+            instance_state,
+            Some(anchor.state),
+            start_view,
+            start_epoch,
+            actioned_view,
+            saved_proposals,
+            high_qc,
+            decided_upgrade_certificate,
+            undecided_leafs,
+            undecided_state,
+            drb_results,
+            stake_tables,
+        ))
+    }
+}
@@ -0,0 +1,738 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A [`Storage`] implementation backed by the local file system.
+//!
+//! Every record lives in its own file underneath a root directory. Per-view records (VID shares,
+//! DA proposals, quorum proposals, and archived decided leaves and quorum certificates) are kept
+//! in one subdirectory per kind, named by view number, as are an index of archived leaves by
+//! height and the DRB results and stake tables by epoch, while singleton records (high QC,
+//! undecided state, the decided leaf, the decided upgrade certificate, the last actioned view and
+//! the signing history used for slashing protection) live directly in the root. Per-view records
+//! are garbage collected on decide according to a [`RetentionPolicy`].
+//!
+//! Writes go to a temporary file which is fsynced and then atomically renamed over the previous
+//! version, so a crash in the middle of a write leaves either the old or the new record on disk,
+//! never a torn one.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use committable::Committable;
+use hotshot_types::{
+    consensus::CommitmentMap,
+    data::{DaProposal, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare},
+    drb::DrbOutput,
+    event::{HotShotAction, LeafInfo},
+    message::Proposal,
+    simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
+    slashing_protection::{SigningHistory, SigningRoot},
+    traits::{
+        node_implementation::{ConsensusTime, NodeType},
+        storage::Storage,
+        BlockPayload,
+    },
+    utils::View,
+    vid::VidCommitment,
+    vote::HasViewNumber,
+    PeerConfig,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::spawn_blocking;
+
+use super::{RecoverableStorage, RetentionPolicy, UndecidedState};
+
+/// Directory holding VID shares, one file per view.
+const VID_DIR: &str = "vid";
+/// Directory holding DA proposals, one file per view.
+const DA_DIR: &str = "da";
+/// Directory holding legacy quorum proposals, one file per view.
+const PROPOSAL_DIR: &str = "quorum_proposals";
+/// Directory holding quorum proposals, one file per view.
+const PROPOSAL2_DIR: &str = "quorum_proposals2";
+/// Directory holding archived decided leaves, one file per view.
+const DECIDED_LEAVES_DIR: &str = "decided_leaves";
+/// Directory holding the view of each archived decided leaf, one file per height.
+const DECIDED_HEIGHTS_DIR: &str = "decided_heights";
+/// Directory holding the quorum certificates of archived decided leaves, one file per view.
+const DECIDED_QCS_DIR: &str = "decided_qcs";
+/// Directory holding DRB results, one file per epoch.
+const DRB_RESULTS_DIR: &str = "drb_results";
+/// Directory holding stake tables, one file per epoch in which one takes effect.
+const STAKE_TABLES_DIR: &str = "stake_tables";
+/// Every directory holding per-view, per-height or per-epoch records.
+const VIEW_DIRS: [&str; 9] = [
+    VID_DIR,
+    DA_DIR,
+    PROPOSAL_DIR,
+    PROPOSAL2_DIR,
+    DECIDED_LEAVES_DIR,
+    DECIDED_HEIGHTS_DIR,
+    DECIDED_QCS_DIR,
+    DRB_RESULTS_DIR,
+    STAKE_TABLES_DIR,
+];
+/// File holding the legacy high QC.
+const HIGH_QC_FILE: &str = "high_qc";
+/// File holding the high QC.
+const HIGH_QC2_FILE: &str = "high_qc2";
+/// File holding the legacy undecided leaves and state.
+const UNDECIDED_STATE_FILE: &str = "undecided_state";
+/// File holding the undecided leaves and state.
+const UNDECIDED_STATE2_FILE: &str = "undecided_state2";
+/// File holding the most recently decided leaf.
+const DECIDED_LEAF_FILE: &str = "decided_leaf";
+/// File holding the decided upgrade certificate.
+const UPGRADE_CERTIFICATE_FILE: &str = "upgrade_certificate";
+/// File holding the last view in which we voted or proposed.
+const LAST_ACTION_FILE: &str = "last_action";
+/// File holding the history of signed actions.
+const SIGNING_HISTORY_FILE: &str = "signing_history";
+/// Extension used for in-flight writes.
+const TEMP_EXTENSION: &str = "tmp";
+
+/// VID shares for a single view, keyed by recipient.
+type VidSharesForView<TYPES> =
+    HashMap<<TYPES as NodeType>::SignatureKey, Proposal<TYPES, VidDisperseShare<TYPES>>>;
+
+/// Persistent storage which keeps consensus data in files underneath a root directory.
+#[derive(Clone, Debug)]
+pub struct FileSystemStorage<TYPES: NodeType> {
+    /// Root directory of the storage.
+    path: Arc<PathBuf>,
+    /// Serializes writers, so that read-modify-write updates are never interleaved.
+    lock: Arc<RwLock<()>>,
+    /// What to keep once views are decided.
+    retention_policy: RetentionPolicy,
+    /// Phantom for TYPES
+    _pd: PhantomData<TYPES>,
+}
+
+impl<TYPES: NodeType> FileSystemStorage<TYPES> {
+    /// Open the storage rooted at `path`, creating it if it does not exist yet.
+    ///
+    /// Any temporary files left behind by a write that was interrupted by a crash are removed.
+    ///
+    /// # Errors
+    /// Returns an error if the directory layout cannot be created or cleaned up.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+
+        for dir in VIEW_DIRS {
+            fs::create_dir_all(path.join(dir))
+                .with_context(|| format!("failed to create storage directory {dir}"))?;
+        }
+        remove_temp_files(&path)?;
+        for dir in VIEW_DIRS {
+            remove_temp_files(&path.join(dir))?;
+        }
+
+        Ok(Self {
+            path: Arc::new(path),
+            lock: Arc::new(RwLock::new(())),
+            retention_policy: RetentionPolicy::default(),
+            _pd: PhantomData,
+        })
+    }
+
+    /// Replace the default [`RetentionPolicy`] of this storage.
+    #[must_use]
+    pub fn with_retention_policy(mut self, retention_policy: RetentionPolicy) -> Self {
+        self.retention_policy = retention_policy;
+        self
+    }
+
+    /// The root directory of this storage.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the record for `view` in the directory `dir`.
+    fn view_path(&self, dir: &str, view: TYPES::View) -> PathBuf {
+        self.path.join(dir).join(view.u64().to_string())
+    }
+
+    /// Path of the record for `epoch` in the directory `dir`.
+    fn epoch_path(&self, dir: &str, epoch: TYPES::Epoch) -> PathBuf {
+        self.path.join(dir).join(epoch.u64().to_string())
+    }
+
+    /// Load the VID shares stored for `view`, keyed by recipient.
+    ///
+    /// # Errors
+    /// Returns an error if the record exists but cannot be read.
+    pub async fn load_vid_shares(&self, view: TYPES::View) -> Result<VidSharesForView<TYPES>> {
+        let _guard = self.lock.read().await;
+        Ok(read_record(self.view_path(VID_DIR, view))
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Load the archived decided leaf for `view`, if [`RetentionPolicy::archive_decided_leaves`]
+    /// was set when it was decided.
+    ///
+    /// # Errors
+    /// Returns an error if the record exists but cannot be read.
+    pub async fn load_archived_leaf(&self, view: TYPES::View) -> Result<Option<LeafInfo<TYPES>>> {
+        let _guard = self.lock.read().await;
+        read_record(self.view_path(DECIDED_LEAVES_DIR, view)).await
+    }
+
+    /// Load the DA proposal stored for `view`, together with its VID commitment.
+    ///
+    /// # Errors
+    /// Returns an error if the record exists but cannot be read.
+    pub async fn load_da_proposal(
+        &self,
+        view: TYPES::View,
+    ) -> Result<Option<(Proposal<TYPES, DaProposal<TYPES>>, VidCommitment)>> {
+        let _guard = self.lock.read().await;
+        read_record(self.view_path(DA_DIR, view)).await
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> Storage<TYPES> for FileSystemStorage<TYPES> {
+    async fn append_vid(&self, proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> Result<()> {
+        let _guard = self.lock.write().await;
+        let path = self.view_path(VID_DIR, proposal.data.view_number);
+
+        let mut shares: VidSharesForView<TYPES> =
+            read_record(path.clone()).await?.unwrap_or_default();
+        shares.insert(proposal.data.recipient_key.clone(), proposal.clone());
+
+        write_record(path, &shares)
+            .await
+            .context("failed to append VID share")
+    }
+
+    async fn append_da(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal<TYPES>>,
+        vid_commit: VidCommitment,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(
+            self.view_path(DA_DIR, proposal.data.view_number),
+            &(proposal, vid_commit),
+        )
+        .await
+        .context("failed to append DA proposal")
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(
+            self.view_path(PROPOSAL_DIR, proposal.data.view_number),
+            proposal,
+        )
+        .await
+        .context("failed to append quorum proposal")
+    }
+
+    async fn append_proposal2(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(
+            self.view_path(PROPOSAL2_DIR, proposal.data.view_number),
+            proposal,
+        )
+        .await
+        .context("failed to append quorum proposal")
+    }
+
+    async fn record_action(&self, view: TYPES::View, action: HotShotAction) -> Result<()> {
+        // Only votes and proposals matter for deciding where a restarted node may safely resume.
+        if !matches!(action, HotShotAction::Vote | HotShotAction::Propose) {
+            return Ok(());
+        }
+
+        let _guard = self.lock.write().await;
+        let path = self.path.join(LAST_ACTION_FILE);
+        let last_actioned_view: Option<TYPES::View> = read_record(path.clone()).await?;
+        if last_actioned_view.is_some_and(|last| last >= view) {
+            return Ok(());
+        }
+
+        write_record(path, &view)
+            .await
+            .context("failed to record action")
+    }
+
+    async fn record_signed_action(
+        &self,
+        view: TYPES::View,
+        action: HotShotAction,
+        signing_root: SigningRoot,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        let path = self.path.join(SIGNING_HISTORY_FILE);
+        let mut history: SigningHistory<TYPES> =
+            read_record(path.clone()).await?.unwrap_or_default();
+        if !history.check_and_insert(view, action, signing_root)? {
+            return Ok(());
+        }
+
+        write_record(path, &history)
+            .await
+            .context("failed to record signed action")
+    }
+
+    async fn load_signing_history(&self) -> Result<SigningHistory<TYPES>> {
+        let _guard = self.lock.read().await;
+        Ok(read_record(self.path.join(SIGNING_HISTORY_FILE))
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn import_signing_history(&self, imported: SigningHistory<TYPES>) -> Result<()> {
+        let _guard = self.lock.write().await;
+        let path = self.path.join(SIGNING_HISTORY_FILE);
+        let mut history: SigningHistory<TYPES> =
+            read_record(path.clone()).await?.unwrap_or_default();
+        history.merge(imported);
+
+        write_record(path, &history)
+            .await
+            .context("failed to import signing history")
+    }
+
+    async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()> {
+        let _guard = self.lock.write().await;
+        let path = self.path.join(HIGH_QC_FILE);
+        let current: Option<QuorumCertificate<TYPES>> = read_record(path.clone()).await?;
+        if current.is_some_and(|qc| qc.view_number() >= high_qc.view_number()) {
+            return Ok(());
+        }
+
+        write_record(path, &high_qc)
+            .await
+            .context("failed to update high QC")
+    }
+
+    async fn update_high_qc2(&self, high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
+        let _guard = self.lock.write().await;
+        let path = self.path.join(HIGH_QC2_FILE);
+        let current: Option<QuorumCertificate2<TYPES>> = read_record(path.clone()).await?;
+        if current.is_some_and(|qc| qc.view_number() >= high_qc.view_number()) {
+            return Ok(());
+        }
+
+        write_record(path, &high_qc)
+            .await
+            .context("failed to update high QC")
+    }
+
+    async fn update_undecided_state(
+        &self,
+        leafs: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(self.path.join(UNDECIDED_STATE_FILE), &(leafs, state))
+            .await
+            .context("failed to update undecided state")
+    }
+
+    async fn update_undecided_state2(
+        &self,
+        leafs: CommitmentMap<Leaf2<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(self.path.join(UNDECIDED_STATE2_FILE), &(leafs, state))
+            .await
+            .context("failed to update undecided state")
+    }
+
+    async fn update_decided_leaf(&self, leaf_info: &LeafInfo<TYPES>) -> Result<()> {
+        let _guard = self.lock.write().await;
+        if self.retention_policy.archive_decided_leaves {
+            write_record(
+                self.view_path(DECIDED_LEAVES_DIR, leaf_info.leaf.view_number()),
+                leaf_info,
+            )
+            .await
+            .context("failed to archive decided leaf")?;
+            write_record(
+                self.path
+                    .join(DECIDED_HEIGHTS_DIR)
+                    .join(leaf_info.leaf.height().to_string()),
+                &leaf_info.leaf.view_number().u64(),
+            )
+            .await
+            .context("failed to index decided leaf")?;
+            let justify_qc = leaf_info.leaf.justify_qc();
+            write_record(
+                self.view_path(DECIDED_QCS_DIR, justify_qc.view_number()),
+                &justify_qc,
+            )
+            .await
+            .context("failed to archive quorum certificate")?;
+        }
+
+        let path = self.path.join(DECIDED_LEAF_FILE);
+        let current: Option<LeafInfo<TYPES>> = read_record(path.clone()).await?;
+        if current.is_some_and(|info| info.leaf.view_number() >= leaf_info.leaf.view_number()) {
+            return Ok(());
+        }
+
+        write_record(path, leaf_info)
+            .await
+            .context("failed to update decided leaf")
+    }
+
+    async fn gc(&self, decided_view: TYPES::View) -> Result<()> {
+        let _guard = self.lock.write().await;
+        let cutoff = decided_view
+            .u64()
+            .saturating_sub(self.retention_policy.views_to_keep);
+        let vid_cutoff = if self.retention_policy.prune_vid_on_decide {
+            decided_view.u64() + 1
+        } else {
+            cutoff
+        };
+
+        prune_view_records(self.path.join(VID_DIR), vid_cutoff)
+            .await
+            .context("failed to garbage collect VID shares")?;
+        for dir in [DA_DIR, PROPOSAL_DIR, PROPOSAL2_DIR] {
+            prune_view_records(self.path.join(dir), cutoff)
+                .await
+                .with_context(|| format!("failed to garbage collect {dir}"))?;
+        }
+
+        let path = self.path.join(SIGNING_HISTORY_FILE);
+        if let Some(mut history) = read_record::<SigningHistory<TYPES>>(path.clone()).await? {
+            history.prune(TYPES::View::new(cutoff));
+            write_record(path, &history)
+                .await
+                .context("failed to garbage collect signing history")?;
+        }
+
+        Ok(())
+    }
+
+    async fn load_leaf_at_height(&self, height: u64) -> Result<Option<Leaf2<TYPES>>> {
+        let _guard = self.lock.read().await;
+        if let Some(view) =
+            read_record::<u64>(self.path.join(DECIDED_HEIGHTS_DIR).join(height.to_string())).await?
+        {
+            let archived: Option<LeafInfo<TYPES>> =
+                read_record(self.view_path(DECIDED_LEAVES_DIR, TYPES::View::new(view))).await?;
+            return Ok(archived.map(|info| info.leaf));
+        }
+
+        // Without an archive, only the most recently decided leaf is kept
+        let decided: Option<LeafInfo<TYPES>> =
+            read_record(self.path.join(DECIDED_LEAF_FILE)).await?;
+        Ok(decided
+            .map(|info| info.leaf)
+            .filter(|leaf| leaf.height() == height))
+    }
+
+    async fn load_payload(&self, view: TYPES::View) -> Result<Option<TYPES::BlockPayload>> {
+        let _guard = self.lock.read().await;
+        let archived: Option<LeafInfo<TYPES>> =
+            read_record(self.view_path(DECIDED_LEAVES_DIR, view)).await?;
+        if let Some(payload) = archived.and_then(|info| info.leaf.block_payload()) {
+            return Ok(Some(payload));
+        }
+
+        // DA proposals hold the payload of recent views, decided or not
+        let da_proposal: Option<(Proposal<TYPES, DaProposal<TYPES>>, VidCommitment)> =
+            read_record(self.view_path(DA_DIR, view)).await?;
+        Ok(da_proposal.map(|(proposal, _)| {
+            TYPES::BlockPayload::from_bytes(
+                &proposal.data.encoded_transactions,
+                &proposal.data.metadata,
+            )
+        }))
+    }
+
+    async fn load_quorum_certificate(
+        &self,
+        view: TYPES::View,
+    ) -> Result<Option<QuorumCertificate2<TYPES>>> {
+        let _guard = self.lock.read().await;
+        if let Some(qc) = read_record(self.view_path(DECIDED_QCS_DIR, view)).await? {
+            return Ok(Some(qc));
+        }
+
+        let high_qc: Option<QuorumCertificate2<TYPES>> =
+            read_record(self.path.join(HIGH_QC2_FILE)).await?;
+        Ok(high_qc.filter(|qc| qc.view_number() == view))
+    }
+
+    async fn update_decided_upgrade_certificate(
+        &self,
+        decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(
+            self.path.join(UPGRADE_CERTIFICATE_FILE),
+            &decided_upgrade_certificate,
+        )
+        .await
+        .context("failed to update decided upgrade certificate")
+    }
+
+    async fn add_drb_result(&self, epoch: TYPES::Epoch, drb_output: &DrbOutput) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(self.epoch_path(DRB_RESULTS_DIR, epoch), drb_output)
+            .await
+            .context("failed to store DRB result")
+    }
+
+    async fn add_stake_table(
+        &self,
+        epoch: TYPES::Epoch,
+        stake_table: &[PeerConfig<TYPES::SignatureKey>],
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+        write_record(self.epoch_path(STAKE_TABLES_DIR, epoch), stake_table)
+            .await
+            .context("failed to store stake table")
+    }
+
+    async fn migrate_consensus(
+        &self,
+        convert_leaf: fn(Leaf<TYPES>) -> Leaf2<TYPES>,
+        convert_proposal: fn(
+            Proposal<TYPES, QuorumProposal<TYPES>>,
+        ) -> Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        let _guard = self.lock.write().await;
+
+        // Proposals which already exist in the new format win; they can only have been written
+        // after the legacy ones.
+        let proposals: BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal<TYPES>>> =
+            read_view_records(self.path.join(PROPOSAL_DIR)).await?;
+        for (view, proposal) in proposals {
+            let path = self.view_path(PROPOSAL2_DIR, view);
+            if path.exists() {
+                continue;
+            }
+            write_record(path, &convert_proposal(proposal))
+                .await
+                .context("failed to migrate quorum proposal")?;
+        }
+
+        let undecided_state2_path = self.path.join(UNDECIDED_STATE2_FILE);
+        if !undecided_state2_path.exists() {
+            let undecided_state: Option<UndecidedState<TYPES, Leaf<TYPES>>> =
+                read_record(self.path.join(UNDECIDED_STATE_FILE)).await?;
+            if let Some((leafs, state)) = undecided_state {
+                let leafs: CommitmentMap<Leaf2<TYPES>> = leafs
+                    .into_values()
+                    .map(|leaf| {
+                        let leaf = convert_leaf(leaf);
+                        (leaf.commit(), leaf)
+                    })
+                    .collect();
+                write_record(undecided_state2_path, &(leafs, state))
+                    .await
+                    .context("failed to migrate undecided state")?;
+            }
+        }
+
+        let high_qc2_path = self.path.join(HIGH_QC2_FILE);
+        if !high_qc2_path.exists() {
+            let high_qc: Option<QuorumCertificate<TYPES>> =
+                read_record(self.path.join(HIGH_QC_FILE)).await?;
+            if let Some(high_qc) = high_qc {
+                write_record(high_qc2_path, &high_qc.to_qc2())
+                    .await
+                    .context("failed to migrate high QC")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> RecoverableStorage<TYPES> for FileSystemStorage<TYPES> {
+    async fn load_decided_leaf(&self) -> Result<Option<LeafInfo<TYPES>>> {
+        let _guard = self.lock.read().await;
+        read_record(self.path.join(DECIDED_LEAF_FILE)).await
+    }
+
+    async fn load_high_qc2(&self) -> Result<Option<QuorumCertificate2<TYPES>>> {
+        let _guard = self.lock.read().await;
+        read_record(self.path.join(HIGH_QC2_FILE)).await
+    }
+
+    async fn load_proposals2(
+        &self,
+    ) -> Result<BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>> {
+        let _guard = self.lock.read().await;
+        read_view_records(self.path.join(PROPOSAL2_DIR)).await
+    }
+
+    async fn load_undecided_state2(&self) -> Result<Option<UndecidedState<TYPES, Leaf2<TYPES>>>> {
+        let _guard = self.lock.read().await;
+        read_record(self.path.join(UNDECIDED_STATE2_FILE)).await
+    }
+
+    async fn load_decided_upgrade_certificate(&self) -> Result<Option<UpgradeCertificate<TYPES>>> {
+        let _guard = self.lock.read().await;
+        Ok(read_record(self.path.join(UPGRADE_CERTIFICATE_FILE))
+            .await?
+            .flatten())
+    }
+
+    async fn load_last_actioned_view(&self) -> Result<TYPES::View> {
+        let _guard = self.lock.read().await;
+        Ok(read_record(self.path.join(LAST_ACTION_FILE))
+            .await?
+            .unwrap_or(TYPES::View::genesis()))
+    }
+
+    async fn load_drb_results(&self) -> Result<BTreeMap<TYPES::Epoch, DrbOutput>> {
+        let _guard = self.lock.read().await;
+        read_view_records(self.path.join(DRB_RESULTS_DIR)).await
+    }
+
+    async fn load_stake_tables(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Epoch, Vec<PeerConfig<TYPES::SignatureKey>>>> {
+        let _guard = self.lock.read().await;
+        read_view_records(self.path.join(STAKE_TABLES_DIR)).await
+    }
+}
+
+/// Serialize `value` and atomically replace the file at `path` with it.
+async fn write_record<T: Serialize + ?Sized>(path: PathBuf, value: &T) -> Result<()> {
+    let bytes = bincode::serialize(value).context("failed to serialize record")?;
+    spawn_blocking(move || write_atomic(&path, &bytes)).await??;
+
+    Ok(())
+}
+
+/// Read and deserialize the file at `path`, returning `None` if it does not exist.
+async fn read_record<T: DeserializeOwned>(path: PathBuf) -> Result<Option<T>> {
+    let display = path.display().to_string();
+    let bytes = spawn_blocking(move || match fs::read(&path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    })
+    .await??;
+
+    bytes
+        .map(|bytes| bincode::deserialize(&bytes))
+        .transpose()
+        .with_context(|| format!("failed to deserialize record {display}"))
+}
+
+/// Read every per-view record in `dir`, keyed by view number.
+async fn read_view_records<VIEW: ConsensusTime, T: DeserializeOwned>(
+    dir: PathBuf,
+) -> Result<BTreeMap<VIEW, T>> {
+    let views = spawn_blocking(move || -> std::io::Result<Vec<(u64, PathBuf)>> {
+        let mut views = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            // Skip anything that isn't a committed record, such as an in-flight write.
+            if let Some(view) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<u64>().ok())
+            {
+                views.push((view, path));
+            }
+        }
+        Ok(views)
+    })
+    .await??;
+
+    let mut records = BTreeMap::new();
+    for (view, path) in views {
+        if let Some(record) = read_record(path).await? {
+            records.insert(VIEW::new(view), record);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Remove every per-view record in `dir` for a view strictly below `cutoff`.
+async fn prune_view_records(dir: PathBuf, cutoff: u64) -> Result<()> {
+    spawn_blocking(move || -> std::io::Result<()> {
+        let mut pruned = false;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let view = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<u64>().ok());
+            if view.is_some_and(|view| view < cutoff) {
+                fs::remove_file(&path)?;
+                pruned = true;
+            }
+        }
+
+        if pruned {
+            File::open(&dir)?.sync_all()?;
+        }
+        Ok(())
+    })
+    .await??;
+
+    Ok(())
+}
+
+/// Write `bytes` to a temporary file next to `path`, fsync it and rename it into place.
+///
+/// The parent directory is fsynced as well so that the rename itself is durable.
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let temp_path = path.with_extension(TEMP_EXTENSION);
+    {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, path)?;
+
+    if let Some(parent) = path.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Remove any temporary files left in `dir` by writes that never completed.
+fn remove_temp_files(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == TEMP_EXTENSION)
+        {
+            tracing::warn!("Removing incomplete storage write {}", path.display());
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
@@ -6,4 +6,4 @@
 
 //! Events that a [`SystemContext`](crate::SystemContext) instance can emit
 
-pub use hotshot_types::event::{Event, EventType};
+pub use hotshot_types::event::{Event, EventFilter, EventKind, EventType, EventsLagged};
@@ -6,34 +6,45 @@
 
 //! Provides an event-streaming handle for a [`SystemContext`] running in the background
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use anyhow::{anyhow, Context, Ok, Result};
-use async_broadcast::{InactiveReceiver, Receiver, Sender};
+use async_broadcast::{broadcast, InactiveReceiver, Receiver, RecvError, Sender};
 use async_lock::RwLock;
 use committable::{Commitment, Committable};
-use futures::Stream;
+use futures::{future::ready, stream, Stream, StreamExt};
 use hotshot_task::{
     dependency::{Dependency, EventDependency},
     task::{ConsensusTaskRegistry, NetworkTaskRegistry, Task, TaskState},
 };
-use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
+use hotshot_task_impls::{
+    builder_reputation::BuilderStats,
+    events::HotShotEvent,
+    helpers::broadcast_event,
+    mempool::{Mempool, TransactionStatus},
+};
 use hotshot_types::{
-    consensus::Consensus,
+    consensus::{Consensus, ConsensusStatus},
+    constants::LOOK_AHEAD,
     data::{Leaf2, QuorumProposal2},
     error::HotShotError,
+    event::{EventFilter, EventsLagged},
     message::{Message, MessageKind, Proposal, RecipientList},
     request_response::ProposalRequestPayload,
+    slashing_protection::{Interchange, SigningHistory},
     traits::{
         consensus_api::ConsensusApi,
         election::Membership,
         network::{BroadcastDelay, ConnectedNetwork, Topic},
         node_implementation::NodeType,
         signature_key::SignatureKey,
+        storage::Storage,
     },
     vote::HasViewNumber,
 };
+use tokio::spawn;
 use tracing::instrument;
+use url::Url;
 
 use crate::{traits::NodeImplementation, types::Event, Memberships, SystemContext, Versions};
 
@@ -94,6 +105,43 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.output_event_stream.1.activate_cloned()
     }
 
+    /// Obtains a stream of the events matching `filter`.
+    ///
+    /// Unlike [`event_stream`](Self::event_stream), a subscriber which falls behind is told how
+    /// many events it missed with an [`EventsLagged`] item, rather than skipping them silently.
+    /// Without [`EventFilter::capacity`] the events are read straight from the output channel, so
+    /// the subscriber lags once that channel overflows, and the count includes events which
+    /// would not have matched. With it, the matching events are forwarded to a queue of that
+    /// capacity, and the subscriber lags once the queue is full.
+    pub fn event_stream_filtered(
+        &self,
+        filter: EventFilter<TYPES>,
+    ) -> impl Stream<Item = Result<Event<TYPES>, EventsLagged>> + Send + Unpin {
+        let capacity = filter.capacity;
+        let events = lagging_stream(self.output_event_stream.1.activate_cloned())
+            .filter(move |item| ready(item.as_ref().map_or(true, |event| filter.matches(event))));
+
+        let Some(capacity) = capacity else {
+            return events.boxed();
+        };
+
+        let (mut queue_sender, queue_receiver) = broadcast(capacity.get());
+        queue_sender.set_overflow(true);
+        spawn(async move {
+            let mut events = Box::pin(events);
+            while let Some(item) = events.next().await {
+                // The subscriber dropped the stream
+                if queue_sender.broadcast(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        lagging_stream(queue_receiver)
+            .map(|item| item.and_then(std::convert::identity))
+            .boxed()
+    }
+
     /// Message other participents with a serialized message from the application
     /// Receivers of this message will get an `Event::ExternalMessageReceived` via
     /// the event stream.
@@ -281,6 +329,92 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.hotshot.publish_transaction_async(tx).await
     }
 
+    /// The transactions in the mempool which are waiting to be included in a block, highest
+    /// priority first. Empty if this node keeps no mempool.
+    #[must_use]
+    pub fn pending_transactions(&self) -> Vec<TYPES::Transaction> {
+        self.hotshot
+            .mempool
+            .as_ref()
+            .map(Mempool::pending_transactions)
+            .unwrap_or_default()
+    }
+
+    /// Whether the transaction with commitment `hash` is pending, in a DA proposal, decided or
+    /// evicted, or `None` if the mempool has not seen it or this node keeps no mempool.
+    #[must_use]
+    pub fn transaction_status(
+        &self,
+        hash: &Commitment<TYPES::Transaction>,
+    ) -> Option<TransactionStatus<TYPES>> {
+        self.hotshot.mempool.as_ref()?.transaction_status(hash)
+    }
+
+    /// The reputation of each builder this node has queried for blocks as a leader, by URL
+    #[must_use]
+    pub fn builder_reputation(&self) -> BTreeMap<Url, BuilderStats> {
+        self.hotshot.builder_reputation.all_stats()
+    }
+
+    /// Get a snapshot of the state of consensus and the network, for status and health queries.
+    ///
+    /// # Panics
+    /// If the internal consensus is in an inconsistent state.
+    pub async fn status(&self) -> ConsensusStatus<TYPES> {
+        let consensus = self.hotshot.consensus.read().await;
+        self.status_of(&consensus)
+    }
+
+    /// Tries to get a snapshot of the state of consensus and the network, returning instantly if
+    /// we can't acquire the consensus lock.
+    ///
+    /// # Panics
+    /// If the internal consensus is in an inconsistent state.
+    #[must_use]
+    pub fn try_status(&self) -> Option<ConsensusStatus<TYPES>> {
+        let consensus = self.hotshot.consensus.try_read()?;
+        Some(self.status_of(&consensus))
+    }
+
+    /// Build a [`ConsensusStatus`] from the consensus state
+    fn status_of(&self, consensus: &Consensus<TYPES>) -> ConsensusStatus<TYPES> {
+        let cur_view = consensus.cur_view();
+        let cur_epoch = consensus.cur_epoch();
+        let last_decided_view = consensus.last_decided_view();
+
+        let upcoming_leader_views = (1..=LOOK_AHEAD)
+            .map(|offset| cur_view + offset)
+            .filter(|view| {
+                self.memberships
+                    .quorum_membership
+                    .leader(*view, cur_epoch)
+                    .is_ok_and(|leader| leader == self.hotshot.public_key)
+            })
+            .collect();
+
+        ConsensusStatus {
+            cur_view,
+            cur_epoch,
+            last_decided_view,
+            last_decided_height: consensus.decided_leaf().height(),
+            locked_view: consensus.locked_view(),
+            high_qc_view: consensus.high_qc().view_number(),
+            undecided_views: consensus.validated_state_map().len(),
+            pending_vid_shares: consensus
+                .vid_shares()
+                .range(last_decided_view + 1..)
+                .map(|(_, shares)| shares.len())
+                .sum(),
+            pending_da_certificates: consensus
+                .saved_da_certs()
+                .keys()
+                .filter(|view| **view > last_decided_view)
+                .count(),
+            upcoming_leader_views,
+            network_ready: self.network.is_ready(),
+        }
+    }
+
     /// Get the underlying consensus state for this [`SystemContext`]
     #[must_use]
     pub fn consensus(&self) -> Arc<RwLock<Consensus<TYPES>>> {
@@ -372,4 +506,42 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
     pub fn storage(&self) -> Arc<RwLock<I::Storage>> {
         Arc::clone(&self.storage)
     }
+
+    /// Export the history of votes and proposals signed by this node, so that its key can be moved
+    /// to another machine without risking signing conflicting messages.
+    ///
+    /// # Errors
+    /// Returns an error if the history cannot be read from storage.
+    pub async fn export_slashing_protection(&self) -> Result<Interchange<TYPES>> {
+        let history = self.storage.read().await.load_signing_history().await?;
+        Ok(history.to_interchange(self.hotshot.public_key.clone()))
+    }
+
+    /// Import a signing history exported from another machine. From then on, this node refuses to
+    /// sign anything which conflicts with it. This should be done before starting consensus.
+    ///
+    /// # Errors
+    /// Returns an error if the interchange is not supported, or cannot be written to storage.
+    pub async fn import_slashing_protection(&self, interchange: &Interchange<TYPES>) -> Result<()> {
+        let history = SigningHistory::from_interchange(interchange, &self.hotshot.public_key)?;
+        self.storage
+            .read()
+            .await
+            .import_signing_history(history)
+            .await
+    }
+}
+
+/// Receive everything from `receiver`, reporting overflows as [`EventsLagged`] instead of
+/// skipping them
+fn lagging_stream<T: Clone + Send + 'static>(
+    receiver: Receiver<T>,
+) -> impl Stream<Item = Result<T, EventsLagged>> + Send {
+    stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            std::result::Result::Ok(item) => Some((std::result::Result::Ok(item), receiver)),
+            Err(RecvError::Overflowed(missed)) => Some((Err(EventsLagged { missed }), receiver)),
+            Err(RecvError::Closed) => None,
+        }
+    })
 }
@@ -7,7 +7,10 @@
 use hotshot_types::traits::signature_key::SignatureKey;
 use libp2p::{
     autonat,
-    gossipsub::{Behaviour as GossipBehaviour, Event as GossipEvent, IdentTopic},
+    gossipsub::{
+        Behaviour as GossipBehaviour, Event as GossipEvent, IdentTopic, MessageAcceptance,
+        MessageId,
+    },
     identify::{Behaviour as IdentifyBehaviour, Event as IdentifyEvent},
     kad::store::MemoryStore,
     request_response::{OutboundRequestId, ResponseChannel},
@@ -95,6 +98,55 @@ impl<K: SignatureKey + 'static> NetworkDef<K> {
             tracing::warn!("Failed to publish gossip message. Error: {:?}", e);
         }
     }
+    /// The peers a message published on `topic` is sent to: every peer subscribed to the topic
+    /// with `flood_publish`, or just our mesh peers for the topic without it
+    pub fn gossip_recipients(&self, topic: &IdentTopic, flood_publish: bool) -> Vec<PeerId> {
+        let hash = topic.hash();
+        if flood_publish {
+            self.gossipsub
+                .all_peers()
+                .filter(|(_, topics)| topics.contains(&&hash))
+                .map(|(peer, _)| *peer)
+                .collect()
+        } else {
+            self.gossipsub.mesh_peers(&hash).copied().collect()
+        }
+    }
+
+    /// Tell gossipsub whether to forward the message `id` received from `source`
+    pub fn report_gossip_validation(
+        &mut self,
+        id: &MessageId,
+        source: &PeerId,
+        acceptance: MessageAcceptance,
+    ) {
+        // The message may have been dropped from the cache already, which is harmless
+        let _ = self
+            .gossipsub
+            .report_message_validation_result(id, source, acceptance);
+    }
+
+    /// Set the application-specific score of `peer`, which gossipsub weighs into its overall
+    /// peer score
+    pub fn set_gossip_score(&mut self, peer: &PeerId, score: f64) {
+        if !self.gossipsub.set_application_score(peer, score) {
+            debug!(
+                "Could not set gossip score of {:?}: peer scoring disabled",
+                peer
+            );
+        }
+    }
+
+    /// Ignore all gossip to and from `peer`
+    pub fn blacklist_gossip_peer(&mut self, peer: &PeerId) {
+        self.gossipsub.blacklist_peer(peer);
+    }
+
+    /// Stop ignoring gossip to and from `peer`
+    pub fn unblacklist_gossip_peer(&mut self, peer: &PeerId) {
+        self.gossipsub.remove_blacklisted_peer(peer);
+    }
+
     /// Subscribe to a given topic
     pub fn subscribe_gossip(&mut self, t: &str) {
         if let Err(e) = self.gossipsub.subscribe(&IdentTopic::new(t)) {
@@ -16,32 +16,37 @@ pub mod transport;
 /// Forked `cbor` codec with altered request/response sizes
 pub mod cbor;
 
-use std::{collections::HashSet, fmt::Debug};
+use std::{collections::HashSet, fmt::Debug, time::Duration};
 
 use futures::channel::oneshot::Sender;
-use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
+use hotshot_types::{
+    network::Libp2pTransport,
+    traits::{network::NetworkError, node_implementation::NodeType},
+};
 use libp2p::{
     build_multiaddr,
-    core::{muxing::StreamMuxerBox, transport::Boxed},
+    core::{muxing::StreamMuxerBox, transport::Boxed, upgrade::Version},
     dns::tokio::Transport as DnsTransport,
     gossipsub::Event as GossipEvent,
     identify::Event as IdentifyEvent,
     identity::Keypair,
-    quic,
+    noise, quic,
     request_response::ResponseChannel,
-    Multiaddr, Transport,
+    tcp, yamux, Multiaddr, Transport,
 };
 use libp2p_identity::PeerId;
 use quic::tokio::Transport as QuicTransport;
+use tcp::tokio::Transport as TcpTransport;
 use tracing::instrument;
 use transport::StakeTableAuthentication;
 
 pub use self::{
     def::NetworkDef,
     node::{
-        spawn_network_node, GossipConfig, NetworkNode, NetworkNodeConfig, NetworkNodeConfigBuilder,
-        NetworkNodeConfigBuilderError, NetworkNodeHandle, NetworkNodeReceiver,
-        RequestResponseConfig, DEFAULT_REPLICATION_FACTOR,
+        spawn_network_node, BandwidthMetricsValue, GossipConfig, MessageClass, NetworkNode,
+        NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeConfigBuilderError,
+        NetworkNodeHandle, NetworkNodeReceiver, RateLimit, RateLimitAction, RateLimitConfig,
+        RequestResponseConfig, Traffic, DEFAULT_REPLICATION_FACTOR,
     },
 };
 
@@ -103,14 +108,20 @@ pub enum ClientRequest {
     GetRoutingTable(Sender<()>),
     /// Get address of peer
     LookupPeer(PeerId, Sender<()>),
+    /// Set the application-specific gossipsub score of a peer
+    SetPeerScore(PeerId, f64),
+    /// Disconnect from a peer and refuse to talk to it for the given duration
+    BanPeer(PeerId, Duration),
+    /// Lift the ban on a peer
+    UnbanPeer(PeerId),
 }
 
 /// events generated by the swarm that we wish
 /// to relay to the client
 #[derive(Debug)]
 pub enum NetworkEvent {
-    /// Recv-ed a broadcast
-    GossipMsg(Vec<u8>),
+    /// Recv-ed a broadcast, along with its author if the message was signed
+    GossipMsg(Vec<u8>, Option<PeerId>),
     /// Recv-ed a direct message from a node
     DirectRequest(Vec<u8>, PeerId, ResponseChannel<Vec<u8>>),
     /// Recv-ed a direct response from a node (that hopefully was initiated by this node)
@@ -138,11 +149,14 @@ pub enum NetworkEventInternal {
     AutonatEvent(libp2p::autonat::Event),
 }
 
-/// Bind all interfaces on port `port`
+/// Bind all interfaces on port `port`, using the address format of `transport`
 /// NOTE we may want something more general in the fture.
 #[must_use]
-pub fn gen_multiaddr(port: u16) -> Multiaddr {
-    build_multiaddr!(Ip4([0, 0, 0, 0]), Udp(port), QuicV1)
+pub fn gen_multiaddr(port: u16, transport: Libp2pTransport) -> Multiaddr {
+    match transport {
+        Libp2pTransport::Quic => build_multiaddr!(Ip4([0, 0, 0, 0]), Udp(port), QuicV1),
+        Libp2pTransport::Tcp => build_multiaddr!(Ip4([0, 0, 0, 0]), Tcp(port)),
+    }
 }
 
 /// `BoxedTransport` is a type alias for a boxed tuple containing a `PeerId` and a `StreamMuxerBox`.
@@ -150,38 +164,71 @@ pub fn gen_multiaddr(port: u16) -> Multiaddr {
 /// This type is used to represent a transport in the libp2p network framework. The `PeerId` is a unique identifier for each peer in the network, and the `StreamMuxerBox` is a type of multiplexer that can handle multiple substreams over a single connection.
 type BoxedTransport = Boxed<(PeerId, StreamMuxerBox)>;
 
+/// The timeout for establishing a secure, multiplexed connection, before we even
+/// start the stake table authentication handshake
+const CONNECTION_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(20);
+
 /// Generates an authenticated transport checked against the stake table.
 /// If the stake table or authentication message is not provided, the transport will
 /// not participate in stake table authentication.
 ///
+/// The underlying transport is either QUIC, or TCP secured with Noise and multiplexed
+/// with Yamux, depending on `kind`. The stake table handshake is the same for both.
+///
 /// # Errors
-/// If we could not create a DNS transport
+/// If we could not create a DNS transport, or the Noise configuration
 #[instrument(skip(identity))]
 pub async fn gen_transport<T: NodeType>(
     identity: Keypair,
     stake_table: Option<T::Membership>,
     auth_message: Option<Vec<u8>>,
+    kind: Libp2pTransport,
 ) -> Result<BoxedTransport, NetworkError> {
-    // Create the initial `Quic` transport
-    let transport = {
-        let mut config = quic::Config::new(&identity);
-        config.handshake_timeout = std::time::Duration::from_secs(20);
-        QuicTransport::new(config)
-    };
-
-    // Require authentication against the stake table
-    let transport: StakeTableAuthentication<_, T, _> =
-        StakeTableAuthentication::new(transport, stake_table, auth_message);
-
-    // Support DNS resolution
-    let transport = {
-        {
-            DnsTransport::system(transport)
+    match kind {
+        Libp2pTransport::Quic => {
+            // Create the initial `Quic` transport
+            let transport = {
+                let mut config = quic::Config::new(&identity);
+                config.handshake_timeout = CONNECTION_HANDSHAKE_TIMEOUT;
+                QuicTransport::new(config)
+            };
+
+            // Require authentication against the stake table
+            let transport: StakeTableAuthentication<_, T, _> =
+                StakeTableAuthentication::new(transport, stake_table, auth_message);
+
+            // Support DNS resolution
+            let transport = DnsTransport::system(transport).map_err(|e| {
+                NetworkError::ConfigError(format!("failed to build DNS transport: {e}"))
+            })?;
+
+            Ok(transport
+                .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+                .boxed())
         }
-    }
-    .map_err(|e| NetworkError::ConfigError(format!("failed to build DNS transport: {e}")))?;
+        Libp2pTransport::Tcp => {
+            // Create the initial `Tcp` transport, secured with `Noise` and multiplexed with `Yamux`
+            let noise_config = noise::Config::new(&identity).map_err(|e| {
+                NetworkError::ConfigError(format!("failed to build Noise config: {e}"))
+            })?;
+            let transport = TcpTransport::new(tcp::Config::default().nodelay(true))
+                .upgrade(Version::V1Lazy)
+                .authenticate(noise_config)
+                .multiplex(yamux::Config::default())
+                .timeout(CONNECTION_HANDSHAKE_TIMEOUT)
+                .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+                .map_err(std::io::Error::other);
+
+            // Require authentication against the stake table
+            let transport: StakeTableAuthentication<_, T, StreamMuxerBox> =
+                StakeTableAuthentication::new(transport, stake_table, auth_message);
 
-    Ok(transport
-        .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
-        .boxed())
+            // Support DNS resolution
+            let transport = DnsTransport::system(transport).map_err(|e| {
+                NetworkError::ConfigError(format!("failed to build DNS transport: {e}"))
+            })?;
+
+            Ok(transport.boxed())
+        }
+    }
 }
@@ -11,6 +11,9 @@ mod config;
 /// allows for control over the libp2p network
 mod handle;
 
+/// per-peer bandwidth accounting and rate limiting
+mod bandwidth;
+
 use std::{
     collections::{HashMap, HashSet},
     iter,
@@ -27,16 +30,22 @@ use libp2p::{
     core::transport::ListenerId,
     gossipsub::{
         Behaviour as Gossipsub, ConfigBuilder as GossipsubConfigBuilder, Event as GossipEvent,
-        Message as GossipsubMessage, MessageAuthenticity, MessageId, Topic, ValidationMode,
+        Message as GossipsubMessage, MessageAcceptance, MessageAuthenticity, MessageId,
+        PeerScoreParams, PeerScoreThresholds, Topic, ValidationMode,
     },
     identify::{
         Behaviour as IdentifyBehaviour, Config as IdentifyConfig, Event as IdentifyEvent,
         Info as IdentifyInfo,
     },
     identity::Keypair,
-    kad::{store::MemoryStore, Behaviour, Config, Mode, Record},
+    kad::{
+        store::{MemoryStore, RecordStore},
+        Behaviour, Config, Event as KademliaEvent, GetRecordOk, InboundRequest, Mode, PeerRecord,
+        QueryResult, Record, StoreInserts,
+    },
     request_response::{
-        Behaviour as RequestResponse, Config as Libp2pRequestResponseConfig, ProtocolSupport,
+        Behaviour as RequestResponse, Config as Libp2pRequestResponseConfig,
+        Event as RequestResponseEvent, Message as RequestResponseMessage, ProtocolSupport,
     },
     swarm::SwarmEvent,
     Multiaddr, StreamProtocol, Swarm, SwarmBuilder,
@@ -46,12 +55,16 @@ use rand::{prelude::SliceRandom, thread_rng};
 use tokio::{
     select, spawn,
     sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::sleep,
 };
 use tracing::{debug, error, info, info_span, instrument, warn, Instrument};
 
+use self::bandwidth::BandwidthTracker;
 pub use self::{
+    bandwidth::{BandwidthMetricsValue, Traffic},
     config::{
-        GossipConfig, NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeConfigBuilderError,
+        GossipConfig, MessageClass, NetworkNodeConfig, NetworkNodeConfigBuilder,
+        NetworkNodeConfigBuilderError, RateLimit, RateLimitAction, RateLimitConfig,
         RequestResponseConfig, DEFAULT_REPLICATION_FACTOR,
     },
     handle::{spawn_network_node, NetworkNodeHandle, NetworkNodeReceiver},
@@ -96,6 +109,13 @@ pub struct NetworkNode<T: NodeType> {
     dht_handler: DHTBehaviour<T::SignatureKey>,
     /// Channel to resend requests, set to Some when we call `spawn_listeners`
     resend_tx: Option<UnboundedSender<ClientRequest>>,
+    /// Per-peer traffic accounting and rate limiting
+    bandwidth: BandwidthTracker,
+    /// Whether gossip we publish is sent to every peer subscribed to the topic, rather than just
+    /// our mesh peers
+    flood_publish: bool,
+    /// Peers which are banned for misbehaving. We disconnect from them whenever they connect.
+    banned_peers: HashSet<PeerId>,
 }
 
 impl<T: NodeType> NetworkNode<T> {
@@ -109,6 +129,11 @@ impl<T: NodeType> NetworkNode<T> {
         self.swarm.connected_peers().copied().collect()
     }
 
+    /// Returns the traffic of class `class` exchanged with `peer` since we connected to it
+    pub fn peer_traffic(&self, peer: PeerId, class: MessageClass) -> Traffic {
+        self.bandwidth.traffic(peer, class)
+    }
+
     /// starts the swarm listening on `listen_addr`
     /// and optionally dials into peer `known_peer`
     /// returns the address the swarm is listening upon
@@ -173,6 +198,7 @@ impl<T: NodeType> NetworkNode<T> {
             keypair.clone(),
             config.stake_table.clone(),
             config.auth_message.clone(),
+            config.transport,
         )
         .await?;
 
@@ -188,6 +214,7 @@ impl<T: NodeType> NetworkNode<T> {
             let gossipsub_config = GossipsubConfigBuilder::default()
                 .message_id_fn(message_id_fn) // Use the (blake3) hash of a message as its ID
                 .validation_mode(ValidationMode::Strict) // Force all messages to have valid signatures
+                .validate_messages() // Only forward messages once we've checked them against the sender's rate limit
                 .heartbeat_interval(config.gossip_config.heartbeat_interval) // Time between gossip heartbeats
                 .history_gossip(config.gossip_config.history_gossip) // Number of heartbeats to gossip about
                 .history_length(config.gossip_config.history_length) // Number of heartbeats to remember the full message for
@@ -216,7 +243,7 @@ impl<T: NodeType> NetworkNode<T> {
                 })?;
 
             // - Build a gossipsub network behavior
-            let gossipsub: Gossipsub = Gossipsub::new(
+            let mut gossipsub: Gossipsub = Gossipsub::new(
                 MessageAuthenticity::Signed(keypair.clone()),
                 gossipsub_config,
             )
@@ -224,6 +251,21 @@ impl<T: NodeType> NetworkNode<T> {
                 NetworkError::ConfigError(format!("error building gossipsub behaviour: {err:?}"))
             })?;
 
+            // - Enable peer scoring, so misbehaving peers reported by the application are
+            //   eventually ignored. Nodes commonly share an IP in tests and small deployments, so
+            //   don't penalise that.
+            let score_params = PeerScoreParams {
+                ip_colocation_factor_weight: 0.0,
+                ..Default::default()
+            };
+            gossipsub
+                .with_peer_score(score_params, PeerScoreThresholds::default())
+                .map_err(|err| {
+                    NetworkError::ConfigError(format!(
+                        "error enabling gossipsub peer scoring: {err}"
+                    ))
+                })?;
+
             //   Build a identify network behavior needed for own
             //   node connection information
             //   E.g. this will answer the question: how are other nodes
@@ -243,7 +285,10 @@ impl<T: NodeType> NetworkNode<T> {
                 .set_parallelism(NonZeroUsize::new(5).unwrap())
                 .set_provider_publication_interval(Some(record_republication_interval))
                 .set_publication_interval(Some(record_republication_interval))
-                .set_record_ttl(ttl);
+                .set_record_ttl(ttl)
+                // Hand records peers send us to `account_dht_event` rather than storing them
+                // straight away, so records over a peer's limit are never stored
+                .set_record_filtering(StoreInserts::FilterBoth);
 
             // allowing panic here because something is very wrong if this fales
             #[allow(clippy::panic)]
@@ -321,6 +366,12 @@ impl<T: NodeType> NetworkNode<T> {
                     .unwrap_or(NonZeroUsize::new(4).unwrap()),
             ),
             resend_tx: None,
+            bandwidth: BandwidthTracker::new(
+                config.rate_limit_config.clone(),
+                config.bandwidth_metrics.clone(),
+            ),
+            flood_publish: config.gossip_config.flood_publish,
+            banned_peers: HashSet::new(),
         })
     }
 
@@ -433,7 +484,15 @@ impl<T: NodeType> NetworkNode<T> {
                         return Ok(true);
                     }
                     ClientRequest::GossipMsg(topic, contents) => {
-                        behaviour.publish_gossip(Topic::new(topic.clone()), contents.clone());
+                        let topic = Topic::new(topic.clone());
+                        for peer in behaviour.gossip_recipients(&topic, self.flood_publish) {
+                            self.bandwidth.account_outbound(
+                                peer,
+                                MessageClass::Gossip,
+                                contents.len(),
+                            );
+                        }
+                        behaviour.publish_gossip(topic, contents.clone());
                     }
                     ClientRequest::Subscribe(t, chan) => {
                         behaviour.subscribe_gossip(&t);
@@ -457,6 +516,11 @@ impl<T: NodeType> NetworkNode<T> {
                         retry_count,
                     } => {
                         debug!("Sending direct request to {:?}", pid);
+                        self.bandwidth.account_outbound(
+                            pid,
+                            MessageClass::DirectRequest,
+                            contents.len(),
+                        );
                         let id = behaviour.add_direct_request(pid, contents.clone());
                         let req = DMRequest {
                             peer_id: pid,
@@ -477,6 +541,17 @@ impl<T: NodeType> NetworkNode<T> {
                             warn!("Could not disconnect from {:?}", pid);
                         }
                     }
+                    ClientRequest::SetPeerScore(pid, score) => {
+                        behaviour.set_gossip_score(&pid, score);
+                    }
+                    ClientRequest::BanPeer(pid, duration) => {
+                        self.ban_peer(pid, duration);
+                    }
+                    ClientRequest::UnbanPeer(pid) => {
+                        info!("Lifting ban on {:?}", pid);
+                        self.banned_peers.remove(&pid);
+                        behaviour.unblacklist_gossip_peer(&pid);
+                    }
                 }
             }
             None => {
@@ -519,6 +594,13 @@ impl<T: NodeType> NetworkNode<T> {
                     );
                 }
 
+                // Refuse to talk to banned peers
+                if self.banned_peers.contains(&peer_id) {
+                    debug!("Disconnecting from banned peer {:?}", peer_id);
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                    return Ok(());
+                }
+
                 // Send the number of connected peers to the client
                 send_to_client
                     .send(NetworkEvent::ConnectedPeersUpdate(self.num_connected()))
@@ -543,6 +625,11 @@ impl<T: NodeType> NetworkNode<T> {
                     );
                 }
 
+                // Forget the peer's traffic once we have no connections left to it
+                if num_established == 0 {
+                    self.bandwidth.remove_peer(peer_id);
+                }
+
                 // Send the number of connected peers to the client
                 send_to_client
                     .send(NetworkEvent::ConnectedPeersUpdate(self.num_connected()))
@@ -576,9 +663,11 @@ impl<T: NodeType> NetworkNode<T> {
             } => {}
             SwarmEvent::Behaviour(b) => {
                 let maybe_event = match b {
-                    NetworkEventInternal::DHTEvent(e) => self
-                        .dht_handler
-                        .dht_handle_event(e, self.swarm.behaviour_mut().dht.store_mut()),
+                    NetworkEventInternal::DHTEvent(e) => {
+                        self.account_dht_event(&e);
+                        self.dht_handler
+                            .dht_handle_event(e, self.swarm.behaviour_mut().dht.store_mut())
+                    }
                     NetworkEventInternal::IdentifyEvent(e) => {
                         // NOTE feed identified peers into kademlia's routing table for peer discovery.
                         if let IdentifyEvent::Received {
@@ -605,10 +694,41 @@ impl<T: NodeType> NetworkNode<T> {
                     }
                     NetworkEventInternal::GossipEvent(e) => match *e {
                         GossipEvent::Message {
-                            propagation_source: _peer_id,
-                            message_id: _id,
+                            propagation_source,
+                            message_id,
                             message,
-                        } => Some(NetworkEvent::GossipMsg(message.data)),
+                        } => {
+                            let limited = self.bandwidth.check_inbound(
+                                propagation_source,
+                                MessageClass::Gossip,
+                                message.data.len(),
+                            );
+
+                            // Gossipsub holds on to the message until we tell it whether to
+                            // forward it, so messages over the limit go no further than us
+                            let acceptance = if limited.is_none() {
+                                MessageAcceptance::Accept
+                            } else {
+                                MessageAcceptance::Ignore
+                            };
+                            self.swarm.behaviour_mut().report_gossip_validation(
+                                &message_id,
+                                &propagation_source,
+                                acceptance,
+                            );
+
+                            match limited {
+                                None => Some(NetworkEvent::GossipMsg(message.data, message.source)),
+                                Some(action) => {
+                                    self.rate_limit_peer(
+                                        propagation_source,
+                                        MessageClass::Gossip,
+                                        action,
+                                    );
+                                    None
+                                }
+                            }
+                        }
                         GossipEvent::Subscribed { peer_id, topic } => {
                             debug!("Peer {:?} subscribed to topic {:?}", peer_id, topic);
                             None
@@ -622,9 +742,14 @@ impl<T: NodeType> NetworkNode<T> {
                             None
                         }
                     },
-                    NetworkEventInternal::DMEvent(e) => self
-                        .direct_message_state
-                        .handle_dm_event(e, self.resend_tx.clone()),
+                    NetworkEventInternal::DMEvent(e) => {
+                        if self.check_dm_event(&e) {
+                            self.direct_message_state
+                                .handle_dm_event(e, self.resend_tx.clone())
+                        } else {
+                            None
+                        }
+                    }
                     NetworkEventInternal::AutonatEvent(e) => {
                         match e {
                             autonat::Event::InboundProbe(_) => {}
@@ -698,6 +823,144 @@ impl<T: NodeType> NetworkNode<T> {
         Ok(())
     }
 
+    /// Account for the DHT records a peer sent us, and store those within its limit.
+    ///
+    /// Kademlia is configured with [`StoreInserts::FilterBoth`], so records peers send us are
+    /// only stored once they are passed to the store here.
+    fn account_dht_event(&mut self, event: &KademliaEvent) {
+        match event {
+            KademliaEvent::InboundRequest {
+                request:
+                    InboundRequest::PutRecord {
+                        source,
+                        record: Some(record),
+                        ..
+                    },
+            } => {
+                if let Some(action) =
+                    self.bandwidth
+                        .check_inbound(*source, MessageClass::Dht, record.value.len())
+                {
+                    debug!("Refusing to store DHT record from {:?}", source);
+                    self.rate_limit_peer(*source, MessageClass::Dht, action);
+                } else if let Err(err) = self
+                    .swarm
+                    .behaviour_mut()
+                    .dht
+                    .store_mut()
+                    .put(record.clone())
+                {
+                    warn!("Failed to store DHT record from {:?}: {:?}", source, err);
+                }
+            }
+            KademliaEvent::InboundRequest {
+                request:
+                    InboundRequest::AddProvider {
+                        record: Some(record),
+                    },
+            } => {
+                if let Err(err) = self
+                    .swarm
+                    .behaviour_mut()
+                    .dht
+                    .store_mut()
+                    .add_provider(record.clone())
+                {
+                    warn!("Failed to store DHT provider record: {:?}", err);
+                }
+            }
+            KademliaEvent::OutboundQueryProgressed {
+                result:
+                    QueryResult::GetRecord(Ok(GetRecordOk::FoundRecord(PeerRecord {
+                        peer: Some(peer),
+                        record,
+                    }))),
+                ..
+            } => {
+                self.bandwidth
+                    .account_inbound(*peer, MessageClass::Dht, record.value.len());
+            }
+            _ => {}
+        }
+    }
+
+    /// Account for a direct message, and check direct requests against the sender's limit.
+    ///
+    /// Returns whether the event should be handled.
+    fn check_dm_event(&mut self, event: &RequestResponseEvent<Vec<u8>, Vec<u8>>) -> bool {
+        let RequestResponseEvent::Message { peer, message, .. } = event else {
+            return true;
+        };
+
+        match message {
+            RequestResponseMessage::Request { request, .. } => {
+                match self.bandwidth.check_inbound(
+                    *peer,
+                    MessageClass::DirectRequest,
+                    request.len(),
+                ) {
+                    None => true,
+                    Some(action) => {
+                        self.rate_limit_peer(*peer, MessageClass::DirectRequest, action);
+                        false
+                    }
+                }
+            }
+            RequestResponseMessage::Response { response, .. } => {
+                // Responses are bounded by the requests we send, so they are not limited
+                self.bandwidth
+                    .account_inbound(*peer, MessageClass::DirectRequest, response.len());
+                true
+            }
+        }
+    }
+
+    /// Deal with a peer which exceeded its limit for `class`. The offending message has already
+    /// been dropped.
+    fn rate_limit_peer(&mut self, peer: PeerId, class: MessageClass, action: RateLimitAction) {
+        match action {
+            RateLimitAction::Deprioritise => {
+                debug!("Peer {:?} exceeded its {:?} rate limit", peer, class);
+
+                // Stop routing DHT queries through a peer flooding the DHT
+                if class == MessageClass::Dht {
+                    self.swarm.behaviour_mut().dht.remove_peer(&peer);
+                }
+            }
+            RateLimitAction::Disconnect => {
+                warn!(
+                    "Peer {:?} exceeded its {:?} rate limit, disconnecting",
+                    peer, class
+                );
+                if self.swarm.disconnect_peer_id(peer).is_err() {
+                    warn!("Could not disconnect from {:?}", peer);
+                }
+            }
+        }
+    }
+
+    /// Ban `peer` for `duration`: ignore its gossip, stop routing through it and disconnect from
+    /// it, and schedule lifting the ban
+    fn ban_peer(&mut self, peer: PeerId, duration: Duration) {
+        warn!("Banning {:?} for {:?}", peer, duration);
+        self.banned_peers.insert(peer);
+
+        let behaviour = self.swarm.behaviour_mut();
+        behaviour.blacklist_gossip_peer(&peer);
+        behaviour.dht.remove_peer(&peer);
+        if self.swarm.disconnect_peer_id(peer).is_err() {
+            debug!("Banned peer {:?} was not connected", peer);
+        }
+
+        if let Some(resend_tx) = self.resend_tx.clone() {
+            spawn(async move {
+                sleep(duration).await;
+                // The node has shut down if this fails, so there is nothing to unban
+                let _ = resend_tx.send(ClientRequest::UnbanPeer(peer));
+            });
+        }
+    }
+
     /// Spawn a task to listen for requests on the returned channel
     /// as well as any events produced by libp2p
     #[instrument]
@@ -750,3 +1013,168 @@ impl<T: NodeType> NetworkNode<T> {
         self.peer_id
     }
 }
+
+#[cfg(test)]
+mod test {
+    use hotshot_example_types::node_types::TestTypes;
+    use hotshot_types::{signature_key::BLSPubKey, traits::signature_key::SignatureKey};
+    use libp2p::{build_multiaddr, kad::Quorum};
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::network::behaviours::dht::record::{Namespace, RecordKey, RecordValue};
+
+    /// The topic both nodes gossip on
+    const TOPIC: &str = "global";
+
+    /// How many messages of each class the sender sends
+    const NUM_MESSAGES: u64 = 5;
+
+    /// One message per second, with no more than one second's worth of burst
+    fn one_per_second() -> RateLimit {
+        RateLimit {
+            messages_per_second: 1,
+            bytes_per_second: 1 << 20,
+            burst: Duration::from_secs(1),
+        }
+    }
+
+    /// A node listening on a random local port, and the address it listens on
+    async fn node(rate_limit_config: RateLimitConfig) -> (NetworkNode<TestTypes>, Multiaddr) {
+        let config = NetworkNodeConfigBuilder::default()
+            .replication_factor(NonZeroUsize::new(1).unwrap())
+            .to_connect_addrs(HashSet::new())
+            .rate_limit_config(rate_limit_config)
+            .build()
+            .unwrap();
+        let mut node = NetworkNode::new(config).await.unwrap();
+        let addr = node
+            .start_listen(build_multiaddr!(Ip4([127, 0, 0, 1]), Udp(0u16), QuicV1))
+            .await
+            .unwrap();
+        (node, addr)
+    }
+
+    /// Drive the swarms of both nodes until `done` holds, collecting the events `receiver` hands
+    /// to its client in `events`
+    async fn drive_until(
+        sender: &mut NetworkNode<TestTypes>,
+        receiver: &mut NetworkNode<TestTypes>,
+        events: &mut Vec<NetworkEvent>,
+        mut done: impl FnMut(&mut NetworkNode<TestTypes>, &mut NetworkNode<TestTypes>) -> bool,
+    ) {
+        let (sender_tx, _sender_rx) = unbounded_channel();
+        let (receiver_tx, mut receiver_rx) = unbounded_channel();
+
+        timeout(Duration::from_secs(30), async {
+            while !done(sender, receiver) {
+                select! {
+                    Some(event) = sender.swarm.next() => {
+                        sender.handle_swarm_events(event, &sender_tx).await.unwrap();
+                    }
+                    Some(event) = receiver.swarm.next() => {
+                        receiver.handle_swarm_events(event, &receiver_tx).await.unwrap();
+                    }
+                }
+                while let Ok(event) = receiver_rx.try_recv() {
+                    events.push(event);
+                }
+            }
+        })
+        .await
+        .expect("Timed out driving the swarms");
+    }
+
+    /// A peer over its limit can neither get its gossip delivered (and so forwarded) nor its DHT
+    /// records stored
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rate_limit_applies_before_forwarding_and_storing() {
+        let (mut receiver, receiver_addr) = node(RateLimitConfig {
+            gossip: Some(one_per_second()),
+            dht: Some(one_per_second()),
+            action: RateLimitAction::Deprioritise,
+            ..Default::default()
+        })
+        .await;
+        let (mut sender, _) = node(RateLimitConfig::default()).await;
+        let mut events = Vec::new();
+
+        // Connect, and wait until the sender knows the receiver is subscribed to the topic
+        sender
+            .swarm
+            .behaviour_mut()
+            .add_address(&receiver.peer_id(), receiver_addr.clone());
+        sender.swarm.dial(receiver_addr).unwrap();
+        sender.swarm.behaviour_mut().subscribe_gossip(TOPIC);
+        receiver.swarm.behaviour_mut().subscribe_gossip(TOPIC);
+        let topic = Topic::new(TOPIC);
+        drive_until(&mut sender, &mut receiver, &mut events, |sender, _| {
+            !sender
+                .swarm
+                .behaviour()
+                .gossip_recipients(&topic, true)
+                .is_empty()
+        })
+        .await;
+
+        // Gossip
+        for i in 0..NUM_MESSAGES {
+            sender
+                .swarm
+                .behaviour_mut()
+                .publish_gossip(topic.clone(), vec![u8::try_from(i).unwrap()]);
+        }
+        let sender_id = sender.peer_id();
+        drive_until(&mut sender, &mut receiver, &mut events, |_, receiver| {
+            receiver
+                .peer_traffic(sender_id, MessageClass::Gossip)
+                .inbound_messages
+                == NUM_MESSAGES
+        })
+        .await;
+
+        let refused = receiver
+            .peer_traffic(sender_id, MessageClass::Gossip)
+            .rate_limit_violations;
+        let delivered = events
+            .iter()
+            .filter(|event| matches!(event, NetworkEvent::GossipMsg(..)))
+            .count();
+        assert!(refused > 0, "No gossip was over the limit");
+        assert_eq!(delivered as u64, NUM_MESSAGES - refused);
+
+        // DHT records
+        let keys: Vec<_> = (0..NUM_MESSAGES)
+            .map(|i| {
+                let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([7; 32], i);
+                let key = RecordKey::new(Namespace::Lookup, public_key.to_bytes());
+                let value: RecordValue<BLSPubKey> =
+                    RecordValue::new_signed(&key, vec![1, 2, 3], &private_key).unwrap();
+                let record = Record::new(key.to_bytes(), bincode::serialize(&value).unwrap());
+                let record_key = record.key.clone();
+                sender
+                    .swarm
+                    .behaviour_mut()
+                    .dht
+                    .put_record(record, Quorum::One)
+                    .unwrap();
+                record_key
+            })
+            .collect();
+        drive_until(&mut sender, &mut receiver, &mut events, |_, receiver| {
+            receiver
+                .peer_traffic(sender_id, MessageClass::Dht)
+                .inbound_messages
+                == NUM_MESSAGES
+        })
+        .await;
+
+        let refused = receiver
+            .peer_traffic(sender_id, MessageClass::Dht)
+            .rate_limit_violations;
+        let store = receiver.swarm.behaviour_mut().dht.store_mut();
+        let stored = keys.iter().filter(|key| store.get(key).is_some()).count();
+        assert!(refused > 0, "No DHT record was over the limit");
+        assert_eq!(stored as u64, NUM_MESSAGES - refused);
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, time::Instant};
+
+use hotshot_types::traits::metrics::{
+    Counter, CounterFamily, Gauge, GaugeFamily, Metrics, NoMetrics,
+};
+use libp2p_identity::PeerId;
+
+use super::config::{MessageClass, RateLimit, RateLimitAction, RateLimitConfig};
+
+/// Per-peer traffic metrics, labelled by peer ID and message class
+#[derive(Clone, Debug)]
+pub struct BandwidthMetricsValue {
+    /// Bytes received from each peer
+    pub inbound_bytes: Box<dyn CounterFamily>,
+    /// Messages received from each peer
+    pub inbound_messages: Box<dyn CounterFamily>,
+    /// Bytes sent to each peer
+    pub outbound_bytes: Box<dyn CounterFamily>,
+    /// Messages sent to each peer
+    pub outbound_messages: Box<dyn CounterFamily>,
+    /// Messages dropped because the peer exceeded its limit
+    pub rate_limit_violations: Box<dyn CounterFamily>,
+    /// Whether the peer is currently over its limit
+    pub rate_limited: Box<dyn GaugeFamily>,
+}
+
+impl BandwidthMetricsValue {
+    /// Populate the metrics with per-peer traffic metrics
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        let labels = || vec!["peer".to_string(), "class".to_string()];
+
+        Self {
+            inbound_bytes: metrics.counter_family("peer_inbound_bytes".into(), labels()),
+            inbound_messages: metrics.counter_family("peer_inbound_messages".into(), labels()),
+            outbound_bytes: metrics.counter_family("peer_outbound_bytes".into(), labels()),
+            outbound_messages: metrics.counter_family("peer_outbound_messages".into(), labels()),
+            rate_limit_violations: metrics
+                .counter_family("peer_rate_limit_violations".into(), labels()),
+            rate_limited: metrics.gauge_family("peer_rate_limited".into(), labels()),
+        }
+    }
+}
+
+impl Default for BandwidthMetricsValue {
+    /// Initialize with empty metrics
+    fn default() -> Self {
+        Self::new(&*NoMetrics::boxed())
+    }
+}
+
+/// A token bucket, refilled continuously at a fixed rate
+#[derive(Debug)]
+struct TokenBucket {
+    /// The maximum number of tokens
+    capacity: f64,
+    /// The number of tokens added per second
+    rate: f64,
+    /// The current number of tokens. May be negative after a single large message.
+    tokens: f64,
+    /// When we last refilled the bucket
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+        Self {
+            capacity,
+            rate,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// Try to take `amount` tokens from the bucket.
+    ///
+    /// An amount larger than the capacity is allowed when the bucket is full, which leaves the
+    /// bucket in debt, so that messages larger than the burst are throttled rather than refused
+    /// forever.
+    fn try_take(&mut self, amount: f64, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= amount.min(self.capacity) {
+            self.tokens -= amount;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The buckets limiting a single class of traffic from a single peer
+#[derive(Debug)]
+struct Limiter {
+    /// Limits the number of messages
+    messages: TokenBucket,
+    /// Limits the number of bytes
+    bytes: TokenBucket,
+}
+
+impl Limiter {
+    /// Create a limiter with full buckets
+    #[allow(clippy::cast_precision_loss)]
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        let burst = limit.burst.as_secs_f64();
+        let messages_per_second = f64::from(limit.messages_per_second);
+        let bytes_per_second = limit.bytes_per_second as f64;
+
+        Self {
+            messages: TokenBucket::new(
+                messages_per_second,
+                (messages_per_second * burst).max(1.0),
+                now,
+            ),
+            bytes: TokenBucket::new(bytes_per_second, bytes_per_second * burst, now),
+        }
+    }
+
+    /// Try to let a message of `bytes` bytes through
+    #[allow(clippy::cast_precision_loss)]
+    fn try_take(&mut self, bytes: usize, now: Instant) -> bool {
+        // A message refused for its size gives back its message token
+        let messages = self.messages.try_take(1.0, now);
+        let bytes = messages && self.bytes.try_take(bytes as f64, now);
+        if messages && !bytes {
+            self.messages.tokens += 1.0;
+        }
+        bytes
+    }
+}
+
+/// Totals of the traffic with a peer in one class
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Traffic {
+    /// Bytes received
+    pub inbound_bytes: u64,
+    /// Messages received
+    pub inbound_messages: u64,
+    /// Bytes sent
+    pub outbound_bytes: u64,
+    /// Messages sent
+    pub outbound_messages: u64,
+    /// Messages dropped because the peer exceeded its limit
+    pub rate_limit_violations: u64,
+}
+
+/// The metrics for a single peer and class, created lazily
+#[derive(Debug)]
+struct TrafficMetrics {
+    /// See [`BandwidthMetricsValue::inbound_bytes`]
+    inbound_bytes: Box<dyn Counter>,
+    /// See [`BandwidthMetricsValue::inbound_messages`]
+    inbound_messages: Box<dyn Counter>,
+    /// See [`BandwidthMetricsValue::outbound_bytes`]
+    outbound_bytes: Box<dyn Counter>,
+    /// See [`BandwidthMetricsValue::outbound_messages`]
+    outbound_messages: Box<dyn Counter>,
+    /// See [`BandwidthMetricsValue::rate_limit_violations`]
+    rate_limit_violations: Box<dyn Counter>,
+    /// See [`BandwidthMetricsValue::rate_limited`]
+    rate_limited: Box<dyn Gauge>,
+}
+
+/// Everything we track about one class of traffic with a peer
+#[derive(Debug)]
+struct PeerTraffic {
+    /// The totals so far
+    traffic: Traffic,
+    /// The metrics the totals are reported to
+    metrics: TrafficMetrics,
+    /// The limit on inbound traffic, if there is one
+    limiter: Option<Limiter>,
+}
+
+/// Accounts for the traffic with each peer, and decides when a peer has exceeded its limits
+#[derive(Debug)]
+pub(crate) struct BandwidthTracker {
+    /// The configured limits
+    config: RateLimitConfig,
+    /// The metric families
+    metrics: BandwidthMetricsValue,
+    /// The traffic with each peer, per class
+    peers: HashMap<(PeerId, MessageClass), PeerTraffic>,
+}
+
+impl BandwidthTracker {
+    /// Create a new tracker with the given limits, reporting to the given metrics
+    pub(crate) fn new(config: RateLimitConfig, metrics: BandwidthMetricsValue) -> Self {
+        Self {
+            config,
+            metrics,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Get the entry for `peer` and `class`, creating it if needed
+    fn entry(&mut self, peer: PeerId, class: MessageClass, now: Instant) -> &mut PeerTraffic {
+        let Self {
+            config,
+            metrics,
+            peers,
+        } = self;
+
+        peers.entry((peer, class)).or_insert_with(|| {
+            let labels = vec![peer.to_string(), class.label().to_string()];
+            PeerTraffic {
+                traffic: Traffic::default(),
+                metrics: TrafficMetrics {
+                    inbound_bytes: metrics.inbound_bytes.create(labels.clone()),
+                    inbound_messages: metrics.inbound_messages.create(labels.clone()),
+                    outbound_bytes: metrics.outbound_bytes.create(labels.clone()),
+                    outbound_messages: metrics.outbound_messages.create(labels.clone()),
+                    rate_limit_violations: metrics.rate_limit_violations.create(labels.clone()),
+                    rate_limited: metrics.rate_limited.create(labels),
+                },
+                limiter: config.limit(class).map(|limit| Limiter::new(limit, now)),
+            }
+        })
+    }
+
+    /// Account for a message of `bytes` bytes received from `peer`, without limiting it
+    pub(crate) fn account_inbound(&mut self, peer: PeerId, class: MessageClass, bytes: usize) {
+        let entry = self.entry(peer, class, Instant::now());
+        entry.traffic.inbound_bytes += bytes as u64;
+        entry.traffic.inbound_messages += 1;
+        entry.metrics.inbound_bytes.add(bytes);
+        entry.metrics.inbound_messages.add(1);
+    }
+
+    /// Account for a message of `bytes` bytes received from `peer`, and check it against the
+    /// peer's limit.
+    ///
+    /// Returns what to do with the peer if it exceeded its limit, in which case the message should
+    /// be dropped.
+    pub(crate) fn check_inbound(
+        &mut self,
+        peer: PeerId,
+        class: MessageClass,
+        bytes: usize,
+    ) -> Option<RateLimitAction> {
+        self.check_inbound_at(peer, class, bytes, Instant::now())
+    }
+
+    /// [`Self::check_inbound`] at a given time
+    fn check_inbound_at(
+        &mut self,
+        peer: PeerId,
+        class: MessageClass,
+        bytes: usize,
+        now: Instant,
+    ) -> Option<RateLimitAction> {
+        let action = self.config.action;
+        self.account_inbound(peer, class, bytes);
+
+        let entry = self.entry(peer, class, now);
+        let limiter = entry.limiter.as_mut()?;
+        if limiter.try_take(bytes, now) {
+            entry.metrics.rate_limited.set(0);
+            None
+        } else {
+            entry.traffic.rate_limit_violations += 1;
+            entry.metrics.rate_limit_violations.add(1);
+            entry.metrics.rate_limited.set(1);
+            Some(action)
+        }
+    }
+
+    /// Account for a message of `bytes` bytes sent to `peer`
+    pub(crate) fn account_outbound(&mut self, peer: PeerId, class: MessageClass, bytes: usize) {
+        let entry = self.entry(peer, class, Instant::now());
+        entry.traffic.outbound_bytes += bytes as u64;
+        entry.traffic.outbound_messages += 1;
+        entry.metrics.outbound_bytes.add(bytes);
+        entry.metrics.outbound_messages.add(1);
+    }
+
+    /// The traffic with `peer` in `class` since we connected to it
+    pub(crate) fn traffic(&self, peer: PeerId, class: MessageClass) -> Traffic {
+        self.peers
+            .get(&(peer, class))
+            .map(|entry| entry.traffic)
+            .unwrap_or_default()
+    }
+
+    /// Forget about a peer we are no longer connected to. Its limits start afresh if it
+    /// reconnects, but the metrics keep their totals.
+    pub(crate) fn remove_peer(&mut self, peer: PeerId) {
+        self.peers.retain(|(other, _), entry| {
+            if *other == peer {
+                entry.metrics.rate_limited.set(0);
+            }
+            *other != peer
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn random_peer() -> PeerId {
+        libp2p::identity::Keypair::generate_ed25519()
+            .public()
+            .to_peer_id()
+    }
+
+    /// A limit of 10 messages or 1000 bytes per second, with a one second burst
+    fn limit() -> RateLimit {
+        RateLimit {
+            messages_per_second: 10,
+            bytes_per_second: 1000,
+            burst: Duration::from_secs(1),
+        }
+    }
+
+    fn tracker(action: RateLimitAction) -> BandwidthTracker {
+        BandwidthTracker::new(
+            RateLimitConfig {
+                gossip: Some(limit()),
+                action,
+                ..Default::default()
+            },
+            BandwidthMetricsValue::default(),
+        )
+    }
+
+    #[test]
+    fn token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 10.0, start);
+
+        for _ in 0..10 {
+            assert!(bucket.try_take(1.0, start));
+        }
+        assert!(!bucket.try_take(1.0, start));
+
+        // Half a second refills half the bucket
+        let later = start + Duration::from_millis(500);
+        for _ in 0..5 {
+            assert!(bucket.try_take(1.0, later));
+        }
+        assert!(!bucket.try_take(1.0, later));
+
+        // The bucket never holds more than its capacity
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..10 {
+            assert!(bucket.try_take(1.0, much_later));
+        }
+        assert!(!bucket.try_take(1.0, much_later));
+    }
+
+    #[test]
+    fn token_bucket_allows_large_message_when_full() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 10.0, start);
+
+        // A message larger than the burst goes through once, and leaves the bucket in debt
+        assert!(bucket.try_take(25.0, start));
+        assert!(!bucket.try_take(1.0, start + Duration::from_secs(1)));
+        assert!(bucket.try_take(1.0, start + Duration::from_millis(1600)));
+    }
+
+    #[test]
+    fn limits_each_peer_separately() {
+        let mut tracker = tracker(RateLimitAction::Disconnect);
+        let noisy = random_peer();
+        let quiet = random_peer();
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert_eq!(
+                tracker.check_inbound_at(noisy, MessageClass::Gossip, 10, now),
+                None
+            );
+        }
+        assert_eq!(
+            tracker.check_inbound_at(noisy, MessageClass::Gossip, 10, now),
+            Some(RateLimitAction::Disconnect)
+        );
+        assert_eq!(
+            tracker.check_inbound_at(quiet, MessageClass::Gossip, 10, now),
+            None
+        );
+
+        // Classes without a limit are only accounted for
+        for _ in 0..100 {
+            assert_eq!(
+                tracker.check_inbound_at(noisy, MessageClass::DirectRequest, 10, now),
+                None
+            );
+        }
+
+        assert_eq!(
+            tracker.traffic(noisy, MessageClass::Gossip),
+            Traffic {
+                inbound_bytes: 110,
+                inbound_messages: 11,
+                rate_limit_violations: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            tracker
+                .traffic(noisy, MessageClass::DirectRequest)
+                .inbound_messages,
+            100
+        );
+    }
+
+    #[test]
+    fn limits_bytes() {
+        let mut tracker = tracker(RateLimitAction::Deprioritise);
+        let peer = random_peer();
+        let now = Instant::now();
+
+        assert_eq!(
+            tracker.check_inbound_at(peer, MessageClass::Gossip, 600, now),
+            None
+        );
+        assert_eq!(
+            tracker.check_inbound_at(peer, MessageClass::Gossip, 600, now),
+            Some(RateLimitAction::Deprioritise)
+        );
+        // The refused message didn't use up a message token
+        for _ in 0..9 {
+            assert_eq!(
+                tracker.check_inbound_at(peer, MessageClass::Gossip, 0, now),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn accounts_outbound_and_forgets_peers() {
+        let mut tracker = tracker(RateLimitAction::Disconnect);
+        let peer = random_peer();
+
+        tracker.account_outbound(peer, MessageClass::DirectRequest, 42);
+        tracker.account_outbound(peer, MessageClass::DirectRequest, 8);
+        assert_eq!(
+            tracker.traffic(peer, MessageClass::DirectRequest),
+            Traffic {
+                outbound_bytes: 50,
+                outbound_messages: 2,
+                ..Default::default()
+            }
+        );
+
+        tracker.remove_peer(peer);
+        assert_eq!(
+            tracker.traffic(peer, MessageClass::DirectRequest),
+            Traffic::default()
+        );
+    }
+}
@@ -6,11 +6,11 @@
 
 use std::{collections::HashSet, num::NonZeroUsize, time::Duration};
 
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::{network::Libp2pTransport, traits::node_implementation::NodeType};
 use libp2p::{identity::Keypair, Multiaddr};
 use libp2p_identity::PeerId;
 
-use super::MAX_GOSSIP_MSG_SIZE;
+use super::{BandwidthMetricsValue, MAX_GOSSIP_MSG_SIZE};
 
 /// The default Kademlia replication factor
 pub const DEFAULT_REPLICATION_FACTOR: Option<NonZeroUsize> = NonZeroUsize::new(10);
@@ -25,6 +25,9 @@ pub struct NetworkNodeConfig<T: NodeType> {
     /// The address to bind to
     #[builder(default)]
     pub bind_address: Option<Multiaddr>,
+    /// The transport to connect to other nodes over. Must match `bind_address`
+    #[builder(default)]
+    pub transport: Libp2pTransport,
     /// Replication factor for entries in the DHT
     #[builder(setter(into, strip_option), default = "DEFAULT_REPLICATION_FACTOR")]
     pub replication_factor: Option<NonZeroUsize>,
@@ -59,6 +62,15 @@ pub struct NetworkNodeConfig<T: NodeType> {
     #[builder(default)]
     /// The timeout for DHT lookups.
     pub dht_timeout: Option<Duration>,
+
+    #[builder(default)]
+    /// Per-peer limits on the traffic we accept
+    pub rate_limit_config: RateLimitConfig,
+
+    #[builder(default)]
+    #[debug(skip)]
+    /// Metrics for the traffic of each peer
+    pub bandwidth_metrics: BandwidthMetricsValue,
 }
 
 /// Configuration for Libp2p's Gossipsub
@@ -173,3 +185,74 @@ impl Default for RequestResponseConfig {
         }
     }
 }
+
+/// The kinds of traffic we account for and limit separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+    /// Gossipsub messages
+    Gossip,
+    /// Direct requests and their responses
+    DirectRequest,
+    /// Kademlia DHT records
+    Dht,
+}
+
+impl MessageClass {
+    /// The label used for this class in metrics
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Gossip => "gossip",
+            Self::DirectRequest => "direct_request",
+            Self::Dht => "dht",
+        }
+    }
+}
+
+/// A token-bucket limit on the traffic a single peer may send us
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    /// The sustained number of messages per second
+    pub messages_per_second: u32,
+    /// The sustained number of bytes per second
+    pub bytes_per_second: u64,
+    /// How long a peer may send at full speed after being idle. The buckets hold this many
+    /// seconds worth of the sustained rates.
+    pub burst: Duration,
+}
+
+/// What to do with a peer which exceeds its limit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Drop the offending message, and stop using the peer for DHT queries if it is flooding the
+    /// DHT, but stay connected
+    #[default]
+    Deprioritise,
+    /// Drop the offending message and disconnect from the peer
+    Disconnect,
+}
+
+/// Configuration for per-peer rate limiting. Classes without a limit are only accounted for.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimitConfig {
+    /// The limit on gossip messages
+    pub gossip: Option<RateLimit>,
+    /// The limit on direct requests
+    pub direct_request: Option<RateLimit>,
+    /// The limit on DHT records
+    pub dht: Option<RateLimit>,
+    /// What to do with peers which exceed a limit
+    pub action: RateLimitAction,
+}
+
+impl RateLimitConfig {
+    /// The limit for messages of class `class`, if any
+    #[must_use]
+    pub fn limit(&self, class: MessageClass) -> Option<RateLimit> {
+        match class {
+            MessageClass::Gossip => self.gossip,
+            MessageClass::DirectRequest => self.direct_request,
+            MessageClass::Dht => self.dht,
+        }
+    }
+}
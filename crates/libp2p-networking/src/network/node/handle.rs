@@ -88,7 +88,7 @@ pub async fn spawn_network_node<T: NodeType>(
     let listen_addr = config
         .bind_address
         .clone()
-        .unwrap_or_else(|| gen_multiaddr(0));
+        .unwrap_or_else(|| gen_multiaddr(0, config.transport));
     let peer_id = network.peer_id();
     let listen_addr = network.start_listen(listen_addr).await.map_err(|e| {
         NetworkError::ListenError(format!("failed to start listening on Libp2p: {e}"))
@@ -379,6 +379,23 @@ impl<T: NodeType> NetworkNodeHandle<T> {
         self.send_request(req)
     }
 
+    /// Set the application-specific gossipsub score of `pid`. Negative scores make gossipsub
+    /// gradually stop exchanging messages with the peer.
+    /// # Errors
+    /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
+    pub fn set_peer_score(&self, pid: PeerId, score: f64) -> Result<(), NetworkError> {
+        let req = ClientRequest::SetPeerScore(pid, score);
+        self.send_request(req)
+    }
+
+    /// Disconnect from `pid` and refuse to talk to it for `duration`
+    /// # Errors
+    /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
+    pub fn ban_peer(&self, pid: PeerId, duration: Duration) -> Result<(), NetworkError> {
+        let req = ClientRequest::BanPeer(pid, duration);
+        self.send_request(req)
+    }
+
     /// Gossip a message to peers
     /// # Errors
     /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
@@ -520,17 +520,24 @@ pub async fn write_length_delimited<S: AsyncWrite + Unpin>(
 mod test {
     use std::sync::Arc;
 
+    use futures::StreamExt;
     use hotshot_example_types::node_types::TestTypes;
     use hotshot_types::{
         light_client::StateVerKey,
+        network::Libp2pTransport,
         signature_key::BLSPubKey,
         traits::{network::Topic, signature_key::SignatureKey},
         PeerConfig,
     };
-    use libp2p::{core::transport::dummy::DummyTransport, quic::Connection};
+    use libp2p::{
+        build_multiaddr,
+        core::transport::{dummy::DummyTransport, ListenerId},
+        quic::Connection,
+    };
     use rand::Rng;
 
     use super::*;
+    use crate::network::gen_transport;
 
     /// A mock type to help with readability
     type MockStakeTableAuth = StakeTableAuthentication<DummyTransport, TestTypes, Connection>;
@@ -732,6 +739,116 @@ mod test {
         );
     }
 
+    /// Connect a dialer to a listener over `transport`, using the full transport stack that the
+    /// network uses, and return the peer ID each side authenticated. The listener is always in the
+    /// stake table, the dialer only if `dialer_staked` is set.
+    async fn connect_over(
+        transport: Libp2pTransport,
+        dialer_staked: bool,
+    ) -> AnyhowResult<(PeerId, PeerId)> {
+        // Create a BLS key, a Libp2p identity, and an authentication message for a node
+        let new_node = || {
+            let seed = rand::rngs::OsRng.gen::<[u8; 32]>();
+            let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed(seed, 1337);
+            let identity = libp2p::identity::Keypair::generate_ed25519();
+            let auth_message = super::construct_auth_message(
+                &public_key,
+                &identity.public().to_peer_id(),
+                &private_key,
+            )
+            .unwrap();
+            (public_key, identity, auth_message)
+        };
+        let (listener_key, listener_identity, listener_auth_message) = new_node();
+        let (dialer_key, dialer_identity, dialer_auth_message) = new_node();
+
+        // Create the stake table
+        let mut peers = vec![listener_key];
+        if dialer_staked {
+            peers.push(dialer_key);
+        }
+        let peer_configs: Vec<_> = peers
+            .into_iter()
+            .map(|key| PeerConfig {
+                stake_table_entry: key.stake_table_entry(1),
+                state_ver_key: StateVerKey::default(),
+            })
+            .collect();
+        let stake_table = <TestTypes as NodeType>::Membership::new(
+            peer_configs.clone(),
+            peer_configs,
+            Topic::Global,
+        );
+
+        // Create both transports
+        let mut listener = gen_transport::<TestTypes>(
+            listener_identity,
+            Some(stake_table.clone()),
+            Some(listener_auth_message),
+            transport,
+        )
+        .await?;
+        let mut dialer = gen_transport::<TestTypes>(
+            dialer_identity,
+            Some(stake_table),
+            Some(dialer_auth_message),
+            transport,
+        )
+        .await?;
+
+        // Listen on a random local port and wait until we are bound
+        let listen_addr = match transport {
+            Libp2pTransport::Quic => build_multiaddr!(Ip4([127, 0, 0, 1]), Udp(0u16), QuicV1),
+            Libp2pTransport::Tcp => build_multiaddr!(Ip4([127, 0, 0, 1]), Tcp(0u16)),
+        };
+        listener.listen_on(ListenerId::next(), listen_addr)?;
+        let addr = loop {
+            if let TransportEvent::NewAddress { listen_addr, .. } =
+                listener.select_next_some().await
+            {
+                break listen_addr;
+            }
+        };
+
+        // Dial the listener, and accept the connection on the other side
+        let dial = dialer.dial(addr)?;
+        let accept = async {
+            loop {
+                if let TransportEvent::Incoming { upgrade, .. } = listener.select_next_some().await
+                {
+                    return upgrade.await;
+                }
+            }
+        };
+        let (dialed, accepted) = futures::join!(dial, accept);
+
+        Ok((dialed?.0, accepted?.0))
+    }
+
+    /// Test that the handshake succeeds over both transports when both peers are in the stake table
+    #[tokio::test(flavor = "multi_thread")]
+    async fn handshake_over_transports() {
+        for transport in [Libp2pTransport::Quic, Libp2pTransport::Tcp] {
+            let result = connect_over(transport, true).await;
+            assert!(
+                result.is_ok(),
+                "Should have connected over {transport:?} but did not: {result:?}"
+            );
+        }
+    }
+
+    /// Test that the handshake fails over both transports when the dialer is not in the
+    /// stake table
+    #[tokio::test(flavor = "multi_thread")]
+    async fn handshake_over_transports_not_in_stake_table() {
+        for transport in [Libp2pTransport::Quic, Libp2pTransport::Tcp] {
+            assert!(
+                connect_over(transport, false).await.is_err(),
+                "Should have failed authentication over {transport:?} but did not"
+            );
+        }
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn read_and_write_length_delimited() {
         // Create a message
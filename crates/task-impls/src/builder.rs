@@ -4,10 +4,15 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::time::{Duration, Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::{FutureExt, SinkExt, StreamExt};
 
 use hotshot_builder_api::v0_1::{
-    block_info::AvailableBlockInfo,
+    block_info::{AvailableBlockInfo, AvailableBlocksRequest, AvailableBlocksUpdate},
     builder::{BuildError, Error as BuilderApiError},
 };
 use hotshot_types::{
@@ -15,11 +20,12 @@ use hotshot_types::{
     traits::{node_implementation::NodeType, signature_key::SignatureKey},
     vid::VidCommitment,
 };
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use surf_disco::{client::HealthStatus, Client, Url};
+use surf_disco::{client::HealthStatus, socket::Connection, Client, Url};
 use tagged_base64::TaggedBase64;
 use thiserror::Error;
-use tokio::time::sleep;
+use tokio::{sync::Mutex as AsyncMutex, time::sleep};
 use vbs::version::StaticVersionType;
 
 #[derive(Debug, Error, Serialize, Deserialize)]
@@ -59,14 +65,55 @@ impl From<BuilderApiError> for BuilderClientError {
     }
 }
 
+/// How long we poll a builder after subscribing to its available blocks first fails, before we
+/// try to subscribe again
+const STREAMING_RETRY_MIN: Duration = Duration::from_secs(1);
+
+/// The longest we poll a builder before we try to subscribe to its available blocks again
+const STREAMING_RETRY_MAX: Duration = Duration::from_secs(5 * 60);
+
+/// When to try subscribing to the available blocks of a builder again
+#[derive(Debug)]
+struct StreamingRetry {
+    /// We poll the builder until then
+    after: Instant,
+    /// How long we poll it for, which doubles each time subscribing fails again
+    backoff: Duration,
+}
+
+/// Our subscription to the blocks a builder makes available
+type AvailableBlocksSubscription<TYPES, Ver> =
+    Connection<AvailableBlocksUpdate<TYPES>, AvailableBlocksRequest<TYPES>, BuilderApiError, Ver>;
+
 /// Client for builder API
 pub struct BuilderClient<TYPES: NodeType, Ver: StaticVersionType> {
     /// Underlying surf_disco::Client for the legacy builder api
     client: Client<BuilderApiError, Ver>,
+    /// Base URL of the builder
+    url: Url,
+    /// Set while we poll the builder because subscribing to its available blocks failed; we try
+    /// to subscribe again once it expires, in case the builder was only unavailable or upgraded.
+    /// Clones share it.
+    streaming_retry: Arc<Mutex<Option<StreamingRetry>>>,
+    /// Our subscription to the blocks the builder makes available, which we keep across views.
+    /// Clones share it.
+    subscription: Arc<AsyncMutex<Option<AvailableBlocksSubscription<TYPES, Ver>>>>,
     /// Marker for [`NodeType`] used here
     _marker: std::marker::PhantomData<TYPES>,
 }
 
+impl<TYPES: NodeType, Ver: StaticVersionType> Clone for BuilderClient<TYPES, Ver> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            url: self.url.clone(),
+            streaming_retry: Arc::clone(&self.streaming_retry),
+            subscription: Arc::clone(&self.subscription),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
 impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
     /// Construct a new client from base url
     ///
@@ -80,10 +127,19 @@ impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
             client: Client::builder(url.clone())
                 .set_timeout(Some(Duration::from_secs(2)))
                 .build(),
+            url,
+            streaming_retry: Arc::new(Mutex::new(None)),
+            subscription: Arc::new(AsyncMutex::new(None)),
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// Base URL of the builder
+    #[must_use]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
     /// Wait for server to become available
     /// Returns `false` if server doesn't respond
     /// with OK healthcheck before `timeout`
@@ -124,6 +180,120 @@ impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
             .await
             .map_err(Into::into)
     }
+
+    /// Subscribe to the blocks the builder makes available. Only builders serving the stream of
+    /// available blocks support this.
+    ///
+    /// # Errors
+    /// - [`BuilderClientError::Api`] if API isn't responding or doesn't support the subscription
+    async fn subscribe_available_blocks(
+        &self,
+    ) -> Result<AvailableBlocksSubscription<TYPES, Ver>, BuilderClientError> {
+        self.client
+            .socket(&format!("{LEGACY_BUILDER_MODULE}/availableblocksstream"))
+            .connect()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Ask for the blocks available on top of `parent` over `subscription`, and wait for the
+    /// builder to push the first of them
+    ///
+    /// # Errors
+    /// - [`BuilderClientError::Api`] if the subscription fails
+    async fn next_from_subscription(
+        subscription: &mut AvailableBlocksSubscription<TYPES, Ver>,
+        request: AvailableBlocksRequest<TYPES>,
+    ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuilderClientError> {
+        subscription.send(&request).await?;
+        // Skip the blocks still pushed for the parents we asked for before
+        let answers = |update: &AvailableBlocksUpdate<TYPES>| {
+            update.parent_hash == request.parent_hash && update.view_number == request.view_number
+        };
+        loop {
+            let update = subscription.next().await.ok_or_else(|| {
+                BuilderClientError::Api("The builder closed the subscription".to_string())
+            })??;
+            if !answers(&update) {
+                continue;
+            }
+            let mut blocks = vec![update.block];
+            // Take the blocks which were pushed along with the first one
+            while let Some(Some(Ok(update))) = subscription.next().now_or_never() {
+                if answers(&update) {
+                    blocks.push(update.block);
+                }
+            }
+            return Ok(blocks);
+        }
+    }
+
+    /// Get the blocks available on top of `parent`, waiting for the builder to push them to us
+    /// over our subscription to it if it supports it, and falling back to
+    /// [`Self::available_blocks`] otherwise, for a while that grows each time subscribing fails
+    /// again
+    ///
+    /// # Errors
+    /// - [`BuilderClientError::BlockNotFound`] if blocks aren't available for this parent
+    /// - [`BuilderClientError::Api`] if API isn't responding or responds incorrectly
+    pub async fn next_available_blocks(
+        &self,
+        parent: VidCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuilderClientError> {
+        let may_stream = self
+            .streaming_retry
+            .lock()
+            .as_ref()
+            .map_or(true, |retry| Instant::now() >= retry.after);
+        if may_stream {
+            let mut subscription = self.subscription.lock().await;
+            if subscription.is_none() {
+                match self.subscribe_available_blocks().await {
+                    Ok(connection) => {
+                        *self.streaming_retry.lock() = None;
+                        *subscription = Some(connection);
+                    }
+                    Err(err) => {
+                        let mut retry = self.streaming_retry.lock();
+                        let backoff = retry.as_ref().map_or(STREAMING_RETRY_MIN, |retry| {
+                            retry.backoff.saturating_mul(2).min(STREAMING_RETRY_MAX)
+                        });
+                        tracing::info!(
+                            %err,
+                            "Builder {} doesn't push available blocks, polling it for {backoff:?}",
+                            self.url
+                        );
+                        *retry = Some(StreamingRetry {
+                            after: Instant::now() + backoff,
+                            backoff,
+                        });
+                    }
+                }
+            }
+            if let Some(connection) = subscription.as_mut() {
+                let request = AvailableBlocksRequest {
+                    parent_hash: parent,
+                    view_number,
+                    sender: sender.clone(),
+                    signature: signature.clone(),
+                };
+                match Self::next_from_subscription(connection, request).await {
+                    Ok(blocks) => return Ok(blocks),
+                    Err(err) => {
+                        // We subscribe again the next time
+                        tracing::info!(%err, "Subscription to builder {} failed", self.url);
+                        *subscription = None;
+                    }
+                }
+            }
+        }
+
+        self.available_blocks(parent, view_number, sender, signature)
+            .await
+    }
 }
 
 /// Version 0.1
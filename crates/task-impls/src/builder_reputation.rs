@@ -0,0 +1,272 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Reputation of the builders a leader gets blocks from
+//!
+//! This module provides [`BuilderReputation`], which scores each builder by how it has behaved
+//! in the views we led: whether it reported its blocks in time, whether claiming the blocks it
+//! offered succeeded, whether its signatures were valid and whether it honoured the fee and size
+//! it advertised. The transaction task weighs offered blocks by the score of their builder, and
+//! doesn't query builders whose score dropped too low for a while.
+//!
+//! Reputations are only kept in memory, for as long as the node runs. A restarted node trusts
+//! every builder again until it misbehaves anew, which at worst costs it a few views with a bad
+//! block or none; builders are few and misbehave again quickly enough that this isn't worth
+//! persisting.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hotshot_types::consensus::ConsensusMetricsValue;
+use parking_lot::Mutex;
+use url::Url;
+
+/// Score below which a builder is excluded
+pub const EXCLUSION_THRESHOLD: f64 = 0.5;
+
+/// How long a builder is excluded the first time its score drops below [`EXCLUSION_THRESHOLD`].
+/// Each further exclusion before it succeeds again lasts twice as long.
+pub const BASE_EXCLUSION: Duration = Duration::from_secs(10);
+
+/// The longest a builder is excluded for
+pub const MAX_EXCLUSION: Duration = Duration::from_secs(5 * 60);
+
+/// How much a new observation moves the statistics of a builder
+const EWMA_WEIGHT: f64 = 0.2;
+
+/// What we know about how a builder behaves. Each rate is a moving average between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BuilderStats {
+    /// Moving average of the time the builder took to report its available blocks
+    pub latency: Option<Duration>,
+    /// How often the builder reported its available blocks within the builder timeout
+    pub timeliness: f64,
+    /// How often claiming a block the builder offered succeeded
+    pub claim_success: f64,
+    /// How often the builder's signatures were valid
+    pub signature_validity: f64,
+    /// How often the blocks the builder delivered matched the fee and size it advertised
+    pub fee_honesty: f64,
+    /// When the builder may be queried again, if it is excluded
+    pub excluded_until: Option<Instant>,
+}
+
+impl Default for BuilderStats {
+    fn default() -> Self {
+        Self {
+            latency: None,
+            timeliness: 1.0,
+            claim_success: 1.0,
+            signature_validity: 1.0,
+            fee_honesty: 1.0,
+            excluded_until: None,
+        }
+    }
+}
+
+impl BuilderStats {
+    /// How much the builder can be relied on, between 0 and 1
+    #[must_use]
+    pub fn score(&self) -> f64 {
+        self.timeliness * self.claim_success * self.signature_validity * self.fee_honesty
+    }
+
+    /// Whether the builder is excluded at `now`
+    #[must_use]
+    pub fn is_excluded(&self, now: Instant) -> bool {
+        self.excluded_until.is_some_and(|until| now < until)
+    }
+}
+
+/// The statistics of a builder, and how many times in a row it was excluded
+#[derive(Debug, Default)]
+struct Record {
+    /// The statistics of the builder
+    stats: BuilderStats,
+    /// Number of exclusions since the builder last delivered a block
+    exclusions: u32,
+}
+
+/// Scores builders by how reliably they deliver the blocks they offer, and excludes the ones
+/// whose score drops below [`EXCLUSION_THRESHOLD`] for a while.
+///
+/// An excluded builder starts over with a clean score once its exclusion is over, but is excluded
+/// for longer if it misbehaves again before it delivers a block. Clones share the same
+/// reputations, which are lost when the node restarts.
+#[derive(Debug, Clone)]
+pub struct BuilderReputation {
+    /// The record of each builder, by URL
+    records: Arc<Mutex<HashMap<Url, Record>>>,
+    /// Metrics to export the scores to
+    metrics: Arc<ConsensusMetricsValue>,
+}
+
+impl BuilderReputation {
+    /// Reputations of builders nothing is known about yet
+    #[must_use]
+    pub fn new(metrics: Arc<ConsensusMetricsValue>) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(HashMap::new())),
+            metrics,
+        }
+    }
+
+    /// The statistics of the builder at `url`, if it has been queried
+    #[must_use]
+    pub fn stats(&self, url: &Url) -> Option<BuilderStats> {
+        self.records.lock().get(url).map(|record| record.stats)
+    }
+
+    /// The statistics of every builder which has been queried
+    #[must_use]
+    pub fn all_stats(&self) -> BTreeMap<Url, BuilderStats> {
+        self.records
+            .lock()
+            .iter()
+            .map(|(url, record)| (url.clone(), record.stats))
+            .collect()
+    }
+
+    /// The score of the builder at `url`; builders nothing is known about have a perfect score
+    #[must_use]
+    pub fn score(&self, url: &Url) -> f64 {
+        self.stats(url).map_or(1.0, |stats| stats.score())
+    }
+
+    /// The indices of the builders in `urls` which are not excluded, or of all of them if they
+    /// all are, so that the leader still has someone to ask
+    #[must_use]
+    pub fn eligible(&self, urls: &[&Url]) -> Vec<usize> {
+        let records = self.records.lock();
+        let now = Instant::now();
+        let eligible: Vec<usize> = urls
+            .iter()
+            .enumerate()
+            .filter(|(_, url)| {
+                !records
+                    .get(**url)
+                    .is_some_and(|record| record.stats.is_excluded(now))
+            })
+            .map(|(index, _)| index)
+            .collect();
+        self.export_exclusions(&records, now);
+
+        if eligible.is_empty() {
+            (0..urls.len()).collect()
+        } else {
+            eligible
+        }
+    }
+
+    /// Record that the builder at `url` reported its available blocks after `latency`
+    pub fn record_response(&self, url: &Url, latency: Duration) {
+        self.update(url, |stats| {
+            stats.latency = Some(match stats.latency {
+                Some(average) => average.mul_f64(1.0 - EWMA_WEIGHT) + latency.mul_f64(EWMA_WEIGHT),
+                None => latency,
+            });
+            stats.timeliness = ewma(stats.timeliness, true);
+        });
+    }
+
+    /// Record that the builder at `url` failed to report its available blocks before we stopped
+    /// waiting
+    pub fn record_no_response(&self, url: &Url) {
+        self.update(url, |stats| {
+            stats.timeliness = ewma(stats.timeliness, false);
+        });
+    }
+
+    /// Record whether claiming a block from the builder at `url` succeeded
+    pub fn record_claim(&self, url: &Url, success: bool) {
+        self.update(url, |stats| {
+            stats.claim_success = ewma(stats.claim_success, success);
+        });
+        if success {
+            if let Some(record) = self.records.lock().get_mut(url) {
+                record.exclusions = 0;
+            }
+        }
+    }
+
+    /// Record whether a signature of the builder at `url` was valid
+    pub fn record_signature(&self, url: &Url, valid: bool) {
+        self.update(url, |stats| {
+            stats.signature_validity = ewma(stats.signature_validity, valid);
+        });
+    }
+
+    /// Record whether a block from the builder at `url` matched the fee and size it advertised
+    pub fn record_fee(&self, url: &Url, honest: bool) {
+        self.update(url, |stats| {
+            stats.fee_honesty = ewma(stats.fee_honesty, honest);
+        });
+    }
+
+    /// Apply `f` to the statistics of the builder at `url`, and exclude it if its score drops
+    /// too low
+    fn update(&self, url: &Url, f: impl FnOnce(&mut BuilderStats)) {
+        let mut records = self.records.lock();
+        let now = Instant::now();
+        let record = records.entry(url.clone()).or_default();
+
+        // A builder whose exclusion is over starts over
+        if record
+            .stats
+            .excluded_until
+            .is_some_and(|until| now >= until)
+        {
+            record.stats = BuilderStats {
+                latency: record.stats.latency,
+                ..BuilderStats::default()
+            };
+        }
+
+        f(&mut record.stats);
+
+        if record.stats.score() < EXCLUSION_THRESHOLD && !record.stats.is_excluded(now) {
+            let exclusion = BASE_EXCLUSION
+                .saturating_mul(2_u32.saturating_pow(record.exclusions))
+                .min(MAX_EXCLUSION);
+            tracing::warn!("Excluding builder {url} for {exclusion:?} for its low score");
+            record.stats.excluded_until = Some(now + exclusion);
+            record.exclusions = record.exclusions.saturating_add(1);
+        }
+
+        let stats = record.stats;
+        let labels = vec![url.to_string()];
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        self.metrics
+            .builder_score
+            .create(labels.clone())
+            .set((stats.score() * 100.0).round() as usize);
+        if let Some(latency) = stats.latency {
+            self.metrics
+                .builder_latency
+                .create(labels)
+                .set(usize::try_from(latency.as_millis()).unwrap_or(usize::MAX));
+        }
+        self.export_exclusions(&records, now);
+    }
+
+    /// Export the number of builders excluded at `now`
+    fn export_exclusions(&self, records: &HashMap<Url, Record>, now: Instant) {
+        self.metrics.excluded_builders.set(
+            records
+                .values()
+                .filter(|record| record.stats.is_excluded(now))
+                .count(),
+        );
+    }
+}
+
+/// Move the moving average `rate` towards 1 if `success`, and towards 0 otherwise
+fn ewma(rate: f64, success: bool) -> f64 {
+    rate * (1.0 - EWMA_WEIGHT) + if success { EWMA_WEIGHT } else { 0.0 }
+}
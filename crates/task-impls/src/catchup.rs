@@ -0,0 +1,325 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Instant};
+
+use async_broadcast::{InactiveReceiver, Sender};
+use committable::{Commitment, Committable};
+use futures::{stream, StreamExt};
+use hotshot_task::dependency::{Dependency, EventDependency};
+use hotshot_types::{
+    data::Leaf2,
+    error::HotShotError,
+    event::{Event, EventType, LeafInfo},
+    traits::{
+        election::Membership,
+        network::{DataRequest, RequestKind, ResponseData},
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+        ValidatedState,
+    },
+    utils::epoch_from_block_number,
+    vid::VidCodeRate,
+};
+use sha2::{Digest, Sha256};
+use tokio::time::timeout;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event, request_scheduler::RequestScheduler};
+
+/// How many DA members to ask for a missing leaf before giving up on it
+pub const LEAF_REQUEST_ATTEMPTS: usize = 5;
+
+/// How many missing heights are fetched at the same time
+pub const CONCURRENT_LEAF_FETCHES: usize = 16;
+
+/// Fetches decided leaves this node missed from the DA committee, so that the decided chain
+/// handed to the application has no gaps.
+pub struct LeafFetcher<TYPES: NodeType> {
+    /// This nodes public key
+    pub public_key: TYPES::SignatureKey,
+    /// This nodes private key, used to sign requests
+    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Membership for Quorum certs/votes, which sets the number of VID storage nodes
+    pub quorum_membership: Arc<TYPES::Membership>,
+    /// Membership for the DA committee, whose members have the payloads
+    pub da_membership: Arc<TYPES::Membership>,
+    /// Number of blocks in an epoch, zero means there are no epochs
+    pub epoch_height: u64,
+    /// Erasure code rate of VID, to check the payloads against their commitments
+    pub vid_code_rate: VidCodeRate,
+    /// Picks the peers to ask first, and how long to wait for them
+    pub scheduler: RequestScheduler<TYPES::SignatureKey>,
+    /// Event sender, to send the requests
+    pub sender: Sender<Arc<HotShotEvent<TYPES>>>,
+    /// Event receiver, to wait for the responses
+    pub receiver: InactiveReceiver<Arc<HotShotEvent<TYPES>>>,
+}
+
+impl<TYPES: NodeType> LeafFetcher<TYPES> {
+    /// Fetch the ancestors of `child` down to height `to_height`, newest first.
+    ///
+    /// Up to [`CONCURRENT_LEAF_FETCHES`] heights are requested at once. Each leaf must be the
+    /// parent `child` committed to, or the parent of the leaf fetched before it, and must come
+    /// with its payload; a leaf which isn't is requested again by its commitment. Stops at the
+    /// first height no peer could provide.
+    pub async fn fetch_ancestors(
+        &self,
+        child: &Leaf2<TYPES>,
+        to_height: u64,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Vec<Leaf2<TYPES>> {
+        let recipients: Vec<_> = self
+            .scheduler
+            .rank(self.da_membership.committee_members(view, epoch))
+            .into_iter()
+            .filter(|key| *key != self.public_key)
+            .collect();
+
+        // The commitments are only known once the child of each leaf is in, so the leaves are
+        // fetched by height and linked as they arrive, newest first
+        let recipients = &recipients;
+        let mut fetched = stream::iter((to_height..child.height()).rev())
+            .map(|height| async move {
+                (
+                    height,
+                    self.fetch_leaf(height, None, recipients, view).await,
+                )
+            })
+            .buffered(CONCURRENT_LEAF_FETCHES);
+
+        let mut leaves = Vec::new();
+        let mut parent_commitment = child.parent_commitment();
+        while let Some((height, leaf)) = fetched.next().await {
+            let leaf = match leaf {
+                Some(leaf) if leaf.commit() == parent_commitment => Some(leaf),
+                Some(_) => {
+                    tracing::warn!("Fetched a leaf for height {height} which is not decided");
+                    self.fetch_leaf(height, Some(parent_commitment), recipients, view)
+                        .await
+                }
+                None => None,
+            };
+            let Some(leaf) = leaf else {
+                tracing::warn!("Could not fetch the decided leaf at height {height}");
+                break;
+            };
+            parent_commitment = leaf.parent_commitment();
+            leaves.push(leaf);
+        }
+        leaves
+    }
+
+    /// Ask `recipients` in turn for the leaf at `height`, with commitment `commitment` if it is
+    /// known. If the leaf comes without its payload, the payload is requested separately.
+    async fn fetch_leaf(
+        &self,
+        height: u64,
+        commitment: Option<Commitment<Leaf2<TYPES>>>,
+        recipients: &[TYPES::SignatureKey],
+        view: TYPES::View,
+    ) -> Option<Leaf2<TYPES>> {
+        let mut without_payload = None;
+        for recipient in recipients.iter().take(LEAF_REQUEST_ATTEMPTS) {
+            let Some(ResponseData::Leaf(leaf)) = self
+                .request(RequestKind::Leaf { height }, recipient, view)
+                .await
+            else {
+                continue;
+            };
+            if leaf.height() != height
+                || commitment.is_some_and(|commitment| leaf.commit() != commitment)
+            {
+                tracing::warn!(
+                    "{recipient:?} sent a leaf for height {height} which is not decided"
+                );
+                continue;
+            }
+            if self.has_valid_payload(&leaf) {
+                return Some(leaf);
+            }
+            tracing::debug!("{recipient:?} sent the leaf for height {height} without its payload");
+            without_payload = Some(leaf);
+        }
+
+        let mut leaf = without_payload?;
+        let payload_request = RequestKind::Payload {
+            view: leaf.view_number(),
+        };
+        for recipient in recipients.iter().take(LEAF_REQUEST_ATTEMPTS) {
+            let Some(ResponseData::Payload { payload, .. }) =
+                self.request(payload_request.clone(), recipient, view).await
+            else {
+                continue;
+            };
+            leaf.fill_block_payload_unchecked(payload);
+            if self.has_valid_payload(&leaf) {
+                return Some(leaf);
+            }
+            tracing::warn!("{recipient:?} sent the wrong payload for height {height}");
+        }
+        None
+    }
+
+    /// Send `request` to `recipient`, and wait for the data it asks for
+    async fn request(
+        &self,
+        request: RequestKind<TYPES>,
+        recipient: &TYPES::SignatureKey,
+        view: TYPES::View,
+    ) -> Option<ResponseData<TYPES>> {
+        let signature = sign_request::<TYPES>(&request, &self.private_key)?;
+
+        // Listen before asking, so the response can't slip past
+        let receiver = self.receiver.activate_cloned();
+        let expected = recipient.clone();
+        let expected_request = request.clone();
+        let response = EventDependency::new(
+            receiver,
+            Box::new(move |event: &Arc<HotShotEvent<TYPES>>| {
+                matches!(
+                    event.as_ref(),
+                    HotShotEvent::DataResponseRecv(sender, data)
+                        if *sender == expected && data.answers(&expected_request)
+                )
+            }),
+        );
+
+        broadcast_event(
+            HotShotEvent::DataRequestSend(
+                DataRequest {
+                    request,
+                    view,
+                    signature,
+                },
+                self.public_key.clone(),
+                recipient.clone(),
+            )
+            .into(),
+            &self.sender,
+        )
+        .await;
+
+        let sent = Instant::now();
+        let Ok(Some(event)) = timeout(self.scheduler.request_timeout(), response.completed()).await
+        else {
+            self.scheduler.record_failure(recipient);
+            return None;
+        };
+        self.scheduler.record_response(recipient, sent.elapsed());
+        let HotShotEvent::DataResponseRecv(_, data) = event.as_ref() else {
+            return None;
+        };
+        Some(data.clone())
+    }
+
+    /// Whether `leaf` comes with the payload its header commits to
+    fn has_valid_payload(&self, leaf: &Leaf2<TYPES>) -> bool {
+        let Some(payload) = leaf.block_payload() else {
+            return false;
+        };
+        let epoch = TYPES::Epoch::new(epoch_from_block_number(leaf.height(), self.epoch_height));
+        let num_storage_nodes = self.quorum_membership.total_nodes(epoch);
+        leaf.clone()
+            .fill_block_payload(payload, num_storage_nodes, self.vid_code_rate)
+            .is_ok()
+    }
+
+    /// Fill the height gap between `last_decided`, the leaf of the previous `Decide` event, and
+    /// the oldest leaf of `decide`, then emit `decide` to `output_event_stream`.
+    ///
+    /// The recovered leaves are added to the end of the leaf chain of `decide`, with the
+    /// placeholder states described there. If some can't be recovered, an
+    /// [`EventType::DecideGap`] for them is emitted first. If the recovered leaves don't extend
+    /// `last_decided`, the decided chain has forked from what this node decided before: an
+    /// [`EventType::Error`] naming the heights which don't link up is emitted first instead, and
+    /// `decide` still carries the leaves its QC certifies.
+    pub async fn fill_gap_and_emit(
+        &self,
+        last_decided: Leaf2<TYPES>,
+        mut decide: Event<TYPES>,
+        epoch: TYPES::Epoch,
+        output_event_stream: &Sender<Event<TYPES>>,
+    ) {
+        if let EventType::Decide { leaf_chain, .. } = &mut decide.event {
+            let from_height = last_decided.height() + 1;
+            if let Some(oldest) = leaf_chain
+                .last()
+                .map(|info| info.leaf.clone())
+                .filter(|oldest| oldest.height() > from_height)
+            {
+                tracing::info!(
+                    "Fetching decided leaves {from_height} to {}",
+                    oldest.height() - 1
+                );
+                let recovered = self
+                    .fetch_ancestors(&oldest, from_height, decide.view_number, epoch)
+                    .await;
+
+                let first_recovered = recovered
+                    .last()
+                    .map_or(oldest.height(), |leaf| leaf.height());
+                if first_recovered > from_height {
+                    broadcast_event(
+                        Event {
+                            view_number: decide.view_number,
+                            event: EventType::DecideGap {
+                                from_height,
+                                to_height: first_recovered - 1,
+                            },
+                        },
+                        output_event_stream,
+                    )
+                    .await;
+                } else if recovered
+                    .last()
+                    .is_some_and(|leaf| leaf.parent_commitment() != last_decided.commit())
+                {
+                    let message = format!(
+                        "The decided leaves {from_height} to {} don't extend the leaf this node \
+                         decided at height {}",
+                        oldest.height() - 1,
+                        last_decided.height()
+                    );
+                    tracing::error!("{message}");
+                    broadcast_event(
+                        Event {
+                            view_number: decide.view_number,
+                            event: EventType::Error {
+                                error: Arc::new(HotShotError::InvalidState(message)),
+                            },
+                        },
+                        output_event_stream,
+                    )
+                    .await;
+                }
+
+                Arc::make_mut(leaf_chain).extend(recovered.into_iter().map(|leaf| {
+                    let state = TYPES::ValidatedState::from_header(leaf.block_header());
+                    LeafInfo::new(leaf, Arc::new(state), None, None)
+                }));
+            }
+        }
+
+        broadcast_event(decide, output_event_stream).await;
+    }
+}
+
+/// Sign the serialized version of `request`
+fn sign_request<TYPES: NodeType>(
+    request: &RequestKind<TYPES>,
+    private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+) -> Option<<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType> {
+    let Ok(data) = bincode::serialize(request) else {
+        tracing::error!("Failed to serialize request!");
+        return None;
+    };
+    let Ok(signature) = TYPES::SignatureKey::sign(private_key, &Sha256::digest(data)) else {
+        tracing::error!("Failed to sign Data Request");
+        return None;
+    };
+    Some(signature)
+}
@@ -25,6 +25,7 @@ use hotshot_types::{
         signature_key::SignatureKey,
         storage::Storage,
     },
+    vid::VidCodeRate,
     vote::HasViewNumber,
 };
 use sha2::{Digest, Sha256};
@@ -80,6 +81,9 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Erasure code rate of VID
+    pub vid_code_rate: VidCodeRate,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYPES, I, V> {
@@ -180,8 +184,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
 
                 let txns = Arc::clone(&proposal.data.encoded_transactions);
                 let num_nodes = self.quorum_membership.total_nodes(self.cur_epoch);
+                let code_rate = self.vid_code_rate;
                 let payload_commitment =
-                    spawn_blocking(move || vid_commitment(&txns, num_nodes)).await;
+                    spawn_blocking(move || vid_commitment(&txns, num_nodes, code_rate)).await;
                 let payload_commitment = payload_commitment.unwrap();
                 self.storage
                     .write()
@@ -230,6 +235,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     let public_key = self.public_key.clone();
                     let chan = event_stream.clone();
                     let current_epoch = self.cur_epoch;
+                    let code_rate = self.vid_code_rate;
                     spawn(async move {
                         Consensus::calculate_and_update_vid(
                             OuterConsensus::new(Arc::clone(&consensus.inner_consensus)),
@@ -237,6 +243,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                             membership,
                             &pk,
                             current_epoch,
+                            code_rate,
                         )
                         .await;
                         if let Some(Some(vid_share)) = consensus
@@ -26,8 +26,11 @@ use hotshot_types::{
         ViewSyncPreCommitVote,
     },
     traits::{
-        block_contents::BuilderFee, network::DataRequest, node_implementation::NodeType,
-        signature_key::SignatureKey, BlockPayload,
+        block_contents::BuilderFee,
+        network::{DataRequest, ResponseData},
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+        BlockPayload,
     },
     utils::BuilderCommitment,
     vid::VidCommitment,
@@ -35,7 +38,7 @@ use hotshot_types::{
 };
 use vec1::Vec1;
 
-use crate::view_sync::ViewSyncPhase;
+use crate::{mempool::TransactionSource, view_sync::ViewSyncPhase};
 
 impl<TYPES: NodeType> TaskEvent for HotShotEvent<TYPES> {
     fn shutdown_event() -> Self {
@@ -163,8 +166,11 @@ pub enum HotShotEvent<TYPES: NodeType> {
     ViewSyncTrigger(TYPES::View),
     /// A consensus view has timed out; emitted by a replica in the consensus task; received by the view sync task; internal event only
     Timeout(TYPES::View),
-    /// Receive transactions from the network
-    TransactionsRecv(Vec<TYPES::Transaction>),
+    /// Receive transactions from the network, delivered by the given source
+    TransactionsRecv(
+        Vec<TYPES::Transaction>,
+        TransactionSource<TYPES::SignatureKey>,
+    ),
     /// Send transactions to the network
     TransactionSend(TYPES::Transaction, TYPES::SignatureKey),
     /// Event to send block payload commitment and metadata from DA leader to the quorum; internal event only
@@ -238,11 +244,44 @@ pub enum HotShotEvent<TYPES: NodeType> {
         Proposal<TYPES, VidDisperseShare<TYPES>>,
     ),
 
+    /// Send a request for a decided leaf, a payload or a quorum certificate to the network;
+    /// emitted to one of the members of the DA committee, e.g. while filling a gap between
+    /// decides.
+    DataRequestSend(
+        DataRequest<TYPES>,
+        // Sender
+        TYPES::SignatureKey,
+        // Recipient
+        TYPES::SignatureKey,
+    ),
+
+    /// Receive a request for a decided leaf, a payload or a quorum certificate from the network.
+    /// Includes the data request and nodes public key.
+    DataRequestRecv(DataRequest<TYPES>, TYPES::SignatureKey),
+
+    /// Send the requested data to the network; emitted to the requesting node.
+    DataResponseSend(
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+        ResponseData<TYPES>,
+        /// Signature of the data by the sender
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ),
+
+    /// Receive requested data, whose signature has been checked, from the network; received by
+    /// the node that requested it.
+    DataResponseRecv(TYPES::SignatureKey, ResponseData<TYPES>),
+
     /// A replica send us a High QC
     HighQcRecv(QuorumCertificate2<TYPES>, TYPES::SignatureKey),
 
     /// Send our HighQc to the next leader, should go to the same leader as our vote
     HighQcSend(QuorumCertificate2<TYPES>, TYPES::SignatureKey),
+
+    /// Leaves were decided, newest first, with their payloads where we have them
+    LeavesDecided(Vec<Leaf2<TYPES>>),
 }
 
 impl<TYPES: NodeType> HotShotEvent<TYPES> {
@@ -297,7 +336,7 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::BlockRecv(packed_bundle) => Some(packed_bundle.view_number),
             HotShotEvent::Shutdown
             | HotShotEvent::TransactionSend(_, _)
-            | HotShotEvent::TransactionsRecv(_) => None,
+            | HotShotEvent::TransactionsRecv(_, _) => None,
             HotShotEvent::VidDisperseSend(proposal, _) => Some(proposal.data.view_number()),
             HotShotEvent::VidShareRecv(_, proposal) | HotShotEvent::VidShareValidated(proposal) => {
                 Some(proposal.data.view_number())
@@ -322,9 +361,14 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             | HotShotEvent::VidRequestRecv(request, _) => Some(request.view),
             HotShotEvent::VidResponseSend(_, _, proposal)
             | HotShotEvent::VidResponseRecv(_, proposal) => Some(proposal.data.view_number),
+            HotShotEvent::DataRequestSend(request, _, _)
+            | HotShotEvent::DataRequestRecv(request, _) => Some(request.view),
+            HotShotEvent::DataResponseSend(_, _, data, _)
+            | HotShotEvent::DataResponseRecv(_, data) => Some(data.view_number()),
             HotShotEvent::HighQcRecv(qc, _) | HotShotEvent::HighQcSend(qc, _) => {
                 Some(qc.view_number())
             }
+            HotShotEvent::LeavesDecided(leaves) => leaves.first().map(Leaf2::view_number),
         }
     }
 }
@@ -493,7 +537,7 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 write!(f, "ViewSyncTrigger(view_number={view_number:?})")
             }
             HotShotEvent::Timeout(view_number) => write!(f, "Timeout(view_number={view_number:?})"),
-            HotShotEvent::TransactionsRecv(_) => write!(f, "TransactionsRecv"),
+            HotShotEvent::TransactionsRecv(_, _) => write!(f, "TransactionsRecv"),
             HotShotEvent::TransactionSend(_, _) => write!(f, "TransactionSend"),
             HotShotEvent::SendPayloadCommitmentAndMetadata(_, _, _, view_number, _, _) => {
                 write!(
@@ -587,12 +631,31 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                     proposal.data.view_number
                 )
             }
+            HotShotEvent::DataRequestSend(request, _, _) => {
+                write!(f, "DataRequestSend(view_number={:?}", request.view)
+            }
+            HotShotEvent::DataRequestRecv(request, _) => {
+                write!(f, "DataRequestRecv(view_number={:?}", request.view)
+            }
+            HotShotEvent::DataResponseSend(_, _, data, _) => {
+                write!(f, "DataResponseSend(view_number={:?}", data.view_number())
+            }
+            HotShotEvent::DataResponseRecv(_, data) => {
+                write!(f, "DataResponseRecv(view_number={:?}", data.view_number())
+            }
             HotShotEvent::HighQcRecv(qc, _) => {
                 write!(f, "HighQcRecv(view_number={:?}", qc.view_number())
             }
             HotShotEvent::HighQcSend(qc, _) => {
                 write!(f, "HighQcSend(view_number={:?}", qc.view_number())
             }
+            HotShotEvent::LeavesDecided(leaves) => {
+                write!(
+                    f,
+                    "LeavesDecided(view_number={:?})",
+                    leaves.first().map(Leaf2::view_number)
+                )
+            }
         }
     }
 }
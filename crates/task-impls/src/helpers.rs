@@ -34,7 +34,10 @@ use tokio::time::timeout;
 use tracing::instrument;
 use utils::anytrace::*;
 
-use crate::{events::HotShotEvent, quorum_proposal_recv::ValidationInfo, request::REQUEST_TIMEOUT};
+use crate::{
+    events::HotShotEvent, quorum_proposal_recv::ValidationInfo,
+    request_scheduler::INITIAL_REQUEST_TIMEOUT,
+};
 
 /// Trigger a request to the network for a proposal for a view and wait for the response or timeout.
 #[instrument(skip_all)]
@@ -75,8 +78,9 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     let cur_epoch = consensus.read().await.cur_epoch();
     // Make a background task to await the arrival of the event data.
     let Ok(Some(proposal)) =
-        // We want to explicitly timeout here so we aren't waiting around for the data.
-        timeout(REQUEST_TIMEOUT, async move {
+        // We want to explicitly timeout here so we aren't waiting around for the data. The
+        // request goes to everyone, so there is no peer whose round trips could set the timeout.
+        timeout(INITIAL_REQUEST_TIMEOUT, async move {
             // We want to iterate until the proposal is not None, or until we reach the timeout.
             let mut proposal = None;
             while proposal.is_none() {
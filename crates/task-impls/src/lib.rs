@@ -44,6 +44,15 @@ pub mod upgrade;
 /// Should contain builder task in the future
 pub mod builder;
 
+/// Scoring the builders a leader gets blocks from
+pub mod builder_reputation;
+
+/// Building blocks locally when no builder delivers one
+pub mod local_builder;
+
+/// Client for the Solver, caching and prefetching the auction results
+pub mod solver;
+
 /// Helper functions used by any task
 pub mod helpers;
 
@@ -53,6 +62,15 @@ pub mod response;
 /// Task for requesting the network for things
 pub mod request;
 
+/// Ranking peers and timing requests by how peers have answered before
+pub mod request_scheduler;
+
+/// Local pool of transactions waiting to be included in a block
+pub mod mempool;
+
+/// Fetching the decided leaves a node missed, to fill gaps between decides
+pub mod catchup;
+
 /// Task for handling logic for quorum proposals
 pub mod quorum_proposal;
 
@@ -0,0 +1,132 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Building blocks from the node's own mempool
+//!
+//! This module provides [`LocalBuilder`], which a leader falls back to when none of the external
+//! builders delivered a block in time, so that the transactions the node has seen still make it
+//! into a block instead of the leader proposing an empty one.
+
+use hotshot_types::{
+    traits::{
+        block_contents::{precompute_vid_commitment, BuilderFee, EncodeBytes, Transaction},
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::BuilderSignatureKey,
+        BlockPayload,
+    },
+    vid::VidCodeRate,
+};
+use utils::anytrace::*;
+use vbs::version::{StaticVersionType, Version};
+
+use crate::transactions::BuilderResponse;
+
+/// The fee a locally built block pays; the leader has no one to pay but itself
+const LOCAL_BUILDER_FEE: u64 = 0;
+
+/// Builds blocks from locally observed transactions, signing their fee with its own builder key
+#[derive(Clone)]
+pub struct LocalBuilder<TYPES: NodeType> {
+    /// The key the fees of the blocks are signed with
+    public_key: TYPES::BuilderSignatureKey,
+    /// The private key the fees of the blocks are signed with
+    private_key: <TYPES::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
+    /// The total size of the transactions in a block, in bytes
+    max_block_size: u64,
+}
+
+impl<TYPES: NodeType> LocalBuilder<TYPES> {
+    /// A builder signing fees with `private_key`, building blocks of up to `max_block_size` bytes
+    #[must_use]
+    pub fn new(
+        public_key: TYPES::BuilderSignatureKey,
+        private_key: <TYPES::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
+        max_block_size: u64,
+    ) -> Self {
+        Self {
+            public_key,
+            private_key,
+            max_block_size,
+        }
+    }
+
+    /// The key the fees of the blocks are signed with
+    #[must_use]
+    pub fn public_key(&self) -> &TYPES::BuilderSignatureKey {
+        &self.public_key
+    }
+
+    /// The longest prefix of `transactions`, in order of preference, which fits in a block
+    #[must_use]
+    pub fn select_transactions(
+        &self,
+        transactions: impl IntoIterator<Item = TYPES::Transaction>,
+    ) -> Vec<TYPES::Transaction> {
+        let mut size = 0;
+        transactions
+            .into_iter()
+            .take_while(|transaction| {
+                size += transaction.minimum_block_size();
+                size <= self.max_block_size
+            })
+            .collect()
+    }
+
+    /// Build the block for `view` out of `transactions`, on top of `validated_state`, with its VID
+    /// commitment precomputed for `num_storage_nodes` and `vid_code_rate`, and its fee signed the
+    /// way `version` expects.
+    ///
+    /// # Errors
+    /// If the payload can't be built from the transactions, or the fee can't be signed.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn build_block<V: Versions>(
+        &self,
+        transactions: Vec<TYPES::Transaction>,
+        validated_state: &TYPES::ValidatedState,
+        instance_state: &TYPES::InstanceState,
+        num_storage_nodes: usize,
+        vid_code_rate: VidCodeRate,
+        version: Version,
+        view: TYPES::View,
+    ) -> Result<BuilderResponse<TYPES>> {
+        let (block_payload, metadata) =
+            TYPES::BlockPayload::from_transactions(transactions, validated_state, instance_state)
+                .await
+                .wrap()
+                .context(error!("Failed to build a block payload locally"))?;
+
+        let (vid_commitment, precompute_data) =
+            precompute_vid_commitment(&block_payload.encode(), num_storage_nodes, vid_code_rate);
+
+        let fee_signature = if version >= V::Marketplace::VERSION {
+            TYPES::BuilderSignatureKey::sign_sequencing_fee_marketplace(
+                &self.private_key,
+                LOCAL_BUILDER_FEE,
+                view.u64(),
+            )
+        } else {
+            TYPES::BuilderSignatureKey::sign_fee(
+                &self.private_key,
+                LOCAL_BUILDER_FEE,
+                &metadata,
+                &vid_commitment,
+            )
+        }
+        .wrap()
+        .context(error!("Failed to sign the fee of a locally built block"))?;
+
+        Ok(BuilderResponse {
+            fee: BuilderFee {
+                fee_amount: LOCAL_BUILDER_FEE,
+                fee_account: self.public_key.clone(),
+                fee_signature,
+            },
+            block_payload,
+            metadata,
+            precompute_data: Some(precompute_data),
+        })
+    }
+}
@@ -0,0 +1,447 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Local pool of transactions waiting to be included in a block
+//!
+//! This module provides [`Mempool`], which keeps the transactions a node has received until they
+//! are decided, evicted for being too old, or evicted to make room for transactions with a higher
+//! priority. It also remembers what happened to the transactions it has seen, see
+//! [`TransactionStatus`].
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    num::NonZeroUsize,
+    sync::Arc,
+    time::Instant,
+};
+
+use committable::{Commitment, Committable};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    traits::{
+        block_contents::Transaction, network::AuthenticatedPeer, node_implementation::NodeType,
+    },
+    MempoolConfig,
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+use thiserror::Error;
+
+/// Number of transactions which are no longer pending whose status is remembered
+const STATUS_HISTORY: NonZeroUsize = match NonZeroUsize::new(65_536) {
+    Some(capacity) => capacity,
+    None => unreachable!(),
+};
+
+/// What happened to a transaction the mempool has seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus<TYPES: NodeType> {
+    /// Waiting to be included in a block
+    Pending,
+    /// Included in the DA proposal for `view`, which is not decided yet
+    InDaProposal {
+        /// The view of the proposal
+        view: TYPES::View,
+    },
+    /// Included in the block decided in `view`
+    Decided {
+        /// The view of the decided leaf
+        view: TYPES::View,
+        /// The height of the decided leaf
+        height: u64,
+    },
+    /// Dropped from the mempool before it was decided
+    Evicted(EvictionReason),
+}
+
+/// Why a transaction was dropped from the mempool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// It was pending for longer than [`MempoolConfig::max_age`]
+    Expired,
+    /// The mempool was full, and it made room for a transaction with a higher priority
+    Outbid,
+}
+
+/// Who a transaction was received from, which its size counts against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TransactionSource<K> {
+    /// Submitted through this node's own API. Exempt from the limit per sender, so that peers
+    /// can't crowd out the node's own transactions.
+    Local,
+    /// Delivered by a peer, as authenticated by the network. The sender named in the message is
+    /// not used, since anyone can claim to be anyone.
+    Peer(AuthenticatedPeer<K>),
+    /// Delivered by a network which doesn't tell us who by, such as the CDN, where every message
+    /// comes from the broker. These all share [`MempoolConfig::max_bytes_unattributed`] rather
+    /// than the limit of a single peer.
+    Unattributed,
+}
+
+impl<K> From<Option<AuthenticatedPeer<K>>> for TransactionSource<K> {
+    fn from(peer: Option<AuthenticatedPeer<K>>) -> Self {
+        peer.map_or(Self::Unattributed, Self::Peer)
+    }
+}
+
+/// Why a transaction was not added to the mempool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum MempoolRejection {
+    /// The transaction is already pending, proposed or decided
+    #[error("transaction is already known")]
+    Duplicate,
+    /// The transaction alone exceeds the size limits
+    #[error("transaction is too large")]
+    TooLarge,
+    /// The sender's pending transactions already take up all the space it is allowed
+    #[error("sender has too many pending transactions")]
+    SenderLimit,
+    /// The mempool is full of transactions with at least the same priority
+    #[error("mempool is full")]
+    Full,
+}
+
+/// A transaction in the mempool
+#[derive(Debug)]
+struct Entry<TYPES: NodeType> {
+    /// The transaction
+    transaction: TYPES::Transaction,
+    /// Who we received it from
+    source: TransactionSource<TYPES::SignatureKey>,
+    /// Its size, in bytes
+    size: u64,
+    /// Its position in the priority order
+    order: Order,
+    /// When we received it
+    received: Instant,
+    /// The view of the last DA proposal which included it
+    proposed_in: Option<TYPES::View>,
+}
+
+/// Position of a transaction in the priority order: lowest priority first, and most recently
+/// received first among those with the same priority
+type Order = (u64, Reverse<u64>);
+
+/// The contents of the mempool
+#[derive(Debug)]
+struct Pool<TYPES: NodeType> {
+    /// Every transaction in the pool
+    entries: HashMap<Commitment<TYPES::Transaction>, Entry<TYPES>>,
+    /// The transactions which are not in a DA proposal, in priority order
+    by_priority: BTreeMap<Order, Commitment<TYPES::Transaction>>,
+    /// Every transaction in the pool, by the sequence number it was received with
+    by_age: BTreeMap<u64, Commitment<TYPES::Transaction>>,
+    /// The transactions which are in a DA proposal, by the view of the proposal
+    by_proposal: BTreeMap<TYPES::View, HashSet<Commitment<TYPES::Transaction>>>,
+    /// The total size of the transactions received from each source
+    source_bytes: HashMap<TransactionSource<TYPES::SignatureKey>, u64>,
+    /// The total size of the transactions in the pool
+    total_bytes: u64,
+    /// The sequence number of the next transaction received
+    next_sequence: u64,
+    /// The status of transactions which are no longer in the pool
+    history: LruCache<Commitment<TYPES::Transaction>, TransactionStatus<TYPES>>,
+}
+
+impl<TYPES: NodeType> Pool<TYPES> {
+    /// Remove the transaction with `commitment` from the pool
+    fn remove(&mut self, commitment: &Commitment<TYPES::Transaction>) -> Option<Entry<TYPES>> {
+        let entry = self.entries.remove(commitment)?;
+        self.by_priority.remove(&entry.order);
+        self.by_age.remove(&entry.order.1 .0);
+        if let Some(view) = entry.proposed_in {
+            self.unpropose(view, commitment);
+        }
+        self.total_bytes -= entry.size;
+        if let Some(bytes) = self.source_bytes.get_mut(&entry.source) {
+            *bytes -= entry.size;
+            if *bytes == 0 {
+                self.source_bytes.remove(&entry.source);
+            }
+        }
+        Some(entry)
+    }
+
+    /// Remove the transaction with `commitment` from the index of the DA proposal for `view`
+    fn unpropose(&mut self, view: TYPES::View, commitment: &Commitment<TYPES::Transaction>) {
+        if let Some(commitments) = self.by_proposal.get_mut(&view) {
+            commitments.remove(commitment);
+            if commitments.is_empty() {
+                self.by_proposal.remove(&view);
+            }
+        }
+    }
+
+    /// Evict the transactions which have been in the pool for longer than `config.max_age`
+    fn evict_expired(&mut self, config: &MempoolConfig) {
+        while let Some((_, commitment)) = self.by_age.first_key_value() {
+            let commitment = *commitment;
+            if self.entries[&commitment].received.elapsed() <= config.max_age {
+                break;
+            }
+            self.remove(&commitment);
+            self.history.put(
+                commitment,
+                TransactionStatus::Evicted(EvictionReason::Expired),
+            );
+        }
+    }
+
+    /// The transactions to evict to fit `size` more bytes, all with a priority lower than
+    /// `priority`, or `None` if there are not enough of them
+    fn outbid(
+        &self,
+        config: &MempoolConfig,
+        size: u64,
+        priority: u64,
+    ) -> Option<Vec<Commitment<TYPES::Transaction>>> {
+        let mut excess = (self.total_bytes + size).saturating_sub(config.max_bytes);
+        let mut evicted = Vec::new();
+        for ((other_priority, _), commitment) in &self.by_priority {
+            if excess == 0 {
+                break;
+            }
+            if *other_priority >= priority {
+                return None;
+            }
+            excess = excess.saturating_sub(self.entries[commitment].size);
+            evicted.push(*commitment);
+        }
+        (excess == 0).then_some(evicted)
+    }
+}
+
+/// A pool of transactions waiting to be included in a block.
+///
+/// Transactions are deduplicated by their commitment, and limited in total size and in size per
+/// [`TransactionSource`], except for the node's own. When the pool is full, transactions with the
+/// lowest priority make room for ones with a higher priority, and transactions which stay pending
+/// for too long are evicted. Clones share the same pool.
+#[derive(Debug)]
+pub struct Mempool<TYPES: NodeType> {
+    /// The limits of the pool
+    config: MempoolConfig,
+    /// The contents of the pool
+    pool: Arc<Mutex<Pool<TYPES>>>,
+    /// Metrics to export the size of the pool to
+    metrics: Arc<ConsensusMetricsValue>,
+}
+
+impl<TYPES: NodeType> Clone for Mempool<TYPES> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config,
+            pool: Arc::clone(&self.pool),
+            metrics: Arc::clone(&self.metrics),
+        }
+    }
+}
+
+impl<TYPES: NodeType> Mempool<TYPES> {
+    /// An empty mempool with the limits in `config`
+    #[must_use]
+    pub fn new(config: MempoolConfig, metrics: Arc<ConsensusMetricsValue>) -> Self {
+        Self {
+            config,
+            pool: Arc::new(Mutex::new(Pool {
+                entries: HashMap::new(),
+                by_priority: BTreeMap::new(),
+                by_age: BTreeMap::new(),
+                by_proposal: BTreeMap::new(),
+                source_bytes: HashMap::new(),
+                total_bytes: 0,
+                next_sequence: 0,
+                history: LruCache::new(STATUS_HISTORY),
+            })),
+            metrics,
+        }
+    }
+
+    /// Add `transaction`, received from `source`, with the priority the transaction itself
+    /// declares.
+    ///
+    /// # Errors
+    /// If the transaction is already known, or doesn't fit within the limits of the pool.
+    pub fn insert(
+        &self,
+        transaction: TYPES::Transaction,
+        source: TransactionSource<TYPES::SignatureKey>,
+    ) -> Result<Commitment<TYPES::Transaction>, MempoolRejection> {
+        let priority = transaction.priority();
+        self.insert_with_priority(transaction, source, priority)
+    }
+
+    /// Add `transaction`, received from `source`, with `priority`.
+    ///
+    /// # Errors
+    /// If the transaction is already known, or doesn't fit within the limits of the pool.
+    pub fn insert_with_priority(
+        &self,
+        transaction: TYPES::Transaction,
+        source: TransactionSource<TYPES::SignatureKey>,
+        priority: u64,
+    ) -> Result<Commitment<TYPES::Transaction>, MempoolRejection> {
+        let commitment = transaction.commit();
+        let size = transaction.minimum_block_size();
+
+        let mut pool = self.pool.lock();
+        if pool.entries.contains_key(&commitment)
+            || matches!(
+                pool.history.peek(&commitment),
+                Some(TransactionStatus::InDaProposal { .. } | TransactionStatus::Decided { .. })
+            )
+        {
+            return Err(MempoolRejection::Duplicate);
+        }
+        let source_limit = match source {
+            TransactionSource::Local => None,
+            TransactionSource::Peer(_) => Some(self.config.max_bytes_per_sender),
+            TransactionSource::Unattributed => Some(self.config.max_bytes_unattributed),
+        };
+        if size > self.config.max_bytes || source_limit.is_some_and(|limit| size > limit) {
+            return Err(MempoolRejection::TooLarge);
+        }
+        pool.evict_expired(&self.config);
+        if source_limit.is_some_and(|limit| {
+            pool.source_bytes.get(&source).copied().unwrap_or(0) + size > limit
+        }) {
+            return Err(MempoolRejection::SenderLimit);
+        }
+        let outbid = pool
+            .outbid(&self.config, size, priority)
+            .ok_or(MempoolRejection::Full)?;
+        for evicted in outbid {
+            pool.remove(&evicted);
+            pool.history
+                .put(evicted, TransactionStatus::Evicted(EvictionReason::Outbid));
+        }
+
+        let sequence = pool.next_sequence;
+        pool.next_sequence += 1;
+        let order = (priority, Reverse(sequence));
+        pool.by_priority.insert(order, commitment);
+        pool.by_age.insert(sequence, commitment);
+        *pool.source_bytes.entry(source.clone()).or_default() += size;
+        pool.total_bytes += size;
+        pool.history.pop(&commitment);
+        pool.entries.insert(
+            commitment,
+            Entry {
+                transaction,
+                source,
+                size,
+                order,
+                received: Instant::now(),
+                proposed_in: None,
+            },
+        );
+        self.export(&pool);
+        Ok(commitment)
+    }
+
+    /// The transactions which are pending and not in a DA proposal, highest priority first, and
+    /// oldest first among those with the same priority
+    #[must_use]
+    pub fn pending_transactions(&self) -> Vec<TYPES::Transaction> {
+        let mut pool = self.pool.lock();
+        pool.evict_expired(&self.config);
+        self.export(&pool);
+        pool.by_priority
+            .values()
+            .rev()
+            .map(|commitment| pool.entries[commitment].transaction.clone())
+            .collect()
+    }
+
+    /// What happened to the transaction with `commitment`, or `None` if the mempool has not seen
+    /// it or has forgotten about it
+    #[must_use]
+    pub fn transaction_status(
+        &self,
+        commitment: &Commitment<TYPES::Transaction>,
+    ) -> Option<TransactionStatus<TYPES>> {
+        let pool = self.pool.lock();
+        match pool.entries.get(commitment) {
+            Some(Entry {
+                proposed_in: Some(view),
+                ..
+            }) => Some(TransactionStatus::InDaProposal { view: *view }),
+            Some(_) => Some(TransactionStatus::Pending),
+            None => pool.history.peek(commitment).copied(),
+        }
+    }
+
+    /// Record that the DA proposal for `view` includes the transactions with `commitments`. They
+    /// are no longer offered for inclusion, unless the view fails.
+    pub fn record_da_proposal(
+        &self,
+        view: TYPES::View,
+        commitments: impl IntoIterator<Item = Commitment<TYPES::Transaction>>,
+    ) {
+        let mut pool = self.pool.lock();
+        let pool = &mut *pool;
+        for commitment in commitments {
+            if let Some(entry) = pool.entries.get_mut(&commitment) {
+                pool.by_priority.remove(&entry.order);
+                if let Some(previous) = entry.proposed_in.replace(view) {
+                    pool.unpropose(previous, &commitment);
+                }
+                pool.by_proposal.entry(view).or_default().insert(commitment);
+            } else if !matches!(
+                pool.history.peek(&commitment),
+                Some(TransactionStatus::Decided { .. })
+            ) {
+                pool.history
+                    .put(commitment, TransactionStatus::InDaProposal { view });
+            }
+        }
+    }
+
+    /// Record that the leaf decided in `view`, at `height`, includes the transactions with
+    /// `commitments`. Leaves must be recorded in the order they were decided in, and only if
+    /// their payload is known.
+    ///
+    /// Transactions in DA proposals for this view or earlier ones which were not decided become
+    /// pending again, since those views have failed.
+    pub fn record_decided(
+        &self,
+        view: TYPES::View,
+        height: u64,
+        commitments: impl IntoIterator<Item = Commitment<TYPES::Transaction>>,
+    ) {
+        let mut pool = self.pool.lock();
+        for commitment in commitments {
+            pool.remove(&commitment);
+            pool.history
+                .put(commitment, TransactionStatus::Decided { view, height });
+        }
+
+        let pool = &mut *pool;
+        while let Some(proposal) = pool.by_proposal.first_entry() {
+            if *proposal.key() > view {
+                break;
+            }
+            for commitment in proposal.remove() {
+                if let Some(entry) = pool.entries.get_mut(&commitment) {
+                    entry.proposed_in = None;
+                    pool.by_priority.insert(entry.order, commitment);
+                }
+            }
+        }
+        self.export(pool);
+    }
+
+    /// Export the size of `pool` to the metrics
+    fn export(&self, pool: &Pool<TYPES>) {
+        self.metrics
+            .outstanding_transactions
+            .set(pool.entries.len());
+        self.metrics
+            .outstanding_transactions_memory_size
+            .set(usize::try_from(pool.total_bytes).unwrap_or(usize::MAX));
+    }
+}
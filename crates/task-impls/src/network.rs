@@ -13,26 +13,30 @@ use std::{
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::Committable;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
     consensus::OuterConsensus,
-    data::{VidDisperse, VidDisperseShare},
+    data::{Leaf, Leaf2, QuorumProposal2, VidDisperse, VidDisperseShare},
     event::{Event, EventType, HotShotAction},
     message::{
         convert_proposal, DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message,
         MessageKind, Proposal, SequencingMessage, UpgradeLock,
     },
+    slashing_protection::SigningRoot,
     traits::{
         election::Membership,
         network::{
-            BroadcastDelay, ConnectedNetwork, RequestKind, ResponseMessage, TransmitType,
-            ViewMessage,
+            AuthenticatedPeer, BroadcastDelay, ConnectedNetwork, Misbehaviour, RequestKind,
+            ResponseMessage, TransmitType, ViewMessage,
         },
         node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::SignatureKey,
         storage::Storage,
     },
     vote::{HasViewNumber, Vote},
 };
+use sha2::{Digest, Sha256};
 use tokio::{spawn, task::JoinHandle};
 use tracing::instrument;
 use utils::anytrace::*;
@@ -44,7 +48,7 @@ use crate::{
 
 /// the network message task state
 #[derive(Clone)]
-pub struct NetworkMessageTaskState<TYPES: NodeType> {
+pub struct NetworkMessageTaskState<TYPES: NodeType, NET: ConnectedNetwork<TYPES::SignatureKey>> {
     /// Sender to send internal events this task generates to other tasks
     pub internal_event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
 
@@ -56,16 +60,53 @@ pub struct NetworkMessageTaskState<TYPES: NodeType> {
 
     /// Transaction Cache to ignore previously seen transatctions
     pub transactions_cache: lru::LruCache<u64, ()>,
+
+    /// The network the messages come from, to which we report misbehaving senders
+    pub network: Arc<NET>,
 }
 
-impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
+impl<TYPES: NodeType, NET: ConnectedNetwork<TYPES::SignatureKey>>
+    NetworkMessageTaskState<TYPES, NET>
+{
     #[instrument(skip_all, name = "Network message task", level = "trace")]
-    /// Handles a (deserialized) message from the network
-    pub async fn handle_message(&mut self, message: Message<TYPES>) {
+    /// Handles a (deserialized) message from the network, delivered by `peer` if the network
+    /// could authenticate it
+    pub async fn handle_message(
+        &mut self,
+        message: Message<TYPES>,
+        peer: Option<AuthenticatedPeer<TYPES::SignatureKey>>,
+    ) {
         tracing::trace!("Received message from network:\n\n{message:?}");
 
-        // Match the message kind and send the appropriate event to the internal event stream
         let sender = message.sender;
+
+        // Ignore banned peers entirely. Networks which can't tell who delivered a message keep
+        // their bans by key, so check the key the message names instead; someone else claiming a
+        // banned key only gets their own messages dropped.
+        let banned = match &peer {
+            Some(peer) => self.network.is_peer_banned(peer),
+            None => self
+                .network
+                .is_peer_banned(&AuthenticatedPeer::Key(sender.clone())),
+        };
+        if banned {
+            tracing::debug!("Dropping message from banned peer {peer:?} ({sender:?})");
+            return;
+        }
+
+        // Drop messages with bad signatures, and report whoever delivered them to the network.
+        // The sender named in the message is not authenticated, so it is never the one reported.
+        if let Some(misbehaviour) = self.misbehaviour(&sender, &message.kind) {
+            tracing::warn!(
+                "Dropping message from {peer:?} claiming to be from {sender:?}: {misbehaviour:?}"
+            );
+            if let Some(peer) = &peer {
+                self.network.report_peer(peer, misbehaviour);
+            }
+            return;
+        }
+
+        // Match the message kind and send the appropriate event to the internal event stream
         match message.kind {
             // Handle consensus messages
             MessageKind::Consensus(consensus_message) => {
@@ -143,37 +184,55 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                         return;
                     }
                     broadcast_event(
-                        Arc::new(HotShotEvent::TransactionsRecv(vec![transaction])),
+                        Arc::new(HotShotEvent::TransactionsRecv(
+                            vec![transaction],
+                            peer.into(),
+                        )),
                         &self.internal_event_stream,
                     )
                     .await;
                 }
-                DataMessage::DataResponse(response) => {
-                    if let ResponseMessage::Found(message) = response {
-                        match message {
-                            SequencingMessage::Da(da_message) => {
-                                if let DaConsensusMessage::VidDisperseMsg(proposal) = da_message {
-                                    broadcast_event(
-                                        Arc::new(HotShotEvent::VidResponseRecv(sender, proposal)),
-                                        &self.internal_event_stream,
-                                    )
-                                    .await;
-                                }
+                DataMessage::DataResponse(response) => match response {
+                    ResponseMessage::Found(message) => match message {
+                        SequencingMessage::Da(da_message) => {
+                            if let DaConsensusMessage::VidDisperseMsg(proposal) = da_message {
+                                broadcast_event(
+                                    Arc::new(HotShotEvent::VidResponseRecv(sender, proposal)),
+                                    &self.internal_event_stream,
+                                )
+                                .await;
                             }
-                            SequencingMessage::General(_) => {}
                         }
+                        SequencingMessage::General(_) => {}
+                    },
+                    ResponseMessage::Data { data, .. } => {
+                        broadcast_event(
+                            Arc::new(HotShotEvent::DataResponseRecv(sender, data)),
+                            &self.internal_event_stream,
+                        )
+                        .await;
                     }
-                }
-                DataMessage::RequestData(data) => {
-                    let req_data = data.clone();
-                    if let RequestKind::Vid(_view_number, _key) = req_data.request {
+                    ResponseMessage::NotFound | ResponseMessage::Denied => {}
+                },
+                DataMessage::RequestData(data) => match data.request {
+                    RequestKind::Vid(_view_number, _key) => {
                         broadcast_event(
                             Arc::new(HotShotEvent::VidRequestRecv(data, sender)),
                             &self.internal_event_stream,
                         )
                         .await;
                     }
-                }
+                    RequestKind::Leaf { .. }
+                    | RequestKind::Payload { .. }
+                    | RequestKind::QuorumCertificate { .. } => {
+                        broadcast_event(
+                            Arc::new(HotShotEvent::DataRequestRecv(data, sender)),
+                            &self.internal_event_stream,
+                        )
+                        .await;
+                    }
+                    RequestKind::DaProposal(_) | RequestKind::Proposal(_) => {}
+                },
             },
 
             // Handle external messages
@@ -193,6 +252,62 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
             }
         }
     }
+
+    /// Checks the signature of signed proposals and responses from `sender`, returning how the
+    /// message is invalid if a signature doesn't verify. Other messages are left to the tasks
+    /// that handle them; in particular, votes are checked when the vote tasks accumulate them,
+    /// so we don't verify them twice. Oversized responses to data requests never get here, as
+    /// they fail to deserialize.
+    ///
+    /// Proposals and VID shares sent in response to a request are not checked here: a responder
+    /// which stored them relays them with the signature of the leader, not its own.
+    fn misbehaviour(
+        &self,
+        sender: &TYPES::SignatureKey,
+        kind: &MessageKind<TYPES>,
+    ) -> Option<Misbehaviour> {
+        match kind {
+            MessageKind::Consensus(SequencingMessage::General(
+                GeneralConsensusMessage::Proposal(proposal),
+            )) => {
+                let proposal: Proposal<TYPES, QuorumProposal2<TYPES>> =
+                    convert_proposal(proposal.clone());
+                let proposed_leaf = Leaf2::from_quorum_proposal(&proposal.data);
+                if !sender.validate(&proposal.signature, proposed_leaf.commit().as_ref()) {
+                    return Some(Misbehaviour::InvalidProposal);
+                }
+            }
+            MessageKind::Consensus(SequencingMessage::Da(DaConsensusMessage::DaProposal(
+                proposal,
+            ))) => {
+                let encoded_transactions_hash = Sha256::digest(&proposal.data.encoded_transactions);
+                if !sender.validate(&proposal.signature, &encoded_transactions_hash) {
+                    return Some(Misbehaviour::InvalidProposal);
+                }
+            }
+            MessageKind::Consensus(SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(
+                proposal,
+            ))) => {
+                if !sender.validate(
+                    &proposal.signature,
+                    proposal.data.payload_commitment.as_ref(),
+                ) {
+                    return Some(Misbehaviour::InvalidVidShare);
+                }
+            }
+            MessageKind::Data(DataMessage::DataResponse(ResponseMessage::Data {
+                data,
+                signature,
+            })) => {
+                if !data.is_signed_by(sender, signature) {
+                    return Some(Misbehaviour::InvalidResponse);
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
 }
 
 /// network event task state
@@ -301,6 +416,7 @@ impl<
         spawn(async move {
             if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
                 Some(HotShotAction::VidDisperse),
+                None,
                 storage,
                 consensus,
                 view,
@@ -319,9 +435,13 @@ impl<
         None
     }
 
-    /// Record `HotShotAction` if available
+    /// Record `HotShotAction` if available.
+    ///
+    /// If we know what the action signed, it is first checked against our signing history, and
+    /// the action is refused if it conflicts with something we signed before.
     async fn maybe_record_action(
         maybe_action: Option<HotShotAction>,
+        signing_root: Option<SigningRoot>,
         storage: Arc<RwLock<S>>,
         consensus: OuterConsensus<TYPES>,
         view: <TYPES as NodeType>::View,
@@ -331,6 +451,17 @@ impl<
                 tracing::warn!("Already actioned {:?} in view {:?}", action, view);
                 return Err(());
             }
+            if let Some(signing_root) = signing_root {
+                if let Err(e) = storage
+                    .write()
+                    .await
+                    .record_signed_action(view, action, signing_root)
+                    .await
+                {
+                    tracing::error!("Refusing to send {:?} in view {:?}: {:?}", action, view, e);
+                    return Err(());
+                }
+            }
             // If the action was view sync record it as a vote, but we don't
             // want to limit to 1 View sycn vote above so change the action here.
             if matches!(action, HotShotAction::ViewSyncVote) {
@@ -483,6 +614,7 @@ impl<
                 ))
             }
             HotShotEvent::ViewSyncPreCommitVoteSend(vote) => {
+                *maybe_action = Some(HotShotAction::ViewSyncVote);
                 let view_number = vote.view_number() + vote.date().relay;
                 let leader = match self.quorum_membership.leader(view_number, self.epoch) {
                     Ok(l) => l,
@@ -652,6 +784,19 @@ impl<
                     TransmitType::Direct(to),
                 ))
             }
+            HotShotEvent::DataRequestSend(req, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::RequestData(req)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::DataResponseSend(sender, to, data, signature) => Some((
+                sender,
+                MessageKind::Data(DataMessage::DataResponse(ResponseMessage::Data {
+                    data,
+                    signature,
+                })),
+                TransmitType::Direct(to),
+            )),
             _ => None,
         }
     }
@@ -675,7 +820,15 @@ impl<
             sender,
             kind: message_kind,
         };
-        let view_number = message.kind.view_number();
+        // Responses can carry data from long decided views, so they are tracked under the
+        // current view to keep the next view change from cancelling them
+        let view_number = match &message.kind {
+            MessageKind::Data(DataMessage::DataResponse(_)) => {
+                message.kind.view_number().max(self.view)
+            }
+            _ => message.kind.view_number(),
+        };
+        let signing_root = signing_root(&message.kind);
         let committee_topic = self.quorum_membership.committee_topic();
         let da_committee = self
             .da_membership
@@ -687,6 +840,7 @@ impl<
         let handle = spawn(async move {
             if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
                 maybe_action,
+                signing_root,
                 Arc::clone(&storage),
                 consensus,
                 view_number,
@@ -745,6 +899,39 @@ impl<
     }
 }
 
+/// Returns a commitment to what was signed in `message_kind`, if it is an action which must never
+/// be signed twice with different contents in the same view.
+///
+/// Timeout votes are recorded under the same action as quorum votes, so a node signs either a vote
+/// or a timeout vote in a view, and never two different timeout votes. View sync votes only attest
+/// to the round being synced, and are resent verbatim to each relay, so they are tracked under the
+/// round alone.
+fn signing_root<TYPES: NodeType>(message_kind: &MessageKind<TYPES>) -> Option<SigningRoot> {
+    match message_kind {
+        MessageKind::Consensus(SequencingMessage::General(message)) => match message {
+            GeneralConsensusMessage::Proposal(proposal) => {
+                Some(Leaf::from_quorum_proposal(&proposal.data).commit().into())
+            }
+            GeneralConsensusMessage::Vote(vote) => Some(vote.data_commitment().into()),
+            GeneralConsensusMessage::TimeoutVote(vote) => Some(vote.data_commitment().into()),
+            GeneralConsensusMessage::ViewSyncPreCommitVote(vote) => {
+                Some(vote.date().round.commit().into())
+            }
+            GeneralConsensusMessage::ViewSyncCommitVote(vote) => {
+                Some(vote.date().round.commit().into())
+            }
+            GeneralConsensusMessage::ViewSyncFinalizeVote(vote) => {
+                Some(vote.date().round.commit().into())
+            }
+            _ => None,
+        },
+        MessageKind::Consensus(SequencingMessage::Da(DaConsensusMessage::DaVote(vote))) => {
+            Some(vote.data_commitment().into())
+        }
+        _ => None,
+    }
+}
+
 /// A module with test helpers
 pub mod test {
     use std::ops::{Deref, DerefMut};
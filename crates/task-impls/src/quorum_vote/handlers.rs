@@ -6,14 +6,15 @@
 
 use std::sync::Arc;
 
-use async_broadcast::{InactiveReceiver, Sender};
+use async_broadcast::{InactiveReceiver, Receiver, Sender};
 use async_lock::RwLock;
 use chrono::Utc;
 use committable::Committable;
 use hotshot_types::{
     consensus::OuterConsensus,
     data::{Leaf2, QuorumProposal2, VidDisperseShare},
-    event::{Event, EventType},
+    drb::{compute_drb_result, drb_seed_input, verify_drb_result, DrbOutput, DrbSeedInput},
+    event::{Event, EventType, LeafInfo},
     message::{Proposal, UpgradeLock},
     simple_vote::{QuorumData2, QuorumVote2},
     traits::{
@@ -25,12 +26,14 @@ use hotshot_types::{
     },
     vote::HasViewNumber,
 };
+use tokio::task::JoinHandle;
 use tracing::instrument;
 use utils::anytrace::*;
 use vbs::version::StaticVersionType;
 
 use super::QuorumVoteTaskState;
 use crate::{
+    catchup::LeafFetcher,
     events::HotShotEvent,
     helpers::{
         broadcast_event, decide_from_proposal, decide_from_proposal_2, fetch_proposal,
@@ -48,6 +51,8 @@ pub(crate) async fn handle_quorum_proposal_validated<
 >(
     proposal: &QuorumProposal2<TYPES>,
     task_state: &mut QuorumVoteTaskState<TYPES, I, V>,
+    event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    event_receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
 ) -> Result<()> {
     let version = task_state
         .upgrade_lock
@@ -106,6 +111,8 @@ pub(crate) async fn handle_quorum_proposal_validated<
         // Bring in the cleanup crew. When a new decide is indeed valid, we need to clear out old memory.
 
         let old_decided_view = consensus_writer.last_decided_view();
+        let old_decided_leaf = consensus_writer.decided_leaf();
+        let cur_epoch = consensus_writer.cur_epoch();
         consensus_writer.collect_garbage(old_decided_view, decided_view_number);
 
         // Set the new decided view.
@@ -135,26 +142,326 @@ pub(crate) async fn handle_quorum_proposal_validated<
         // We don't need to hold this while we broadcast
         drop(consensus_writer);
 
-        // First, send an update to everyone saying that we've reached a decide
+        // Persist the newly decided leaves, oldest first, so that a restarted node can pick up
+        // from here.
+        let storage = task_state.storage.write().await;
+        for decided_leaf in leaf_views.iter().rev() {
+            if let Err(e) = storage.update_decided_leaf(decided_leaf).await {
+                tracing::error!("Failed to store decided leaf: {e:?}");
+            }
+        }
+        drop(storage);
+
+        update_stake_tables(&leaf_views, task_state).await;
+        let drb_computations = start_drb_computations(&leaf_views, task_state);
+
         broadcast_event(
-            Event {
-                view_number: decided_view_number,
-                event: EventType::Decide {
-                    leaf_chain: Arc::new(leaf_views),
-                    // This is never *not* none if we've reached a new decide, so this is safe to unwrap.
-                    qc: Arc::new(new_decide_qc.unwrap()),
-                    block_size: included_txns.map(|txns| txns.len().try_into().unwrap()),
-                },
-            },
-            &task_state.output_event_stream,
+            Arc::new(HotShotEvent::LeavesDecided(
+                leaf_views.iter().map(|info| info.leaf.clone()).collect(),
+            )),
+            event_sender,
         )
         .await;
-        tracing::debug!("Successfully sent decide event");
+
+        // If we missed some of the leaves decided since the last decide, fetch them before
+        // telling anyone about this one.
+        let has_gap = leaf_views
+            .last()
+            .is_some_and(|info| info.leaf.height() > old_decided_leaf.height() + 1);
+        let decide = Event {
+            view_number: decided_view_number,
+            event: EventType::Decide {
+                leaf_chain: Arc::new(leaf_views),
+                // This is never *not* none if we've reached a new decide, so this is safe to unwrap.
+                qc: Arc::new(new_decide_qc.unwrap()),
+                block_size: included_txns.map(|txns| txns.len().try_into().unwrap()),
+            },
+        };
+
+        if !has_gap
+            && task_state
+                .decide_catchup
+                .as_ref()
+                .map_or(true, JoinHandle::is_finished)
+        {
+            // First, send an update to everyone saying that we've reached a decide
+            broadcast_event(decide, &task_state.output_event_stream).await;
+            tracing::debug!("Successfully sent decide event");
+            collect_storage_garbage(&task_state.storage, decided_view_number).await;
+        } else {
+            let previous = task_state.decide_catchup.take();
+            let fetcher = LeafFetcher {
+                public_key: task_state.public_key.clone(),
+                private_key: task_state.private_key.clone(),
+                quorum_membership: Arc::clone(&task_state.quorum_membership),
+                da_membership: Arc::clone(&task_state.da_membership),
+                epoch_height: task_state.epoch_height,
+                vid_code_rate: task_state.vid_code_rate,
+                scheduler: task_state.request_scheduler.clone(),
+                sender: event_sender.clone(),
+                receiver: event_receiver.clone().deactivate(),
+            };
+            let output_event_stream = task_state.output_event_stream.clone();
+            let storage = Arc::clone(&task_state.storage);
+            task_state.decide_catchup = Some(tokio::spawn(async move {
+                if let Some(previous) = previous {
+                    let _ = previous.await;
+                }
+                fetcher
+                    .fill_gap_and_emit(old_decided_leaf, decide, cur_epoch, &output_event_stream)
+                    .await;
+                collect_storage_garbage(&storage, decided_view_number).await;
+            }));
+        }
+
+        drb_computations?;
+    }
+
+    Ok(())
+}
+
+/// Let storage drop whatever it no longer needs now that `decided_view` is decided. This runs once
+/// the decide has been sent, so that nothing is dropped before the application has seen it.
+async fn collect_storage_garbage<TYPES: NodeType, S: Storage<TYPES>>(
+    storage: &RwLock<S>,
+    decided_view: TYPES::View,
+) {
+    if let Err(e) = storage.write().await.gc(decided_view).await {
+        tracing::warn!("Failed to garbage collect storage: {e:?}");
+    }
+}
+
+/// Persist the stake table set by the last block of each epoch which was just decided, then pass it
+/// to the memberships. The stake table set at the end of epoch `e` takes effect in epoch `e + 2`.
+async fn update_stake_tables<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    leaf_views: &[LeafInfo<TYPES>],
+    task_state: &QuorumVoteTaskState<TYPES, I, V>,
+) {
+    if task_state.epoch_height == 0 {
+        return;
+    }
+
+    for LeafInfo { leaf, state, .. } in leaf_views {
+        let height = leaf.height();
+        if height == 0 || height % task_state.epoch_height != 0 {
+            continue;
+        }
+        let Some(stake_table) = state.stake_table_update(&task_state.instance_state) else {
+            continue;
+        };
+
+        let target_epoch = TYPES::Epoch::new(height / task_state.epoch_height) + 2;
+        tracing::info!(
+            "Block {height} sets a stake table of {} nodes for epoch {target_epoch}",
+            stake_table.len()
+        );
+        // Store the stake table before using it, so that a restarted node has the same committee
+        if let Err(e) = task_state
+            .storage
+            .write()
+            .await
+            .add_stake_table(target_epoch, &stake_table)
+            .await
+        {
+            tracing::error!("Failed to store the stake table for epoch {target_epoch}: {e:?}");
+        }
+        task_state
+            .da_membership
+            .update_stake_table(target_epoch, stake_table.clone());
+        task_state
+            .quorum_membership
+            .update_stake_table(target_epoch, stake_table);
+    }
+}
+
+/// Start computing the DRB result for each epoch whose last block was just decided.
+///
+/// The seed for epoch `e` comes from the QC the last block of `e` was proposed with, which every
+/// node agrees on once the block is decided. The result chooses the leaders of epoch `e + 2`, and
+/// is persisted, then published to the memberships and to `Consensus` once it's ready.
+///
+/// # Errors
+/// Returns an error if the last block of an epoch has no DRB seed, after starting the
+/// computations for every other epoch. There is no result to fall back on for that epoch, so it
+/// will have no leaders.
+fn start_drb_computations<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    leaf_views: &[LeafInfo<TYPES>],
+    task_state: &mut QuorumVoteTaskState<TYPES, I, V>,
+) -> Result<()> {
+    if task_state.epoch_height == 0 {
+        return Ok(());
+    }
+
+    // Forget about the computations which are done
+    task_state
+        .drb_computations
+        .retain(|_, handle| !handle.is_finished());
+
+    let mut unseeded_epochs = Vec::new();
+    for LeafInfo { leaf, .. } in leaf_views {
+        let height = leaf.height();
+        if height == 0 || height % task_state.epoch_height != 0 {
+            continue;
+        }
+        let epoch = TYPES::Epoch::new(height / task_state.epoch_height);
+        let target_epoch = epoch + 2;
+        if task_state.drb_computations.contains_key(&target_epoch) {
+            continue;
+        }
+        let Some(drb_seed_input) = drb_seed_input(&leaf.justify_qc()) else {
+            unseeded_epochs.push(target_epoch);
+            continue;
+        };
+
+        tracing::info!("Computing the DRB result for epoch {target_epoch} from epoch {epoch}");
+        let handle = spawn_drb_computation(task_state, target_epoch, drb_seed_input, None);
+        task_state.drb_computations.insert(target_epoch, handle);
     }
 
+    ensure!(
+        unseeded_epochs.is_empty(),
+        error!(
+            "No DRB seed for epochs {unseeded_epochs:?}, the QCs of the blocks seeding them have no signatures"
+        )
+    );
+
     Ok(())
 }
 
+/// Restart the DRB computations a restart interrupted, and check the DRB results restored on
+/// startup, which we may not have computed ourselves.
+///
+/// This covers every epoch whose last block is decided and whose result is still needed. The seed
+/// comes from the decided leaf at the end of the epoch, so an epoch is skipped if that leaf is
+/// neither the last decided leaf nor in storage.
+pub(crate) async fn resume_drb_computations<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    V: Versions,
+>(
+    task_state: &mut QuorumVoteTaskState<TYPES, I, V>,
+) {
+    if task_state.epoch_height == 0 {
+        return;
+    }
+
+    let consensus_reader = task_state.consensus.read().await;
+    let decided_leaf = consensus_reader.decided_leaf();
+    let cur_epoch = consensus_reader.cur_epoch();
+    drop(consensus_reader);
+
+    // The result of epoch `e + 2` is seeded by the last block of epoch `e`
+    let first_seed_epoch = cur_epoch.u64().saturating_sub(2).max(1);
+    let last_seed_epoch = decided_leaf.height() / task_state.epoch_height;
+    for epoch in (first_seed_epoch..=last_seed_epoch).map(TYPES::Epoch::new) {
+        let target_epoch = epoch + 2;
+        let height = epoch.u64() * task_state.epoch_height;
+        let seed_leaf = if height == decided_leaf.height() {
+            Some(decided_leaf.clone())
+        } else {
+            match task_state
+                .storage
+                .read()
+                .await
+                .load_leaf_at_height(height)
+                .await
+            {
+                Ok(leaf) => leaf,
+                Err(e) => {
+                    tracing::warn!("Failed to load the leaf at height {height}: {e:?}");
+                    None
+                }
+            }
+        };
+        let Some(drb_seed_input) = seed_leaf.and_then(|leaf| drb_seed_input(&leaf.justify_qc()))
+        else {
+            tracing::warn!(
+                "No DRB seed for epoch {target_epoch}, can't check or compute its DRB result"
+            );
+            continue;
+        };
+
+        let restored = task_state
+            .consensus
+            .read()
+            .await
+            .drb_output(target_epoch)
+            .cloned();
+        if restored.is_some() {
+            tracing::info!("Checking the restored DRB result for epoch {target_epoch}");
+        } else {
+            tracing::info!("Resuming the DRB computation for epoch {target_epoch}");
+        }
+        let handle = spawn_drb_computation(task_state, target_epoch, drb_seed_input, restored);
+        task_state.drb_computations.insert(target_epoch, handle);
+    }
+}
+
+/// Compute the DRB result for `target_epoch` from `drb_seed_input` in the background, then persist
+/// it and publish it to the memberships and to `Consensus`.
+///
+/// If we already have a result which we didn't compute ourselves, as `restored`, it is verified
+/// instead, and only computed afresh if it turns out to be wrong.
+fn spawn_drb_computation<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    task_state: &QuorumVoteTaskState<TYPES, I, V>,
+    target_epoch: TYPES::Epoch,
+    drb_seed_input: DrbSeedInput,
+    restored: Option<DrbOutput>,
+) -> JoinHandle<()> {
+    let consensus = OuterConsensus::new(Arc::clone(&task_state.consensus.inner_consensus));
+    let quorum_membership = Arc::clone(&task_state.quorum_membership);
+    let da_membership = Arc::clone(&task_state.da_membership);
+    let storage = Arc::clone(&task_state.storage);
+    let drb_config = task_state.drb_config;
+    tokio::spawn(async move {
+        if let Some(restored) = restored {
+            let valid = tokio::task::spawn_blocking(move || {
+                verify_drb_result(drb_seed_input, &restored, &drb_config)
+            })
+            .await
+            .unwrap_or(false);
+            if valid {
+                return;
+            }
+            tracing::error!(
+                "The restored DRB result for epoch {target_epoch} is invalid, computing it again"
+            );
+            consensus.write().await.remove_drb_result(target_epoch);
+        }
+
+        let output = match tokio::task::spawn_blocking(move || {
+            compute_drb_result(drb_seed_input, &drb_config)
+        })
+        .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                tracing::error!("Failed to compute the DRB result for epoch {target_epoch}: {e}");
+                return;
+            }
+        };
+
+        // Store the result before using it, so that a restarted node chooses the same leaders
+        if let Err(e) = storage
+            .write()
+            .await
+            .add_drb_result(target_epoch, &output)
+            .await
+        {
+            tracing::error!("Failed to store the DRB result for epoch {target_epoch}: {e:?}");
+        }
+        quorum_membership.add_drb_result(target_epoch, output.result);
+        da_membership.add_drb_result(target_epoch, output.result);
+        if let Err(e) = consensus
+            .write()
+            .await
+            .update_drb_result(target_epoch, output)
+        {
+            tracing::error!("{e}");
+        }
+    })
+}
+
 /// Updates the shared consensus state with the new voting data.
 #[instrument(skip_all, target = "VoteDependencyHandle", fields(view = *view_number))]
 #[allow(clippy::too_many_arguments)]
@@ -18,6 +18,7 @@ use hotshot_task::{
 use hotshot_types::{
     consensus::OuterConsensus,
     data::{Leaf2, QuorumProposal2},
+    drb::DrbConfig,
     event::Event,
     message::{Proposal, UpgradeLock},
     traits::{
@@ -28,7 +29,7 @@ use hotshot_types::{
         storage::Storage,
     },
     utils::epoch_from_block_number,
-    vid::vid_scheme,
+    vid::{vid_scheme, VidCodeRate},
     vote::{Certificate, HasViewNumber},
 };
 use jf_vid::VidScheme;
@@ -40,7 +41,10 @@ use vbs::version::StaticVersionType;
 use crate::{
     events::HotShotEvent,
     helpers::broadcast_event,
-    quorum_vote::handlers::{handle_quorum_proposal_validated, submit_vote, update_shared_state},
+    quorum_vote::handlers::{
+        handle_quorum_proposal_validated, resume_drb_computations, submit_vote, update_shared_state,
+    },
+    request_scheduler::RequestScheduler,
 };
 
 /// Event handlers for `QuorumProposalValidated`.
@@ -289,9 +293,31 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// How the DRB is computed
+    pub drb_config: DrbConfig,
+
+    /// The DRB computations in progress, keyed by the epoch whose leaders they choose
+    pub drb_computations: BTreeMap<TYPES::Epoch, JoinHandle<()>>,
+
+    /// The decide being emitted once the leaves missing before it are fetched. Later decides wait
+    /// for it, so that they are emitted in order.
+    pub decide_catchup: Option<JoinHandle<()>>,
+
+    /// Picks the peers to fetch missing leaves from, and how long to wait for them
+    pub request_scheduler: RequestScheduler<TYPES::SignatureKey>,
+
+    /// Erasure code rate of VID
+    pub vid_code_rate: VidCodeRate,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskState<TYPES, I, V> {
+    /// Resume the DRB computations interrupted by a restart, and verify the DRB results restored
+    /// from storage against the decided seeds. Call this once, when the task is created.
+    pub async fn resume_drb_computations(&mut self) {
+        resume_drb_computations(self).await;
+    }
+
     /// Create an event dependency.
     #[instrument(skip_all, fields(id = self.id, latest_voted_view = *self.latest_voted_view), name = "Quorum vote create event dependency", level = "error")]
     fn create_event_dependency(
@@ -432,7 +458,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 );
 
                 // Handle the event before creating the dependency task.
-                if let Err(e) = handle_quorum_proposal_validated(&proposal.data, self).await {
+                if let Err(e) = handle_quorum_proposal_validated(
+                    &proposal.data,
+                    self,
+                    &event_sender,
+                    &event_receiver,
+                )
+                .await
+                {
                     tracing::debug!(
                         "Failed to handle QuorumProposalValidated event; error = {e:#}"
                     );
@@ -527,11 +560,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
 
                 // NOTE: `verify_share` returns a nested `Result`, so we must check both the inner
                 // and outer results
-                match vid_scheme(self.quorum_membership.total_nodes(cur_epoch)).verify_share(
-                    &disperse.data.share,
-                    &disperse.data.common,
-                    payload_commitment,
-                ) {
+                match vid_scheme(
+                    self.quorum_membership.total_nodes(cur_epoch),
+                    self.vid_code_rate,
+                )
+                .and_then(|vid| {
+                    vid.verify_share(
+                        &disperse.data.share,
+                        &disperse.data.common,
+                        payload_commitment,
+                    )
+                }) {
                     Ok(Err(())) | Err(_) => {
                         bail!("Failed to verify VID share");
                     }
@@ -721,5 +760,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
         while let Some((_, handle)) = self.vote_dependencies.pop_last() {
             handle.abort();
         }
+        while let Some((_, handle)) = self.drb_computations.pop_last() {
+            handle.abort();
+        }
+        if let Some(handle) = self.decide_catchup.take() {
+            handle.abort();
+        }
     }
 }
@@ -10,7 +10,7 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use async_broadcast::{Receiver, Sender};
@@ -29,7 +29,6 @@ use hotshot_types::{
     },
     vote::HasViewNumber,
 };
-use rand::{seq::SliceRandom, thread_rng};
 use sha2::{Digest, Sha256};
 use tokio::{
     spawn,
@@ -39,15 +38,13 @@ use tokio::{
 use tracing::instrument;
 use utils::anytrace::Result;
 
-use crate::{events::HotShotEvent, helpers::broadcast_event};
-
-/// Amount of time to try for a request before timing out.
-pub const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+use crate::{events::HotShotEvent, helpers::broadcast_event, request_scheduler::RequestScheduler};
 
 /// Long running task which will request information after a proposal is received.
 /// The task will wait a it's `delay` and then send a request iteratively to peers
-/// for any data they don't have related to the proposal.  For now it's just requesting VID
-/// shares.
+/// for any data they don't have related to the proposal, fastest and most reliable peers first.
+/// A request which is slow to be answered is hedged by sending it to the next peer as well.
+/// For now it's just requesting VID shares.
 pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Network to send requests over
     /// The underlying network
@@ -59,6 +56,8 @@ pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     pub view: TYPES::View,
     /// Delay before requesting peers
     pub delay: Duration,
+    /// Picks the peers to request from, and how long to wait for them
+    pub scheduler: RequestScheduler<TYPES::SignatureKey>,
     /// DA Membership
     pub da_membership: TYPES::Membership,
     /// This nodes public key
@@ -186,14 +185,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         }
 
         // Get committee members for view
-        let mut recipients: Vec<TYPES::SignatureKey> = self
-            .da_membership
-            .committee_members(view, epoch)
-            .into_iter()
-            .collect();
-        // Randomize the recipients so all replicas don't overload the same 1 recipients
-        // and so we don't implicitly rely on the same replica all the time.
-        recipients.shuffle(&mut thread_rng());
+        let recipients = self.da_membership.committee_members(view, epoch);
+        let scheduler = self.scheduler.clone();
 
         // prepare request
         let data_request = DataRequest::<TYPES> {
@@ -207,7 +200,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
                 sleep(delay).await;
             }
 
-            let mut recipients_it = recipients.iter();
+            // Ask the peers which have answered quickly and reliably before first, and no need
+            // to send a message to ourselves
+            let mut recipients = scheduler
+                .rank(recipients)
+                .into_iter()
+                .filter(|recipient| *recipient != public_key)
+                .peekable();
+            // The recipients still expected to answer, with when we asked them
+            let mut outstanding: Vec<(TYPES::SignatureKey, Instant)> = Vec::new();
+
             // First check if we got the data before continuing
             while !Self::cancel_vid_request_task(
                 &consensus,
@@ -218,88 +220,76 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
             )
             .await
             {
-                // Cycle da members we send the request to each time
-                if let Some(recipient) = recipients_it.next() {
-                    if *recipient == public_key {
-                        // no need to send a message to ourselves.
-                        // just check for the data at start of loop in `cancel_vid_request_task`
-                        continue;
+                let request_timeout = scheduler.request_timeout();
+                outstanding.retain(|(recipient, sent)| {
+                    let timed_out = sent.elapsed() >= request_timeout;
+                    if timed_out {
+                        scheduler.record_failure(recipient);
                     }
-                    // If we got the data after we make the request then we are done
-                    if Self::handle_vid_request_task(
+                    !timed_out
+                });
+
+                if let Some(recipient) = recipients.next() {
+                    if !outstanding.is_empty() {
+                        scheduler.record_hedge();
+                    }
+                    broadcast_event(
+                        HotShotEvent::VidRequestSend(
+                            data_request.clone(),
+                            public_key.clone(),
+                            recipient.clone(),
+                        )
+                        .into(),
                         &sender,
-                        &receiver,
-                        &data_request,
-                        recipient,
-                        &da_committee_for_view,
-                        &public_key,
-                        view,
                     )
-                    .await
-                    {
-                        return;
-                    }
-                } else {
-                    // This shouldnt be possible `recipients_it.next()` should clone original and start over if `None`
+                    .await;
+                    outstanding.push((recipient, Instant::now()));
+                }
+                let Some((_, oldest)) = outstanding.first() else {
                     tracing::warn!(
                         "Sent VID request to all available DA members and got no reponse for view: {:?}",
                         view
                     );
                     return;
+                };
+
+                // Wait for a response, until it's time to ask another recipient as well, or for
+                // the last requests to time out
+                let wait = if recipients.peek().is_some() {
+                    scheduler.hedge_delay()
+                } else {
+                    request_timeout.saturating_sub(oldest.elapsed())
+                };
+                let Ok(Some(event)) = timeout(
+                    wait,
+                    Self::handle_event_dependency(&receiver, da_committee_for_view.clone(), view),
+                )
+                .await
+                else {
+                    continue;
+                };
+                if let HotShotEvent::VidResponseRecv(sender_pub_key, proposal) = event.as_ref() {
+                    if let Some((_, sent)) = outstanding
+                        .iter()
+                        .find(|(recipient, _)| recipient == sender_pub_key)
+                    {
+                        scheduler.record_response(sender_pub_key, sent.elapsed());
+                    }
+                    broadcast_event(
+                        Arc::new(HotShotEvent::VidShareRecv(
+                            sender_pub_key.clone(),
+                            proposal.clone(),
+                        )),
+                        &sender,
+                    )
+                    .await;
+                    return;
                 }
             }
         });
         self.spawned_tasks.entry(view).or_default().push(handle);
     }
 
-    /// Handles main logic for the Request / Response of a vid share
-    /// Make the request to get VID share to a DA member and wait for the response.
-    /// Returns true if response received, otherwise false
-    async fn handle_vid_request_task(
-        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
-        receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
-        data_request: &DataRequest<TYPES>,
-        recipient: &TYPES::SignatureKey,
-        da_committee_for_view: &BTreeSet<<TYPES as NodeType>::SignatureKey>,
-        public_key: &<TYPES as NodeType>::SignatureKey,
-        view: TYPES::View,
-    ) -> bool {
-        // First send request to a random DA member for the view
-        broadcast_event(
-            HotShotEvent::VidRequestSend(
-                data_request.clone(),
-                public_key.clone(),
-                recipient.clone(),
-            )
-            .into(),
-            sender,
-        )
-        .await;
-
-        // Wait for a response
-        let result = timeout(
-            REQUEST_TIMEOUT,
-            Self::handle_event_dependency(receiver, da_committee_for_view.clone(), view),
-        )
-        .await;
-
-        // Check if we got a result, if not we timed out
-        if let Ok(Some(event)) = result {
-            if let HotShotEvent::VidResponseRecv(sender_pub_key, proposal) = event.as_ref() {
-                broadcast_event(
-                    Arc::new(HotShotEvent::VidShareRecv(
-                        sender_pub_key.clone(),
-                        proposal.clone(),
-                    )),
-                    sender,
-                )
-                .await;
-                return true;
-            }
-        }
-        false
-    }
-
     /// Create event dependency and wait for `VidResponseRecv` after we send out the request
     /// Returns an optional with `VidResponseRecv` if received, otherwise None
     async fn handle_event_dependency(
@@ -0,0 +1,248 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Adaptive scheduling of requests to peers
+//!
+//! This module provides [`RequestScheduler`], which learns how quickly and how reliably each peer
+//! answers our requests. Requesters use it to pick the peers to ask first, to decide how long to
+//! wait for a response, and to decide when to hedge a slow request by asking another peer too.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    hash::Hash,
+    sync::Arc,
+    time::Duration,
+};
+
+use hotshot_types::consensus::ConsensusMetricsValue;
+use parking_lot::Mutex;
+use rand::{seq::SliceRandom, thread_rng};
+
+/// Timeout for requests until enough round trips have been observed to derive one
+pub const INITIAL_REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The shortest timeout requests are given
+pub const MIN_REQUEST_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The longest timeout requests are given
+pub const MAX_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of recent round trips the timeouts are derived from
+const ROUND_TRIP_SAMPLES: usize = 128;
+
+/// Number of round trips to observe before deriving timeouts from them
+const MIN_ROUND_TRIP_SAMPLES: usize = 16;
+
+/// How much a new observation moves the latency and success rate of a peer
+const EWMA_WEIGHT: f64 = 0.2;
+
+/// What we know about how a peer answers requests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeerStats {
+    /// Moving average of the round trip time of the requests the peer answered
+    pub latency: Option<Duration>,
+    /// Moving average of the fraction of requests the peer answered in time
+    pub success_rate: f64,
+}
+
+impl Default for PeerStats {
+    fn default() -> Self {
+        Self {
+            latency: None,
+            success_rate: 1.0,
+        }
+    }
+}
+
+/// What the scheduler has observed
+#[derive(Debug)]
+struct Observations<K> {
+    /// Statistics of each peer we've sent a request to
+    peers: HashMap<K, PeerStats>,
+    /// The most recent round trip times, of any peer
+    round_trips: VecDeque<Duration>,
+}
+
+/// Ranks peers by how quickly and reliably they answer requests, and derives request timeouts
+/// from the round trip times observed across all peers.
+///
+/// Peers we know nothing about yet rank like a peer with the median latency, so that they are
+/// tried, and peers which fail to answer sink to the back. Clones share the same observations.
+#[derive(Debug)]
+pub struct RequestScheduler<K> {
+    /// What has been observed so far
+    observations: Arc<Mutex<Observations<K>>>,
+    /// Metrics to export the statistics to
+    metrics: Arc<ConsensusMetricsValue>,
+}
+
+impl<K> Clone for RequestScheduler<K> {
+    fn clone(&self) -> Self {
+        Self {
+            observations: Arc::clone(&self.observations),
+            metrics: Arc::clone(&self.metrics),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash + Display> RequestScheduler<K> {
+    /// A scheduler which has not observed anything yet
+    #[must_use]
+    pub fn new(metrics: Arc<ConsensusMetricsValue>) -> Self {
+        metrics
+            .request_timeout
+            .set(duration_millis(INITIAL_REQUEST_TIMEOUT));
+        Self {
+            observations: Arc::new(Mutex::new(Observations {
+                peers: HashMap::new(),
+                round_trips: VecDeque::with_capacity(ROUND_TRIP_SAMPLES),
+            })),
+            metrics,
+        }
+    }
+
+    /// How long to wait for a response before giving up on a request: twice the 99th
+    /// percentile of the observed round trip times
+    #[must_use]
+    pub fn request_timeout(&self) -> Duration {
+        Self::timeout(&self.observations.lock().round_trips)
+    }
+
+    /// How long to wait for a response before asking another peer as well: the 90th percentile
+    /// of the observed round trip times
+    #[must_use]
+    pub fn hedge_delay(&self) -> Duration {
+        let observations = self.observations.lock();
+        match percentile(&observations.round_trips, 0.9) {
+            Some(delay) => delay.clamp(
+                MIN_REQUEST_TIMEOUT / 2,
+                Self::timeout(&observations.round_trips),
+            ),
+            None => INITIAL_REQUEST_TIMEOUT / 2,
+        }
+    }
+
+    /// The statistics of `peer`, if we've sent it a request
+    #[must_use]
+    pub fn peer_stats(&self, peer: &K) -> Option<PeerStats> {
+        self.observations.lock().peers.get(peer).copied()
+    }
+
+    /// Order `peers` from the one to ask first to the one to ask last. Peers which rank the same
+    /// are shuffled, so that all replicas don't overload the same peers.
+    #[must_use]
+    pub fn rank(&self, peers: impl IntoIterator<Item = K>) -> Vec<K> {
+        let mut peers: Vec<K> = peers.into_iter().collect();
+        peers.shuffle(&mut thread_rng());
+
+        let observations = self.observations.lock();
+        let median =
+            percentile(&observations.round_trips, 0.5).unwrap_or(INITIAL_REQUEST_TIMEOUT / 2);
+        let score = |peer: &K| {
+            let stats = observations.peers.get(peer).copied().unwrap_or_default();
+            // Expected responses per second of waiting
+            stats.success_rate
+                / stats
+                    .latency
+                    .unwrap_or(median)
+                    .max(Duration::from_millis(1))
+                    .as_secs_f64()
+        };
+        peers.sort_by(|a, b| score(b).total_cmp(&score(a)));
+        peers
+    }
+
+    /// Record that `peer` answered a request after `round_trip_time`
+    pub fn record_response(&self, peer: &K, round_trip_time: Duration) {
+        let mut observations = self.observations.lock();
+        if observations.round_trips.len() == ROUND_TRIP_SAMPLES {
+            observations.round_trips.pop_front();
+        }
+        observations.round_trips.push_back(round_trip_time);
+
+        let stats = observations.peers.entry(peer.clone()).or_default();
+        stats.latency = Some(match stats.latency {
+            Some(latency) => {
+                latency.mul_f64(1.0 - EWMA_WEIGHT) + round_trip_time.mul_f64(EWMA_WEIGHT)
+            }
+            None => round_trip_time,
+        });
+        stats.success_rate = stats.success_rate * (1.0 - EWMA_WEIGHT) + EWMA_WEIGHT;
+        let stats = *stats;
+
+        self.metrics
+            .request_round_trip_time
+            .add_point(round_trip_time.as_secs_f64());
+        self.metrics
+            .request_timeout
+            .set(duration_millis(Self::timeout(&observations.round_trips)));
+        drop(observations);
+        self.export(peer, stats);
+    }
+
+    /// Record that `peer` did not answer a request in time
+    pub fn record_failure(&self, peer: &K) {
+        let mut observations = self.observations.lock();
+        let stats = observations.peers.entry(peer.clone()).or_default();
+        stats.success_rate *= 1.0 - EWMA_WEIGHT;
+        let stats = *stats;
+        drop(observations);
+
+        self.metrics.failed_requests.add(1);
+        self.export(peer, stats);
+    }
+
+    /// Record that a request was hedged, by asking another peer before the first one answered
+    pub fn record_hedge(&self) {
+        self.metrics.hedged_requests.add(1);
+    }
+
+    /// The timeout derived from `round_trips`
+    fn timeout(round_trips: &VecDeque<Duration>) -> Duration {
+        match percentile(round_trips, 0.99) {
+            Some(round_trip) => (round_trip * 2).clamp(MIN_REQUEST_TIMEOUT, MAX_REQUEST_TIMEOUT),
+            None => INITIAL_REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Export the statistics of `peer` to the metrics
+    fn export(&self, peer: &K, stats: PeerStats) {
+        let labels = vec![peer.to_string()];
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        self.metrics
+            .peer_request_success_rate
+            .create(labels.clone())
+            .set((stats.success_rate * 100.0).round() as usize);
+        if let Some(latency) = stats.latency {
+            self.metrics
+                .peer_request_latency
+                .create(labels)
+                .set(duration_millis(latency));
+        }
+    }
+}
+
+/// The `quantile` of `samples`, or `None` if there are too few samples to tell
+fn percentile(samples: &VecDeque<Duration>, quantile: f64) -> Option<Duration> {
+    if samples.len() < MIN_ROUND_TRIP_SAMPLES {
+        return None;
+    }
+    let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let index = ((sorted.len() - 1) as f64 * quantile).round() as usize;
+    sorted.get(index).copied()
+}
+
+/// `duration` in whole milliseconds, for gauges
+fn duration_millis(duration: Duration) -> usize {
+    usize::try_from(duration.as_millis()).unwrap_or(usize::MAX)
+}
@@ -4,33 +4,146 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
 use committable::Committable;
 use hotshot_types::{
     consensus::{Consensus, LockedConsensusState, OuterConsensus},
-    data::VidDisperseShare,
+    constants::MAX_DATA_RESPONSE_SIZE,
+    data::{Leaf2, VidDisperseShare},
     message::Proposal,
+    simple_certificate::QuorumCertificate2,
     traits::{
-        election::Membership, network::DataRequest, node_implementation::NodeType,
+        block_contents::BlockHeader,
+        election::Membership,
+        network::{DataRequest, RequestKind, ResponseData},
+        node_implementation::{NodeImplementation, NodeType},
         signature_key::SignatureKey,
+        storage::Storage,
+        BlockPayload,
     },
+    vid::VidCodeRate,
+    vote::HasViewNumber,
 };
+use lru::LruCache;
 use sha2::{Digest, Sha256};
 use tokio::{spawn, task::JoinHandle, time::sleep};
 use tracing::instrument;
 
-use crate::{events::HotShotEvent, helpers::broadcast_event};
-/// Time to wait for txns before sending `ResponseMessage::NotFound`
-const TXNS_TIMEOUT: Duration = Duration::from_millis(100);
+use crate::{
+    events::HotShotEvent, helpers::broadcast_event, request_scheduler::MIN_REQUEST_TIMEOUT,
+};
+/// How often to check whether the txns for a requested VID share have arrived
+const TXNS_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How many data requests per second we serve to each node without stake
+pub const UNSTAKED_REQUESTS_PER_SECOND: f64 = 1.0;
+/// How many data requests we serve to a node without stake at once, after it has been idle
+pub const UNSTAKED_REQUEST_BURST: u32 = 10;
+/// How many data requests per second we serve to all nodes without stake together, so that they
+/// can't get around their own limit by making up new keys
+pub const UNSTAKED_TOTAL_REQUESTS_PER_SECOND: f64 = 20.0;
+/// How many data requests we serve to all nodes without stake together at once
+pub const UNSTAKED_TOTAL_REQUEST_BURST: u32 = 100;
+/// How many nodes without stake we keep track of. The least recently seen are forgotten, and
+/// start over with a full burst.
+const TRACKED_UNSTAKED_REQUESTERS: NonZeroUsize = match NonZeroUsize::new(1024) {
+    Some(size) => size,
+    None => unreachable!(),
+};
+
+/// A token bucket, refilled continuously at a fixed rate
+#[derive(Debug)]
+struct TokenBucket {
+    /// The number of tokens added per second
+    rate: f64,
+    /// The maximum number of tokens
+    capacity: f64,
+    /// The current number of tokens
+    tokens: f64,
+    /// When we last refilled the bucket
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    fn new(rate: f64, capacity: u32, now: Instant) -> Self {
+        Self {
+            rate,
+            capacity: f64::from(capacity),
+            tokens: f64::from(capacity),
+            last_refill: now,
+        }
+    }
+
+    /// Take a token from the bucket, if there is one
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Limits the data requests we serve to nodes without stake, both each and together. Staked
+/// nodes are not limited.
+#[derive(Debug)]
+struct UnstakedRequestLimiter<K: Hash + Eq> {
+    /// The requests left to each node we have heard from recently
+    requesters: LruCache<K, TokenBucket>,
+    /// The requests left to all of them together
+    total: TokenBucket,
+}
+
+impl<K: Hash + Eq + Clone> UnstakedRequestLimiter<K> {
+    /// Create a limiter which lets every node make a full burst of requests
+    fn new(now: Instant) -> Self {
+        Self {
+            requesters: LruCache::new(TRACKED_UNSTAKED_REQUESTERS),
+            total: TokenBucket::new(
+                UNSTAKED_TOTAL_REQUESTS_PER_SECOND,
+                UNSTAKED_TOTAL_REQUEST_BURST,
+                now,
+            ),
+        }
+    }
+
+    /// Whether to serve a request from `requester`
+    fn allow(&mut self, requester: &K, now: Instant) -> bool {
+        // The requester's own limit goes first, so a node over its limit can't use up the budget
+        // shared with everyone else
+        self.requesters
+            .get_or_insert_mut(requester.clone(), || {
+                TokenBucket::new(UNSTAKED_REQUESTS_PER_SECOND, UNSTAKED_REQUEST_BURST, now)
+            })
+            .try_take(now)
+            && self.total.try_take(now)
+    }
+}
 
 /// Task state for the Network Request Task. The task is responsible for handling
 /// requests sent to this node by the network.  It will validate the sender,
 /// parse the request, and try to find the data request in the consensus stores.
-pub struct NetworkResponseState<TYPES: NodeType> {
+pub struct NetworkResponseState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Locked consensus state
     consensus: LockedConsensusState<TYPES>,
+    /// Storage, to serve decided data which is no longer in memory
+    storage: Arc<RwLock<I::Storage>>,
     /// Quorum membership for checking if requesters have state
     quorum: Arc<TYPES::Membership>,
     /// This replicas public key
@@ -39,29 +152,38 @@ pub struct NetworkResponseState<TYPES: NodeType> {
     private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
     /// The node's id
     id: u64,
+    /// Erasure code rate of VID, to calculate the shares we are asked for
+    vid_code_rate: VidCodeRate,
+    /// Limits the data requests we serve to nodes without stake
+    unstaked_limiter: UnstakedRequestLimiter<TYPES::SignatureKey>,
 }
 
-impl<TYPES: NodeType> NetworkResponseState<TYPES> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkResponseState<TYPES, I> {
     /// Create the network request state with the info it needs
     pub fn new(
         consensus: LockedConsensusState<TYPES>,
+        storage: Arc<RwLock<I::Storage>>,
         quorum: Arc<TYPES::Membership>,
         pub_key: TYPES::SignatureKey,
         private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
         id: u64,
+        vid_code_rate: VidCodeRate,
     ) -> Self {
         Self {
             consensus,
+            storage,
             quorum,
             pub_key,
             private_key,
             id,
+            vid_code_rate,
+            unstaked_limiter: UnstakedRequestLimiter::new(Instant::now()),
         }
     }
 
     /// Process request events or loop until a `HotShotEvent::Shutdown` is received.
     async fn run_response_loop(
-        self,
+        mut self,
         mut receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
         event_sender: Sender<Arc<HotShotEvent<TYPES>>>,
     ) {
@@ -117,6 +239,42 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
                                 .await;
                             }
                         }
+                        HotShotEvent::DataRequestRecv(request, sender) => {
+                            // Decided data is public, so nodes without stake, such as those
+                            // catching up to join, are served too, up to a limit
+                            if !valid_signature::<TYPES>(request, sender)
+                                || !self.may_request_data(sender).await
+                            {
+                                continue;
+                            }
+                            let Some(data) = self.decided_data(&request.request).await else {
+                                continue;
+                            };
+                            if bincode::serialized_size(&data)
+                                .map_or(true, |size| size > MAX_DATA_RESPONSE_SIZE)
+                            {
+                                tracing::warn!(
+                                    "Not sending data for view {:?}, it is too large",
+                                    data.view_number()
+                                );
+                                continue;
+                            }
+                            let Some(signature) = data.sign(&self.private_key) else {
+                                tracing::error!("Failed to sign data response");
+                                continue;
+                            };
+                            broadcast_event(
+                                HotShotEvent::DataResponseSend(
+                                    self.pub_key.clone(),
+                                    sender.clone(),
+                                    data,
+                                    signature,
+                                )
+                                .into(),
+                                &event_sender,
+                            )
+                            .await;
+                        }
                         HotShotEvent::Shutdown => {
                             return;
                         }
@@ -149,26 +307,25 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
         let cur_epoch = consensus_reader.cur_epoch();
         drop(consensus_reader);
 
-        if Consensus::calculate_and_update_vid(
+        // Wait for the transactions no longer than the shortest timeout requesters use; by then
+        // they will have asked another peer as well
+        let deadline = Instant::now() + MIN_REQUEST_TIMEOUT;
+        while Consensus::calculate_and_update_vid(
             OuterConsensus::new(Arc::clone(&self.consensus)),
             view,
             Arc::clone(&self.quorum),
             &self.private_key,
             cur_epoch,
+            self.vid_code_rate,
         )
         .await
         .is_none()
         {
+            if Instant::now() >= deadline {
+                return None;
+            }
             // Sleep in hope we receive txns in the meantime
-            sleep(TXNS_TIMEOUT).await;
-            Consensus::calculate_and_update_vid(
-                OuterConsensus::new(Arc::clone(&self.consensus)),
-                view,
-                Arc::clone(&self.quorum),
-                &self.private_key,
-                cur_epoch,
-            )
-            .await?;
+            sleep(TXNS_POLL_INTERVAL).await;
         }
         return self
             .consensus
@@ -180,10 +337,118 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
             .cloned();
     }
 
+    /// Get the data `request` asks for, from memory if we still have it and from storage
+    /// otherwise
+    async fn decided_data(&self, request: &RequestKind<TYPES>) -> Option<ResponseData<TYPES>> {
+        let data = match *request {
+            RequestKind::Leaf { height } => {
+                let leaf = match self.decided_leaf(height).await {
+                    Some(leaf) => Ok(Some(leaf)),
+                    None => self.storage.read().await.load_leaf_at_height(height).await,
+                };
+                leaf.map(|leaf| leaf.map(ResponseData::Leaf))
+            }
+            RequestKind::Payload { view } => {
+                let payload = match self.payload(view).await {
+                    Some(payload) => Ok(Some(payload)),
+                    None => self.storage.read().await.load_payload(view).await,
+                };
+                payload
+                    .map(|payload| payload.map(|payload| ResponseData::Payload { view, payload }))
+            }
+            RequestKind::QuorumCertificate { view } => {
+                let qc = match self.quorum_certificate(view).await {
+                    Some(qc) => Ok(Some(qc)),
+                    None => {
+                        self.storage
+                            .read()
+                            .await
+                            .load_quorum_certificate(view)
+                            .await
+                    }
+                };
+                qc.map(|qc| qc.map(ResponseData::QuorumCertificate))
+            }
+            RequestKind::Vid(..) | RequestKind::DaProposal(_) | RequestKind::Proposal(_) => {
+                Ok(None)
+            }
+        };
+
+        data.unwrap_or_else(|e| {
+            tracing::warn!("Failed to load {request:?} from storage: {e:?}");
+            None
+        })
+    }
+
+    /// Get the decided leaf at `height` from consensus storage, with its payload if we have it.
+    /// Only the ancestors of the last decided leaf which are still in memory can be found.
+    async fn decided_leaf(&self, height: u64) -> Option<Leaf2<TYPES>> {
+        let consensus_reader = self.consensus.read().await;
+        let mut leaf = consensus_reader.decided_leaf();
+        while leaf.height() > height {
+            leaf = consensus_reader
+                .saved_leaves()
+                .get(&leaf.parent_commitment())?
+                .clone();
+        }
+        if leaf.height() != height {
+            return None;
+        }
+
+        if let Some(encoded_txns) = consensus_reader.saved_payloads().get(&leaf.view_number()) {
+            let payload = BlockPayload::from_bytes(encoded_txns, leaf.block_header().metadata());
+            leaf.fill_block_payload_unchecked(payload);
+        }
+        Some(leaf)
+    }
+
+    /// Get the payload of the leaf proposed in `view` from consensus storage
+    async fn payload(&self, view: TYPES::View) -> Option<TYPES::BlockPayload> {
+        let consensus_reader = self.consensus.read().await;
+        let encoded_txns = consensus_reader.saved_payloads().get(&view)?;
+        let commitment = consensus_reader
+            .validated_state_map()
+            .get(&view)?
+            .leaf_commitment()?;
+        let leaf = consensus_reader.saved_leaves().get(&commitment)?;
+        Some(BlockPayload::from_bytes(
+            encoded_txns,
+            leaf.block_header().metadata(),
+        ))
+    }
+
+    /// Get the quorum certificate formed in `view` from consensus storage
+    async fn quorum_certificate(&self, view: TYPES::View) -> Option<QuorumCertificate2<TYPES>> {
+        let consensus_reader = self.consensus.read().await;
+        let high_qc = consensus_reader.high_qc();
+        if high_qc.view_number() == view {
+            return Some(high_qc.clone());
+        }
+        consensus_reader
+            .saved_leaves()
+            .values()
+            .map(Leaf2::justify_qc)
+            .find(|qc| qc.view_number() == view)
+    }
+
     /// Makes sure the sender is allowed to send a request in the given epoch.
     fn valid_sender(&self, sender: &TYPES::SignatureKey, epoch: TYPES::Epoch) -> bool {
         self.quorum.has_stake(sender, epoch)
     }
+
+    /// Whether to serve a request for decided data from `sender`: always if it has stake, and
+    /// within the limits for nodes without stake otherwise
+    async fn may_request_data(&mut self, sender: &TYPES::SignatureKey) -> bool {
+        let epoch = self.consensus.read().await.cur_epoch();
+        if self.valid_sender(sender, epoch) {
+            return true;
+        }
+        if self.unstaked_limiter.allow(sender, Instant::now()) {
+            return true;
+        }
+        tracing::debug!("Not serving {sender:?}, it has no stake and is over its request limit");
+        false
+    }
 }
 
 /// Check the signature
@@ -200,8 +465,8 @@ fn valid_signature<TYPES: NodeType>(
 /// Spawn the network response task to handle incoming request for data
 /// from other nodes.  It will shutdown when it gets `HotshotEvent::Shutdown`
 /// on the `event_stream` arg.
-pub fn run_response_task<TYPES: NodeType>(
-    task_state: NetworkResponseState<TYPES>,
+pub fn run_response_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
+    task_state: NetworkResponseState<TYPES, I>,
     event_stream: Receiver<Arc<HotShotEvent<TYPES>>>,
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
 ) -> JoinHandle<()> {
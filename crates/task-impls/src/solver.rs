@@ -0,0 +1,155 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Client for the Solver, which runs the auctions of the marketplace
+//!
+//! This module provides [`SolverClient`], an [`AuctionResultsProvider`] which fetches the auction
+//! results from the Solver API over HTTP. Results are cached per view, so that asking for the
+//! result of a view again doesn't hit the Solver, and can be prefetched for the views the node is
+//! going to lead.
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use async_lock::OnceCell;
+use async_trait::async_trait;
+use hotshot_types::traits::{
+    auction_results_provider::AuctionResultsProvider,
+    node_implementation::{ConsensusTime, NodeType},
+    signature_key::SignatureKey,
+};
+use parking_lot::Mutex;
+use surf_disco::{error::ClientError, Client, Url};
+use tagged_base64::TaggedBase64;
+use tokio::{spawn, time::timeout};
+use vbs::version::StaticVersion;
+
+/// Version of the Solver API
+pub type SolverVersion = StaticVersion<0, 1>;
+
+/// Module the Solver serves the auction results under
+const SOLVER_MODULE: &str = "api";
+
+/// Number of views the auction results are cached for
+pub const CACHED_VIEWS: usize = 32;
+
+/// How long we wait for the Solver by default
+pub const DEFAULT_SOLVER_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The auction result of each view, once fetched
+type AuctionResultCache<TYPES> =
+    BTreeMap<<TYPES as NodeType>::View, Arc<OnceCell<<TYPES as NodeType>::AuctionResult>>>;
+
+/// Fetches the auction results from the Solver, caching them for the last [`CACHED_VIEWS`] views.
+///
+/// Clones share the same cache.
+#[derive(Clone)]
+pub struct SolverClient<TYPES: NodeType> {
+    /// Client for the Solver API
+    client: Arc<Client<ClientError, SolverVersion>>,
+    /// Key to sign the requests with, if the permissioned API is to be used
+    private_key: Option<<TYPES::SignatureKey as SignatureKey>::PrivateKey>,
+    /// How long to wait for the Solver
+    timeout: Duration,
+    /// The auction results we have fetched or are fetching, by view
+    cache: Arc<Mutex<AuctionResultCache<TYPES>>>,
+}
+
+impl<TYPES: NodeType> std::fmt::Debug for SolverClient<TYPES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SolverClient")
+            .field("permissioned", &self.private_key.is_some())
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<TYPES: NodeType> SolverClient<TYPES> {
+    /// A client for the Solver at `url`, using the non-permissioned API
+    #[must_use]
+    pub fn new(url: Url) -> Self {
+        Self {
+            client: Arc::new(Client::new(url)),
+            private_key: None,
+            timeout: DEFAULT_SOLVER_TIMEOUT,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Use the permissioned API, signing the requests with `private_key`
+    #[must_use]
+    pub fn with_private_key(
+        mut self,
+        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    ) -> Self {
+        self.private_key = Some(private_key);
+        self
+    }
+
+    /// Give up on the Solver after `timeout`
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The cache entry for `view_number`, dropping the entries of the oldest views if the cache
+    /// is full
+    fn cache_entry(&self, view_number: TYPES::View) -> Arc<OnceCell<TYPES::AuctionResult>> {
+        let mut cache = self.cache.lock();
+        let entry = Arc::clone(cache.entry(view_number).or_default());
+        while cache.len() > CACHED_VIEWS {
+            cache.pop_first();
+        }
+        entry
+    }
+
+    /// Request the auction result for `view_number` from the Solver
+    async fn request(&self, view_number: TYPES::View) -> Result<TYPES::AuctionResult> {
+        let path = match &self.private_key {
+            Some(private_key) => {
+                let signature: TaggedBase64 =
+                    TYPES::SignatureKey::sign(private_key, &view_number.u64().to_le_bytes())
+                        .map_err(|e| anyhow!("Failed to sign the auction result request: {e}"))?
+                        .into();
+                format!(
+                    "{SOLVER_MODULE}/auction_results/{}/{signature}",
+                    *view_number
+                )
+            }
+            None => format!("{SOLVER_MODULE}/auction_results/{}", *view_number),
+        };
+
+        timeout(self.timeout, self.client.get(&path).send())
+            .await
+            .map_err(|_| anyhow!("Solver didn't respond in {:?}", self.timeout))?
+            .map_err(|e| anyhow!("Failed to get the auction result for view {view_number:?}: {e}"))
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> AuctionResultsProvider<TYPES> for SolverClient<TYPES> {
+    /// Fetch the auction result from the Solver, unless it is already cached or being fetched.
+    /// Failures are not cached, so the next call for the same view asks the Solver again.
+    async fn fetch_auction_result(&self, view_number: TYPES::View) -> Result<TYPES::AuctionResult> {
+        let entry = self.cache_entry(view_number);
+        entry
+            .get_or_try_init(|| self.request(view_number))
+            .await
+            .cloned()
+    }
+
+    fn prefetch_auction_result(&self, view_number: TYPES::View) {
+        let client = self.clone();
+        spawn(async move {
+            if let Err(e) = client.fetch_auction_result(view_number).await {
+                tracing::debug!(
+                    "Failed to prefetch the auction result for view {view_number:?}: {e:#}"
+                );
+            }
+        });
+    }
+}
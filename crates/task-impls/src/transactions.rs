@@ -5,6 +5,7 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
+    collections::HashMap,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -21,16 +22,20 @@ use hotshot_types::{
     message::UpgradeLock,
     traits::{
         auction_results_provider::AuctionResultsProvider,
-        block_contents::{precompute_vid_commitment, BuilderFee, EncodeBytes},
+        block_contents::{precompute_vid_commitment, BlockHeader, BuilderFee, EncodeBytes},
         election::Membership,
         node_implementation::{ConsensusTime, HasUrls, NodeImplementation, NodeType, Versions},
         signature_key::{BuilderSignatureKey, SignatureKey},
         BlockPayload,
     },
     utils::ViewInner,
-    vid::{VidCommitment, VidPrecomputeData},
+    vid::{VidCodeRate, VidCommitment, VidPrecomputeData},
+    vote::HasViewNumber,
+};
+use tokio::{
+    spawn,
+    time::{sleep, timeout, timeout_at},
 };
-use tokio::time::{sleep, timeout};
 use tracing::instrument;
 use url::Url;
 use utils::anytrace::*;
@@ -40,9 +45,13 @@ use vec1::Vec1;
 use crate::{
     builder::{
         v0_1::BuilderClient as BuilderClientBase, v0_3::BuilderClient as BuilderClientMarketplace,
+        BuilderClientError,
     },
+    builder_reputation::BuilderReputation,
     events::{HotShotEvent, HotShotTaskCompleted},
     helpers::broadcast_event,
+    local_builder::LocalBuilder,
+    mempool::Mempool,
 };
 
 // Parameters for builder querying algorithm
@@ -60,6 +69,10 @@ const BUILDER_ADDITIONAL_TIME_MULTIPLIER: f32 = 0.2;
 const BUILDER_MINIMUM_QUERY_TIME: Duration = Duration::from_millis(300);
 /// Delay between re-tries on unsuccessful calls
 const RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Share of the builder timeout the solver has to deliver the auction result, as a divisor
+const AUCTION_RESULT_TIMEOUT_DIVISOR: u32 = 2;
+/// Number of upcoming views we prefetch the auction results for, if we lead them
+const AUCTION_RESULT_PREFETCH_VIEWS: u64 = 3;
 
 /// Builder Provided Responses
 pub struct BuilderResponse<TYPES: NodeType> {
@@ -119,6 +132,34 @@ pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V
 
     /// fallback builder url
     pub fallback_builder_url: Url,
+
+    /// Pool of the transactions waiting to be included in a block, if we keep one
+    pub mempool: Option<Mempool<TYPES>>,
+
+    /// Scores of the builders, shared across views
+    pub builder_reputation: BuilderReputation,
+
+    /// Builder for blocks of the transactions in our mempool, used when no builder delivers one
+    pub local_builder: Option<LocalBuilder<TYPES>>,
+
+    /// Erasure code rate of VID
+    pub vid_code_rate: VidCodeRate,
+}
+
+/// Record the outcome of a query for available blocks to the builder at `url`, which took
+/// `latency`
+fn record_query<T>(
+    reputation: &BuilderReputation,
+    url: &Url,
+    latency: Duration,
+    result: &std::result::Result<T, BuilderClientError>,
+) {
+    match result {
+        Ok(_) => reputation.record_response(url, latency),
+        // Not having a block for this parent yet is not the builder's fault
+        Err(BuilderClientError::BlockNotFound) => {}
+        Err(_) => reputation.record_no_response(url),
+    }
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTaskState<TYPES, I, V> {
@@ -172,7 +213,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             {
                 None
             } else {
-                self.wait_for_block(block_view).await
+                match self.wait_for_block(block_view).await {
+                    Some(block) => Some(block),
+                    None => self.build_block_locally(block_view, version).await,
+                }
             }
         };
 
@@ -213,6 +257,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             let membership_total_nodes = self.membership.total_nodes(self.cur_epoch);
             let Some(null_fee) = null_block::builder_fee::<TYPES, V>(
                 self.membership.total_nodes(self.cur_epoch),
+                self.vid_code_rate,
                 version,
                 *block_view,
             ) else {
@@ -223,7 +268,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             // Create an empty block payload and metadata
             let (_, metadata) = <TYPES as NodeType>::BlockPayload::empty();
 
-            let (_, precompute_data) = precompute_vid_commitment(&[], membership_total_nodes);
+            let (_, precompute_data) =
+                precompute_vid_commitment(&[], membership_total_nodes, self.vid_code_rate);
 
             // Broadcast the empty block
             broadcast_event(
@@ -247,7 +293,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
     ///
     /// # Errors
     ///
-    /// Returns an error if the solver cannot be contacted, or if none of the builders respond.
+    /// Returns an error if none of the builders respond. If the solver cannot be contacted in time,
+    /// only the fallback builder is asked.
     async fn produce_block_marketplace(
         &mut self,
         block_view: TYPES::View,
@@ -272,18 +319,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
 
         let start = Instant::now();
 
-        let maybe_auction_result = timeout(
-            self.builder_timeout,
+        // A slow solver mustn't cost us the block: past a share of the time we have, we go on
+        // with the fallback builder alone
+        let auction_result = match timeout(
+            self.builder_timeout / AUCTION_RESULT_TIMEOUT_DIVISOR,
             self.auction_results_provider
                 .fetch_auction_result(block_view),
         )
         .await
-        .wrap()
-        .context(warn!("Timeout while getting auction result"))?;
-
-        let auction_result = maybe_auction_result
-            .map_err(|e| tracing::warn!("Failed to get auction results: {e:#}"))
-            .unwrap_or_default(); // We continue here, as we still have fallback builder URL
+        {
+            Ok(Ok(auction_result)) => auction_result,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to get auction results: {e:#}");
+                TYPES::AuctionResult::default()
+            }
+            Err(_) => {
+                tracing::warn!("Timeout while getting auction result, using the fallback builder");
+                TYPES::AuctionResult::default()
+            }
+        };
 
         let mut futures = Vec::new();
 
@@ -349,6 +403,66 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         ))
     }
 
+    /// Build a block for `block_view` out of the pending transactions in our mempool, if we have
+    /// a local builder, a mempool, and any pending transactions
+    async fn build_block_locally(
+        &self,
+        block_view: TYPES::View,
+        version: Version,
+    ) -> Option<BuilderResponse<TYPES>> {
+        let local_builder = self.local_builder.as_ref()?;
+        let Some(mempool) = &self.mempool else {
+            tracing::warn!("Cannot build a block locally without a mempool");
+            return None;
+        };
+        let transactions = local_builder.select_transactions(mempool.pending_transactions());
+        if transactions.is_empty() {
+            return None;
+        }
+
+        // The block has to be valid on top of the leaf it will be proposed on, which is the one
+        // the high QC is for, not the last decided one
+        let validated_state = {
+            let consensus = self.consensus.read().await;
+            let parent_view = consensus.high_qc().view_number();
+            let Some(state) = consensus.state(parent_view) else {
+                tracing::warn!(
+                    "Cannot build a block locally without the state of the parent leaf in view \
+                     {parent_view:?}"
+                );
+                return None;
+            };
+            Arc::clone(state)
+        };
+        match local_builder
+            .build_block::<V>(
+                transactions,
+                &validated_state,
+                &self.instance_state,
+                self.membership.total_nodes(self.cur_epoch),
+                self.vid_code_rate,
+                version,
+                block_view,
+            )
+            .await
+        {
+            Ok(block) => {
+                tracing::info!("No builder delivered a block for view {block_view:?}, proposing one built locally");
+                self.consensus
+                    .write()
+                    .await
+                    .metrics
+                    .number_of_locally_built_blocks_proposed
+                    .add(1);
+                Some(block)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to build a block locally: {e}");
+                None
+            }
+        }
+    }
+
     /// Produce a null block
     pub fn null_block(
         &self,
@@ -358,6 +472,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         let membership_total_nodes = self.membership.total_nodes(self.cur_epoch);
         let Some(null_fee) = null_block::builder_fee::<TYPES, V>(
             self.membership.total_nodes(self.cur_epoch),
+            self.vid_code_rate,
             version,
             *block_view,
         ) else {
@@ -368,7 +483,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         // Create an empty block payload and metadata
         let (_, metadata) = <TYPES as NodeType>::BlockPayload::empty();
 
-        let (_, precompute_data) = precompute_vid_commitment(&[], membership_total_nodes);
+        let (_, precompute_data) =
+            precompute_vid_commitment(&[], membership_total_nodes, self.vid_code_rate);
 
         Some(PackedBundle::new(
             vec![].into(),
@@ -409,17 +525,34 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     e
                 );
 
-                let null_block = self.null_block(block_view, version)?;
+                if let Some(BuilderResponse {
+                    block_payload,
+                    metadata,
+                    fee,
+                    precompute_data,
+                }) = self.build_block_locally(block_view, version).await
+                {
+                    PackedBundle::new(
+                        block_payload.encode(),
+                        metadata,
+                        block_view,
+                        vec1::vec1![fee],
+                        precompute_data,
+                        Some(TYPES::AuctionResult::default()),
+                    )
+                } else {
+                    let null_block = self.null_block(block_view, version)?;
 
-                // Increment the metric for number of empty blocks proposed
-                self.consensus
-                    .write()
-                    .await
-                    .metrics
-                    .number_of_empty_blocks_proposed
-                    .add(1);
+                    // Increment the metric for number of empty blocks proposed
+                    self.consensus
+                        .write()
+                        .await
+                        .metrics
+                        .number_of_empty_blocks_proposed
+                        .add(1);
 
-                null_block
+                    null_block
+                }
             }
         };
 
@@ -456,18 +589,69 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     ) -> Result<()> {
         match event.as_ref() {
-            HotShotEvent::TransactionsRecv(transactions) => {
+            HotShotEvent::TransactionsRecv(transactions, source) => {
+                let transactions = match &self.mempool {
+                    // Only pass on the transactions the mempool accepts, so that its limits also
+                    // protect the builders
+                    Some(mempool) => transactions
+                        .iter()
+                        .filter(|transaction| {
+                            mempool
+                                .insert((*transaction).clone(), source.clone())
+                                .inspect_err(|e| {
+                                    tracing::debug!("Dropping transaction from {source:?}: {e}");
+                                })
+                                .is_ok()
+                        })
+                        .cloned()
+                        .collect(),
+                    None => transactions.clone(),
+                };
+                if transactions.is_empty() {
+                    return Ok(());
+                }
                 broadcast_event(
                     Event {
                         view_number: self.cur_view,
-                        event: EventType::Transactions {
-                            transactions: transactions.clone(),
-                        },
+                        event: EventType::Transactions { transactions },
                     },
                     &self.output_event_stream,
                 )
                 .await;
             }
+            HotShotEvent::DaProposalValidated(proposal, _)
+            | HotShotEvent::DaProposalSend(proposal, _) => {
+                if let Some(mempool) = &self.mempool {
+                    let proposal = &proposal.data;
+                    let payload = TYPES::BlockPayload::from_bytes(
+                        &proposal.encoded_transactions,
+                        &proposal.metadata,
+                    );
+                    mempool.record_da_proposal(
+                        proposal.view_number,
+                        payload.transaction_commitments(&proposal.metadata),
+                    );
+                }
+            }
+            HotShotEvent::LeavesDecided(leaves) => {
+                if let Some(mempool) = &self.mempool {
+                    for leaf in leaves.iter().rev() {
+                        // Without the payload we can't tell which transactions were decided, and
+                        // recording none would make those proposed in this view pending again
+                        let Some(payload) = leaf.block_payload() else {
+                            tracing::debug!(
+                                "Not recording the transactions of decided leaf {}, its payload \
+                                 is missing",
+                                leaf.height()
+                            );
+                            continue;
+                        };
+                        let commitments =
+                            payload.transaction_commitments(leaf.block_header().metadata());
+                        mempool.record_decided(leaf.view_number(), leaf.height(), commitments);
+                    }
+                }
+            }
             HotShotEvent::ViewChange(view, epoch) => {
                 if *epoch > self.cur_epoch {
                     self.cur_epoch = *epoch;
@@ -480,6 +664,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     )
                 );
                 self.cur_view = view;
+                self.prefetch_auction_results(view).await;
                 if self.membership.leader(view, self.cur_epoch)? == self.public_key {
                     self.handle_view_change(&event_stream, view).await;
                     return Ok(());
@@ -490,6 +675,27 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         Ok(())
     }
 
+    /// Have the auction results for the next views we lead in the marketplace fetched ahead of
+    /// time, so that we don't have to wait for the solver once we get to them
+    async fn prefetch_auction_results(&self, view: TYPES::View) {
+        for offset in 1..=AUCTION_RESULT_PREFETCH_VIEWS {
+            let upcoming_view = TYPES::View::new(*view + offset);
+            if self
+                .membership
+                .leader(upcoming_view, self.cur_epoch)
+                .is_ok_and(|leader| leader == self.public_key)
+                && self
+                    .upgrade_lock
+                    .version(upcoming_view)
+                    .await
+                    .is_ok_and(|version| version >= V::Marketplace::VERSION)
+            {
+                self.auction_results_provider
+                    .prefetch_auction_result(upcoming_view);
+            }
+        }
+    }
+
     /// Get VID commitment for the last successful view before `block_view`.
     /// Returns None if we don't have said commitment recorded.
     #[instrument(skip_all, target = "TransactionTaskState", fields(id = self.id, cur_view = *self.cur_view, block_view = *block_view))]
@@ -611,40 +817,50 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         None
     }
 
-    /// Query the builders for available blocks. Queries only fraction of the builders
+    /// Query the builders which are not excluded for available blocks, which the builders
+    /// supporting it push to us as soon as they have them. Queries only fraction of the builders
     /// based on the response time.
+    ///
+    /// The builders we stop waiting for are still awaited in the background, so their reputation
+    /// only suffers if they fail or miss the builder timeout, not for being slower than the rest.
     async fn get_available_blocks(
         &self,
         parent_comm: VidCommitment,
         view_number: TYPES::View,
         parent_comm_sig: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     ) -> Vec<(AvailableBlockInfo<TYPES>, usize)> {
-        let tasks = self
+        let urls: Vec<&Url> = self
             .builder_clients
             .iter()
-            .enumerate()
-            .map(|(builder_idx, client)| async move {
-                client
-                    .available_blocks(
-                        parent_comm,
-                        view_number.u64(),
-                        self.public_key.clone(),
-                        parent_comm_sig,
-                    )
-                    .await
-                    .map(move |blocks| {
-                        blocks
-                            .into_iter()
-                            .map(move |block_info| (block_info, builder_idx))
-                    })
+            .map(BuilderClientBase::url)
+            .collect();
+        let eligible = self.builder_reputation.eligible(&urls);
+        let mut tasks = eligible
+            .iter()
+            .map(|&builder_idx| {
+                let client = self.builder_clients[builder_idx].clone();
+                let public_key = self.public_key.clone();
+                let signature = parent_comm_sig.clone();
+                async move {
+                    let start = Instant::now();
+                    let result = client
+                        .next_available_blocks(
+                            parent_comm,
+                            view_number.u64(),
+                            public_key,
+                            &signature,
+                        )
+                        .await;
+                    (builder_idx, start.elapsed(), result)
+                }
             })
             .collect::<FuturesUnordered<_>>();
-        let mut results = Vec::with_capacity(self.builder_clients.len());
+        let mut results = Vec::with_capacity(eligible.len());
         let query_start = Instant::now();
-        let threshold = (self.builder_clients.len() * BUILDER_MAIN_BATCH_THRESHOLD_DIVIDEND)
+        let threshold = (eligible.len() * BUILDER_MAIN_BATCH_THRESHOLD_DIVIDEND)
             .div_ceil(BUILDER_MAIN_BATCH_THRESHOLD_DIVISOR);
-        let mut tasks = tasks.take(threshold);
-        while let Some(result) = tasks.next().await {
+        let mut main_batch = tasks.by_ref().take(threshold);
+        while let Some(result) = main_batch.next().await {
             results.push(result);
             if query_start.elapsed() > BUILDER_MAIN_BATCH_CUTOFF {
                 break;
@@ -657,14 +873,50 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             BUILDER_MINIMUM_QUERY_TIME.saturating_sub(query_start.elapsed()),
         ));
         futures::pin_mut!(timeout);
-        let mut tasks = tasks.into_inner().take_until(timeout);
-        while let Some(result) = tasks.next().await {
+        let mut second_batch = tasks.by_ref().take_until(timeout);
+        while let Some(result) = second_batch.next().await {
             results.push(result);
         }
+
+        for (builder_idx, latency, result) in &results {
+            record_query(
+                &self.builder_reputation,
+                urls[*builder_idx],
+                *latency,
+                result,
+            );
+        }
+        if !tasks.is_empty() {
+            let mut pending: HashMap<usize, Url> = eligible
+                .iter()
+                .filter(|&&builder_idx| !results.iter().any(|(idx, _, _)| *idx == builder_idx))
+                .map(|&builder_idx| (builder_idx, urls[builder_idx].clone()))
+                .collect();
+            let reputation = self.builder_reputation.clone();
+            let deadline = tokio::time::Instant::from_std(query_start + self.builder_timeout);
+            spawn(async move {
+                while let Ok(Some((builder_idx, latency, result))) =
+                    timeout_at(deadline, tasks.next()).await
+                {
+                    if let Some(url) = pending.remove(&builder_idx) {
+                        record_query(&reputation, &url, latency, &result);
+                    }
+                }
+                // Only the builders which missed the builder timeout count as not responding
+                for url in pending.into_values() {
+                    reputation.record_no_response(&url);
+                }
+            });
+        }
+
         results
             .into_iter()
-            .filter_map(|result| match result {
-                Ok(value) => Some(value),
+            .filter_map(|(builder_idx, _, result)| match result {
+                Ok(blocks) => Some(
+                    blocks
+                        .into_iter()
+                        .map(move |block_info| (block_info, builder_idx)),
+                ),
                 Err(err) => {
                     tracing::warn!(%err,"Error getting available blocks");
                     None
@@ -676,7 +928,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
 
     /// Get a block from builder.
     /// Queries the sufficiently fast builders for available blocks and chooses the one with the
-    /// best fee/byte ratio, re-trying with the next best one in case of failure.
+    /// best fee/byte ratio weighted by the reputation of its builder, re-trying with the next
+    /// best one in case of failure.
     ///
     /// # Errors
     /// If none of the builder reports any available blocks or claiming block fails for all of the
@@ -692,17 +945,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             .get_available_blocks(parent_comm, view_number, parent_comm_sig)
             .await;
 
-        available_blocks.sort_by(|(l, _), (r, _)| {
-            // We want the block with the highest fee per byte of data we're going to have to
-            // process, thus our comparison function is:
-            //      (l.offered_fee / l.block_size) < (r.offered_fee / r.block_size)
-            // To avoid floating point math (which doesn't even have an `Ord` impl) we multiply
-            // through by the denominators to get
-            //      l.offered_fee * r.block_size < r.offered_fee * l.block_size
-            // We cast up to u128 to avoid overflow.
-            (u128::from(l.offered_fee) * u128::from(r.block_size))
-                .cmp(&(u128::from(r.offered_fee) * u128::from(l.block_size)))
-        });
+        // We want the block with the highest fee per byte of data we're going to have to
+        // process, discounted by how likely its builder is to actually deliver it.
+        #[allow(clippy::cast_precision_loss)]
+        let expected_fee_per_byte =
+            |(block_info, builder_idx): &(AvailableBlockInfo<TYPES>, usize)| {
+                block_info.offered_fee as f64 / block_info.block_size.max(1) as f64
+                    * self
+                        .builder_reputation
+                        .score(self.builder_clients[*builder_idx].url())
+            };
+        available_blocks
+            .sort_by(|l, r| expected_fee_per_byte(r).total_cmp(&expected_fee_per_byte(l)));
 
         if available_blocks.is_empty() {
             bail!("No available blocks");
@@ -716,6 +970,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         };
 
         for (block_info, builder_idx) in available_blocks {
+            let url = self.builder_clients[builder_idx].url();
+
             // Verify signature over chosen block.
             if !block_info.sender.validate_block_info_signature(
                 &block_info.signature,
@@ -724,8 +980,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 &block_info.block_hash,
             ) {
                 tracing::warn!("Failed to verify available block info response message signature");
+                self.builder_reputation.record_signature(url, false);
                 continue;
             }
+            self.builder_reputation.record_signature(url, true);
 
             let request_signature = match <<TYPES as NodeType>::SignatureKey as SignatureKey>::sign(
                 &self.private_key,
@@ -759,6 +1017,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     Ok(block_data) => block_data,
                     Err(err) => {
                         tracing::warn!(%err, "Error claiming block data");
+                        self.builder_reputation.record_claim(url, false);
                         continue;
                     }
                 };
@@ -767,6 +1026,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     Ok(block_data) => block_data,
                     Err(err) => {
                         tracing::warn!(%err, "Error claiming header input");
+                        self.builder_reputation.record_claim(url, false);
                         continue;
                     }
                 };
@@ -776,6 +1036,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     tracing::warn!(
                         "Failed to verify available block data response message signature"
                     );
+                    self.builder_reputation.record_signature(url, false);
                     continue;
                 }
 
@@ -784,8 +1045,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     tracing::warn!(
                     "Failed to verify available block header input data response message signature"
                 );
+                    self.builder_reputation.record_signature(url, false);
+                    continue;
+                }
+
+                // the fee per byte the block was chosen for only holds if it is no larger than
+                // advertised
+                if block_data.block_payload.encode().len() as u64 > block_info.block_size {
+                    tracing::warn!("Block is larger than its builder advertised");
+                    self.builder_reputation.record_fee(url, false);
                     continue;
                 }
+                self.builder_reputation.record_claim(url, true);
+                self.builder_reputation.record_fee(url, true);
 
                 let fee = BuilderFee {
                     fee_amount: block_info.offered_fee,
@@ -18,6 +18,7 @@ use hotshot_types::{
         signature_key::SignatureKey,
         BlockPayload,
     },
+    vid::VidCodeRate,
 };
 use tracing::{debug, error, info, instrument};
 use utils::anytrace::Result;
@@ -52,6 +53,9 @@ pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
 
     /// This state's ID
     pub id: u64,
+
+    /// Erasure code rate of VID
+    pub vid_code_rate: VidCodeRate,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
@@ -82,6 +86,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                     *view_number,
                     self.cur_epoch,
                     vid_precompute.clone(),
+                    self.vid_code_rate,
                 )
                 .await;
                 let payload_commitment = vid_disperse.payload_commitment;
@@ -0,0 +1,168 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Benchmarks for message compression in `UpgradeLock::serialize`.
+//!
+//! Besides timing the round trip of each message kind, this prints how many bytes compression
+//! saves for each, using the transactions the examples send.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::{MarketplaceUpgradeTestVersions, MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    compression::{CompressionAlgorithm, CompressionConfig},
+    constants::ORCHESTRATOR_DEFAULT_TRANSACTION_SIZE,
+    message::{
+        convert_proposal, DaConsensusMessage, GeneralConsensusMessage, Message, MessageKind,
+        SequencingMessage, UpgradeLock,
+    },
+    traits::states::TestableState,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::runtime::Runtime;
+
+/// The number of transactions in the benchmarked block
+const NUM_TRANSACTIONS: usize = 1_000;
+
+/// Build one message of each kind we care about, for a view with a full block
+async fn messages() -> Vec<(&'static str, Message<TestTypes>)> {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let transactions = (0..NUM_TRANSACTIONS)
+        .map(|_| {
+            <TestValidatedState as TestableState<TestTypes>>::create_random_transaction(
+                None,
+                &mut rng,
+                ORCHESTRATOR_DEFAULT_TRANSACTION_SIZE as u64,
+            )
+        })
+        .collect();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    generator.next().await;
+    generator.add_transactions(transactions);
+    let view = generator.next().await.unwrap();
+
+    let sender = handle.public_key();
+    let message = |kind| Message {
+        sender: sender.clone(),
+        kind: MessageKind::Consensus(kind),
+    };
+
+    vec![
+        (
+            "da_proposal",
+            message(SequencingMessage::Da(DaConsensusMessage::DaProposal(
+                view.da_proposal.clone(),
+            ))),
+        ),
+        (
+            "vid_share",
+            message(SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(
+                view.vid_proposal.0[0].clone(),
+            ))),
+        ),
+        (
+            "quorum_proposal",
+            message(SequencingMessage::General(
+                GeneralConsensusMessage::Proposal(convert_proposal(view.quorum_proposal.clone())),
+            )),
+        ),
+        (
+            "quorum_vote",
+            message(SequencingMessage::General(GeneralConsensusMessage::Vote(
+                view.create_quorum_vote(&handle).await.to_vote(),
+            ))),
+        ),
+        (
+            "da_certificate",
+            message(SequencingMessage::Da(DaConsensusMessage::DaCertificate(
+                view.da_certificate.clone(),
+            ))),
+        ),
+    ]
+}
+
+/// An upgrade lock whose base version compresses messages with `algorithm`
+fn compressing_lock(
+    algorithm: CompressionAlgorithm,
+) -> UpgradeLock<TestTypes, MarketplaceUpgradeTestVersions> {
+    UpgradeLock::new().with_compression(CompressionConfig {
+        algorithm,
+        ..CompressionConfig::default()
+    })
+}
+
+fn compression(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let messages = runtime.block_on(messages());
+    let uncompressed_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let algorithms = [CompressionAlgorithm::Zstd, CompressionAlgorithm::Lz4];
+
+    // Report the bytes saved for each message kind
+    println!(
+        "{:<16} {:>12} {:>12} {:>8} {:>12} {:>8}",
+        "message", "raw", "zstd", "saved", "lz4", "saved"
+    );
+    for (name, message) in &messages {
+        let raw = runtime
+            .block_on(uncompressed_lock.serialize(message))
+            .unwrap()
+            .len();
+        let mut row = format!("{name:<16} {raw:>12}");
+        for algorithm in algorithms {
+            let compressed = runtime
+                .block_on(compressing_lock(algorithm).serialize(message))
+                .unwrap()
+                .len();
+            #[allow(clippy::cast_precision_loss)]
+            let saved = 100.0 * (1.0 - compressed as f64 / raw as f64);
+            row.push_str(&format!(" {compressed:>12} {saved:>7.1}%"));
+        }
+        println!("{row}");
+    }
+
+    // Time the round trip through `serialize` and `deserialize`
+    for (name, message) in &messages {
+        let mut group = c.benchmark_group(format!("compression/{name}"));
+        let raw = runtime
+            .block_on(uncompressed_lock.serialize(message))
+            .unwrap();
+        group.throughput(Throughput::Bytes(raw.len() as u64));
+
+        group.bench_function(BenchmarkId::from_parameter("none"), |b| {
+            b.to_async(&runtime).iter(|| async {
+                let bytes = uncompressed_lock.serialize(message).await.unwrap();
+                let _: Message<TestTypes> = uncompressed_lock.deserialize(&bytes).await.unwrap();
+            });
+        });
+        for algorithm in algorithms {
+            let lock = compressing_lock(algorithm);
+            group.bench_function(
+                BenchmarkId::from_parameter(format!("{algorithm:?}").to_lowercase()),
+                |b| {
+                    b.to_async(&runtime).iter(|| async {
+                        let bytes = lock.serialize(message).await.unwrap();
+                        let _: Message<TestTypes> = lock.deserialize(&bytes).await.unwrap();
+                    });
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, compression);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Benchmarks for the DRB calculation.
+//!
+//! This measures the rate of iterated hashing, which `DRB_HASHES_PER_SECOND` is derived from, and
+//! prints how long the default difficulty takes on this machine.
+
+use std::time::Instant;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hotshot_types::drb::{
+    compute_drb_result, verify_drb_result, DrbConfig, DEFAULT_DRB_DIFFICULTY, DRB_CALCULATION_TIME,
+    DRB_HASHES_PER_SECOND,
+};
+
+/// The difficulty we time the calculation with
+const DIFFICULTY: u64 = 1_000_000;
+
+fn drb(c: &mut Criterion) {
+    // Report the hash rate of this machine, and what it means for the default difficulty
+    let start = Instant::now();
+    let _ = compute_drb_result(
+        [0u8; 32],
+        &DrbConfig {
+            difficulty: DIFFICULTY,
+            checkpoint_interval: 0,
+        },
+    );
+    let hashes_per_second = DIFFICULTY as f64 / start.elapsed().as_secs_f64();
+    println!(
+        "{hashes_per_second:.0} hashes per second, the default difficulty of \
+         {DEFAULT_DRB_DIFFICULTY} takes {:.0}s (assumed {DRB_HASHES_PER_SECOND} hashes per \
+         second, {}s)",
+        DEFAULT_DRB_DIFFICULTY as f64 / hashes_per_second,
+        DRB_CALCULATION_TIME.as_secs()
+    );
+
+    let mut group = c.benchmark_group("drb");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(DIFFICULTY));
+    for checkpoint_interval in [0, DIFFICULTY / 64] {
+        let config = DrbConfig {
+            difficulty: DIFFICULTY,
+            checkpoint_interval,
+        };
+        group.bench_function(BenchmarkId::new("compute", checkpoint_interval), |b| {
+            b.iter(|| compute_drb_result([0u8; 32], &config));
+        });
+
+        let output = compute_drb_result([0u8; 32], &config);
+        group.bench_function(BenchmarkId::new("verify", checkpoint_interval), |b| {
+            b.iter(|| assert!(verify_drb_result([0u8; 32], &output, &config)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, drb);
+criterion_main!(benches);
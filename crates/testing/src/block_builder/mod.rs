@@ -26,6 +26,7 @@ use hotshot_types::{
         node_implementation::NodeType,
         signature_key::BuilderSignatureKey,
     },
+    vid::VidCodeRate,
 };
 use tide_disco::{method::ReadState, App, Url};
 use tokio::spawn;
@@ -48,6 +49,7 @@ where
 
     async fn start(
         num_storage_nodes: usize,
+        vid_code_rate: VidCodeRate,
         url: Url,
         options: Self::Config,
         changes: HashMap<u64, BuilderChange>,
@@ -170,6 +172,7 @@ pub fn run_builder_source_0_1<TYPES, Source>(
 async fn build_block<TYPES: NodeType>(
     transactions: Vec<TYPES::Transaction>,
     num_storage_nodes: Arc<RwLock<usize>>,
+    vid_code_rate: VidCodeRate,
     pub_key: TYPES::BuilderSignatureKey,
     priv_key: <TYPES::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
 ) -> BlockEntry<TYPES>
@@ -186,8 +189,11 @@ where
 
     let commitment = block_payload.builder_commitment(&metadata);
 
-    let (vid_commitment, precompute_data) =
-        precompute_vid_commitment(&block_payload.encode(), *num_storage_nodes.read_arc().await);
+    let (vid_commitment, precompute_data) = precompute_vid_commitment(
+        &block_payload.encode(),
+        *num_storage_nodes.read_arc().await,
+        vid_code_rate,
+    );
 
     // Get block size from the encoded payload
     let block_size = block_payload.encode().len() as u64;
@@ -30,7 +30,7 @@ use hotshot_types::{
     network::RandomBuilderConfig,
     traits::{node_implementation::NodeType, signature_key::BuilderSignatureKey},
     utils::BuilderCommitment,
-    vid::VidCommitment,
+    vid::{VidCodeRate, VidCommitment},
 };
 use lru::LruCache;
 use rand::{rngs::SmallRng, Rng, RngCore, SeedableRng};
@@ -47,6 +47,7 @@ pub struct RandomBuilderImplementation;
 impl RandomBuilderImplementation {
     pub async fn create<TYPES: NodeType<Transaction = TestTransaction>>(
         num_nodes: usize,
+        vid_code_rate: VidCodeRate,
         config: RandomBuilderConfig,
         changes: HashMap<u64, BuilderChange>,
         change_sender: Sender<BuilderChange>,
@@ -68,6 +69,7 @@ impl RandomBuilderImplementation {
             blocks,
             config,
             num_nodes: num_nodes.clone(),
+            vid_code_rate,
             changes,
             change_sender,
             pub_key,
@@ -87,13 +89,15 @@ where
 
     async fn start(
         num_nodes: usize,
+        vid_code_rate: VidCodeRate,
         url: Url,
         config: RandomBuilderConfig,
         changes: HashMap<u64, BuilderChange>,
     ) -> Box<dyn BuilderTask<TYPES>> {
         let (change_sender, change_receiver) = broadcast(128);
 
-        let (task, source) = Self::create(num_nodes, config, changes, change_sender).await;
+        let (task, source) =
+            Self::create(num_nodes, vid_code_rate, config, changes, change_sender).await;
         run_builder_source_0_1(url, change_receiver, source);
         Box::new(task)
     }
@@ -101,6 +105,7 @@ where
 
 pub struct RandomBuilderTask<TYPES: NodeType<Transaction = TestTransaction>> {
     num_nodes: Arc<RwLock<usize>>,
+    vid_code_rate: VidCodeRate,
     config: RandomBuilderConfig,
     changes: HashMap<u64, BuilderChange>,
     change_sender: Sender<BuilderChange>,
@@ -113,6 +118,7 @@ impl<TYPES: NodeType<Transaction = TestTransaction>> RandomBuilderTask<TYPES> {
     async fn build_blocks(
         options: RandomBuilderConfig,
         num_nodes: Arc<RwLock<usize>>,
+        vid_code_rate: VidCodeRate,
         pub_key: <TYPES as NodeType>::BuilderSignatureKey,
         priv_key: <<TYPES as NodeType>::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
         blocks: Arc<RwLock<LruCache<BuilderCommitment, BlockEntry<TYPES>>>>,
@@ -139,6 +145,7 @@ impl<TYPES: NodeType<Transaction = TestTransaction>> RandomBuilderTask<TYPES> {
             let block = build_block(
                 transactions,
                 num_nodes.clone(),
+                vid_code_rate,
                 pub_key.clone(),
                 priv_key.clone(),
             )
@@ -174,6 +181,7 @@ where
         let mut task = Some(spawn(Self::build_blocks(
             self.config.clone(),
             self.num_nodes.clone(),
+            self.vid_code_rate,
             self.pub_key.clone(),
             self.priv_key.clone(),
             self.blocks.clone(),
@@ -194,6 +202,7 @@ where
                                             task = Some(spawn(Self::build_blocks(
                                                 self.config.clone(),
                                                 self.num_nodes.clone(),
+                                                self.vid_code_rate,
                                                 self.pub_key.clone(),
                                                 self.priv_key.clone(),
                                                 self.blocks.clone(),
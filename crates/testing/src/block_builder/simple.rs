@@ -18,7 +18,7 @@ use async_broadcast::{broadcast, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use committable::{Commitment, Committable};
-use futures::{future::BoxFuture, Stream, StreamExt};
+use futures::{future::BoxFuture, FutureExt, Stream, StreamExt};
 use hotshot::{
     traits::BlockPayload,
     types::{Event, EventType, SignatureKey},
@@ -40,11 +40,11 @@ use hotshot_types::{
         signature_key::BuilderSignatureKey,
     },
     utils::BuilderCommitment,
-    vid::VidCommitment,
+    vid::{VidCodeRate, VidCommitment},
 };
 use lru::LruCache;
 use tide_disco::{method::ReadState, App, Url};
-use tokio::spawn;
+use tokio::{spawn, sync::watch};
 use vbs::version::StaticVersionType;
 
 use super::{build_block, run_builder_source, BlockEntry, BuilderTask, TestBuilderImplementation};
@@ -55,6 +55,7 @@ pub struct SimpleBuilderImplementation;
 impl SimpleBuilderImplementation {
     pub async fn create<TYPES: NodeType>(
         num_nodes: usize,
+        vid_code_rate: VidCodeRate,
         changes: HashMap<u64, BuilderChange>,
         change_sender: Sender<BuilderChange>,
     ) -> (SimpleBuilderSource<TYPES>, SimpleBuilderTask<TYPES>) {
@@ -64,6 +65,7 @@ impl SimpleBuilderImplementation {
         let transactions = Arc::new(RwLock::new(HashMap::new()));
         let blocks = Arc::new(RwLock::new(HashMap::new()));
         let should_fail_claims = Arc::new(AtomicBool::new(false));
+        let new_transactions = Arc::new(watch::channel(()).0);
 
         let source = SimpleBuilderSource {
             pub_key,
@@ -71,7 +73,9 @@ impl SimpleBuilderImplementation {
             transactions: transactions.clone(),
             blocks: blocks.clone(),
             num_nodes: Arc::new(RwLock::new(num_nodes)),
+            vid_code_rate,
             should_fail_claims: Arc::clone(&should_fail_claims),
+            new_transactions: Arc::clone(&new_transactions),
         };
 
         let task = SimpleBuilderTask {
@@ -79,6 +83,7 @@ impl SimpleBuilderImplementation {
             blocks,
             decided_transactions: LruCache::new(NonZeroUsize::new(u16::MAX.into()).expect("> 0")),
             should_fail_claims,
+            new_transactions,
             change_sender,
             changes,
         };
@@ -96,12 +101,13 @@ where
 
     async fn start(
         num_nodes: usize,
+        vid_code_rate: VidCodeRate,
         url: Url,
         _config: Self::Config,
         changes: HashMap<u64, BuilderChange>,
     ) -> Box<dyn BuilderTask<TYPES>> {
         let (change_sender, change_receiver) = broadcast(128);
-        let (source, task) = Self::create(num_nodes, changes, change_sender).await;
+        let (source, task) = Self::create(num_nodes, vid_code_rate, changes, change_sender).await;
         run_builder_source(url, change_receiver, source);
 
         Box::new(task)
@@ -113,10 +119,13 @@ pub struct SimpleBuilderSource<TYPES: NodeType> {
     pub_key: TYPES::BuilderSignatureKey,
     priv_key: <TYPES::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
     num_nodes: Arc<RwLock<usize>>,
+    vid_code_rate: VidCodeRate,
     #[allow(clippy::type_complexity)]
     transactions: Arc<RwLock<HashMap<Commitment<TYPES::Transaction>, SubmittedTransaction<TYPES>>>>,
     blocks: Arc<RwLock<HashMap<BuilderCommitment, BlockEntry<TYPES>>>>,
     should_fail_claims: Arc<AtomicBool>,
+    /// Notified when transactions are submitted, which the available blocks are built from
+    new_transactions: Arc<watch::Sender<()>>,
 }
 
 #[async_trait]
@@ -249,6 +258,7 @@ where
         let block_entry = build_block(
             transactions,
             self.num_nodes.clone(),
+            self.vid_code_rate,
             self.pub_key.clone(),
             self.priv_key.clone(),
         )
@@ -264,6 +274,15 @@ where
         Ok(vec![metadata])
     }
 
+    fn available_blocks_changed(&self, _for_parent: &VidCommitment) -> BoxFuture<'static, ()> {
+        // The blocks are built out of the submitted transactions, so look again once there are more
+        let mut new_transactions = self.new_transactions.subscribe();
+        async move {
+            let _ = new_transactions.changed().await;
+        }
+        .boxed()
+    }
+
     async fn claim_block(
         &self,
         block_hash: &BuilderCommitment,
@@ -371,6 +390,7 @@ pub struct SimpleBuilderTask<TYPES: NodeType> {
     blocks: Arc<RwLock<HashMap<BuilderCommitment, BlockEntry<TYPES>>>>,
     decided_transactions: LruCache<Commitment<TYPES::Transaction>, ()>,
     should_fail_claims: Arc<AtomicBool>,
+    new_transactions: Arc<watch::Sender<()>>,
     changes: HashMap<u64, BuilderChange>,
     change_sender: Sender<BuilderChange>,
 }
@@ -447,6 +467,7 @@ impl<TYPES: NodeType> BuilderTask<TYPES> for SimpleBuilderTask<TYPES> {
                                     );
                                 }
                             }
+                            self.new_transactions.send_replace(());
                         }
                         _ => {}
                     },
@@ -18,6 +18,7 @@ use hotshot::{
 use hotshot_example_types::{
     auction_results_provider_types::TestAuctionResultsProvider,
     block_types::TestTransaction,
+    faulty_storage::FaultyStorage,
     node_types::TestTypes,
     state_types::{TestInstanceState, TestValidatedState},
     storage_types::TestStorage,
@@ -37,7 +38,7 @@ use hotshot_types::{
         node_implementation::{NodeType, Versions},
     },
     utils::{View, ViewInner},
-    vid::{vid_scheme, VidCommitment, VidProposal, VidSchemeType},
+    vid::{vid_scheme, VidCodeRate, VidCommitment, VidProposal, VidSchemeType},
     vote::{Certificate, HasViewNumber, Vote},
     ValidatorConfig,
 };
@@ -53,7 +54,7 @@ pub async fn build_system_handle<
     TYPES: NodeType<InstanceState = TestInstanceState>,
     I: NodeImplementation<
             TYPES,
-            Storage = TestStorage<TYPES>,
+            Storage = FaultyStorage<TestStorage<TYPES>>,
             AuctionResultsProvider = TestAuctionResultsProvider<TYPES>,
         > + TestableNodeImplementation<TYPES>,
     V: Versions,
@@ -77,7 +78,7 @@ pub async fn build_system_handle_from_launcher<
     TYPES: NodeType<InstanceState = TestInstanceState>,
     I: NodeImplementation<
             TYPES,
-            Storage = TestStorage<TYPES>,
+            Storage = FaultyStorage<TestStorage<TYPES>>,
             AuctionResultsProvider = TestAuctionResultsProvider<TYPES>,
         > + TestableNodeImplementation<TYPES>,
     V: Versions,
@@ -94,11 +95,10 @@ pub async fn build_system_handle_from_launcher<
     let marketplace_config = (launcher.resource_generator.marketplace_config)(node_id);
     let config = launcher.resource_generator.config.clone();
 
-    let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(TestInstanceState::new(
-        launcher.metadata.async_delay_config.clone(),
-    ))
-    .await
-    .unwrap();
+    let initializer =
+        HotShotInitializer::<TYPES>::from_genesis::<V>(launcher.metadata.instance_state())
+            .await
+            .unwrap();
 
     // See whether or not we should be DA
     let is_da = node_id < config.da_staked_committee_size as u64;
@@ -223,7 +223,7 @@ pub async fn build_assembled_sig<
     let real_qc_pp: <TYPES::SignatureKey as SignatureKey>::QcParams =
         <TYPES::SignatureKey as SignatureKey>::public_parameter(
             stake_table.clone(),
-            U256::from(CERT::threshold(membership)),
+            CERT::threshold(membership, epoch),
         );
     let total_nodes = stake_table.len();
     let signers = bitvec![1; total_nodes];
@@ -268,7 +268,7 @@ pub fn key_pair_for_id<TYPES: NodeType>(
     (private_key, public_key)
 }
 
-/// initialize VID
+/// initialize VID, with the default code rate the test networks use
 /// # Panics
 /// if unable to create a [`VidSchemeType`]
 #[must_use]
@@ -280,7 +280,7 @@ pub fn vid_scheme_from_view_number<TYPES: NodeType>(
     let num_storage_nodes = membership
         .committee_members(view_number, epoch_number)
         .len();
-    vid_scheme(num_storage_nodes)
+    vid_scheme(num_storage_nodes, VidCodeRate::default()).unwrap()
 }
 
 pub fn vid_payload_commitment<TYPES: NodeType>(
@@ -307,6 +307,7 @@ pub fn da_payload_commitment<TYPES: NodeType>(
     vid_commitment(
         &encoded_transactions,
         quorum_membership.total_nodes(epoch_number),
+        VidCodeRate::default(),
     )
 }
 
@@ -379,6 +380,7 @@ pub async fn build_da_certificate<TYPES: NodeType, V: Versions>(
     let da_payload_commitment = vid_commitment(
         &encoded_transactions,
         quorum_membership.total_nodes(epoch_number),
+        VidCodeRate::default(),
     );
 
     let da_data = DaData {
@@ -12,6 +12,7 @@ use hotshot_task_impls::events::{HotShotEvent, HotShotEvent::*};
 use hotshot_types::{
     data::null_block,
     traits::{block_contents::BlockHeader, node_implementation::NodeType},
+    vid::VidCodeRate,
 };
 
 use crate::predicates::{Predicate, PredicateResult};
@@ -213,7 +214,7 @@ where
         Arc::new(move |e: Arc<HotShotEvent<TYPES>>| match e.as_ref() {
             QuorumProposalSend(proposal, _) => {
                 Some(proposal.data.block_header.payload_commitment())
-                    == null_block::commitment(num_storage_nodes)
+                    == null_block::commitment(num_storage_nodes, VidCodeRate::default())
             }
             _ => false,
         });
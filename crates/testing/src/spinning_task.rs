@@ -15,14 +15,16 @@ use async_lock::RwLock;
 use async_trait::async_trait;
 use futures::future::join_all;
 use hotshot::{
-    traits::TestableNodeImplementation, types::EventType, HotShotInitializer, SystemContext,
+    traits::{RecoverableStorage, TestableNodeImplementation},
+    types::EventType,
+    HotShotInitializer, SystemContext,
 };
 use hotshot_example_types::{
     auction_results_provider_types::TestAuctionResultsProvider,
     block_types::TestBlockHeader,
+    faulty_storage::FaultyStorage,
     state_types::{TestInstanceState, TestValidatedState},
     storage_types::TestStorage,
-    testable_delay::DelayConfig,
 };
 use hotshot_types::{
     constants::EVENT_CHANNEL_SIZE,
@@ -65,8 +67,8 @@ pub struct SpinningTask<
     pub(crate) last_decided_leaf: Leaf2<TYPES>,
     /// Highest qc seen in the test for restarting nodes
     pub(crate) high_qc: QuorumCertificate2<TYPES>,
-    /// Add specified delay to async calls
-    pub(crate) async_delay_config: DelayConfig,
+    /// The instance state to start nodes with, which adds the specified delay to async calls
+    pub(crate) instance_state: TestInstanceState,
     /// Context stored for nodes to be restarted with
     pub(crate) restart_contexts: HashMap<usize, RestartContext<TYPES, N, I, V>>,
     /// Generate network channel for restart nodes
@@ -89,7 +91,7 @@ where
     I: NodeImplementation<
         TYPES,
         Network = N,
-        Storage = TestStorage<TYPES>,
+        Storage = FaultyStorage<TestStorage<TYPES>>,
         AuctionResultsProvider = TestAuctionResultsProvider<TYPES>,
     >,
 {
@@ -153,7 +155,7 @@ where
 
                                         let initializer = HotShotInitializer::<TYPES>::from_reload(
                                             self.last_decided_leaf.clone(),
-                                            TestInstanceState::new(self.async_delay_config.clone()),
+                                            self.instance_state.clone(),
                                             None,
                                             TYPES::View::genesis(),
                                             TYPES::Epoch::genesis(),
@@ -163,6 +165,8 @@ where
                                             None,
                                             Vec::new(),
                                             BTreeMap::new(),
+                                            BTreeMap::new(),
+                                            BTreeMap::new(),
                                         );
                                         // We assign node's public key and stake value rather than read from config file since it's a test
                                         let validator_config =
@@ -212,7 +216,8 @@ where
                                 node.handle.shut_down().await;
                             }
                         }
-                        NodeAction::RestartDown(delay_views) => {
+                        NodeAction::RestartDown(delay_views)
+                        | NodeAction::RestartDownFromStorage(delay_views) => {
                             let node_id = idx.try_into().unwrap();
                             if let Some(node) = self.handles.write().await.get_mut(idx) {
                                 tracing::error!("Node {} shutting down", idx);
@@ -233,27 +238,54 @@ where
                                 let config = node.handle.hotshot.config.clone();
                                 let marketplace_config =
                                     node.handle.hotshot.marketplace_config.clone();
-                                let read_storage = storage.read().await;
-                                let initializer = HotShotInitializer::<TYPES>::from_reload(
-                                    self.last_decided_leaf.clone(),
-                                    TestInstanceState::new(self.async_delay_config.clone()),
-                                    None,
-                                    read_storage.last_actioned_view().await,
-                                    read_storage.last_actioned_epoch().await,
-                                    read_storage.last_actioned_view().await,
-                                    read_storage.proposals_cloned().await,
-                                    read_storage.high_qc_cloned().await.unwrap_or(
-                                        QuorumCertificate::genesis::<V>(
-                                            &TestValidatedState::default(),
-                                            &TestInstanceState::default(),
+                                let mut node_storage = storage.read().await.clone();
+                                let from_storage =
+                                    matches!(updown, NodeAction::RestartDownFromStorage(_));
+                                if from_storage && node_storage.is_on_disk() {
+                                    // Forget everything held in memory, so the node recovers
+                                    // only from what it wrote to disk
+                                    *node_storage = node_storage
+                                        .reopen()
+                                        .expect("Failed to reopen storage on disk");
+                                }
+                                let initializer = if from_storage {
+                                    node_storage
+                                        .load_initializer::<V>(
+                                            self.instance_state.clone(),
+                                            config.epoch_height,
                                         )
                                         .await
-                                        .to_qc2(),
-                                    ),
-                                    read_storage.decided_upgrade_certificate().await,
-                                    Vec::new(),
-                                    BTreeMap::new(),
-                                );
+                                        .expect("Failed to load initializer from storage")
+                                } else {
+                                    HotShotInitializer::<TYPES>::from_reload(
+                                        self.last_decided_leaf.clone(),
+                                        self.instance_state.clone(),
+                                        None,
+                                        node_storage.last_actioned_view().await,
+                                        node_storage.last_actioned_epoch().await,
+                                        node_storage.last_actioned_view().await,
+                                        node_storage.proposals_cloned().await,
+                                        node_storage.high_qc_cloned().await.unwrap_or(
+                                            QuorumCertificate::genesis::<V>(
+                                                &TestValidatedState::default(),
+                                                &TestInstanceState::default(),
+                                            )
+                                            .await
+                                            .to_qc2(),
+                                        ),
+                                        node_storage.decided_upgrade_certificate().await,
+                                        Vec::new(),
+                                        BTreeMap::new(),
+                                        node_storage
+                                            .load_drb_results()
+                                            .await
+                                            .expect("Failed to load DRB results from storage"),
+                                        node_storage
+                                            .load_stake_tables()
+                                            .await
+                                            .expect("Failed to load stake tables from storage"),
+                                    )
+                                };
                                 // We assign node's public key and stake value rather than read from config file since it's a test
                                 let validator_config = ValidatorConfig::generated_from_seed_indexed(
                                     [0u8; 32],
@@ -271,7 +303,7 @@ where
                                         initializer,
                                         config,
                                         validator_config,
-                                        (*read_storage).clone(),
+                                        node_storage,
                                         marketplace_config.clone(),
                                         internal_chan,
                                         (
@@ -390,6 +422,9 @@ pub enum NodeAction {
     NetworkDown,
     /// Take a node down to be restarted after a number of views
     RestartDown(u64),
+    /// Take a node down to be restarted after a number of views, rebuilding it purely from what
+    /// it persisted to its storage
+    RestartDownFromStorage(u64),
     /// Start a node up again after it's been shutdown for restart.  This
     /// should only be created following a `ResartDown`
     RestartUp,
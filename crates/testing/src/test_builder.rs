@@ -14,12 +14,21 @@ use hotshot::{
     HotShotInitializer, MarketplaceConfig, Memberships, SystemContext, TwinsHandlerState,
 };
 use hotshot_example_types::{
-    auction_results_provider_types::TestAuctionResultsProvider, state_types::TestInstanceState,
-    storage_types::TestStorage, testable_delay::DelayConfig,
+    auction_results_provider_types::TestAuctionResultsProvider,
+    faulty_storage::{FaultyStorage, StorageFault},
+    state_types::{StakeTableUpdates, TestInstanceState},
+    storage_types::TestStorage,
+    testable_delay::DelayConfig,
 };
+use hotshot_fakeapi::fake_solver::FakeSolverFaultType;
 use hotshot_types::{
     consensus::ConsensusMetricsValue,
-    traits::node_implementation::{NodeType, Versions},
+    drb::DrbConfig,
+    traits::{
+        node_implementation::{NodeType, Versions},
+        storage::RetentionPolicy,
+    },
+    vid::VidCodeRate,
     HotShotConfig, ValidatorConfig,
 };
 use tide_disco::Url;
@@ -92,6 +101,11 @@ pub struct TestDescription<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Ver
     pub behaviour: Rc<dyn Fn(u64) -> Behaviour<TYPES, I, V>>,
     /// Delay config if any to add delays to asynchronous calls
     pub async_delay_config: DelayConfig,
+    /// Faults to inject into the storage of each node, keyed by node id
+    pub storage_faults: HashMap<u64, Vec<StorageFault>>,
+    /// Whether nodes persist their storage to disk, so restarting them from storage rebuilds
+    /// their state from the files they wrote
+    pub storage_on_disk: bool,
     /// view in which to propose an upgrade
     pub upgrade_view: Option<u64>,
     /// whether to initialize the solver on startup
@@ -100,6 +114,15 @@ pub struct TestDescription<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Ver
     pub validate_transactions: TransactionValidator,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// How the DRB is computed. Defaults to a low difficulty, so tests don't spend their time
+    /// hashing.
+    pub drb: DrbConfig,
+    /// Erasure code rate of VID, which the builders use as well
+    pub vid_code_rate: VidCodeRate,
+    /// What the storage of the nodes keeps around once views are decided
+    pub retention_policy: RetentionPolicy,
+    /// The stake tables set by the blocks at given heights, as node indices and stakes
+    pub stake_table_updates: StakeTableUpdates,
 }
 
 pub fn nonempty_block_threshold(threshold: (u64, u64)) -> TransactionValidator {
@@ -180,11 +203,9 @@ pub async fn create_test_handle<
     storage: I::Storage,
     marketplace_config: MarketplaceConfig<TYPES, I>,
 ) -> SystemContextHandle<TYPES, I, V> {
-    let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(TestInstanceState::new(
-        metadata.async_delay_config,
-    ))
-    .await
-    .unwrap();
+    let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(metadata.instance_state())
+        .await
+        .unwrap();
 
     // See whether or not we should be DA
     let is_da = node_id < config.da_staked_committee_size as u64;
@@ -277,6 +298,8 @@ pub struct BuilderDescription {
 pub struct FakeSolverApiDescription {
     /// The rate at which errors occur in the mock solver API
     pub error_pct: f32,
+    /// A fault to inject into every response of the mock solver API
+    pub fault: Option<FakeSolverFaultType>,
 }
 
 impl Default for TimingData {
@@ -292,6 +315,13 @@ impl Default for TimingData {
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TestDescription<TYPES, I, V> {
+    /// The instance state the nodes of the test run with
+    #[must_use]
+    pub fn instance_state(&self) -> TestInstanceState {
+        TestInstanceState::new(self.async_delay_config.clone())
+            .with_stake_table_updates(self.stake_table_updates.clone())
+    }
+
     /// the default metadata for a stress test
     #[must_use]
     #[allow(clippy::redundant_field_names)]
@@ -411,13 +441,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Default
             solver: FakeSolverApiDescription {
                 // Default to a 10% error rate.
                 error_pct: 0.1,
+                fault: None,
             },
             behaviour: Rc::new(|_| Behaviour::Standard),
             async_delay_config: DelayConfig::default(),
+            storage_faults: HashMap::new(),
+            storage_on_disk: false,
             upgrade_view: None,
             start_solver: true,
             validate_transactions: Arc::new(|_| Ok(())),
             epoch_height: 0,
+            drb: DrbConfig {
+                difficulty: 1_000,
+                checkpoint_interval: 100,
+            },
+            vid_code_rate: VidCodeRate::default(),
+            retention_policy: RetentionPolicy::default(),
+            stake_table_updates: StakeTableUpdates::new(),
         }
     }
 }
@@ -456,6 +496,9 @@ where
             da_staked_committee_size,
             unreliable_network,
             epoch_height,
+            drb,
+            vid_code_rate,
+            retention_policy,
             ..
         } = self.clone();
 
@@ -513,6 +556,10 @@ where
             start_voting_time: u64::MAX,
             stop_voting_time: 0,
             epoch_height,
+            drb,
+            mempool: None,
+            vid_code_rate,
+            retention_policy,
         };
         let TimingData {
             next_view_timeout,
@@ -540,17 +587,30 @@ where
                     unreliable_network,
                     secondary_network_delay,
                 ),
-                storage: Box::new(move |_| {
-                    let mut storage = TestStorage::<TYPES>::default();
+                storage: Box::new(move |node_id| {
+                    let mut storage = if metadata.storage_on_disk {
+                        TestStorage::<TYPES>::on_disk().expect("Failed to create storage on disk")
+                    } else {
+                        TestStorage::<TYPES>::default()
+                    }
+                    .with_retention_policy(metadata.retention_policy);
                     // update storage impl to use settings delay option
                     storage.delay_config = metadata.async_delay_config.clone();
-                    storage
+                    let faults = metadata
+                        .storage_faults
+                        .get(&node_id)
+                        .cloned()
+                        .unwrap_or_default();
+                    FaultyStorage::new(storage, faults)
                 }),
                 config,
                 validator_config,
                 marketplace_config: Box::new(|_| MarketplaceConfig::<TYPES, I> {
+                    // The test runner gives the provider a `SolverClient` for the fake solver,
+                    // if `start_solver` is set
                     auction_results_provider: TestAuctionResultsProvider::<TYPES>::default().into(),
                     fallback_builder_url: Url::parse("http://localhost:9999").unwrap(),
+                    local_builder: None,
                 }),
             },
             metadata: self,
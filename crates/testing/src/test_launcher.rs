@@ -10,7 +10,7 @@ use hotshot::{
     traits::{NodeImplementation, TestableNodeImplementation},
     MarketplaceConfig,
 };
-use hotshot_example_types::storage_types::TestStorage;
+use hotshot_example_types::{faulty_storage::FaultyStorage, storage_types::TestStorage};
 use hotshot_types::{
     traits::{
         network::{AsyncGenerator, ConnectedNetwork},
@@ -33,7 +33,7 @@ pub struct ResourceGenerators<TYPES: NodeType, I: TestableNodeImplementation<TYP
     /// generate channels
     pub channel_generator: AsyncGenerator<Network<TYPES, I>>,
     /// generate new storage for each node
-    pub storage: Generator<TestStorage<TYPES>>,
+    pub storage: Generator<FaultyStorage<TestStorage<TYPES>>>,
     /// configuration used to generate each hotshot node
     pub config: HotShotConfig<TYPES::SignatureKey>,
     /// config that contains the signature keys
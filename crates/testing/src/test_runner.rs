@@ -22,11 +22,12 @@ use hotshot::{
 use hotshot_example_types::{
     auction_results_provider_types::TestAuctionResultsProvider,
     block_types::TestBlockHeader,
+    faulty_storage::FaultyStorage,
     state_types::{TestInstanceState, TestValidatedState},
     storage_types::TestStorage,
 };
 use hotshot_fakeapi::fake_solver::FakeSolverState;
-use hotshot_task_impls::events::HotShotEvent;
+use hotshot_task_impls::{events::HotShotEvent, solver::SolverClient};
 use hotshot_types::{
     consensus::ConsensusMetricsValue,
     constants::EVENT_CHANNEL_SIZE,
@@ -79,7 +80,7 @@ where
     I: NodeImplementation<
         TYPES,
         Network = N,
-        Storage = TestStorage<TYPES>,
+        Storage = FaultyStorage<TestStorage<TYPES>>,
         AuctionResultsProvider = TestAuctionResultsProvider<TYPES>,
     >,
 {
@@ -104,7 +105,10 @@ where
                 if matches!(change.updown, NodeAction::Up) {
                     late_start_nodes.insert(change.idx.try_into().unwrap());
                 }
-                if matches!(change.updown, NodeAction::RestartDown(_)) {
+                if matches!(
+                    change.updown,
+                    NodeAction::RestartDown(_) | NodeAction::RestartDownFromStorage(_)
+                ) {
                     restart_nodes.insert(change.idx.try_into().unwrap());
                 }
             }
@@ -191,7 +195,7 @@ where
             )
             .await
             .to_qc2(),
-            async_delay_config: launcher.metadata.async_delay_config,
+            instance_state: launcher.metadata.instance_state(),
             restart_contexts: HashMap::new(),
             channel_generator: launcher.resource_generator.channel_generator,
         };
@@ -310,6 +314,19 @@ where
         }
         tracing::info!("Nodes shtudown");
 
+        // Storage faults which were scripted but never fired mean the test didn't exercise what it
+        // was meant to
+        for node in &*nodes {
+            if meta.storage_faults.contains_key(&node.node_id) {
+                let injected = node.handle.storage().read().await.injected_faults();
+                assert!(
+                    injected > 0,
+                    "No storage faults were injected into node {}",
+                    node.node_id
+                );
+            }
+        }
+
         completion_handle.abort();
 
         assert!(
@@ -335,6 +352,7 @@ where
                 Url::parse(&format!("http://localhost:{builder_port}")).expect("Invalid URL");
             let builder_task = B::start(
                 config.num_nodes_with_stake.into(),
+                config.vid_code_rate,
                 builder_url.clone(),
                 B::Config::default(),
                 metadata.changes.clone(),
@@ -350,6 +368,7 @@ where
 
         let fallback_builder_task = B::start(
             config.num_nodes_with_stake.into(),
+            config.vid_code_rate,
             fallback_builder_url.clone(),
             B::Config::default(),
             self.launcher.metadata.fallback_builder.changes.clone(),
@@ -364,6 +383,7 @@ where
     /// Add auction solver.
     pub async fn add_solver(&mut self, builder_urls: Vec<Url>) {
         let solver_error_pct = self.launcher.metadata.solver.error_pct;
+        let solver_fault = self.launcher.metadata.solver.fault;
         let solver_port = portpicker::pick_unused_port().expect("No available ports");
 
         // This should basically never fail
@@ -372,7 +392,8 @@ where
             .expect("Failed to parse solver URL");
 
         // Initialize the solver API state
-        let solver_state = FakeSolverState::new(Some(solver_error_pct), builder_urls);
+        let solver_state =
+            FakeSolverState::new(Some(solver_error_pct), builder_urls).with_fault(solver_fault);
 
         // Then, fire it up as a background thread.
         self.solver_server = Some((
@@ -443,7 +464,8 @@ where
                 let mut new_auction_results_provider =
                     marketplace_config.auction_results_provider.as_ref().clone();
 
-                new_auction_results_provider.broadcast_url = Some(solver_server.0.clone());
+                new_auction_results_provider.solver =
+                    Some(SolverClient::new(solver_server.0.clone()));
 
                 marketplace_config.auction_results_provider = new_auction_results_provider.into();
             }
@@ -475,7 +497,7 @@ where
                     );
                 } else {
                     let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(
-                        TestInstanceState::new(self.launcher.metadata.async_delay_config.clone()),
+                        self.launcher.metadata.instance_state(),
                     )
                     .await
                     .unwrap();
@@ -164,11 +164,12 @@ pub async fn add_network_message_test_task<
     public_key: TYPES::SignatureKey,
 ) -> JoinHandle<()> {
     let net = Arc::clone(&channel);
-    let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
+    let network_state: NetworkMessageTaskState<_, _> = NetworkMessageTaskState {
         internal_event_stream: internal_event_stream.clone(),
         external_event_stream: external_event_stream.clone(),
         public_key,
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
+        network: Arc::clone(&net),
     };
 
     let network = Arc::clone(&net);
@@ -177,7 +178,7 @@ pub async fn add_network_message_test_task<
     spawn(async move {
         loop {
             // Get the next message from the network
-            let message = match network.recv_message().await {
+            let (message, peer) = match network.recv_message_from().await {
                 Ok(message) => message,
                 Err(e) => {
                     error!("Failed to receive message: {:?}", e);
@@ -196,7 +197,7 @@ pub async fn add_network_message_test_task<
                 };
 
             // Handle the message
-            state.handle_message(deserialized_message).await;
+            state.handle_message(deserialized_message, peer).await;
         }
     })
 }
@@ -6,29 +6,42 @@
 
 use std::{
     collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
+use async_broadcast::broadcast;
+use hotshot::types::{Event, EventType};
 use hotshot_builder_api::v0_1::block_info::AvailableBlockData;
 use hotshot_example_types::{
     block_types::{TestBlockPayload, TestMetadata, TestTransaction},
     node_types::{TestTypes, TestVersions},
 };
-use hotshot_task_impls::builder::{BuilderClient, BuilderClientError};
+use hotshot_task_impls::builder::{v0_1, BuilderClient, BuilderClientError};
 use hotshot_testing::block_builder::{
-    BuilderTask, RandomBuilderImplementation, TestBuilderImplementation,
+    run_builder_source_0_1, BuilderTask, RandomBuilderImplementation, SimpleBuilderImplementation,
+    TestBuilderImplementation,
 };
 use hotshot_types::{
+    data::ViewNumber,
     network::RandomBuilderConfig,
     traits::{
         block_contents::vid_commitment,
-        node_implementation::{NodeType, Versions},
-        signature_key::SignatureKey,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::{BuilderSignatureKey, SignatureKey},
         BlockPayload,
     },
+    vid::VidCodeRate,
 };
 use tide_disco::Url;
-use tokio::time::sleep;
+use tokio::{
+    io::{copy_bidirectional, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::sleep,
+};
 
 #[cfg(test)]
 #[tokio::test(flavor = "multi_thread")]
@@ -38,6 +51,7 @@ async fn test_random_block_builder() {
     let api_url = Url::parse(&format!("http://localhost:{port}")).expect("Valid URL");
     let task: Box<dyn BuilderTask<TestTypes>> = RandomBuilderImplementation::start(
         1,
+        VidCodeRate::default(),
         api_url.clone(),
         RandomBuilderConfig {
             blocks_per_second: u32::MAX,
@@ -64,7 +78,7 @@ async fn test_random_block_builder() {
         // Test getting blocks
         let blocks = client
             .available_blocks(
-                vid_commitment(&[], 1),
+                vid_commitment(&[], 1, VidCodeRate::default()),
                 dummy_view_number,
                 pub_key,
                 &signature,
@@ -115,3 +129,155 @@ async fn test_random_block_builder() {
         .await;
     assert!(matches!(result, Err(BuilderClientError::BlockNotFound)));
 }
+
+/// Forward the connections to a new port to the builder at `builder`, refusing the subscriptions to
+/// its available blocks if `refuse_subscriptions`, as a builder which doesn't serve them would.
+/// Returns the URL of that port and the number of subscriptions asked for.
+async fn proxy_builder(builder: &Url, refuse_subscriptions: bool) -> (Url, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    let builder = format!("localhost:{}", builder.port().unwrap());
+    let subscriptions = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&subscriptions);
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            let (builder, counter) = (builder.clone(), Arc::clone(&counter));
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match client.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(read) => head.extend_from_slice(&buf[..read]),
+                    }
+                }
+                let subscribing = String::from_utf8_lossy(&head)
+                    .lines()
+                    .next()
+                    .is_some_and(|line| line.contains("availableblocksstream"));
+                if subscribing {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    if refuse_subscriptions {
+                        let _ = client
+                            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                            .await;
+                        return;
+                    }
+                }
+                let Ok(mut builder) = TcpStream::connect(builder).await else {
+                    return;
+                };
+                if builder.write_all(&head).await.is_ok() {
+                    let _ = copy_bidirectional(&mut client, &mut builder).await;
+                }
+            });
+        }
+    });
+    (proxy, subscriptions)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_builder_client_keeps_its_subscription_across_views() {
+    let port = portpicker::pick_unused_port().expect("No free ports");
+    let api_url = Url::parse(&format!("http://localhost:{port}")).expect("Valid URL");
+    let task: Box<dyn BuilderTask<TestTypes>> = SimpleBuilderImplementation::start(
+        1,
+        VidCodeRate::default(),
+        api_url.clone(),
+        (),
+        HashMap::new(),
+    )
+    .await;
+    let (event_sender, event_receiver) = broadcast(16);
+    task.start(Box::new(event_receiver));
+
+    let (proxy_url, subscriptions) = proxy_builder(&api_url, false).await;
+    let client = v0_1::BuilderClient::<TestTypes>::new(proxy_url);
+    assert!(client.connect(Duration::from_millis(100)).await);
+
+    let (pub_key, private_key) =
+        <TestTypes as NodeType>::SignatureKey::generated_from_seed_indexed([0_u8; 32], 0);
+    let signature = <TestTypes as NodeType>::SignatureKey::sign(&private_key, &[0_u8; 32])
+        .expect("Failed to create dummy signature");
+    let parent = vid_commitment(&[], 1, VidCodeRate::default());
+    let first_view = tokio::spawn({
+        let (client, signature) = (client.clone(), signature.clone());
+        async move {
+            client
+                .next_available_blocks(parent, 1, pub_key, &signature)
+                .await
+        }
+    });
+
+    // The builder has nothing to offer until it sees a transaction, and then pushes it to us
+    sleep(Duration::from_millis(100)).await;
+    event_sender
+        .broadcast(Event {
+            view_number: ViewNumber::new(0),
+            event: EventType::Transactions {
+                transactions: vec![TestTransaction::new(vec![1; 8])],
+            },
+        })
+        .await
+        .unwrap();
+    let blocks = tokio::time::timeout(Duration::from_secs(2), first_view)
+        .await
+        .expect("Builder failed to push a block in two seconds")
+        .unwrap()
+        .expect("Failed to get available blocks");
+    let [block] = blocks.as_slice() else {
+        panic!("Expected one block, got {blocks:?}");
+    };
+    assert!(block.sender.validate_block_info_signature(
+        &block.signature,
+        block.block_size,
+        block.offered_fee,
+        &block.block_hash,
+    ));
+
+    // The next view asks over the same subscription
+    let blocks = client
+        .next_available_blocks(parent, 2, pub_key, &signature)
+        .await
+        .expect("Failed to get available blocks");
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(subscriptions.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_builder_client_polls_builders_which_dont_push_blocks() {
+    let port = portpicker::pick_unused_port().expect("No free ports");
+    let api_url = Url::parse(&format!("http://localhost:{port}")).expect("Valid URL");
+    let (change_sender, change_receiver) = broadcast(1);
+    let (source, _task) = SimpleBuilderImplementation::create::<TestTypes>(
+        1,
+        VidCodeRate::default(),
+        HashMap::new(),
+        change_sender,
+    )
+    .await;
+    run_builder_source_0_1(api_url.clone(), change_receiver, source);
+
+    let (proxy_url, subscriptions) = proxy_builder(&api_url, true).await;
+    let client = v0_1::BuilderClient::<TestTypes>::new(proxy_url);
+    assert!(client.connect(Duration::from_millis(100)).await);
+
+    let (pub_key, private_key) =
+        <TestTypes as NodeType>::SignatureKey::generated_from_seed_indexed([0_u8; 32], 0);
+    let signature = <TestTypes as NodeType>::SignatureKey::sign(&private_key, &[0_u8; 32])
+        .expect("Failed to create dummy signature");
+    for view in 1..=2 {
+        assert!(client
+            .next_available_blocks(
+                vid_commitment(&[], 1, VidCodeRate::default()),
+                view,
+                pub_key,
+                &signature
+            )
+            .await
+            .expect("Failed to get available blocks")
+            .is_empty());
+    }
+    // The builder is polled for a while before we try to subscribe again
+    assert_eq!(subscriptions.load(Ordering::SeqCst), 1);
+}
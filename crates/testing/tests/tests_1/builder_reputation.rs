@@ -0,0 +1,269 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_broadcast::{broadcast, Sender};
+use hotshot::{
+    tasks::task_state::CreateTaskState,
+    types::{Event, EventType},
+};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task_impls::{
+    builder::v0_1::BuilderClient,
+    builder_reputation::{BuilderReputation, BASE_EXCLUSION, EXCLUSION_THRESHOLD},
+    events::HotShotEvent,
+    transactions::TransactionTaskState,
+};
+use hotshot_testing::{
+    block_builder::{BuilderTask, SimpleBuilderImplementation, TestBuilderImplementation},
+    helpers::build_system_handle,
+};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue, data::ViewNumber, traits::node_implementation::ConsensusTime,
+    vid::VidCodeRate,
+};
+use tokio::{
+    io::copy_bidirectional,
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+fn reputation() -> BuilderReputation {
+    BuilderReputation::new(Arc::new(ConsensusMetricsValue::default()))
+}
+
+fn url(port: u16) -> Url {
+    Url::parse(&format!("http://localhost:{port}")).unwrap()
+}
+
+#[test]
+fn test_builder_reputation_scores_builders() {
+    let reputation = reputation();
+    let (honest, slow, cheat) = (url(1), url(2), url(3));
+
+    assert_eq!(reputation.score(&honest), 1.0);
+    assert_eq!(reputation.stats(&honest), None);
+
+    reputation.record_response(&honest, Duration::from_millis(100));
+    reputation.record_response(&honest, Duration::from_millis(200));
+    reputation.record_claim(&honest, true);
+    reputation.record_fee(&honest, true);
+    reputation.record_response(&slow, Duration::from_millis(100));
+    reputation.record_no_response(&slow);
+    reputation.record_fee(&cheat, false);
+    reputation.record_fee(&cheat, false);
+
+    let stats = reputation.stats(&honest).unwrap();
+    let latency = stats.latency.unwrap();
+    assert!(latency.abs_diff(Duration::from_millis(120)) < Duration::from_millis(1));
+    assert!(stats.score() > 0.99);
+    assert!(reputation.score(&slow) < 1.0);
+    assert!(reputation.score(&cheat) < reputation.score(&slow));
+    assert_eq!(
+        reputation.all_stats().keys().cloned().collect::<Vec<_>>(),
+        vec![honest, slow, cheat]
+    );
+}
+
+#[test]
+fn test_builder_reputation_excludes_misbehaving_builders() {
+    let reputation = reputation();
+    let (good, bad) = (url(1), url(2));
+    reputation.record_claim(&good, true);
+
+    // A few failures are tolerated
+    for _ in 0..3 {
+        reputation.record_claim(&bad, false);
+    }
+    assert_eq!(reputation.eligible(&[&good, &bad]), vec![0, 1]);
+
+    reputation.record_signature(&bad, false);
+    let stats = reputation.stats(&bad).unwrap();
+    assert!(stats.score() < EXCLUSION_THRESHOLD);
+    let excluded_until = stats.excluded_until.unwrap();
+    assert!(stats.is_excluded(Instant::now()));
+    assert!(excluded_until <= Instant::now() + BASE_EXCLUSION);
+    assert!(!stats.is_excluded(excluded_until));
+    assert_eq!(reputation.eligible(&[&good, &bad]), vec![0]);
+
+    // Failing again while excluded doesn't extend the exclusion
+    reputation.record_claim(&bad, false);
+    assert_eq!(
+        reputation.stats(&bad).unwrap().excluded_until,
+        Some(excluded_until)
+    );
+
+    // If every builder is excluded, they are all asked anyway
+    assert_eq!(reputation.eligible(&[&bad]), vec![0]);
+}
+
+/// Start a builder, returning its URL and a channel to submit transactions to it
+async fn start_builder() -> (Url, Sender<Event<TestTypes>>) {
+    let port = portpicker::pick_unused_port().expect("No free ports");
+    let url = url(port);
+    let task: Box<dyn BuilderTask<TestTypes>> = SimpleBuilderImplementation::start(
+        10,
+        VidCodeRate::default(),
+        url.clone(),
+        (),
+        HashMap::new(),
+    )
+    .await;
+    let (sender, receiver) = broadcast(16);
+    task.start(Box::new(receiver));
+    (url, sender)
+}
+
+/// Forward the connections to a new port to the builder at `builder`, holding each of them back
+/// for `delay` first, and return the URL of that port
+async fn delay_builder(builder: &Url, delay: Duration) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    let builder = format!("localhost:{}", builder.port().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            let builder = builder.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                if let Ok(mut builder) = TcpStream::connect(builder).await {
+                    let _ = copy_bidirectional(&mut client, &mut builder).await;
+                }
+            });
+        }
+    });
+    proxy
+}
+
+async fn submit(builder: &Sender<Event<TestTypes>>, transaction: &TestTransaction) {
+    builder
+        .broadcast(Event {
+            view_number: ViewNumber::genesis(),
+            event: EventType::Transactions {
+                transactions: vec![transaction.clone()],
+            },
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_task_prefers_builders_with_a_good_score() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    state.builder_timeout = Duration::from_millis(500);
+    let (good, good_transactions) = start_builder().await;
+    let (bad, bad_transactions) = start_builder().await;
+    state.builder_clients = vec![
+        BuilderClient::new(bad.clone()),
+        BuilderClient::new(good.clone()),
+    ];
+    let (sender, mut receiver) = broadcast(1024);
+
+    // The builders offer the same fee, so the smaller block has the better fee per byte, but not
+    // once it is discounted by the score of its builder
+    let small = TestTransaction::new(vec![1; 1]);
+    let large = TestTransaction::new(vec![2; 2]);
+    submit(&bad_transactions, &small).await;
+    submit(&good_transactions, &large).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    state.builder_reputation.record_fee(&bad, false);
+    assert!(state.builder_reputation.score(&bad) > EXCLUSION_THRESHOLD);
+
+    state
+        .handle_view_change_legacy(&sender, ViewNumber::new(1))
+        .await;
+    let event = receiver.recv().await.unwrap();
+    let HotShotEvent::BlockRecv(bundle) = event.as_ref() else {
+        panic!("Expected a block, got {event}");
+    };
+    assert_eq!(
+        *bundle.encoded_transactions,
+        TestTransaction::encode(&[large])
+    );
+
+    // Once it is excluded, the bad builder isn't considered even if its block is far better
+    for _ in 0..3 {
+        state.builder_reputation.record_signature(&bad, false);
+    }
+    assert!(state
+        .builder_reputation
+        .stats(&bad)
+        .unwrap()
+        .is_excluded(Instant::now()));
+    let larger = TestTransaction::new(vec![3; 64]);
+    submit(&good_transactions, &larger).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    state
+        .handle_view_change_legacy(&sender, ViewNumber::new(1))
+        .await;
+    let event = receiver.recv().await.unwrap();
+    let HotShotEvent::BlockRecv(bundle) = event.as_ref() else {
+        panic!("Expected a block, got {event}");
+    };
+    assert_eq!(
+        *bundle.encoded_transactions,
+        TestTransaction::encode(&[larger])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_task_only_penalises_builders_missing_the_timeout() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    state.builder_timeout = Duration::from_secs(2);
+    let mut builders = Vec::new();
+    for _ in 0..3 {
+        let (builder, transactions) = start_builder().await;
+        submit(&transactions, &TestTransaction::new(vec![1; 1])).await;
+        builders.push(builder);
+    }
+    // The slow builder answers well after the other builders and the extra time given to the
+    // ones outside the first batch, but within the builder timeout
+    let slow = delay_builder(&builders[2], Duration::from_millis(800)).await;
+    let down = url(portpicker::pick_unused_port().expect("No free ports"));
+    state.builder_clients = vec![
+        BuilderClient::new(builders[0].clone()),
+        BuilderClient::new(builders[1].clone()),
+        BuilderClient::new(slow.clone()),
+        BuilderClient::new(down.clone()),
+    ];
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let (sender, mut receiver) = broadcast(1024);
+
+    let start = Instant::now();
+    state
+        .handle_view_change_legacy(&sender, ViewNumber::new(1))
+        .await;
+    let event = receiver.recv().await.unwrap();
+    assert!(matches!(event.as_ref(), HotShotEvent::BlockRecv(_)));
+    assert!(start.elapsed() < Duration::from_millis(800));
+    assert_eq!(state.builder_reputation.stats(&slow), None);
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let stats = state.builder_reputation.stats(&slow).unwrap();
+    assert!(stats.latency.unwrap() >= Duration::from_millis(800));
+    assert_eq!(stats.timeliness, 1.0);
+    assert!(state.builder_reputation.stats(&down).unwrap().timeliness < 1.0);
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    consensus::ConsensusStatus,
+    constants::LOOK_AHEAD,
+    data::ViewNumber,
+    traits::{election::Membership, node_implementation::ConsensusTime},
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_consensus_status_snapshot() {
+    hotshot::helpers::initialize_logging();
+
+    let node_id = 2;
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id)
+        .await
+        .0;
+
+    let status = handle.status().await;
+    let cur_view = handle.cur_view().await;
+    let cur_epoch = handle.cur_epoch().await;
+    assert_eq!(status.cur_view, cur_view);
+    assert_eq!(status.cur_epoch, cur_epoch);
+    assert_eq!(status.last_decided_view, ViewNumber::genesis());
+    assert_eq!(status.last_decided_height, 0);
+    assert_eq!(status.pending_vid_shares, 0);
+    assert_eq!(status.pending_da_certificates, 0);
+    assert!(status.network_ready);
+
+    // The upcoming leader views are exactly those the membership assigns to us
+    let expected_leader_views: Vec<_> = (1..=LOOK_AHEAD)
+        .map(|offset| cur_view + offset)
+        .filter(|view| {
+            handle
+                .memberships
+                .quorum_membership
+                .leader(*view, cur_epoch)
+                .unwrap()
+                == handle.public_key()
+        })
+        .collect();
+    assert_eq!(status.upcoming_leader_views, expected_leader_views);
+
+    // Nothing holds the consensus lock, so the non-blocking version sees the same thing
+    assert_eq!(handle.try_status(), Some(status.clone()));
+
+    // The snapshot can be served as is by status endpoints
+    let json = serde_json::to_string(&status).unwrap();
+    let decoded: ConsensusStatus<TestTypes> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, status);
+}
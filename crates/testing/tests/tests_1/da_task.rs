@@ -29,6 +29,7 @@ use hotshot_types::{
         election::Membership,
         node_implementation::{ConsensusTime, Versions},
     },
+    vid::VidCodeRate,
 };
 use vbs::version::StaticVersionType;
 
@@ -53,6 +54,7 @@ async fn test_da_task() {
             .memberships
             .quorum_membership
             .total_nodes(EpochNumber::new(0)),
+        VidCodeRate::default(),
     );
 
     let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
@@ -99,6 +101,7 @@ async fn test_da_task() {
                 ViewNumber::new(2),
                 vec1::vec1![null_block::builder_fee::<TestTypes, TestVersions>(
                     quorum_membership.total_nodes(EpochNumber::new(0)),
+                    VidCodeRate::default(),
                     <TestVersions as Versions>::Base::VERSION,
                     *ViewNumber::new(2),
                 )
@@ -153,6 +156,7 @@ async fn test_da_task_storage_failure() {
             .memberships
             .quorum_membership
             .total_nodes(EpochNumber::new(0)),
+        VidCodeRate::default(),
     );
 
     let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
@@ -199,6 +203,7 @@ async fn test_da_task_storage_failure() {
                 ViewNumber::new(2),
                 vec1::vec1![null_block::builder_fee::<TestTypes, TestVersions>(
                     quorum_membership.total_nodes(EpochNumber::new(0)),
+                    VidCodeRate::default(),
                     <TestVersions as Versions>::Base::VERSION,
                     *ViewNumber::new(2),
                 )
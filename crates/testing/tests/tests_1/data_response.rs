@@ -0,0 +1,259 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::{broadcast, Receiver, Sender};
+use futures::StreamExt;
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_example_types::{
+    block_types::TestBlockPayload,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    response::{run_response_task, NetworkResponseState, UNSTAKED_REQUEST_BURST},
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::{Leaf2, ViewNumber},
+    event::LeafInfo,
+    traits::{
+        network::{DataRequest, RequestKind, ResponseData},
+        node_implementation::ConsensusTime,
+        storage::Storage,
+    },
+    vote::HasViewNumber,
+};
+use sha2::{Digest, Sha256};
+use tokio::time::timeout;
+
+/// A data request for `request`, signed by the node with index `node_id`
+fn signed_request(
+    node_id: u64,
+    request: RequestKind<TestTypes>,
+) -> (BLSPubKey, DataRequest<TestTypes>) {
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], node_id);
+    let signature = BLSPubKey::sign(
+        &private_key,
+        &Sha256::digest(bincode::serialize(&request).unwrap()),
+    )
+    .unwrap();
+    let view = match &request {
+        RequestKind::Payload { view } | RequestKind::QuorumCertificate { view } => *view,
+        _ => ViewNumber::genesis(),
+    };
+    (
+        public_key,
+        DataRequest {
+            request,
+            view,
+            signature,
+        },
+    )
+}
+
+/// Start the response task of node 2 with 4 decided leaves, heights 1 to 4, which are only in
+/// its storage
+async fn setup() -> (
+    BLSPubKey,
+    Vec<Leaf2<TestTypes>>,
+    Sender<Arc<HotShotEvent<TestTypes>>>,
+    Receiver<Arc<HotShotEvent<TestTypes>>>,
+) {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let leaves: Vec<_> = TestViewGenerator::generate(quorum_membership.clone(), da_membership)
+        .take(4)
+        .map(|view| view.leaf)
+        .collect()
+        .await;
+    let storage = handle.storage();
+    for leaf in &leaves {
+        storage
+            .read()
+            .await
+            .update_decided_leaf(&LeafInfo::new(
+                leaf.clone(),
+                Arc::new(TestValidatedState::default()),
+                None,
+                None,
+            ))
+            .await
+            .unwrap();
+    }
+
+    let (sender, receiver) = broadcast(1024);
+    let state = NetworkResponseState::<TestTypes, MemoryImpl>::new(
+        handle.hotshot.consensus(),
+        storage,
+        Arc::new(quorum_membership),
+        handle.public_key().clone(),
+        handle.private_key().clone(),
+        handle.hotshot.id,
+        handle.hotshot.config.vid_code_rate,
+    );
+    run_response_task(state, receiver.clone(), sender.clone());
+
+    (handle.public_key().clone(), leaves, sender, receiver)
+}
+
+/// Send `request` from node `node_id` to the response task, and wait for its response
+async fn request(
+    node_id: u64,
+    request: RequestKind<TestTypes>,
+    responder: &BLSPubKey,
+    sender: &Sender<Arc<HotShotEvent<TestTypes>>>,
+    receiver: &mut Receiver<Arc<HotShotEvent<TestTypes>>>,
+) -> Option<ResponseData<TestTypes>> {
+    let (requester, data_request) = signed_request(node_id, request);
+    sender
+        .broadcast(Arc::new(HotShotEvent::DataRequestRecv(
+            data_request,
+            requester,
+        )))
+        .await
+        .unwrap();
+
+    let response = timeout(Duration::from_secs(1), async {
+        while let Some(event) = receiver.next().await {
+            if let HotShotEvent::DataResponseSend(from, to, data, signature) = event.as_ref() {
+                assert_eq!(from, responder);
+                assert_eq!(*to, requester);
+                assert!(data.is_signed_by(responder, signature));
+                return data.clone();
+            }
+        }
+        panic!("Event stream closed");
+    })
+    .await;
+    response.ok()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_response_task_serves_decided_data_from_storage() {
+    hotshot::helpers::initialize_logging();
+
+    let (responder, leaves, sender, mut receiver) = setup().await;
+
+    let data = request(
+        1,
+        RequestKind::Leaf { height: 3 },
+        &responder,
+        &sender,
+        &mut receiver,
+    )
+    .await;
+    assert_eq!(data, Some(ResponseData::Leaf(leaves[2].clone())));
+
+    let view = leaves[1].view_number();
+    let data = request(
+        1,
+        RequestKind::Payload { view },
+        &responder,
+        &sender,
+        &mut receiver,
+    )
+    .await;
+    assert_eq!(
+        data,
+        Some(ResponseData::Payload {
+            view,
+            payload: leaves[1].block_payload().unwrap(),
+        })
+    );
+
+    // The certificate for a leaf is the one its child carries
+    let view = leaves[2].view_number();
+    let data = request(
+        1,
+        RequestKind::QuorumCertificate { view },
+        &responder,
+        &sender,
+        &mut receiver,
+    )
+    .await;
+    assert_eq!(
+        data,
+        Some(ResponseData::QuorumCertificate(leaves[3].justify_qc()))
+    );
+    assert_eq!(leaves[3].justify_qc().view_number(), view);
+
+    // Nothing is sent for data no one decided
+    let data = request(
+        1,
+        RequestKind::Leaf { height: 10 },
+        &responder,
+        &sender,
+        &mut receiver,
+    )
+    .await;
+    assert_eq!(data, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_response_task_rate_limits_unstaked_nodes() {
+    hotshot::helpers::initialize_logging();
+
+    let (responder, _, sender, mut receiver) = setup().await;
+
+    // An unstaked node is served up to its burst, and then refused
+    for _ in 0..UNSTAKED_REQUEST_BURST {
+        let data = request(
+            1000,
+            RequestKind::Leaf { height: 3 },
+            &responder,
+            &sender,
+            &mut receiver,
+        )
+        .await;
+        assert!(data.is_some());
+    }
+    let data = request(
+        1000,
+        RequestKind::Leaf { height: 3 },
+        &responder,
+        &sender,
+        &mut receiver,
+    )
+    .await;
+    assert_eq!(data, None);
+
+    // Staked nodes are not limited
+    let data = request(
+        1,
+        RequestKind::Leaf { height: 3 },
+        &responder,
+        &sender,
+        &mut receiver,
+    )
+    .await;
+    assert!(data.is_some());
+}
+
+#[test]
+fn test_response_data_signature_covers_the_data() {
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let (other_key, _) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1);
+    let data = ResponseData::<TestTypes>::Payload {
+        view: ViewNumber::genesis(),
+        payload: TestBlockPayload::genesis(),
+    };
+    let signature = data.sign(&private_key).unwrap();
+
+    assert!(data.is_signed_by(&public_key, &signature));
+    assert!(!data.is_signed_by(&other_key, &signature));
+    let other_data = ResponseData::<TestTypes>::Payload {
+        view: ViewNumber::new(1),
+        payload: TestBlockPayload::genesis(),
+    };
+    assert!(!other_data.is_signed_by(&public_key, &signature));
+}
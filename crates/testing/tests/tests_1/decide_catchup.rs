@@ -0,0 +1,383 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use async_broadcast::{broadcast, Receiver, Sender};
+use futures::StreamExt;
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_task_impls::{catchup::LeafFetcher, events::HotShotEvent};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::{EpochNumber, Leaf2},
+    event::{Event, EventType, LeafInfo},
+    traits::{
+        network::{RequestKind, ResponseData},
+        node_implementation::ConsensusTime,
+    },
+    vid::VidCodeRate,
+};
+use tokio::{spawn, task::JoinHandle, time::timeout};
+
+/// Answer the leaf requests sent on `receiver` with the leaves of `chain`, by height, and the
+/// payload requests with the payloads of `payloads`, as if the recipient had sent them
+fn serve_leaves(
+    chain: BTreeMap<u64, Leaf2<TestTypes>>,
+    payloads: Vec<Leaf2<TestTypes>>,
+    sender: Sender<Arc<HotShotEvent<TestTypes>>>,
+    mut receiver: Receiver<Arc<HotShotEvent<TestTypes>>>,
+) -> JoinHandle<()> {
+    spawn(async move {
+        while let Ok(event) = receiver.recv().await {
+            let HotShotEvent::DataRequestSend(request, _, recipient) = event.as_ref() else {
+                continue;
+            };
+            let data = match request.request {
+                RequestKind::Leaf { height } => chain.get(&height).cloned().map(ResponseData::Leaf),
+                RequestKind::Payload { view } => payloads
+                    .iter()
+                    .find(|leaf| leaf.view_number() == view)
+                    .and_then(Leaf2::block_payload)
+                    .map(|payload| ResponseData::Payload { view, payload }),
+                _ => None,
+            };
+            if let Some(data) = data {
+                sender
+                    .broadcast(Arc::new(HotShotEvent::DataResponseRecv(
+                        recipient.clone(),
+                        data,
+                    )))
+                    .await
+                    .unwrap();
+            }
+        }
+    })
+}
+
+/// A fetcher for node 2, and the leaves of 6 consecutive views with heights 1 to 6
+async fn setup() -> (
+    LeafFetcher<TestTypes>,
+    Vec<Leaf2<TestTypes>>,
+    TestViewGenerator,
+    Sender<Arc<HotShotEvent<TestTypes>>>,
+    Receiver<Arc<HotShotEvent<TestTypes>>>,
+) {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator =
+        TestViewGenerator::generate(quorum_membership.clone(), da_membership.clone());
+    let leaves: Vec<_> = (&mut generator)
+        .take(6)
+        .map(|view| view.leaf)
+        .collect()
+        .await;
+    assert_eq!(
+        leaves.iter().map(Leaf2::height).collect::<Vec<_>>(),
+        (1..=6).collect::<Vec<_>>()
+    );
+
+    let (sender, receiver) = broadcast(1024);
+    let fetcher = LeafFetcher {
+        public_key: handle.public_key().clone(),
+        private_key: handle.private_key().clone(),
+        quorum_membership: Arc::new(quorum_membership),
+        da_membership: Arc::new(da_membership),
+        epoch_height: 0,
+        vid_code_rate: VidCodeRate::default(),
+        scheduler: handle.hotshot.request_scheduler.clone(),
+        sender: sender.clone(),
+        receiver: receiver.clone().deactivate(),
+    };
+    (fetcher, leaves, generator, sender, receiver)
+}
+
+/// The leaves of `leaves` with the given heights, by height
+fn chain(leaves: &[Leaf2<TestTypes>], heights: &[u64]) -> BTreeMap<u64, Leaf2<TestTypes>> {
+    heights
+        .iter()
+        .map(|height| (*height, leaves[*height as usize - 1].clone()))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_leaf_fetcher_fetches_missing_ancestors() {
+    hotshot::helpers::initialize_logging();
+
+    let (fetcher, leaves, _, sender, receiver) = setup().await;
+    let peer = serve_leaves(chain(&leaves, &[2, 3, 4, 5]), Vec::new(), sender, receiver);
+
+    let fetched = fetcher
+        .fetch_ancestors(
+            &leaves[5],
+            2,
+            leaves[5].view_number(),
+            EpochNumber::genesis(),
+        )
+        .await;
+    assert_eq!(
+        fetched,
+        leaves[1..5].iter().rev().cloned().collect::<Vec<_>>()
+    );
+    assert!(fetched.iter().all(|leaf| leaf.block_payload().is_some()));
+    peer.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_leaf_fetcher_rejects_undecided_leaves() {
+    hotshot::helpers::initialize_logging();
+
+    let (fetcher, leaves, generator, sender, receiver) = setup().await;
+
+    // A leaf of the same height on another fork
+    let mut fork_generator = TestViewGenerator::generate(
+        generator.quorum_membership.clone(),
+        generator.da_membership.clone(),
+    );
+    fork_generator.next().await.unwrap();
+    fork_generator.add_transactions(vec![TestTransaction::new(vec![1])]);
+    let fork: Vec<_> = (&mut fork_generator)
+        .take(3)
+        .map(|view| view.leaf)
+        .collect()
+        .await;
+    assert_eq!(fork[2].height(), 4);
+    let mut served = chain(&leaves, &[5]);
+    served.insert(4, fork[2].clone());
+    // And a decided leaf without its payload
+    let mut without_payload = leaves[2].clone();
+    without_payload.unfill_block_payload();
+    served.insert(3, without_payload);
+
+    let peer = serve_leaves(served, Vec::new(), sender, receiver);
+
+    // Fetching stops at the forked leaf
+    let fetched = fetcher
+        .fetch_ancestors(
+            &leaves[5],
+            2,
+            leaves[5].view_number(),
+            EpochNumber::genesis(),
+        )
+        .await;
+    assert_eq!(fetched, vec![leaves[4].clone()]);
+
+    // And at the leaf whose payload no one has
+    let fetched = fetcher
+        .fetch_ancestors(
+            &leaves[3],
+            2,
+            leaves[3].view_number(),
+            EpochNumber::genesis(),
+        )
+        .await;
+    assert!(fetched.is_empty());
+    peer.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_leaf_fetcher_fetches_missing_payloads() {
+    hotshot::helpers::initialize_logging();
+
+    let (fetcher, leaves, _, sender, receiver) = setup().await;
+
+    // The peer has the leaf without its payload, which it serves separately
+    let mut without_payload = leaves[4].clone();
+    without_payload.unfill_block_payload();
+    let peer = serve_leaves(
+        BTreeMap::from([(5, without_payload)]),
+        vec![leaves[4].clone()],
+        sender,
+        receiver,
+    );
+
+    let fetched = fetcher
+        .fetch_ancestors(
+            &leaves[5],
+            5,
+            leaves[5].view_number(),
+            EpochNumber::genesis(),
+        )
+        .await;
+    assert_eq!(fetched, vec![leaves[4].clone()]);
+    assert!(fetched[0].block_payload().is_some());
+    peer.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_decide_after_gap_reports_unrecoverable_leaves() {
+    hotshot::helpers::initialize_logging();
+
+    let (fetcher, leaves, _, sender, receiver) = setup().await;
+    // Heights 2 and 3 can't be recovered
+    let peer = serve_leaves(chain(&leaves, &[4, 5]), Vec::new(), sender, receiver);
+
+    let (output_sender, mut output) = broadcast(16);
+    let decide = Event {
+        view_number: leaves[5].view_number(),
+        event: EventType::Decide {
+            leaf_chain: Arc::new(vec![LeafInfo::new(
+                leaves[5].clone(),
+                Arc::new(TestValidatedState::default()),
+                None,
+                None,
+            )]),
+            qc: Arc::new(leaves[5].justify_qc()),
+            block_size: None,
+        },
+    };
+    fetcher
+        .fill_gap_and_emit(
+            leaves[0].clone(),
+            decide,
+            EpochNumber::genesis(),
+            &output_sender,
+        )
+        .await;
+
+    let gap = timeout(Duration::from_secs(1), output.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        gap.event,
+        EventType::DecideGap {
+            from_height: 2,
+            to_height: 3
+        }
+    ));
+
+    let decide = timeout(Duration::from_secs(1), output.next())
+        .await
+        .unwrap()
+        .unwrap();
+    let EventType::Decide { leaf_chain, .. } = &decide.event else {
+        panic!("Expected a decide, got {decide:?}");
+    };
+    assert_eq!(
+        leaf_chain
+            .iter()
+            .map(|info| info.leaf.height())
+            .collect::<Vec<_>>(),
+        vec![6, 5, 4]
+    );
+    peer.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_decide_after_gap_reports_fork() {
+    hotshot::helpers::initialize_logging();
+
+    let (fetcher, leaves, generator, sender, receiver) = setup().await;
+
+    // This node decided a leaf of height 2 on another fork
+    let mut fork_generator = TestViewGenerator::generate(
+        generator.quorum_membership.clone(),
+        generator.da_membership.clone(),
+    );
+    fork_generator.next().await.unwrap();
+    fork_generator.add_transactions(vec![TestTransaction::new(vec![1])]);
+    let fork = fork_generator.next().await.unwrap().leaf;
+    assert_eq!(fork.height(), 2);
+    assert_ne!(fork, leaves[1]);
+
+    let peer = serve_leaves(chain(&leaves, &[3, 4, 5]), Vec::new(), sender, receiver);
+
+    let (output_sender, mut output) = broadcast(16);
+    let decide = Event {
+        view_number: leaves[5].view_number(),
+        event: EventType::Decide {
+            leaf_chain: Arc::new(vec![LeafInfo::new(
+                leaves[5].clone(),
+                Arc::new(TestValidatedState::default()),
+                None,
+                None,
+            )]),
+            qc: Arc::new(leaves[5].justify_qc()),
+            block_size: None,
+        },
+    };
+    fetcher
+        .fill_gap_and_emit(fork, decide, EpochNumber::genesis(), &output_sender)
+        .await;
+    drop(output_sender);
+
+    // Leaves 3 to 5 were recovered, but don't extend the forked leaf
+    let error = output.next().await.unwrap();
+    assert!(
+        matches!(error.event, EventType::Error { .. }),
+        "Expected an error, got {error:?}"
+    );
+    let decide = output.next().await.unwrap();
+    let EventType::Decide { leaf_chain, .. } = &decide.event else {
+        panic!("Expected a decide, got {decide:?}");
+    };
+    assert_eq!(
+        leaf_chain
+            .iter()
+            .map(|info| info.leaf.height())
+            .collect::<Vec<_>>(),
+        vec![6, 5, 4, 3]
+    );
+    assert!(output.next().await.is_none());
+    peer.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_leaf_fetcher_requests_heights_concurrently() {
+    hotshot::helpers::initialize_logging();
+
+    let (fetcher, leaves, _, sender, mut receiver) = setup().await;
+
+    // The peer only answers once it was asked for every missing height, which a fetcher asking
+    // for one height at a time never does
+    let served = chain(&leaves, &[2, 3, 4, 5]);
+    let peer = spawn(async move {
+        let mut requests = Vec::new();
+        while let Ok(event) = receiver.recv().await {
+            if let HotShotEvent::DataRequestSend(request, _, recipient) = event.as_ref() {
+                if let RequestKind::Leaf { height } = request.request {
+                    if !requests.iter().any(|(requested, _)| *requested == height) {
+                        requests.push((height, recipient.clone()));
+                    }
+                }
+            }
+            if requests.len() < served.len() {
+                continue;
+            }
+            for (height, recipient) in requests.drain(..) {
+                sender
+                    .broadcast(Arc::new(HotShotEvent::DataResponseRecv(
+                        recipient,
+                        ResponseData::Leaf(served[&height].clone()),
+                    )))
+                    .await
+                    .unwrap();
+            }
+        }
+    });
+
+    let fetched = fetcher
+        .fetch_ancestors(
+            &leaves[5],
+            2,
+            leaves[5].view_number(),
+            EpochNumber::genesis(),
+        )
+        .await;
+    assert_eq!(
+        fetched,
+        leaves[1..5].iter().rev().cloned().collect::<Vec<_>>()
+    );
+    peer.abort();
+}
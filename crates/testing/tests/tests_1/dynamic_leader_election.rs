@@ -0,0 +1,231 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, time::Duration};
+
+use hotshot::traits::election::dynamic::{leader, DynamicCommittee};
+use hotshot_example_types::node_types::{
+    EpochsTestVersions, MemoryImpl, TestDynamicCommitteeTypes, TestTypes,
+};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    overall_safety_task::OverallSafetyPropertiesDescription,
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::{TestDescription, TimingData},
+};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    signature_key::BLSPubKey,
+    traits::{
+        election::Membership, network::Topic, node_implementation::ConsensusTime,
+        signature_key::SignatureKey,
+    },
+    PeerConfig,
+};
+
+/// The stakes of the nodes in these tests
+const STAKES: [u64; 4] = [1, 2, 3, 4];
+
+/// The number of views to sample leaders over
+const NUM_VIEWS: u64 = 40_000;
+
+/// How far the observed leader frequency may stray from the stake share. The standard deviation
+/// of the frequency is at most `0.5 / sqrt(NUM_VIEWS) = 0.0025`.
+const TOLERANCE: f64 = 0.02;
+
+fn keys(count: usize) -> Vec<BLSPubKey> {
+    (0..count as u64)
+        .map(|i| BLSPubKey::generated_from_seed_indexed([0u8; 32], i).0)
+        .collect()
+}
+
+fn peer_configs(keys: &[BLSPubKey], stakes: &[u64]) -> Vec<PeerConfig<BLSPubKey>> {
+    keys.iter()
+        .zip(stakes)
+        .map(|(key, stake)| PeerConfig {
+            stake_table_entry: key.stake_table_entry(*stake),
+            ..PeerConfig::default()
+        })
+        .collect()
+}
+
+/// Assert that each key leads a share of the views within `TOLERANCE` of its share of the stake
+fn assert_tracks_stake(counts: &HashMap<BLSPubKey, u64>, keys: &[BLSPubKey], stakes: &[u64]) {
+    let total_stake: u64 = stakes.iter().sum();
+    for (key, stake) in keys.iter().zip(stakes) {
+        let expected = *stake as f64 / total_stake as f64;
+        let observed = *counts.get(key).unwrap_or(&0) as f64 / NUM_VIEWS as f64;
+        assert!(
+            (observed - expected).abs() < TOLERANCE,
+            "Node with stake {stake} led {observed} of the views, expected {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_leader_frequency_tracks_stake() {
+    let keys = keys(STAKES.len());
+    let stake_table: Vec<_> = keys
+        .iter()
+        .zip(STAKES)
+        .map(|(key, stake)| key.stake_table_entry(stake))
+        .collect();
+
+    for drb_result in [[0u8; 32], [0xab; 32]] {
+        let mut counts = HashMap::new();
+        for view in 0..NUM_VIEWS {
+            let leader = leader::<TestTypes>(view, &stake_table, drb_result).unwrap();
+            *counts.entry(leader).or_insert(0) += 1;
+        }
+        assert_tracks_stake(&counts, &keys, &STAKES);
+    }
+}
+
+#[test]
+fn test_leader_selection_is_portable() {
+    let keys = keys(STAKES.len());
+    let stake_table: Vec<_> = keys
+        .iter()
+        .zip(STAKES)
+        .map(|(key, stake)| key.stake_table_entry(stake))
+        .collect();
+
+    // Computed independently from the specification in the documentation of `leader`
+    for (drb_result, expected) in [
+        ([0u8; 32], [3, 2, 3, 1, 3, 3, 0, 2, 2, 1, 3, 3]),
+        (
+            core::array::from_fn(|i| i as u8),
+            [3, 1, 0, 2, 3, 3, 1, 3, 2, 3, 3, 1],
+        ),
+    ] {
+        for (view, index) in expected.into_iter().enumerate() {
+            assert_eq!(
+                leader::<TestTypes>(view as u64, &stake_table, drb_result).as_ref(),
+                Some(&keys[index])
+            );
+        }
+    }
+}
+
+#[test]
+fn test_zero_stake_never_leads() {
+    let keys = keys(3);
+    let stake_table = vec![
+        keys[0].stake_table_entry(0),
+        keys[1].stake_table_entry(5),
+        keys[2].stake_table_entry(0),
+    ];
+
+    for view in 0..1_000 {
+        assert_eq!(
+            leader::<TestTypes>(view, &stake_table, [7u8; 32]).as_ref(),
+            Some(&keys[1])
+        );
+    }
+
+    let no_stake = vec![keys[0].stake_table_entry(0)];
+    assert_eq!(leader::<TestTypes>(0, &no_stake, [7u8; 32]), None);
+    assert_eq!(leader::<TestTypes>(0, &[], [7u8; 32]), None);
+}
+
+#[test]
+fn test_dynamic_committee_leaders() {
+    let keys = keys(STAKES.len() + 1);
+    // The last node has no stake, and must never be chosen
+    let mut stakes = STAKES.to_vec();
+    stakes.push(0);
+    let peers = peer_configs(&keys, &stakes);
+    let membership = <DynamicCommittee<TestTypes> as Membership<TestTypes>>::new(
+        peers.clone(),
+        peers,
+        Topic::Global,
+    );
+
+    let epoch = EpochNumber::new(1);
+    let mut counts = HashMap::new();
+    for view in 0..NUM_VIEWS {
+        let leader = membership.leader(ViewNumber::new(view), epoch).unwrap();
+        *counts.entry(leader).or_insert(0) += 1;
+    }
+    assert_tracks_stake(&counts, &keys, &stakes);
+
+    // Publishing a DRB result changes the schedule of that epoch, and of no other
+    let schedule = |membership: &DynamicCommittee<TestTypes>, epoch| -> Vec<_> {
+        (0..100)
+            .map(|view| membership.leader(ViewNumber::new(view), epoch).unwrap())
+            .collect()
+    };
+    let before = schedule(&membership, EpochNumber::new(2));
+    // Clones share their DRB results
+    membership
+        .clone()
+        .add_drb_result(EpochNumber::new(2), [0x42; 32]);
+    assert_eq!(
+        membership.drb_result(EpochNumber::new(2)).unwrap(),
+        [0x42; 32]
+    );
+    assert_ne!(schedule(&membership, EpochNumber::new(2)), before);
+    assert_eq!(schedule(&membership, EpochNumber::new(1)), before);
+
+    // Later epochs have no leaders until their DRB result is known
+    assert!(membership.drb_result(EpochNumber::new(3)).is_err());
+    assert!(membership
+        .leader(ViewNumber::new(0), EpochNumber::new(3))
+        .is_err());
+    membership.add_drb_result(EpochNumber::new(3), [0x42; 32]);
+    assert_eq!(
+        schedule(&membership, EpochNumber::new(3)),
+        schedule(&membership, EpochNumber::new(2))
+    );
+}
+
+// Restart every node from storage on disk in epoch 5. The leaders of epochs 5 and 6 were chosen by
+// DRB results computed before the restart, from seeds the restarted nodes no longer have, so
+// consensus only carries on if the results were persisted and reloaded.
+cross_tests!(
+    TestName: test_dynamic_committee_restart_keeps_drb_results,
+    Impls: [MemoryImpl],
+    Types: [TestDynamicCommitteeTypes],
+    Versions: [EpochsTestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription {
+            timing_data: TimingData {
+                next_view_timeout: 2000,
+                ..Default::default()
+            },
+            epoch_height: 10,
+            storage_on_disk: true,
+            ..TestDescription::default()
+        };
+
+        let restarted_nodes = (0..metadata.num_nodes_with_stake)
+            .map(|idx| ChangeNode {
+                idx,
+                updown: NodeAction::RestartDownFromStorage(0),
+            })
+            .collect();
+        metadata.spinning_properties = SpinningTaskDescription {
+            node_changes: vec![(45, restarted_nodes)],
+        };
+        metadata.completion_task_description =
+            CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(120),
+                },
+            );
+        metadata.overall_safety_properties = OverallSafetyPropertiesDescription {
+            // Keep deciding well into epoch 7, whose DRB result is computed after the restart
+            num_successful_views: 70,
+            num_failed_views: 15,
+            ..Default::default()
+        };
+
+        metadata
+    },
+);
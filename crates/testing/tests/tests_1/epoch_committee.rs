@@ -0,0 +1,279 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::traits::election::epoch_committee::EpochCommittee;
+use hotshot_example_types::{
+    node_types::{EpochsTestVersions, MemoryImpl, TestEpochCommitteeTypes},
+    state_types::StakeTableUpdates,
+};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    test_builder::TestDescription,
+    test_runner::Node,
+    test_task::{AnyTestTaskState, TestResult, TestTaskState, TestTaskStateSeed},
+};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    event::{Event, EventType},
+    signature_key::BLSPubKey,
+    traits::{election::Membership, network::Topic, node_implementation::ConsensusTime},
+    PeerConfig, ValidatorConfig,
+};
+use primitive_types::U256;
+
+type Types = TestEpochCommitteeTypes;
+
+fn peer_config(node_id: u64) -> PeerConfig<BLSPubKey> {
+    staked_peer_config(node_id, 1)
+}
+
+fn staked_peer_config(node_id: u64, stake: u64) -> PeerConfig<BLSPubKey> {
+    ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], node_id, stake, false)
+        .public_config()
+}
+
+fn keys(node_ids: impl IntoIterator<Item = u64>) -> BTreeSet<BLSPubKey> {
+    node_ids
+        .into_iter()
+        .map(|node_id| peer_config(node_id).stake_table_entry.stake_key)
+        .collect()
+}
+
+#[test]
+fn test_epoch_committee_rotates_stake_table() {
+    let genesis: Vec<_> = (0..10).map(peer_config).collect();
+    let quorum = <EpochCommittee<Types> as Membership<Types>>::new(
+        genesis.clone(),
+        genesis.clone(),
+        Topic::Global,
+    );
+    let da = <EpochCommittee<Types> as Membership<Types>>::new(
+        genesis.clone(),
+        genesis[..4].to_vec(),
+        Topic::Da,
+    );
+    let view = ViewNumber::new(0);
+    let epoch = EpochNumber::new;
+
+    // Nodes 7 to 9 leave from epoch 3, and node 10 joins
+    let update: Vec<_> = (0..7).chain([10]).map(peer_config).collect();
+    // Clones share the stake tables
+    quorum.clone().update_stake_table(epoch(3), update.clone());
+    da.clone().update_stake_table(epoch(3), update);
+
+    for e in 1..=2 {
+        assert_eq!(quorum.committee_members(view, epoch(e)), keys(0..10));
+        assert_eq!(quorum.total_nodes(epoch(e)), 10);
+        assert_eq!(quorum.success_threshold(epoch(e)), U256::from(7));
+        assert_eq!(quorum.failure_threshold(epoch(e)), U256::from(4));
+        assert_eq!(quorum.upgrade_threshold(epoch(e)), U256::from(9));
+    }
+    // The new stake table stays in effect until the next update
+    for e in 3..=5 {
+        assert_eq!(
+            quorum.committee_members(view, epoch(e)),
+            keys((0..7).chain([10]))
+        );
+        assert_eq!(quorum.total_nodes(epoch(e)), 8);
+        assert_eq!(quorum.success_threshold(epoch(e)), U256::from(6));
+        assert_eq!(quorum.failure_threshold(epoch(e)), U256::from(3));
+        assert_eq!(quorum.upgrade_threshold(epoch(e)), U256::from(7));
+        assert!(quorum.has_stake(&peer_config(10).stake_table_entry.stake_key, epoch(e)));
+        assert!(!quorum.has_stake(&peer_config(8).stake_table_entry.stake_key, epoch(e)));
+        assert!(quorum
+            .stake(&peer_config(9).stake_table_entry.stake_key, epoch(e))
+            .is_none());
+    }
+
+    // Leaders rotate among the validators of each epoch, and the DA leader stays the quorum leader
+    for v in 0..40 {
+        let view = ViewNumber::new(v);
+        assert!(keys(0..10).contains(&quorum.leader(view, epoch(1)).unwrap()));
+        assert!(keys((0..7).chain([10])).contains(&quorum.leader(view, epoch(3)).unwrap()));
+        for e in 1..=4 {
+            assert_eq!(
+                da.leader(view, epoch(e)).unwrap(),
+                quorum.leader(view, epoch(e)).unwrap()
+            );
+        }
+    }
+    assert_eq!(
+        (0..40)
+            .map(|v| quorum.leader(ViewNumber::new(v), epoch(3)).unwrap())
+            .collect::<BTreeSet<_>>(),
+        keys((0..7).chain([10]))
+    );
+
+    // The DA committee keeps its size, and its members are still validators
+    assert_eq!(da.committee_members(view, epoch(3)), keys(0..4));
+    assert_eq!(da.stake_table_epochs(), vec![epoch(0), epoch(3)]);
+}
+
+#[test]
+fn test_epoch_committee_rotates_da_committee() {
+    let genesis: Vec<_> = (0..10).map(peer_config).collect();
+    let da = <EpochCommittee<Types> as Membership<Types>>::new(
+        genesis.clone(),
+        genesis[..4].to_vec(),
+        Topic::Da,
+    );
+    let view = ViewNumber::new(0);
+    let epoch = EpochNumber::new;
+
+    // Nodes 0 and 1 leave from epoch 3, and nodes 7 to 9 stake more than everyone else
+    let update: Vec<_> = (2..7)
+        .map(peer_config)
+        .chain((7..10).map(|node_id| staked_peer_config(node_id, 5)))
+        .collect();
+    da.update_stake_table(epoch(3), update);
+    // From epoch 5, everyone stakes the same again
+    da.update_stake_table(epoch(5), (4..10).map(peer_config).collect());
+
+    for e in 1..=2 {
+        assert_eq!(da.committee_members(view, epoch(e)), keys(0..4));
+    }
+    // The nodes with the most stake make up the committee, then the earliest in the stake table
+    for e in 3..=4 {
+        assert_eq!(da.committee_members(view, epoch(e)), keys([7, 8, 9, 2]));
+        assert_eq!(da.total_nodes(epoch(e)), 4);
+        assert_eq!(da.success_threshold(epoch(e)), U256::from(11));
+        assert!(da
+            .stake(&peer_config(0).stake_table_entry.stake_key, epoch(e))
+            .is_none());
+    }
+    assert_eq!(da.committee_members(view, epoch(5)), keys(4..8));
+    assert_eq!(da.committee_members(view, epoch(6)), keys(4..8));
+    assert_eq!(da.stake_table_epochs(), vec![epoch(0), epoch(3), epoch(5)]);
+
+    // Any validator can still lead
+    assert_eq!(
+        (0..40)
+            .map(|v| da.leader(ViewNumber::new(v), epoch(3)).unwrap())
+            .collect::<BTreeSet<_>>(),
+        keys(2..10)
+    );
+}
+
+/// Checks that each node which decides the last block of an epoch `e` setting a stake table has
+/// that stake table in effect for epoch `e + 2`.
+struct StakeTableCheck {
+    updates: StakeTableUpdates,
+    epoch_height: u64,
+}
+
+struct StakeTableCheckTask {
+    handles: Arc<RwLock<Vec<Node<Types, MemoryImpl, EpochsTestVersions>>>>,
+    updates: StakeTableUpdates,
+    epoch_height: u64,
+    /// The heights of the updates we have seen decided
+    decided: BTreeSet<u64>,
+    errors: Vec<String>,
+}
+
+#[async_trait]
+impl TestTaskStateSeed<Types, MemoryImpl, EpochsTestVersions> for StakeTableCheck {
+    async fn into_state(
+        self: Box<Self>,
+        handles: Arc<RwLock<Vec<Node<Types, MemoryImpl, EpochsTestVersions>>>>,
+    ) -> AnyTestTaskState<Types> {
+        Box::new(StakeTableCheckTask {
+            handles,
+            updates: self.updates,
+            epoch_height: self.epoch_height,
+            decided: BTreeSet::new(),
+            errors: Vec::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl TestTaskState for StakeTableCheckTask {
+    type Event = Event<Types>;
+
+    async fn handle_event(&mut self, (event, id): (Self::Event, usize)) -> Result<()> {
+        let EventType::Decide { leaf_chain, .. } = event.event else {
+            return Ok(());
+        };
+
+        let handles = self.handles.read().await;
+        let membership = &handles[id].handle.memberships.quorum_membership;
+        for leaf_info in leaf_chain.iter() {
+            let height = leaf_info.leaf.height();
+            let Some(update) = self.updates.get(&height) else {
+                continue;
+            };
+            self.decided.insert(height);
+
+            let epoch = EpochNumber::new(height / self.epoch_height + 2);
+            let expected = keys(update.iter().map(|(node_id, _)| *node_id));
+            let actual = membership.committee_members(event.view_number, epoch);
+            if actual != expected {
+                self.errors.push(format!(
+                    "Node {id} decided block {height}, but has {} validators in epoch {epoch} \
+                     instead of {}",
+                    actual.len(),
+                    expected.len()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check(&self) -> TestResult {
+        let undecided: Vec<_> = self
+            .updates
+            .keys()
+            .filter(|height| !self.decided.contains(height))
+            .collect();
+        if !undecided.is_empty() {
+            return TestResult::Fail(Box::new(format!(
+                "The stake table updates at heights {undecided:?} were never decided"
+            )));
+        }
+        if !self.errors.is_empty() {
+            return TestResult::Fail(Box::new(self.errors.clone()));
+        }
+        TestResult::Pass
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_validators_join_and_leave() {
+    hotshot::helpers::initialize_logging();
+
+    let metadata = TestDescription::<Types, MemoryImpl, EpochsTestVersions> {
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_millis(100000),
+            },
+        ),
+        epoch_height: 10,
+        // Nodes 4 and 5 leave in epoch 3, and join again in epoch 5
+        stake_table_updates: [
+            (10, (0..4).map(|node_id| (node_id, 1)).collect()),
+            (30, (0..6).map(|node_id| (node_id, 1)).collect()),
+        ]
+        .into(),
+        ..TestDescription::default()
+    };
+    let check = StakeTableCheck {
+        updates: metadata.stake_table_updates.clone(),
+        epoch_height: metadata.epoch_height,
+    };
+
+    metadata
+        .gen_launcher_with_tasks(0, vec![Box::new(check)])
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
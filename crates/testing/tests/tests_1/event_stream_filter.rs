@@ -0,0 +1,161 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use futures::{Stream, StreamExt};
+use hotshot::types::{Event, EventFilter, EventKind, EventType, EventsLagged};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    data::ViewNumber,
+    signature_key::BLSPubKey,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+};
+use tokio::time::{sleep, timeout};
+
+/// Views used by the events in these tests, far from anything the node emits by itself
+const FIRST_VIEW: u64 = 1000;
+
+fn view_timeout(view: u64) -> Event<TestTypes> {
+    Event {
+        view_number: ViewNumber::new(view),
+        event: EventType::ViewTimeout {
+            view_number: ViewNumber::new(view),
+        },
+    }
+}
+
+fn external_message(view: u64, sender: BLSPubKey) -> Event<TestTypes> {
+    Event {
+        view_number: ViewNumber::new(view),
+        event: EventType::ExternalMessageReceived {
+            sender,
+            data: vec![view as u8],
+        },
+    }
+}
+
+fn key(node_id: u64) -> BLSPubKey {
+    BLSPubKey::generated_from_seed_indexed([0u8; 32], node_id).0
+}
+
+/// The views of the next `count` events of `stream`
+async fn next_views(
+    stream: &mut (impl Stream<Item = Result<Event<TestTypes>, EventsLagged>> + Unpin),
+    count: usize,
+) -> Vec<u64> {
+    let mut views = Vec::new();
+    for _ in 0..count {
+        let event = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("Timed out waiting for an event")
+            .expect("The stream ended")
+            .expect("The subscriber lagged");
+        views.push(*event.view_number);
+    }
+    views
+}
+
+#[test]
+fn test_event_filter_matches() {
+    let all = EventFilter::<TestTypes>::default();
+    assert!(all.matches(&view_timeout(1)));
+    assert!(all.matches(&external_message(1, key(0))));
+
+    let filter = EventFilter::<TestTypes>::default()
+        .kinds([EventKind::ViewTimeout, EventKind::ExternalMessageReceived])
+        .from_view(ViewNumber::new(2))
+        .to_view(ViewNumber::new(4));
+    assert!(!filter.matches(&view_timeout(1)));
+    assert!(filter.matches(&view_timeout(2)));
+    assert!(filter.matches(&external_message(4, key(0))));
+    assert!(!filter.matches(&view_timeout(5)));
+    assert!(!filter.matches(&Event {
+        view_number: ViewNumber::new(3),
+        event: EventType::ViewFinished {
+            view_number: ViewNumber::new(3),
+        },
+    }));
+
+    // Events without a sender never match a sender filter
+    let filter = EventFilter::<TestTypes>::default().senders([key(0)]);
+    assert!(filter.matches(&external_message(1, key(0))));
+    assert!(!filter.matches(&external_message(1, key(1))));
+    assert!(!filter.matches(&view_timeout(1)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_filtered_event_stream() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let sender = handle.external_channel_sender();
+
+    let mut timeouts = handle.event_stream_filtered(
+        EventFilter::default()
+            .kinds([EventKind::ViewTimeout])
+            .from_view(ViewNumber::new(FIRST_VIEW + 2)),
+    );
+    let mut from_node_0 = handle.event_stream_filtered(
+        EventFilter::default()
+            .from_view(ViewNumber::new(FIRST_VIEW))
+            .senders([key(0)]),
+    );
+
+    for view in FIRST_VIEW..FIRST_VIEW + 6 {
+        sender.broadcast(view_timeout(view)).await.unwrap();
+        sender
+            .broadcast(external_message(view, key(view % 2)))
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        next_views(&mut timeouts, 4).await,
+        (FIRST_VIEW + 2..FIRST_VIEW + 6).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        next_views(&mut from_node_0, 3).await,
+        vec![FIRST_VIEW, FIRST_VIEW + 2, FIRST_VIEW + 4]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bounded_event_stream_reports_lag() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let sender = handle.external_channel_sender();
+
+    let mut events = handle.event_stream_filtered(
+        EventFilter::default()
+            .kinds([EventKind::ViewTimeout])
+            .from_view(ViewNumber::new(FIRST_VIEW))
+            .bounded(NonZeroUsize::new(2).unwrap()),
+    );
+
+    // Nothing reads the stream while ten matching events arrive
+    for view in FIRST_VIEW..FIRST_VIEW + 10 {
+        sender.broadcast(view_timeout(view)).await.unwrap();
+    }
+    sleep(Duration::from_millis(500)).await;
+
+    // Only the two latest fit in the queue, and the stream says what was dropped
+    let lag = timeout(Duration::from_secs(5), events.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lag.unwrap_err(), EventsLagged { missed: 8 });
+    assert_eq!(
+        next_views(&mut events, 2).await,
+        vec![FIRST_VIEW + 8, FIRST_VIEW + 9]
+    );
+}
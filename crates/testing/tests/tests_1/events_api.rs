@@ -0,0 +1,342 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use futures::{Stream, StreamExt};
+use hotshot_events_api::{
+    v0_1::{
+        client::EventsClient,
+        events::{define_api, parse_kinds, Error, EventsError, Options},
+        sse::serve_sse,
+        streamer::EventsStreamer,
+        Version,
+    },
+    EVENTS_API_MODULE,
+};
+use hotshot_example_types::{
+    node_types::{TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_types::{
+    data::{Leaf, Leaf2, ViewNumber},
+    event::{Event, EventKind, EventType, LeafInfo},
+    signature_key::BLSPubKey,
+    simple_certificate::QuorumCertificate,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+};
+use tide_disco::{App, Url};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    spawn,
+    time::timeout,
+};
+use vbs::version::StaticVersionType;
+
+fn external_message(view: u64) -> Event<TestTypes> {
+    Event {
+        view_number: ViewNumber::new(view),
+        event: EventType::ExternalMessageReceived {
+            sender: BLSPubKey::generated_from_seed_indexed([0u8; 32], 0).0,
+            data: view.to_le_bytes().to_vec(),
+        },
+    }
+}
+
+fn view_timeout(view: u64) -> Event<TestTypes> {
+    Event {
+        view_number: ViewNumber::new(view),
+        event: EventType::ViewTimeout {
+            view_number: ViewNumber::new(view),
+        },
+    }
+}
+
+/// A `Decide` in `view` of a leaf at `height`
+async fn decide(view: u64, height: u64) -> Event<TestTypes> {
+    let mut leaf: Leaf2<TestTypes> = Leaf::genesis(
+        &TestValidatedState::default(),
+        &TestInstanceState::default(),
+    )
+    .await
+    .into();
+    leaf.block_header_mut().block_number = height;
+    let qc = QuorumCertificate::genesis::<TestVersions>(
+        &TestValidatedState::default(),
+        &TestInstanceState::default(),
+    )
+    .await
+    .to_qc2();
+    Event {
+        view_number: ViewNumber::new(view),
+        event: EventType::Decide {
+            leaf_chain: Arc::new(vec![LeafInfo::new(
+                leaf,
+                Arc::new(TestValidatedState::default()),
+                None,
+                None,
+            )]),
+            qc: Arc::new(qc),
+            block_size: None,
+        },
+    }
+}
+
+/// Serve `streamer` and return a client connected to it
+async fn serve(streamer: EventsStreamer<TestTypes>) -> EventsClient<TestTypes> {
+    let port = portpicker::pick_unused_port().expect("No free ports");
+    let url = Url::parse(&format!("http://localhost:{port}")).expect("Valid URL");
+
+    let api = define_api::<EventsStreamer<TestTypes>, TestTypes>(&Options::default())
+        .expect("Failed to construct the events API");
+    let mut app: App<EventsStreamer<TestTypes>, Error> = App::with_state(streamer);
+    app.register_module(EVENTS_API_MODULE, api)
+        .expect("Failed to register the events API");
+    spawn(app.serve(url.clone(), Version::instance()));
+
+    let client = EventsClient::new(url);
+    assert!(client.connect(Duration::from_secs(5)).await);
+    client
+}
+
+/// The views of the next `count` events of `events`
+async fn next_views(
+    events: &mut (impl Stream<Item = Result<Event<TestTypes>, Error>> + Unpin),
+    count: usize,
+) -> Vec<u64> {
+    let mut views = Vec::new();
+    for _ in 0..count {
+        let event = timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("Timed out waiting for an event")
+            .expect("The stream ended")
+            .expect("Failed to receive an event");
+        views.push(*event.view_number);
+    }
+    views
+}
+
+#[test]
+fn test_parse_event_kinds() {
+    assert_eq!(
+        parse_kinds("decide,da_proposal").unwrap(),
+        BTreeSet::from([EventKind::Decide, EventKind::DaProposal])
+    );
+    // Only the streamed kinds can be asked for
+    assert!(matches!(
+        parse_kinds("decide,view_timeout"),
+        Err(EventsError::UnknownKind(kind)) if kind == "view_timeout"
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_events_api_streams_retained_and_new_events() {
+    hotshot::helpers::initialize_logging();
+
+    let streamer = EventsStreamer::<TestTypes>::new(5, 1_000, 100);
+    let client = serve(streamer.clone()).await;
+
+    for view in 1..=3 {
+        streamer.handle_event(external_message(view)).await;
+        // Not one of the streamed kinds
+        streamer.handle_event(view_timeout(view)).await;
+    }
+
+    let mut events = client.subscribe(Some(2), None).await.unwrap();
+    assert_eq!(next_views(&mut events, 2).await, vec![2, 3]);
+    streamer.handle_event(external_message(4)).await;
+    assert_eq!(next_views(&mut events, 1).await, vec![4]);
+
+    assert_eq!(
+        client
+            .history(1, None)
+            .await
+            .unwrap()
+            .iter()
+            .map(|event| *event.view_number)
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3, 4]
+    );
+    assert!(client
+        .history(1, Some(&BTreeSet::from([EventKind::Decide])))
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Only the last 5 views are kept
+    assert_eq!(client.oldest_view().await.unwrap(), None);
+    for view in 5..=10 {
+        streamer.handle_event(external_message(view)).await;
+    }
+    assert_eq!(client.oldest_view().await.unwrap(), Some(5));
+    assert!(client.history(4, None).await.is_err());
+    assert_eq!(
+        client.history(5, None).await.unwrap().len(),
+        6,
+        "Views 5 to 10 should still be retained"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_events_client_misses_and_repeats_nothing() {
+    hotshot::helpers::initialize_logging();
+
+    // Subscribers which fall more than 2 events behind are disconnected, and the client has to
+    // resume
+    let streamer = EventsStreamer::<TestTypes>::new(100, 1_000, 2);
+    let client = serve(streamer.clone()).await;
+
+    for view in 1..=3 {
+        streamer.handle_event(external_message(view)).await;
+    }
+    let mut events = Box::pin(client.events(2, None));
+    assert_eq!(next_views(&mut events, 2).await, vec![2, 3]);
+
+    // Nothing reads the events while they arrive
+    for view in 4..=50 {
+        streamer.handle_event(external_message(view)).await;
+    }
+    assert_eq!(
+        next_views(&mut events, 47).await,
+        (4..=50).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_events_client_resumes_after_lagging_decide() {
+    hotshot::helpers::initialize_logging();
+
+    let streamer = EventsStreamer::<TestTypes>::new(100, 1_000, 2);
+    let client = serve(streamer.clone()).await;
+
+    for view in 1..=3 {
+        streamer.handle_event(external_message(view)).await;
+    }
+    let mut events = Box::pin(client.events(1, None));
+    let external = BTreeSet::from([EventKind::ExternalMessageReceived]);
+    let mut external_events = Box::pin(client.events(1, Some(external)));
+    assert_eq!(next_views(&mut events, 3).await, vec![1, 2, 3]);
+    assert_eq!(next_views(&mut external_events, 3).await, vec![1, 2, 3]);
+
+    // Both streams fall behind and resume. The `Decide` is for a view long before the events
+    // around it, and must not be skipped.
+    for view in 4..=30 {
+        streamer.handle_event(external_message(view)).await;
+    }
+    streamer.handle_event(decide(5, 1).await).await;
+    for view in 31..=40 {
+        streamer.handle_event(external_message(view)).await;
+    }
+
+    let mut expected: Vec<_> = (4..=30).collect();
+    expected.push(5);
+    expected.extend(31..=40);
+    assert_eq!(next_views(&mut events, expected.len()).await, expected);
+    // The `Decide` is only used to resume
+    assert_eq!(
+        next_views(&mut external_events, 37).await,
+        (4..=40).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_events_streamer_caps_retained_events() {
+    hotshot::helpers::initialize_logging();
+
+    // Every view is in range, but only 5 events are kept
+    let streamer = EventsStreamer::<TestTypes>::new(100, 5, 100);
+    let client = serve(streamer.clone()).await;
+
+    for view in 1..=10 {
+        streamer.handle_event(external_message(view)).await;
+    }
+    assert_eq!(client.oldest_view().await.unwrap(), Some(6));
+    assert!(client.history(5, None).await.is_err());
+    assert_eq!(client.history(6, None).await.unwrap().len(), 5);
+
+    streamer.handle_event(decide(11, 1).await).await;
+    streamer.handle_event(external_message(12)).await;
+    let mut events = client.subscribe_after_decide(1, None).await.unwrap();
+    assert_eq!(next_views(&mut events, 1).await, vec![12]);
+    assert!(client.subscribe_after_decide(0, None).await.is_err());
+}
+
+/// Read from `stream` until what was read contains `expected`
+async fn read_until(stream: &mut TcpStream, expected: &str) -> String {
+    let mut read = Vec::new();
+    let mut buffer = [0u8; 4096];
+    while !String::from_utf8_lossy(&read).contains(expected) {
+        let count = timeout(Duration::from_secs(5), stream.read(&mut buffer))
+            .await
+            .expect("Timed out waiting for events")
+            .expect("Failed to read events");
+        assert!(count > 0, "The connection closed");
+        read.extend_from_slice(&buffer[..count]);
+    }
+    String::from_utf8(read).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_events_served_as_server_sent_events() {
+    hotshot::helpers::initialize_logging();
+
+    let streamer = EventsStreamer::<TestTypes>::new(100, 1_000, 100);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    spawn(serve_sse::<TestTypes, _>(streamer.clone(), listener));
+
+    streamer.handle_event(external_message(1)).await;
+    streamer.handle_event(decide(2, 1).await).await;
+    streamer.handle_event(external_message(3)).await;
+
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(
+            b"GET /hotshot-events/events/from/1/kinds/external_message_received HTTP/1.1\r\n\
+              Host: localhost\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let response = read_until(&mut stream, "id: 1:1\n").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: text/event-stream\r\n"));
+    // The `Decide` is not sent, but the event after it can be resumed from
+    assert_eq!(
+        response
+            .matches("event: external_message_received\n")
+            .count(),
+        2
+    );
+    assert!(!response.contains("event: decide\n"));
+
+    // Resuming after the last event received only sends the new ones
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(
+            b"GET /hotshot-events/events/kinds/external_message_received HTTP/1.1\r\n\
+              Host: localhost\r\nLast-Event-ID: 1:1\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    streamer.handle_event(external_message(4)).await;
+    let response = read_until(&mut stream, "id: 1:2\n").await;
+    assert_eq!(
+        response
+            .matches("event: external_message_received\n")
+            .count(),
+        1
+    );
+
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(b"GET /hotshot-events/events/after/7 HTTP/1.1\r\n\r\n")
+        .await
+        .unwrap();
+    assert!(read_until(&mut stream, "\r\n")
+        .await
+        .starts_with("HTTP/1.1 404 Not Found"));
+}
@@ -0,0 +1,389 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{fs, sync::Arc};
+
+use futures::StreamExt;
+use hotshot::traits::{implementations::FileSystemStorage, RecoverableStorage, RetentionPolicy};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+    storage_types::TestStorage,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::{QuorumProposal, ViewNumber},
+    event::{HotShotAction, LeafInfo},
+    message::{convert_proposal, Proposal},
+    traits::{
+        block_contents::vid_commitment, node_implementation::ConsensusTime, storage::Storage,
+    },
+    vid::VidCodeRate,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_system_storage_survives_restart() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::<TestTypes>::new(dir.path()).unwrap();
+
+    for view in &views {
+        storage
+            .append_proposal2(&view.quorum_proposal)
+            .await
+            .unwrap();
+        storage.append_vid(&view.vid_proposal.0[0]).await.unwrap();
+        let vid_commit = vid_commitment(
+            &view.da_proposal.data.encoded_transactions,
+            1,
+            VidCodeRate::default(),
+        );
+        storage
+            .append_da(&view.da_proposal, vid_commit)
+            .await
+            .unwrap();
+        storage
+            .update_high_qc2(view.quorum_proposal.data.justify_qc.clone())
+            .await
+            .unwrap();
+    }
+    storage
+        .record_action(ViewNumber::new(3), HotShotAction::Vote)
+        .await
+        .unwrap();
+    // Actions that don't protect against equivocation, and stale views, are ignored.
+    storage
+        .record_action(ViewNumber::new(5), HotShotAction::DaVote)
+        .await
+        .unwrap();
+    storage
+        .record_action(ViewNumber::new(2), HotShotAction::Propose)
+        .await
+        .unwrap();
+
+    // Simulate a crash in the middle of a write.
+    fs::write(dir.path().join("high_qc2.tmp"), b"torn").unwrap();
+    drop(storage);
+
+    let storage = FileSystemStorage::<TestTypes>::new(dir.path()).unwrap();
+    assert!(!dir.path().join("high_qc2.tmp").exists());
+
+    let proposals = storage.load_proposals2().await.unwrap();
+    assert_eq!(proposals.len(), views.len());
+    for view in &views {
+        assert_eq!(proposals[&view.view_number], view.quorum_proposal);
+        assert_eq!(
+            storage
+                .load_vid_shares(view.view_number)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(storage
+            .load_da_proposal(view.view_number)
+            .await
+            .unwrap()
+            .is_some());
+    }
+    assert_eq!(
+        storage.load_high_qc2().await.unwrap(),
+        Some(views[2].quorum_proposal.data.justify_qc.clone())
+    );
+    assert_eq!(
+        storage.load_last_actioned_view().await.unwrap(),
+        ViewNumber::new(3)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_system_storage_migration() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::<TestTypes>::new(dir.path()).unwrap();
+
+    for view in &views {
+        let legacy_proposal: Proposal<TestTypes, QuorumProposal<TestTypes>> =
+            convert_proposal(view.quorum_proposal.clone());
+        storage.append_proposal(&legacy_proposal).await.unwrap();
+    }
+    assert!(storage.load_proposals2().await.unwrap().is_empty());
+
+    storage
+        .migrate_consensus(Into::into, convert_proposal)
+        .await
+        .unwrap();
+
+    let proposals = storage.load_proposals2().await.unwrap();
+    assert_eq!(proposals.len(), views.len());
+    for view in &views {
+        assert_eq!(
+            proposals[&view.view_number].data.view_number,
+            view.view_number
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_system_storage_gc() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(4).collect::<Vec<_>>().await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::<TestTypes>::new(dir.path())
+        .unwrap()
+        .with_retention_policy(RetentionPolicy {
+            views_to_keep: 1,
+            prune_vid_on_decide: true,
+            archive_decided_leaves: true,
+        });
+
+    for view in &views {
+        storage
+            .append_proposal2(&view.quorum_proposal)
+            .await
+            .unwrap();
+        storage.append_vid(&view.vid_proposal.0[0]).await.unwrap();
+    }
+
+    let decided = &views[2];
+    let leaf_info = LeafInfo::new(
+        decided.leaf.clone(),
+        Arc::new(TestValidatedState::default()),
+        None,
+        None,
+    );
+    storage.update_decided_leaf(&leaf_info).await.unwrap();
+    storage.gc(decided.view_number).await.unwrap();
+
+    // Proposals are kept for one view before the decided one.
+    let proposals = storage.load_proposals2().await.unwrap();
+    assert_eq!(
+        proposals.keys().copied().collect::<Vec<_>>(),
+        vec![
+            views[1].view_number,
+            views[2].view_number,
+            views[3].view_number
+        ]
+    );
+
+    // VID shares are dropped as soon as their view is decided.
+    for view in &views[..3] {
+        assert!(storage
+            .load_vid_shares(view.view_number)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+    assert!(!storage
+        .load_vid_shares(views[3].view_number)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // The decided leaf is archived, and survives garbage collection.
+    assert_eq!(
+        storage
+            .load_archived_leaf(decided.view_number)
+            .await
+            .unwrap()
+            .map(|info| info.leaf),
+        Some(decided.leaf.clone())
+    );
+    assert_eq!(
+        storage
+            .load_decided_leaf()
+            .await
+            .unwrap()
+            .map(|info| info.leaf),
+        Some(decided.leaf.clone())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_test_storage_honours_retention_policy() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(4).collect::<Vec<_>>().await;
+
+    let storage = TestStorage::<TestTypes>::default().with_retention_policy(RetentionPolicy {
+        views_to_keep: 1,
+        ..RetentionPolicy::default()
+    });
+
+    for view in &views {
+        storage
+            .append_proposal2(&view.quorum_proposal)
+            .await
+            .unwrap();
+        let leaf_info = LeafInfo::new(
+            view.leaf.clone(),
+            Arc::new(TestValidatedState::default()),
+            None,
+            None,
+        );
+        storage.update_decided_leaf(&leaf_info).await.unwrap();
+    }
+    storage.gc(views[2].view_number).await.unwrap();
+
+    // Proposals are kept for one view before the decided one.
+    assert_eq!(
+        storage
+            .proposals_cloned()
+            .await
+            .keys()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![
+            views[1].view_number,
+            views[2].view_number,
+            views[3].view_number
+        ]
+    );
+
+    // Without an archive, decided leaves are dropped along with them.
+    assert_eq!(
+        storage
+            .load_leaf_at_height(views[0].leaf.height())
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        storage
+            .load_leaf_at_height(views[1].leaf.height())
+            .await
+            .unwrap(),
+        Some(views[1].leaf.clone())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_system_storage_serves_decided_data() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(4).collect::<Vec<_>>().await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let archive = FileSystemStorage::<TestTypes>::new(dir.path().join("archive"))
+        .unwrap()
+        .with_retention_policy(RetentionPolicy {
+            archive_decided_leaves: true,
+            ..RetentionPolicy::default()
+        });
+    let latest_only = FileSystemStorage::<TestTypes>::new(dir.path().join("latest")).unwrap();
+
+    for storage in [&archive, &latest_only] {
+        for view in &views[..3] {
+            let leaf_info = LeafInfo::new(
+                view.leaf.clone(),
+                Arc::new(TestValidatedState::default()),
+                None,
+                None,
+            );
+            storage.update_decided_leaf(&leaf_info).await.unwrap();
+        }
+        let vid_commit = vid_commitment(
+            &views[3].da_proposal.data.encoded_transactions,
+            1,
+            VidCodeRate::default(),
+        );
+        storage
+            .append_da(&views[3].da_proposal, vid_commit)
+            .await
+            .unwrap();
+    }
+
+    // Every archived leaf can be found by height, along with its payload and certificate.
+    for (i, view) in views[..3].iter().enumerate() {
+        assert_eq!(
+            archive
+                .load_leaf_at_height(view.leaf.height())
+                .await
+                .unwrap(),
+            Some(view.leaf.clone())
+        );
+        assert_eq!(
+            archive.load_payload(view.view_number).await.unwrap(),
+            view.leaf.block_payload()
+        );
+        if i > 0 {
+            assert_eq!(
+                archive
+                    .load_quorum_certificate(views[i - 1].view_number)
+                    .await
+                    .unwrap(),
+                Some(view.leaf.justify_qc())
+            );
+        }
+    }
+
+    // Without an archive, only the most recently decided leaf is kept.
+    assert_eq!(
+        latest_only
+            .load_leaf_at_height(views[2].leaf.height())
+            .await
+            .unwrap(),
+        Some(views[2].leaf.clone())
+    );
+    assert_eq!(
+        latest_only
+            .load_leaf_at_height(views[1].leaf.height())
+            .await
+            .unwrap(),
+        None
+    );
+
+    // Payloads of recent views are recovered from their DA proposals.
+    for storage in [&archive, &latest_only] {
+        assert_eq!(
+            storage.load_payload(views[3].view_number).await.unwrap(),
+            views[3].leaf.block_payload()
+        );
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    local_builder::LocalBuilder,
+    mempool::{Mempool, TransactionSource},
+    transactions::TransactionTaskState,
+};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    data::ViewNumber,
+    signature_key::BuilderKey,
+    traits::{
+        block_contents::vid_commitment, election::Membership, node_implementation::ConsensusTime,
+        signature_key::BuilderSignatureKey,
+    },
+    MempoolConfig,
+};
+
+fn local_builder(max_block_size: u64) -> LocalBuilder<TestTypes> {
+    let (public_key, private_key) = BuilderKey::generated_from_seed_indexed([1; 32], 0);
+    LocalBuilder::new(public_key, private_key, max_block_size)
+}
+
+#[test]
+fn test_local_builder_selects_transactions_which_fit() {
+    let builder = local_builder(8);
+    let transactions = vec![
+        TestTransaction::new(vec![1; 4]),
+        TestTransaction::new(vec![2; 4]),
+        TestTransaction::new(vec![3; 1]),
+    ];
+
+    assert_eq!(
+        builder.select_transactions(transactions.clone()),
+        transactions[..2]
+    );
+    assert!(local_builder(3)
+        .select_transactions(transactions)
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_task_builds_block_locally_when_builders_time_out() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    state.builder_timeout = Duration::from_millis(100);
+    state.builder_clients = vec![];
+    let mempool = Mempool::new(
+        MempoolConfig::default(),
+        Arc::new(ConsensusMetricsValue::default()),
+    );
+    let transactions = vec![
+        TestTransaction::new(vec![1; 4]),
+        TestTransaction::new(vec![2; 4]),
+    ];
+    for transaction in &transactions {
+        mempool
+            .insert(transaction.clone(), TransactionSource::Local)
+            .unwrap();
+    }
+    state.mempool = Some(mempool);
+    let builder = local_builder(1024);
+    state.local_builder = Some(builder.clone());
+    let (sender, mut receiver) = broadcast(1024);
+
+    state
+        .handle_view_change_legacy(&sender, ViewNumber::new(2))
+        .await;
+
+    let event = receiver.recv().await.unwrap();
+    let HotShotEvent::BlockRecv(bundle) = event.as_ref() else {
+        panic!("Expected a block, got {event}");
+    };
+    assert_eq!(
+        *bundle.encoded_transactions,
+        TestTransaction::encode(&transactions)
+    );
+    let fee = bundle.sequencing_fees.first();
+    assert_eq!(&fee.fee_account, builder.public_key());
+    assert!(fee.fee_account.validate_fee_signature(
+        &fee.fee_signature,
+        fee.fee_amount,
+        &bundle.metadata,
+        &vid_commitment(
+            &bundle.encoded_transactions,
+            state.membership.total_nodes(state.cur_epoch),
+            state.vid_code_rate,
+        ),
+    ));
+}
@@ -0,0 +1,326 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::{
+    tasks::task_state::CreateTaskState,
+    types::{BLSPubKey, SignatureKey},
+};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    mempool::{EvictionReason, Mempool, MempoolRejection, TransactionSource, TransactionStatus},
+    transactions::TransactionTaskState,
+};
+use hotshot_testing::{
+    helpers::build_system_handle_from_launcher, test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue, traits::network::AuthenticatedPeer, MempoolConfig,
+};
+
+fn peer(node_id: u64) -> TransactionSource<BLSPubKey> {
+    TransactionSource::Peer(AuthenticatedPeer::Key(
+        BLSPubKey::generated_from_seed_indexed([0u8; 32], node_id).0,
+    ))
+}
+
+fn mempool(config: MempoolConfig) -> Mempool<TestTypes> {
+    Mempool::new(config, Arc::new(ConsensusMetricsValue::default()))
+}
+
+#[test]
+fn test_mempool_deduplicates_and_limits_size() {
+    let mempool = mempool(MempoolConfig {
+        max_bytes: 10,
+        max_bytes_per_sender: 6,
+        max_bytes_unattributed: 6,
+        max_age: Duration::from_secs(60),
+    });
+
+    let transaction = TestTransaction::new(vec![1; 4]);
+    assert_eq!(
+        mempool.insert(transaction.clone(), peer(1)),
+        Ok(transaction.commit())
+    );
+    assert_eq!(
+        mempool.insert(transaction.clone(), peer(2)),
+        Err(MempoolRejection::Duplicate)
+    );
+    assert_eq!(
+        mempool.insert(TestTransaction::new(vec![2; 7]), peer(2)),
+        Err(MempoolRejection::TooLarge)
+    );
+    assert_eq!(
+        mempool.insert(TestTransaction::new(vec![3; 3]), peer(1)),
+        Err(MempoolRejection::SenderLimit)
+    );
+    mempool
+        .insert(TestTransaction::new(vec![4; 6]), peer(2))
+        .unwrap();
+    assert_eq!(
+        mempool.insert(TestTransaction::new(vec![5; 1]), peer(3)),
+        Err(MempoolRejection::Full)
+    );
+
+    assert_eq!(mempool.pending_transactions().len(), 2);
+    assert_eq!(
+        mempool.transaction_status(&transaction.commit()),
+        Some(TransactionStatus::Pending)
+    );
+    assert_eq!(
+        mempool.transaction_status(&TestTransaction::new(vec![5; 1]).commit()),
+        None
+    );
+}
+
+#[test]
+fn test_mempool_limits_peers_but_not_local_transactions() {
+    let mempool = mempool(MempoolConfig {
+        max_bytes: 24,
+        max_bytes_per_sender: 4,
+        max_bytes_unattributed: 8,
+        max_age: Duration::from_secs(60),
+    });
+
+    // The node's own transactions only count towards the total
+    mempool
+        .insert(TestTransaction::new(vec![1; 6]), TransactionSource::Local)
+        .unwrap();
+    mempool
+        .insert(TestTransaction::new(vec![2; 4]), TransactionSource::Local)
+        .unwrap();
+
+    // Transactions delivered by an unknown peer all share one limit of their own
+    mempool
+        .insert(
+            TestTransaction::new(vec![3; 4]),
+            TransactionSource::Unattributed,
+        )
+        .unwrap();
+    mempool
+        .insert(
+            TestTransaction::new(vec![6; 4]),
+            TransactionSource::Unattributed,
+        )
+        .unwrap();
+    assert_eq!(
+        mempool.insert(
+            TestTransaction::new(vec![4; 1]),
+            TransactionSource::Unattributed
+        ),
+        Err(MempoolRejection::SenderLimit)
+    );
+    mempool
+        .insert(TestTransaction::new(vec![5; 4]), peer(1))
+        .unwrap();
+    assert_eq!(mempool.pending_transactions().len(), 5);
+}
+
+#[test]
+fn test_mempool_orders_and_evicts_by_priority() {
+    let mempool = mempool(MempoolConfig {
+        max_bytes: 6,
+        max_bytes_per_sender: 6,
+        max_bytes_unattributed: 6,
+        max_age: Duration::from_secs(60),
+    });
+
+    let low = TestTransaction::new(vec![1; 2]);
+    let high = TestTransaction::new(vec![2; 2]);
+    let medium = TestTransaction::new(vec![3; 2]);
+    mempool
+        .insert_with_priority(low.clone(), peer(1), 1)
+        .unwrap();
+    mempool
+        .insert_with_priority(high.clone(), peer(2), 10)
+        .unwrap();
+    mempool
+        .insert_with_priority(medium.clone(), peer(3), 5)
+        .unwrap();
+    assert_eq!(
+        mempool.pending_transactions(),
+        vec![high.clone(), medium.clone(), low.clone()]
+    );
+
+    // A transaction can only make room for itself by evicting ones with a lower priority
+    assert_eq!(
+        mempool.insert_with_priority(TestTransaction::new(vec![4; 2]), peer(4), 1),
+        Err(MempoolRejection::Full)
+    );
+    let higher = TestTransaction::new(vec![5; 4]);
+    mempool
+        .insert_with_priority(higher.clone(), peer(4), 20)
+        .unwrap();
+    assert_eq!(mempool.pending_transactions(), vec![higher, high]);
+    assert_eq!(
+        mempool.transaction_status(&low.commit()),
+        Some(TransactionStatus::Evicted(EvictionReason::Outbid))
+    );
+    assert_eq!(
+        mempool.transaction_status(&medium.commit()),
+        Some(TransactionStatus::Evicted(EvictionReason::Outbid))
+    );
+
+    // An evicted transaction is not a duplicate, but still has to outbid the pending ones
+    assert_eq!(
+        mempool.insert_with_priority(low, peer(1), 1),
+        Err(MempoolRejection::Full)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mempool_evicts_expired_transactions() {
+    let mempool = mempool(MempoolConfig {
+        max_age: Duration::from_millis(100),
+        ..MempoolConfig::default()
+    });
+
+    let old = TestTransaction::new(vec![1]);
+    mempool.insert(old.clone(), peer(1)).unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let new = TestTransaction::new(vec![2]);
+    mempool.insert(new.clone(), peer(1)).unwrap();
+
+    assert_eq!(mempool.pending_transactions(), vec![new]);
+    assert_eq!(
+        mempool.transaction_status(&old.commit()),
+        Some(TransactionStatus::Evicted(EvictionReason::Expired))
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_task_tracks_transactions_in_mempool() {
+    hotshot::helpers::initialize_logging();
+
+    let mut launcher =
+        TestDescription::<TestTypes, MemoryImpl, TestVersions>::default_multiple_rounds()
+            .gen_launcher(2);
+    launcher.resource_generator.config.mempool = Some(MempoolConfig {
+        max_bytes_per_sender: 8,
+        ..MempoolConfig::default()
+    });
+    let handle = build_system_handle_from_launcher(2, &launcher).await.0;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let (sender, _receiver) = broadcast(1024);
+
+    let a = TestTransaction::new(vec![1; 4]);
+    let b = TestTransaction::new(vec![2; 4]);
+    let c = TestTransaction::new(vec![3; 4]);
+    let dropped = TestTransaction::new(vec![4; 4]);
+
+    let mut generator = TestViewGenerator::generate(
+        handle.hotshot.memberships.quorum_membership.clone(),
+        handle.hotshot.memberships.da_membership.clone(),
+    );
+    generator.next().await.unwrap();
+    generator.add_transactions(vec![a.clone()]);
+    let proposed_a = generator.next().await.unwrap();
+    generator.add_transactions(vec![b.clone()]);
+    let proposed_b = generator.next().await.unwrap();
+    generator.add_transactions(vec![]);
+    let empty = generator.next().await.unwrap();
+
+    // Node 1 may only have 8 bytes pending, so the last transaction it sends is dropped
+    state
+        .handle(
+            Arc::new(HotShotEvent::TransactionsRecv(
+                vec![a.clone(), b.clone(), dropped.clone()],
+                peer(1),
+            )),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    handle.submit_transaction(c.clone()).await.unwrap();
+    assert!(handle.submit_transaction(c.clone()).await.is_err());
+    assert_eq!(
+        handle.pending_transactions(),
+        vec![a.clone(), b.clone(), c.clone()]
+    );
+    assert_eq!(handle.transaction_status(&dropped.commit()), None);
+
+    for view in [&proposed_a, &proposed_b] {
+        state
+            .handle(
+                Arc::new(HotShotEvent::DaProposalValidated(
+                    view.da_proposal.clone(),
+                    view.leader_public_key.clone(),
+                )),
+                sender.clone(),
+            )
+            .await
+            .unwrap();
+    }
+    assert_eq!(handle.pending_transactions(), vec![c.clone()]);
+    assert_eq!(
+        handle.transaction_status(&a.commit()),
+        Some(TransactionStatus::InDaProposal {
+            view: proposed_a.view_number
+        })
+    );
+
+    state
+        .handle(
+            Arc::new(HotShotEvent::LeavesDecided(vec![proposed_a.leaf.clone()])),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        handle.transaction_status(&a.commit()),
+        Some(TransactionStatus::Decided {
+            view: proposed_a.view_number,
+            height: proposed_a.leaf.height(),
+        })
+    );
+    assert_eq!(
+        handle.transaction_status(&b.commit()),
+        Some(TransactionStatus::InDaProposal {
+            view: proposed_b.view_number
+        })
+    );
+
+    // A leaf whose payload this node doesn't have may include `b`
+    let mut without_payload = empty.leaf.clone();
+    without_payload.unfill_block_payload();
+    state
+        .handle(
+            Arc::new(HotShotEvent::LeavesDecided(vec![without_payload])),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        handle.transaction_status(&b.commit()),
+        Some(TransactionStatus::InDaProposal {
+            view: proposed_b.view_number
+        })
+    );
+
+    // The view `b` was proposed in fails, so it is pending again
+    state
+        .handle(
+            Arc::new(HotShotEvent::LeavesDecided(vec![empty.leaf.clone()])),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(handle.pending_transactions(), vec![b.clone(), c]);
+    assert_eq!(
+        handle.transaction_status(&b.commit()),
+        Some(TransactionStatus::Pending)
+    );
+}
@@ -131,3 +131,58 @@ async fn test_certificate2_validity() {
 
     assert!(leaf2.parent_commitment() == parent_leaf2.commit());
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that large messages are compressed from `Versions::Compression` onwards, and survive the
+// round trip through the upgrade lock.
+async fn compressed_message_round_trip() {
+    use std::sync::Arc;
+
+    use hotshot_example_types::{
+        block_types::TestMetadata,
+        node_types::{MarketplaceUpgradeTestVersions, TestVersions},
+    };
+    use hotshot_types::{
+        data::{DaProposal, ViewNumber},
+        message::{DaConsensusMessage, Proposal, UpgradeLock},
+    };
+
+    let (sender, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let encoded_transactions: Arc<[u8]> = vec![7u8; 64 * 1024].into();
+    let signature = BLSPubKey::sign(&private_key, &encoded_transactions).unwrap();
+    let message: Message<TestTypes> = Message {
+        sender,
+        kind: MessageKind::Consensus(SequencingMessage::Da(DaConsensusMessage::DaProposal(
+            Proposal {
+                data: DaProposal {
+                    encoded_transactions,
+                    metadata: TestMetadata {
+                        num_transactions: 1,
+                    },
+                    view_number: ViewNumber::new(3),
+                },
+                signature,
+                _pd: PhantomData,
+            },
+        ))),
+    };
+
+    // `TestVersions` starts out before compression
+    let uncompressed_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let uncompressed = uncompressed_lock.serialize(&message).await.unwrap();
+
+    // `MarketplaceUpgradeTestVersions` starts out with compression
+    let compressed_lock = UpgradeLock::<TestTypes, MarketplaceUpgradeTestVersions>::new();
+    let compressed = compressed_lock.serialize(&message).await.unwrap();
+    assert!(compressed.len() < uncompressed.len() / 10);
+
+    let deserialized: Message<TestTypes> = compressed_lock.deserialize(&compressed).await.unwrap();
+    assert_eq!(deserialized, message);
+
+    // A node expecting compression rejects a message of the wrong version
+    assert!(compressed_lock
+        .deserialize::<Message<TestTypes>>(&uncompressed)
+        .await
+        .is_err());
+}
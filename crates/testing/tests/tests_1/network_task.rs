@@ -275,3 +275,332 @@ async fn test_network_storage_fail() {
     let res = timeout(Duration::from_millis(100), out_rx_internal.recv_direct()).await;
     assert!(res.is_err());
 }
+
+/// Serializes `message` and sends it directly from `network` to `recipient`
+#[cfg(test)]
+async fn send_direct(
+    network: &MemoryNetwork<<TestTypes as NodeType>::SignatureKey>,
+    upgrade_lock: &UpgradeLock<TestTypes, TestVersions>,
+    message: &hotshot_types::message::Message<TestTypes>,
+    recipient: <TestTypes as NodeType>::SignatureKey,
+) {
+    use hotshot_types::traits::network::ConnectedNetwork;
+
+    let serialized = upgrade_lock.serialize(message).await.unwrap();
+    network.direct_message(serialized, recipient).await.unwrap();
+}
+
+// Test that a node forging proposals in the name of another node gets itself banned, and never the
+// node it impersonates
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_network_reports_forger_not_impersonated_sender() {
+    use hotshot_testing::helpers::key_pair_for_id;
+    use hotshot_types::{
+        message::{DaConsensusMessage, Message, MessageKind, Proposal, SequencingMessage},
+        traits::{
+            network::{AuthenticatedPeer, ConnectedNetwork, Topic},
+            signature_key::SignatureKey,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    hotshot::helpers::initialize_logging();
+
+    let builder: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_multiple_rounds();
+    let launcher = builder.gen_launcher(0);
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+
+    let receiver = (launcher.resource_generator.channel_generator)(1).await;
+    let forger = (launcher.resource_generator.channel_generator)(2).await;
+    let honest = (launcher.resource_generator.channel_generator)(3).await;
+    let (_, receiver_key) = key_pair_for_id::<TestTypes>(1);
+    let (forger_private_key, forger_key) = key_pair_for_id::<TestTypes>(2);
+    let (honest_private_key, honest_key) = key_pair_for_id::<TestTypes>(3);
+
+    let all_nodes = launcher
+        .resource_generator
+        .config
+        .known_nodes_with_stake
+        .clone();
+    let membership =
+        <TestTypes as NodeType>::Membership::new(all_nodes.clone(), all_nodes, Topic::Global);
+    let view = TestViewGenerator::generate(membership.clone(), membership)
+        .next()
+        .await
+        .unwrap();
+    let da_proposal = view.da_proposal.data;
+    let encoded_transactions_hash = Sha256::digest(&da_proposal.encoded_transactions);
+
+    let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(100);
+    let (out_tx_external, _) = async_broadcast::broadcast(10);
+    add_network_message_test_task(
+        out_tx_internal,
+        out_tx_external,
+        upgrade_lock.clone(),
+        Arc::clone(&receiver),
+        receiver_key,
+    )
+    .await;
+
+    // Proposals claiming to be from the honest node, but signed by the forger
+    let forged_message = Message {
+        sender: honest_key,
+        kind: MessageKind::Consensus(SequencingMessage::Da(DaConsensusMessage::DaProposal(
+            Proposal {
+                data: da_proposal.clone(),
+                signature: <TestTypes as NodeType>::SignatureKey::sign(
+                    &forger_private_key,
+                    &encoded_transactions_hash,
+                )
+                .unwrap(),
+                _pd: std::marker::PhantomData,
+            },
+        ))),
+    };
+    // Penalties decay, so send one more than it would take to get banned
+    for _ in 0..5 {
+        send_direct(&forger, &upgrade_lock, &forged_message, receiver_key).await;
+    }
+
+    timeout(Duration::from_secs(1), async {
+        while !receiver.is_peer_banned(&AuthenticatedPeer::Key(forger_key)) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("forger was never banned");
+    assert!(!receiver.is_peer_banned(&AuthenticatedPeer::Key(honest_key)));
+    assert!(out_rx_internal.is_empty(), "a forged proposal was accepted");
+
+    // The impersonated node can still get its proposals through
+    let honest_message = Message {
+        sender: honest_key,
+        kind: MessageKind::Consensus(SequencingMessage::Da(DaConsensusMessage::DaProposal(
+            Proposal {
+                data: da_proposal,
+                signature: <TestTypes as NodeType>::SignatureKey::sign(
+                    &honest_private_key,
+                    &encoded_transactions_hash,
+                )
+                .unwrap(),
+                _pd: std::marker::PhantomData,
+            },
+        ))),
+    };
+    send_direct(&honest, &upgrade_lock, &honest_message, receiver_key).await;
+    let res = timeout(Duration::from_secs(1), out_rx_internal.recv_direct())
+        .await
+        .expect("timed out waiting for the honest proposal")
+        .expect("channel closed");
+    assert!(matches!(
+        res.as_ref(),
+        HotShotEvent::DaProposalRecv(_, sender) if *sender == honest_key
+    ));
+}
+
+// Test that relaying a VID share signed by its leader in response to a request is not treated as
+// misbehaviour
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_network_does_not_report_vid_relayer() {
+    use hotshot_testing::helpers::key_pair_for_id;
+    use hotshot_types::{
+        message::{DaConsensusMessage, DataMessage, Message, MessageKind, SequencingMessage},
+        traits::network::{AuthenticatedPeer, ConnectedNetwork, ResponseMessage, Topic},
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    let builder: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_multiple_rounds();
+    let launcher = builder.gen_launcher(0);
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+
+    let receiver = (launcher.resource_generator.channel_generator)(1).await;
+    let relayer = (launcher.resource_generator.channel_generator)(2).await;
+    let (_, receiver_key) = key_pair_for_id::<TestTypes>(1);
+    let (_, relayer_key) = key_pair_for_id::<TestTypes>(2);
+
+    let all_nodes = launcher
+        .resource_generator
+        .config
+        .known_nodes_with_stake
+        .clone();
+    let membership =
+        <TestTypes as NodeType>::Membership::new(all_nodes.clone(), all_nodes, Topic::Global);
+    let view = TestViewGenerator::generate(membership.clone(), membership)
+        .next()
+        .await
+        .unwrap();
+    let (shares, leader_key) = view.vid_proposal;
+    assert_ne!(leader_key, relayer_key);
+
+    let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(100);
+    let (out_tx_external, _) = async_broadcast::broadcast(10);
+    add_network_message_test_task(
+        out_tx_internal,
+        out_tx_external,
+        upgrade_lock.clone(),
+        Arc::clone(&receiver),
+        receiver_key,
+    )
+    .await;
+
+    // More responses than it would take to get the relayer banned
+    for _ in 0..5 {
+        let message = Message {
+            sender: relayer_key,
+            kind: MessageKind::Data(DataMessage::DataResponse(ResponseMessage::Found(
+                SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(shares[1].clone())),
+            ))),
+        };
+        send_direct(&relayer, &upgrade_lock, &message, receiver_key).await;
+
+        let res = timeout(Duration::from_secs(1), out_rx_internal.recv_direct())
+            .await
+            .expect("timed out waiting for the relayed share")
+            .expect("channel closed");
+        assert!(matches!(
+            res.as_ref(),
+            HotShotEvent::VidResponseRecv(sender, _) if *sender == relayer_key
+        ));
+    }
+
+    assert!(!receiver.is_peer_banned(&AuthenticatedPeer::Key(relayer_key)));
+}
+
+// Test that a response to a data request larger than honest nodes send is dropped when it is
+// deserialized, while smaller ones get through
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_network_drops_oversized_data_response() {
+    use hotshot_example_types::block_types::{TestBlockPayload, TestTransaction};
+    use hotshot_testing::helpers::key_pair_for_id;
+    use hotshot_types::{
+        message::{DataMessage, Message, MessageKind},
+        traits::network::{ResponseData, ResponseMessage},
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    let builder: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_multiple_rounds();
+    let launcher = builder.gen_launcher(0);
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+
+    let receiver = (launcher.resource_generator.channel_generator)(1).await;
+    let responder = (launcher.resource_generator.channel_generator)(2).await;
+    let (_, receiver_key) = key_pair_for_id::<TestTypes>(1);
+    let (responder_private_key, responder_key) = key_pair_for_id::<TestTypes>(2);
+
+    let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(100);
+    let (out_tx_external, _) = async_broadcast::broadcast(10);
+    add_network_message_test_task(
+        out_tx_internal,
+        out_tx_external,
+        upgrade_lock.clone(),
+        Arc::clone(&receiver),
+        receiver_key,
+    )
+    .await;
+
+    // Correctly signed payloads just over and well under the limit
+    let response = |size| {
+        let data = ResponseData::<TestTypes>::Payload {
+            view: ViewNumber::new(1),
+            payload: TestBlockPayload {
+                transactions: vec![TestTransaction::new(vec![0; size])],
+            },
+        };
+        let signature = data.sign(&responder_private_key).unwrap();
+        Message {
+            sender: responder_key,
+            kind: MessageKind::Data(DataMessage::DataResponse(ResponseMessage::Data {
+                data,
+                signature,
+            })),
+        }
+    };
+    send_direct(
+        &responder,
+        &upgrade_lock,
+        &response(21 * 1024 * 1024),
+        receiver_key,
+    )
+    .await;
+    send_direct(&responder, &upgrade_lock, &response(1024), receiver_key).await;
+
+    // Only the small response arrives
+    let res = timeout(Duration::from_secs(5), out_rx_internal.recv_direct())
+        .await
+        .expect("timed out waiting for the small response")
+        .expect("channel closed");
+    let HotShotEvent::DataResponseRecv(_, ResponseData::Payload { payload, .. }) = res.as_ref()
+    else {
+        panic!("Expected a data response, got {res:?}");
+    };
+    assert_eq!(payload.transactions[0].bytes().len(), 1024);
+    assert!(
+        out_rx_internal.is_empty(),
+        "an oversized response was accepted"
+    );
+}
+
+// Test that transactions are attributed to the peer which delivered them, not to the sender named
+// in the message
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_network_attributes_transactions_to_delivering_peer() {
+    use hotshot_example_types::block_types::TestTransaction;
+    use hotshot_task_impls::mempool::TransactionSource;
+    use hotshot_testing::helpers::key_pair_for_id;
+    use hotshot_types::{
+        message::{DataMessage, Message, MessageKind},
+        traits::network::AuthenticatedPeer,
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    let builder: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_multiple_rounds();
+    let launcher = builder.gen_launcher(0);
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+
+    let receiver = (launcher.resource_generator.channel_generator)(1).await;
+    let relayer = (launcher.resource_generator.channel_generator)(2).await;
+    let (_, receiver_key) = key_pair_for_id::<TestTypes>(1);
+    let (_, relayer_key) = key_pair_for_id::<TestTypes>(2);
+    let (_, claimed_key) = key_pair_for_id::<TestTypes>(3);
+
+    let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(100);
+    let (out_tx_external, _) = async_broadcast::broadcast(10);
+    add_network_message_test_task(
+        out_tx_internal,
+        out_tx_external,
+        upgrade_lock.clone(),
+        Arc::clone(&receiver),
+        receiver_key,
+    )
+    .await;
+
+    let message = Message {
+        sender: claimed_key,
+        kind: MessageKind::Data(DataMessage::SubmitTransaction(
+            TestTransaction::new(vec![1; 4]),
+            ViewNumber::new(1),
+        )),
+    };
+    send_direct(&relayer, &upgrade_lock, &message, receiver_key).await;
+
+    let res = timeout(Duration::from_secs(1), out_rx_internal.recv_direct())
+        .await
+        .expect("timed out waiting for the transaction")
+        .expect("channel closed");
+    assert!(matches!(
+        res.as_ref(),
+        HotShotEvent::TransactionsRecv(_, TransactionSource::Peer(AuthenticatedPeer::Key(key)))
+            if *key == relayer_key
+    ));
+}
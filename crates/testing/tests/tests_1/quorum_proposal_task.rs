@@ -32,6 +32,7 @@ use hotshot_types::{
         node_implementation::{ConsensusTime, Versions},
     },
     utils::BuilderCommitment,
+    vid::VidCodeRate,
 };
 use sha2::Digest;
 use vec1::vec1;
@@ -91,6 +92,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_1() {
     let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
     let builder_fee = null_block::builder_fee::<TestTypes, TestVersions>(
         quorum_membership.total_nodes(EpochNumber::new(1)),
+        VidCodeRate::default(),
         <TestVersions as Versions>::Base::VERSION,
         *ViewNumber::new(1),
     )
@@ -183,6 +185,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
     let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
     let builder_fee = null_block::builder_fee::<TestTypes, TestVersions>(
         quorum_membership.total_nodes(EpochNumber::new(1)),
+        VidCodeRate::default(),
         <TestVersions as Versions>::Base::VERSION,
         *ViewNumber::new(1),
     )
@@ -361,6 +364,7 @@ async fn test_quorum_proposal_task_qc_timeout() {
             ViewNumber::new(3),
             vec1![null_block::builder_fee::<TestTypes, TestVersions>(
                 quorum_membership.total_nodes(EpochNumber::new(1)),
+                VidCodeRate::default(),
                 <TestVersions as Versions>::Base::VERSION,
                 *ViewNumber::new(3),
             )
@@ -451,6 +455,7 @@ async fn test_quorum_proposal_task_view_sync() {
             ViewNumber::new(2),
             vec1![null_block::builder_fee::<TestTypes, TestVersions>(
                 quorum_membership.total_nodes(EpochNumber::new(1)),
+                VidCodeRate::default(),
                 <TestVersions as Versions>::Base::VERSION,
                 *ViewNumber::new(2),
             )
@@ -518,6 +523,7 @@ async fn test_quorum_proposal_task_liveness_check() {
     let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
     let builder_fee = null_block::builder_fee::<TestTypes, TestVersions>(
         quorum_membership.total_nodes(EpochNumber::new(1)),
+        VidCodeRate::default(),
         <TestVersions as Versions>::Base::VERSION,
         *ViewNumber::new(1),
     )
@@ -0,0 +1,87 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use hotshot_task_impls::request_scheduler::{
+    RequestScheduler, INITIAL_REQUEST_TIMEOUT, MAX_REQUEST_TIMEOUT, MIN_REQUEST_TIMEOUT,
+};
+use hotshot_types::consensus::ConsensusMetricsValue;
+
+fn scheduler() -> RequestScheduler<u64> {
+    RequestScheduler::new(Arc::new(ConsensusMetricsValue::default()))
+}
+
+#[test]
+fn test_request_timeout_follows_round_trip_times() {
+    let scheduler = scheduler();
+
+    // Until enough round trips are observed, the initial timeout is used
+    for _ in 0..10 {
+        scheduler.record_response(&1, Duration::from_millis(300));
+    }
+    assert_eq!(scheduler.request_timeout(), INITIAL_REQUEST_TIMEOUT);
+    assert_eq!(scheduler.hedge_delay(), INITIAL_REQUEST_TIMEOUT / 2);
+
+    for _ in 0..10 {
+        scheduler.record_response(&1, Duration::from_millis(300));
+    }
+    assert_eq!(scheduler.request_timeout(), Duration::from_millis(600));
+    assert_eq!(scheduler.hedge_delay(), Duration::from_millis(300));
+
+    // Fast peers lower the timeout, down to the minimum
+    for _ in 0..128 {
+        scheduler.record_response(&2, Duration::from_millis(5));
+    }
+    assert_eq!(scheduler.request_timeout(), MIN_REQUEST_TIMEOUT);
+    assert_eq!(scheduler.hedge_delay(), MIN_REQUEST_TIMEOUT / 2);
+
+    // And slow ones raise it, up to the maximum
+    for _ in 0..128 {
+        scheduler.record_response(&3, Duration::from_secs(10));
+    }
+    assert_eq!(scheduler.request_timeout(), MAX_REQUEST_TIMEOUT);
+    assert_eq!(scheduler.hedge_delay(), MAX_REQUEST_TIMEOUT);
+}
+
+#[test]
+fn test_request_scheduler_prefers_fast_reliable_peers() {
+    let scheduler = scheduler();
+
+    for _ in 0..16 {
+        scheduler.record_response(&1, Duration::from_millis(10));
+        scheduler.record_response(&5, Duration::from_millis(50));
+    }
+    for _ in 0..5 {
+        scheduler.record_failure(&3);
+    }
+
+    // Peer 2 is unknown, so it ranks like a peer with the median latency, ahead of the peer
+    // which keeps failing but behind the fast one
+    for _ in 0..10 {
+        assert_eq!(scheduler.rank([3, 2, 1]), vec![1, 2, 3]);
+    }
+
+    let failing = scheduler.peer_stats(&3).unwrap();
+    assert!(failing.success_rate < 0.5);
+    assert_eq!(failing.latency, None);
+    assert_eq!(scheduler.peer_stats(&2), None);
+
+    // A peer which starts answering recovers
+    for _ in 0..20 {
+        scheduler.record_response(&3, Duration::from_millis(5));
+    }
+    assert_eq!(scheduler.rank([3, 2, 1])[0], 3);
+}
+
+#[test]
+fn test_request_scheduler_spreads_equal_peers() {
+    let scheduler = scheduler();
+
+    // Peers nothing is known about are asked in a random order
+    let firsts: HashSet<_> = (0..100).map(|_| scheduler.rank(0..10)[0]).collect();
+    assert!(firsts.len() > 1);
+}
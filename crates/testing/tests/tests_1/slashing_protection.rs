@@ -0,0 +1,134 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use hotshot::traits::implementations::FileSystemStorage;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::ViewNumber,
+    event::HotShotAction,
+    signature_key::BLSPubKey,
+    slashing_protection::{Interchange, SigningHistory, SigningRoot},
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey, storage::Storage},
+};
+
+fn root(n: u64) -> SigningRoot {
+    ViewNumber::new(n).commit().into()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_signing_history_refuses_conflicts_across_restart() {
+    hotshot::helpers::initialize_logging();
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::<TestTypes>::new(dir.path()).unwrap();
+
+    storage
+        .record_signed_action(ViewNumber::new(2), HotShotAction::Vote, root(1))
+        .await
+        .unwrap();
+    // Signing the same thing again is fine, and so is a different action in the same view.
+    storage
+        .record_signed_action(ViewNumber::new(2), HotShotAction::Vote, root(1))
+        .await
+        .unwrap();
+    storage
+        .record_signed_action(ViewNumber::new(2), HotShotAction::DaVote, root(2))
+        .await
+        .unwrap();
+    drop(storage);
+
+    let storage = FileSystemStorage::<TestTypes>::new(dir.path()).unwrap();
+    assert!(storage
+        .record_signed_action(ViewNumber::new(2), HotShotAction::Vote, root(3))
+        .await
+        .is_err());
+    storage
+        .record_signed_action(ViewNumber::new(3), HotShotAction::Vote, root(3))
+        .await
+        .unwrap();
+    assert_eq!(
+        storage
+            .load_signing_history()
+            .await
+            .unwrap()
+            .records()
+            .count(),
+        3
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_signing_history_interchange() {
+    hotshot::helpers::initialize_logging();
+
+    let (pubkey, _) = BLSPubKey::generated_from_seed_indexed([0; 32], 0);
+    let (other_pubkey, _) = BLSPubKey::generated_from_seed_indexed([0; 32], 1);
+
+    let old_dir = tempfile::tempdir().unwrap();
+    let old_storage = FileSystemStorage::<TestTypes>::new(old_dir.path()).unwrap();
+    for view in 5..8 {
+        old_storage
+            .record_signed_action(ViewNumber::new(view), HotShotAction::Vote, root(view))
+            .await
+            .unwrap();
+    }
+    old_storage
+        .record_signed_action(ViewNumber::new(6), HotShotAction::Propose, root(100))
+        .await
+        .unwrap();
+
+    let json = serde_json::to_string(
+        &old_storage
+            .load_signing_history()
+            .await
+            .unwrap()
+            .to_interchange(pubkey),
+    )
+    .unwrap();
+    let interchange: Interchange<TestTypes> = serde_json::from_str(&json).unwrap();
+    assert_eq!(interchange.data[0].signed_actions.len(), 4);
+
+    // Histories for other keys are ignored.
+    assert_eq!(
+        SigningHistory::from_interchange(&interchange, &other_pubkey)
+            .unwrap()
+            .records()
+            .count(),
+        0
+    );
+
+    let new_dir = tempfile::tempdir().unwrap();
+    let new_storage = FileSystemStorage::<TestTypes>::new(new_dir.path()).unwrap();
+    new_storage
+        .import_signing_history(SigningHistory::from_interchange(&interchange, &pubkey).unwrap())
+        .await
+        .unwrap();
+
+    // Conflicts with the imported history are refused.
+    assert!(new_storage
+        .record_signed_action(ViewNumber::new(7), HotShotAction::Vote, root(0))
+        .await
+        .is_err());
+    // So is anything older than what was signed on the old machine.
+    assert!(new_storage
+        .record_signed_action(ViewNumber::new(4), HotShotAction::Vote, root(4))
+        .await
+        .is_err());
+    assert!(new_storage
+        .record_signed_action(ViewNumber::new(5), HotShotAction::Propose, root(5))
+        .await
+        .is_err());
+    // Repeating what was already signed, and signing in newer views, are fine.
+    new_storage
+        .record_signed_action(ViewNumber::new(7), HotShotAction::Vote, root(7))
+        .await
+        .unwrap();
+    new_storage
+        .record_signed_action(ViewNumber::new(8), HotShotAction::Vote, root(8))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, marker::PhantomData};
+
+use bitvec::bitvec;
+use committable::Committable;
+use either::Either;
+use hotshot_example_types::node_types::{TestTypes, TestVersions};
+use hotshot_testing::helpers::key_pair_for_id;
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    message::UpgradeLock,
+    signature_key::BLSPubKey,
+    simple_certificate::DaCertificate,
+    simple_vote::{DaData, DaVote, VersionedVoteData},
+    traits::{
+        block_contents::vid_commitment,
+        election::Membership,
+        network::Topic,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+    },
+    vid::VidCodeRate,
+    vote::{Certificate, HasViewNumber, Vote, VoteAccumulator},
+    PeerConfig,
+};
+use primitive_types::U256;
+
+type Accumulator =
+    VoteAccumulator<TestTypes, DaVote<TestTypes>, DaCertificate<TestTypes>, TestVersions>;
+
+/// Three small validators and one holding most of the stake
+const STAKES: [u64; 4] = [1, 1, 1, 7];
+
+fn committee(stakes: &[u64]) -> <TestTypes as NodeType>::Membership {
+    let peers: Vec<_> = stakes
+        .iter()
+        .enumerate()
+        .map(|(node_id, stake)| PeerConfig::<BLSPubKey> {
+            stake_table_entry: key_pair_for_id::<TestTypes>(node_id as u64)
+                .1
+                .stake_table_entry(*stake),
+            ..PeerConfig::default()
+        })
+        .collect();
+
+    <TestTypes as NodeType>::Membership::new(peers.clone(), peers, Topic::Global)
+}
+
+fn accumulator(upgrade_lock: &UpgradeLock<TestTypes, TestVersions>) -> Accumulator {
+    VoteAccumulator {
+        vote_outcomes: HashMap::new(),
+        signers: HashMap::new(),
+        phantom: PhantomData,
+        upgrade_lock: upgrade_lock.clone(),
+    }
+}
+
+async fn signed_vote(
+    node_id: u64,
+    upgrade_lock: &UpgradeLock<TestTypes, TestVersions>,
+) -> DaVote<TestTypes> {
+    let (private_key, public_key) = key_pair_for_id::<TestTypes>(node_id);
+    DaVote::create_signed_vote(
+        DaData {
+            payload_commit: vid_commitment(&[], STAKES.len(), VidCodeRate::default()),
+        },
+        ViewNumber::new(1),
+        &public_key,
+        &private_key,
+        upgrade_lock,
+    )
+    .await
+    .unwrap()
+}
+
+#[test]
+fn test_thresholds_follow_stake() {
+    let epoch = EpochNumber::new(1);
+
+    let membership = committee(&STAKES);
+    assert_eq!(membership.total_stake(epoch), U256::from(10));
+    assert_eq!(membership.success_threshold(epoch), U256::from(7));
+    assert_eq!(membership.failure_threshold(epoch), U256::from(4));
+    assert_eq!(membership.upgrade_threshold(epoch), U256::from(9));
+
+    // With equal stake the thresholds are the familiar node counts
+    let membership = committee(&[1; 10]);
+    assert_eq!(membership.success_threshold(epoch), U256::from(7));
+    assert_eq!(membership.failure_threshold(epoch), U256::from(4));
+    assert_eq!(membership.upgrade_threshold(epoch), U256::from(9));
+
+    let membership = committee(&[100, 200, 300, 400]);
+    assert_eq!(membership.success_threshold(epoch), U256::from(667));
+    assert_eq!(membership.failure_threshold(epoch), U256::from(334));
+    assert_eq!(membership.upgrade_threshold(epoch), U256::from(900));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_majority_of_nodes_without_enough_stake_forms_no_certificate() {
+    let epoch = EpochNumber::new(1);
+    let membership = committee(&STAKES);
+    let upgrade_lock = UpgradeLock::new();
+    let mut accumulator = accumulator(&upgrade_lock);
+
+    // Three of the four nodes vote, but they hold only 3 of the 7 stake needed
+    for node_id in 0..3 {
+        let vote = signed_vote(node_id, &upgrade_lock).await;
+        assert!(accumulator
+            .accumulate(&vote, &membership, epoch)
+            .await
+            .is_left());
+        // Voting twice doesn't count twice
+        assert!(accumulator
+            .accumulate(&vote, &membership, epoch)
+            .await
+            .is_left());
+    }
+
+    // A node outside the stake table adds nothing
+    let outsider = signed_vote(STAKES.len() as u64, &upgrade_lock).await;
+    assert!(accumulator
+        .accumulate(&outsider, &membership, epoch)
+        .await
+        .is_left());
+
+    // The large validator tips it over
+    let vote = signed_vote(3, &upgrade_lock).await;
+    let Either::Right(cert) = accumulator.accumulate(&vote, &membership, epoch).await else {
+        panic!("Expected a certificate once 10 stake voted, with 7 needed");
+    };
+    assert!(cert.is_valid_cert(&membership, epoch, &upgrade_lock).await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_single_node_with_enough_stake_forms_certificate() {
+    let epoch = EpochNumber::new(1);
+    let membership = committee(&STAKES);
+    let upgrade_lock = UpgradeLock::new();
+    let mut accumulator = accumulator(&upgrade_lock);
+
+    let vote = signed_vote(3, &upgrade_lock).await;
+    let Either::Right(cert) = accumulator.accumulate(&vote, &membership, epoch).await else {
+        panic!("Expected a certificate from the node holding 7 of 10 stake");
+    };
+    assert_eq!(cert.view_number(), ViewNumber::new(1));
+    assert!(cert.is_valid_cert(&membership, epoch, &upgrade_lock).await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_certificate_without_enough_stake_is_invalid() {
+    let epoch = EpochNumber::new(1);
+    let membership = committee(&STAKES);
+    let upgrade_lock = UpgradeLock::new();
+
+    // Assemble a certificate from the three small validators by hand, bypassing the accumulator
+    let mut signatures = Vec::new();
+    for node_id in 0..3 {
+        signatures.push(signed_vote(node_id, &upgrade_lock).await.signature());
+    }
+    let vote = signed_vote(0, &upgrade_lock).await;
+    let qc_params =
+        <BLSPubKey as SignatureKey>::public_parameter(membership.stake_table(epoch), U256::from(3));
+    let signature = <BLSPubKey as SignatureKey>::assemble(
+        &qc_params,
+        bitvec![1, 1, 1, 0].as_bitslice(),
+        &signatures,
+    );
+    let vote_commitment =
+        VersionedVoteData::new(vote.date().clone(), vote.view_number(), &upgrade_lock)
+            .await
+            .unwrap()
+            .commit();
+    let cert = DaCertificate::<TestTypes>::create_signed_certificate(
+        vote_commitment,
+        vote.date().clone(),
+        signature,
+        vote.view_number(),
+    );
+
+    assert!(!cert.is_valid_cert(&membership, epoch, &upgrade_lock).await);
+}
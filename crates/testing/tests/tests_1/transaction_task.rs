@@ -14,6 +14,7 @@ use hotshot_types::{
         election::Membership,
         node_implementation::{ConsensusTime, Versions},
     },
+    vid::VidCodeRate,
 };
 use vbs::version::StaticVersionType;
 
@@ -41,8 +42,11 @@ async fn test_transaction_task_leader_two_views_in_a_row() {
     input.push(HotShotEvent::Shutdown);
     let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
 
-    let (_, precompute_data) =
-        precompute_vid_commitment(&[], quorum_membership.total_nodes(EpochNumber::new(0)));
+    let (_, precompute_data) = precompute_vid_commitment(
+        &[],
+        quorum_membership.total_nodes(EpochNumber::new(0)),
+        VidCodeRate::default(),
+    );
 
     // current view
     let mut exp_packed_bundle = PackedBundle::new(
@@ -54,6 +58,7 @@ async fn test_transaction_task_leader_two_views_in_a_row() {
         vec1::vec1![
             null_block::builder_fee::<TestConsecutiveLeaderTypes, TestVersions>(
                 quorum_membership.total_nodes(EpochNumber::new(0)),
+                VidCodeRate::default(),
                 <TestVersions as Versions>::Base::VERSION,
                 *ViewNumber::new(4),
             )
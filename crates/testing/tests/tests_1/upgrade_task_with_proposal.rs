@@ -38,6 +38,7 @@ use hotshot_types::{
         ValidatedState,
     },
     utils::BuilderCommitment,
+    vid::VidCodeRate,
     vote::HasViewNumber,
 };
 use sha2::Digest;
@@ -127,6 +128,7 @@ async fn test_upgrade_task_with_proposal() {
     let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
     let builder_fee = null_block::builder_fee::<TestTypes, TestVersions>(
         quorum_membership.total_nodes(EpochNumber::new(1)),
+        VidCodeRate::default(),
         <TestVersions as Versions>::Base::VERSION,
         *ViewNumber::new(1),
     )
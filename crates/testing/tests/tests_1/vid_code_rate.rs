@@ -0,0 +1,169 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    test_builder::{nonempty_block_threshold, TestDescription},
+};
+use hotshot_types::{
+    data::null_block,
+    traits::block_contents::vid_commitment,
+    vid::{vid_scheme, VidCodeRate},
+};
+use jf_vid::VidScheme;
+
+/// Committee sizes which aren't powers of two
+const COMMITTEE_SIZES: [usize; 4] = [3, 5, 7, 10];
+
+fn payload() -> Vec<u8> {
+    (0..=255).cycle().take(1000).collect()
+}
+
+#[test]
+fn test_recovery_threshold() {
+    // The default rate recovers from the largest power of two not exceeding the committee
+    for num_storage_nodes in 1..=20_usize {
+        assert_eq!(
+            VidCodeRate::default()
+                .recovery_threshold(num_storage_nodes)
+                .unwrap(),
+            1 << num_storage_nodes.ilog2()
+        );
+    }
+
+    // Otherwise the committee times the rate, rounded up, then down to a power of two
+    for (num_storage_nodes, rate, exact_threshold, threshold) in [
+        (7, VidCodeRate::new(1, 2), 4, 4),
+        (5, VidCodeRate::new(1, 2), 3, 2),
+        (10, VidCodeRate::new(1, 3), 4, 4),
+        (9, VidCodeRate::new(2, 3), 6, 4),
+        (10, VidCodeRate::new(1, 10), 1, 1),
+        (1, VidCodeRate::new(1, 100), 1, 1),
+    ] {
+        assert_eq!(
+            rate.exact_recovery_threshold(num_storage_nodes).unwrap(),
+            exact_threshold,
+            "rate {rate} for {num_storage_nodes} storage nodes"
+        );
+        assert_eq!(
+            rate.recovery_threshold(num_storage_nodes).unwrap(),
+            threshold,
+            "rate {rate} for {num_storage_nodes} storage nodes"
+        );
+    }
+}
+
+#[test]
+fn test_invalid_code_rate() {
+    for rate in [
+        VidCodeRate::new(0, 1),
+        VidCodeRate::new(3, 2),
+        VidCodeRate::new(1, 0),
+    ] {
+        assert!(rate.exact_recovery_threshold(5).is_err(), "rate {rate}");
+        assert!(rate.recovery_threshold(5).is_err(), "rate {rate}");
+        assert!(vid_scheme(5, rate).is_err(), "rate {rate}");
+    }
+
+    assert!(VidCodeRate::default().recovery_threshold(0).is_err());
+    assert!(vid_scheme(0, VidCodeRate::default()).is_err());
+}
+
+#[test]
+fn test_vid_non_power_of_two_committees() {
+    let payload = payload();
+
+    for num_storage_nodes in COMMITTEE_SIZES {
+        for rate in [
+            VidCodeRate::default(),
+            VidCodeRate::new(1, 2),
+            VidCodeRate::new(1, 3),
+        ] {
+            let threshold =
+                usize::try_from(rate.recovery_threshold(num_storage_nodes).unwrap()).unwrap();
+            let mut vid = vid_scheme(num_storage_nodes, rate).unwrap();
+            let disperse = vid.disperse(&payload).unwrap();
+
+            assert_eq!(disperse.shares.len(), num_storage_nodes);
+            for share in &disperse.shares {
+                assert!(vid
+                    .verify_share(share, &disperse.common, &disperse.commit)
+                    .unwrap()
+                    .is_ok());
+            }
+            assert_eq!(
+                disperse.commit,
+                vid_commitment(&payload, num_storage_nodes, rate)
+            );
+
+            // Any `threshold` shares are enough to recover the payload
+            assert_eq!(
+                vid.recover_payload(&disperse.shares[..threshold], &disperse.common)
+                    .unwrap(),
+                payload,
+                "rate {rate} for {num_storage_nodes} storage nodes"
+            );
+            assert_eq!(
+                vid.recover_payload(
+                    &disperse.shares[num_storage_nodes - threshold..],
+                    &disperse.common
+                )
+                .unwrap(),
+                payload,
+                "rate {rate} for {num_storage_nodes} storage nodes"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_commitment_depends_on_code_rate() {
+    let payload = payload();
+
+    // 4 of 7 storage nodes either way
+    assert_eq!(
+        vid_commitment(&payload, 7, VidCodeRate::default()),
+        vid_commitment(&payload, 7, VidCodeRate::new(1, 2))
+    );
+    // 4 and 2 of 7 storage nodes
+    assert_ne!(
+        vid_commitment(&payload, 7, VidCodeRate::default()),
+        vid_commitment(&payload, 7, VidCodeRate::new(1, 4))
+    );
+    assert_ne!(
+        null_block::commitment(7, VidCodeRate::default()),
+        null_block::commitment(7, VidCodeRate::new(1, 4))
+    );
+}
+
+// Run consensus among 6 nodes, which recover payloads from 2 shares at a rate of 1/2 rather than
+// from 4 at the default rate. The builders compute the payload commitments with the same rate, or
+// their blocks would be rejected and only empty blocks would be decided.
+cross_tests!(
+    TestName: test_success_with_vid_code_rate_one_half,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            vid_code_rate: VidCodeRate::new(1, 2),
+            validate_transactions: nonempty_block_threshold((1, 2)),
+            ..TestDescription::default()
+        }
+    },
+);
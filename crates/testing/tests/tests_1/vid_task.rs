@@ -28,6 +28,7 @@ use hotshot_types::{
         node_implementation::{ConsensusTime, NodeType, Versions},
         BlockPayload,
     },
+    vid::VidCodeRate,
 };
 use jf_vid::{precomputable::Precomputable, VidScheme};
 use vbs::version::StaticVersionType;
@@ -111,6 +112,7 @@ async fn test_vid_task() {
                 ViewNumber::new(2),
                 vec1::vec1![null_block::builder_fee::<TestTypes, TestVersions>(
                     quorum_membership.total_nodes(EpochNumber::new(0)),
+                    VidCodeRate::default(),
                     <TestVersions as Versions>::Base::VERSION,
                     *ViewNumber::new(2),
                 )
@@ -133,6 +135,7 @@ async fn test_vid_task() {
                 ViewNumber::new(2),
                 vec1![null_block::builder_fee::<TestTypes, TestVersions>(
                     quorum_membership.total_nodes(EpochNumber::new(0)),
+                    VidCodeRate::default(),
                     <TestVersions as Versions>::Base::VERSION,
                     *ViewNumber::new(2),
                 )
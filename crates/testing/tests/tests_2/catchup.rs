@@ -356,6 +356,58 @@ cross_tests!(
     },
 );
 
+// Restart every node purely from what it persisted to storage on disk: its last decided leaf, high
+// QC, undecided leaves and state, saved proposals and last actioned view.
+cross_tests!(
+    TestName: test_all_restart_from_storage,
+    Impls: [CombinedImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+      let timing_data = TimingData {
+          next_view_timeout: 2000,
+          ..Default::default()
+      };
+      let mut metadata = TestDescription::default();
+      let mut catchup_nodes = vec![];
+
+      for i in 0..20 {
+          catchup_nodes.push(ChangeNode {
+              idx: i,
+              updown: NodeAction::RestartDownFromStorage(0),
+          })
+      }
+
+      metadata.timing_data = timing_data;
+      metadata.start_nodes = 20;
+      metadata.num_nodes_with_stake = 20;
+      metadata.storage_on_disk = true;
+
+      metadata.spinning_properties = SpinningTaskDescription {
+          // Restart all the nodes in view 13
+          node_changes: vec![(13, catchup_nodes)],
+      };
+      metadata.view_sync_properties =
+          hotshot_testing::view_sync_task::ViewSyncTaskDescription::Threshold(0, 20);
+
+      metadata.completion_task_description =
+          CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+              TimeBasedCompletionTaskDescription {
+                  duration: Duration::from_secs(60),
+              },
+          );
+      metadata.overall_safety_properties = OverallSafetyPropertiesDescription {
+          // Make sure we keep committing rounds after the catchup, but not the full 50.
+          num_successful_views: 22,
+          num_failed_views: 15,
+          ..Default::default()
+      };
+
+      metadata
+    },
+);
+
 // This test case ensures that proposals persist off of a restart. We demonstrate this by
 // artificially removing node 0 (the only DA committee member) from the candidate pool,
 // meaning that the entire DA also does not have the proposal, but we're still able to
@@ -0,0 +1,76 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, time::Duration};
+
+use hotshot_example_types::{
+    faulty_storage::{FaultKind, StorageFault, StorageMethod},
+    node_types::{CombinedImpl, TestTypes, TestVersions},
+};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::TestDescription,
+};
+
+// Make sure that a minority of nodes with misbehaving storage can't prevent the network from
+// making progress, or cause it to decide conflicting leaves. The test runner also checks that each
+// of these nodes actually had faults injected into its storage.
+cross_tests!(
+    TestName: test_storage_faults,
+    Impls: [CombinedImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription::default_more_nodes();
+
+        metadata.storage_faults = HashMap::from([
+            // Refuses to vote in view 7, since it can't store its VID share.
+            (
+                1,
+                vec![StorageFault::new(StorageMethod::AppendVid, FaultKind::Fail).in_view(7)],
+            ),
+            // Loses its VID shares and undecided state without noticing.
+            (
+                2,
+                vec![
+                    StorageFault::new(StorageMethod::AppendVid, FaultKind::DropWrite)
+                        .in_views(5..=15),
+                    StorageFault::new(StorageMethod::UpdateUndecidedState2, FaultKind::DropWrite),
+                ],
+            ),
+            // Stores a corrupted high QC, which it picks up again after restarting.
+            (
+                3,
+                vec![StorageFault::new(StorageMethod::UpdateHighQc2, FaultKind::Corrupt)],
+            ),
+        ]);
+
+        metadata.spinning_properties = SpinningTaskDescription {
+            node_changes: vec![(
+                10,
+                vec![ChangeNode {
+                    idx: 3,
+                    updown: NodeAction::RestartDownFromStorage(0),
+                }],
+            )],
+        };
+
+        metadata.completion_task_description =
+            CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(60),
+                },
+            );
+        metadata.overall_safety_properties.num_failed_views = 2;
+        metadata.overall_safety_properties.num_successful_views = 30;
+
+        metadata
+    },
+);
@@ -10,13 +10,14 @@ use hotshot_example_types::{
     node_types::{MarketplaceTestVersions, MarketplaceUpgradeTestVersions, MemoryImpl},
     state_types::TestTypes,
 };
+use hotshot_fakeapi::fake_solver::FakeSolverFaultType;
 use hotshot_macros::cross_tests;
 use hotshot_testing::{
     block_builder::SimpleBuilderImplementation,
     completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
     test_builder::{
         nonempty_block_limit, nonempty_block_threshold, BuilderChange, BuilderDescription,
-        TestDescription,
+        FakeSolverApiDescription, TestDescription,
     },
 };
 use vec1::vec1;
@@ -133,3 +134,29 @@ cross_tests!(
         }
     },
 );
+
+// Test marketplace with a solver which never responds in time
+// Requires at least 70% of blocks to be nonempty, which only the fallback builder can provide
+cross_tests!(
+    TestName: test_marketplace_solver_slow,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [MarketplaceTestVersions],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            solver: FakeSolverApiDescription {
+                error_pct: 0.0,
+                fault: Some(FakeSolverFaultType::TimeoutFault),
+            },
+            validate_transactions: nonempty_block_threshold((35,50)),
+            ..TestDescription::default()
+        }
+    },
+);
@@ -0,0 +1,143 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_fakeapi::fake_solver::{FakeSolverFaultType, FakeSolverState};
+use hotshot_task_impls::solver::SolverClient;
+use hotshot_testing::helpers::key_pair_for_id;
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        auction_results_provider::AuctionResultsProvider, node_implementation::ConsensusTime,
+    },
+};
+use tokio::{spawn, task::JoinHandle, time::sleep};
+use url::Url;
+
+/// Run a fake solver offering one builder, returning its URL
+fn run_solver(solver_state: FakeSolverState) -> (Url, JoinHandle<()>) {
+    let solver_url: Url = format!(
+        "http://localhost:{}",
+        portpicker::pick_unused_port().unwrap()
+    )
+    .parse()
+    .unwrap();
+    let url = solver_url.clone();
+    let handle = spawn(async move {
+        solver_state.run::<TestTypes>(url).await.unwrap();
+    });
+    (solver_url, handle)
+}
+
+fn builder_url() -> Url {
+    "http://localhost:1111".parse().unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_solver_client_caches_auction_results() {
+    hotshot::helpers::initialize_logging();
+
+    let solver_state = FakeSolverState::new(None, vec![builder_url()]);
+    let num_requests = solver_state.num_requests.clone();
+    let (solver_url, solver_handle) = run_solver(solver_state);
+    let client = SolverClient::<TestTypes>::new(solver_url.clone());
+
+    for _ in 0..3 {
+        let result = client
+            .fetch_auction_result(ViewNumber::new(1))
+            .await
+            .unwrap();
+        assert_eq!(result.urls, vec![builder_url()]);
+    }
+    assert_eq!(num_requests.load(Ordering::Relaxed), 1);
+
+    // The permissioned API serves the same results
+    let (private_key, _) = key_pair_for_id::<TestTypes>(0);
+    let permissioned = SolverClient::<TestTypes>::new(solver_url).with_private_key(private_key);
+    let result = permissioned
+        .fetch_auction_result(ViewNumber::new(2))
+        .await
+        .unwrap();
+    assert_eq!(result.urls, vec![builder_url()]);
+    assert_eq!(num_requests.load(Ordering::Relaxed), 2);
+
+    solver_handle.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_solver_client_prefetches_auction_results() {
+    hotshot::helpers::initialize_logging();
+
+    let solver_state = FakeSolverState::new(None, vec![builder_url()]);
+    let num_requests = solver_state.num_requests.clone();
+    let (solver_url, solver_handle) = run_solver(solver_state);
+    let client = SolverClient::<TestTypes>::new(solver_url);
+
+    client.prefetch_auction_result(ViewNumber::new(5));
+    let start = Instant::now();
+    while num_requests.load(Ordering::Relaxed) == 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "Auction result was not prefetched"
+        );
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    let result = client
+        .fetch_auction_result(ViewNumber::new(5))
+        .await
+        .unwrap();
+    assert_eq!(result.urls, vec![builder_url()]);
+    assert_eq!(num_requests.load(Ordering::Relaxed), 1);
+
+    solver_handle.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_solver_client_gives_up_on_slow_solver() {
+    hotshot::helpers::initialize_logging();
+
+    let solver_state = FakeSolverState::new(None, vec![builder_url()])
+        .with_fault(Some(FakeSolverFaultType::TimeoutFault));
+    let (solver_url, solver_handle) = run_solver(solver_state);
+    let client =
+        SolverClient::<TestTypes>::new(solver_url).with_timeout(Duration::from_millis(100));
+
+    let start = Instant::now();
+    assert!(client
+        .fetch_auction_result(ViewNumber::new(1))
+        .await
+        .is_err());
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    solver_handle.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_solver_client_does_not_cache_failures() {
+    hotshot::helpers::initialize_logging();
+
+    let solver_state = FakeSolverState::new(None, vec![builder_url()])
+        .with_fault(Some(FakeSolverFaultType::InternalServerFault));
+    let num_requests = solver_state.num_requests.clone();
+    let (solver_url, solver_handle) = run_solver(solver_state);
+    let client = SolverClient::<TestTypes>::new(solver_url);
+
+    for _ in 0..2 {
+        assert!(client
+            .fetch_auction_result(ViewNumber::new(1))
+            .await
+            .is_err());
+    }
+    assert_eq!(num_requests.load(Ordering::Relaxed), 2);
+
+    solver_handle.abort();
+}
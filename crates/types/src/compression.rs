@@ -0,0 +1,241 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Compression of serialized messages
+//!
+//! From [`Versions::Compression`](crate::traits::node_implementation::Versions::Compression)
+//! onwards, [`UpgradeLock::serialize`](crate::message::UpgradeLock::serialize) compresses the
+//! body of every message (everything after the version prefix) with
+//! [`CompressionConfig::compress`]. The body is prefixed with a one-byte [`CompressionAlgorithm`]
+//! tag, so a receiver can decompress messages regardless of which algorithm the sender chose.
+
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+/// Messages with a body smaller than this are sent uncompressed by default. Votes and
+/// certificates fall below it, while DA proposals and VID shares of non-trivial blocks are above.
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 4 * 1024;
+
+/// The largest body we are willing to decompress a message into by default
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 512 * 1024 * 1024;
+
+/// The zstd compression level we use. Low levels are fast while still doing well on the
+/// repetitive parts of our messages.
+const ZSTD_LEVEL: i32 = 3;
+
+/// The largest ratio by which lz4 can possibly compress data. Anything claiming to be more
+/// compressed than this is malformed.
+const LZ4_MAX_RATIO: usize = 255;
+
+/// The algorithm a message body was compressed with, sent as the first byte of the body
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum CompressionAlgorithm {
+    /// The body is not compressed
+    None = 0,
+    /// The body is compressed with zstd
+    #[default]
+    Zstd = 1,
+    /// The body is compressed with lz4, prefixed with its decompressed size
+    Lz4 = 2,
+}
+
+impl TryFrom<u8> for CompressionAlgorithm {
+    type Error = Error;
+
+    fn try_from(tag: u8) -> Result<Self> {
+        match tag {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Zstd),
+            2 => Ok(Self::Lz4),
+            tag => {
+                bail!("Unknown compression algorithm tag {}", tag);
+            }
+        }
+    }
+}
+
+/// How a node compresses the messages it sends, and which compressed messages it accepts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// The algorithm to compress outgoing messages with
+    pub algorithm: CompressionAlgorithm,
+    /// Outgoing message bodies smaller than this are not compressed
+    pub threshold: usize,
+    /// Incoming messages which decompress to more than this many bytes are rejected
+    pub max_decompressed_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: CompressionAlgorithm::default(),
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Compress `body`, prefixing the result with the algorithm tag.
+    ///
+    /// Bodies below the threshold, and bodies which don't shrink, are sent as they are.
+    ///
+    /// # Errors
+    /// Errors if the compressor fails.
+    pub fn compress(&self, body: &[u8]) -> Result<Vec<u8>> {
+        let algorithm = if body.len() < self.threshold {
+            CompressionAlgorithm::None
+        } else {
+            self.algorithm
+        };
+
+        let compressed = match algorithm {
+            CompressionAlgorithm::None => None,
+            CompressionAlgorithm::Zstd => Some(
+                zstd::bulk::compress(body, ZSTD_LEVEL)
+                    .wrap()
+                    .context(info!("Failed to compress message with zstd"))?,
+            ),
+            CompressionAlgorithm::Lz4 => Some(lz4_flex::compress_prepend_size(body)),
+        };
+
+        let (algorithm, payload) = match compressed {
+            Some(ref compressed) if compressed.len() < body.len() => (algorithm, &compressed[..]),
+            _ => (CompressionAlgorithm::None, body),
+        };
+
+        let mut result = Vec::with_capacity(payload.len() + 1);
+        result.push(algorithm as u8);
+        result.extend_from_slice(payload);
+
+        Ok(result)
+    }
+
+    /// Decompress a body produced by [`Self::compress`], with any algorithm.
+    ///
+    /// # Errors
+    /// Errors if the body is malformed, or would decompress to more than
+    /// `max_decompressed_size` bytes.
+    pub fn decompress(&self, body: &[u8]) -> Result<Vec<u8>> {
+        let (&tag, payload) = body
+            .split_first()
+            .context(info!("Message is missing its compression tag"))?;
+
+        match CompressionAlgorithm::try_from(tag)? {
+            CompressionAlgorithm::None => {
+                ensure!(
+                    payload.len() <= self.max_decompressed_size,
+                    "Message of {} bytes exceeds the limit of {} bytes",
+                    payload.len(),
+                    self.max_decompressed_size
+                );
+                Ok(payload.to_vec())
+            }
+            CompressionAlgorithm::Zstd => {
+                // Stream the output so we never allocate more than the limit, no matter what the
+                // frame header claims
+                let decoder = zstd::stream::read::Decoder::new(payload)
+                    .wrap()
+                    .context(info!("Failed to read zstd frame"))?;
+                let mut decompressed = Vec::new();
+                decoder
+                    .take(self.max_decompressed_size as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .wrap()
+                    .context(info!("Failed to decompress message with zstd"))?;
+                ensure!(
+                    decompressed.len() <= self.max_decompressed_size,
+                    "Message decompresses to more than the limit of {} bytes",
+                    self.max_decompressed_size
+                );
+                Ok(decompressed)
+            }
+            CompressionAlgorithm::Lz4 => {
+                ensure!(payload.len() >= 4, "Message is missing its lz4 size prefix");
+                let (size, compressed) = payload.split_at(4);
+                let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+                ensure!(
+                    size <= self.max_decompressed_size,
+                    "Message claims to decompress to {size} bytes, more than the limit of {} bytes",
+                    self.max_decompressed_size
+                );
+                ensure!(
+                    size <= compressed.len().saturating_mul(LZ4_MAX_RATIO),
+                    "Message claims an impossible lz4 compression ratio"
+                );
+                lz4_flex::block::decompress(compressed, size)
+                    .wrap()
+                    .context(info!("Failed to decompress message with lz4"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(algorithm: CompressionAlgorithm) -> CompressionConfig {
+        CompressionConfig {
+            algorithm,
+            threshold: 64,
+            max_decompressed_size: 1024 * 1024,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let compressible = vec![7u8; 100_000];
+        let incompressible: Vec<u8> = (0..100_000).map(|_| rand::random()).collect();
+        let small = vec![7u8; 10];
+
+        for algorithm in [
+            CompressionAlgorithm::None,
+            CompressionAlgorithm::Zstd,
+            CompressionAlgorithm::Lz4,
+        ] {
+            let config = config(algorithm);
+            for body in [&compressible, &incompressible, &small] {
+                let compressed = config.compress(body).unwrap();
+                assert_eq!(&config.decompress(&compressed).unwrap(), body);
+            }
+
+            // Small and incompressible bodies are sent as they are
+            assert_eq!(config.compress(&small).unwrap()[0], 0);
+            assert_eq!(config.compress(&incompressible).unwrap()[0], 0);
+            assert_eq!(config.compress(&compressible).unwrap()[0], algorithm as u8);
+        }
+    }
+
+    #[test]
+    fn rejects_decompression_bombs() {
+        let bomb = vec![0u8; 2 * 1024 * 1024];
+
+        for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Lz4] {
+            let compressed = config(algorithm).compress(&bomb).unwrap();
+            assert!(compressed.len() < 1024 * 1024);
+            assert!(config(algorithm).decompress(&compressed).is_err());
+        }
+
+        // An lz4 body lying about its size
+        let mut lying = vec![CompressionAlgorithm::Lz4 as u8];
+        lying.extend_from_slice(&u32::MAX.to_le_bytes());
+        lying.extend_from_slice(&[0; 16]);
+        assert!(config(CompressionAlgorithm::Lz4)
+            .decompress(&lying)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_tags() {
+        let config = config(CompressionAlgorithm::Zstd);
+        assert!(config.decompress(&[3, 1, 2, 3]).is_err());
+        assert!(config.decompress(&[]).is_err());
+    }
+}
@@ -15,6 +15,7 @@ use std::{
 
 use async_lock::{RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
 use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
 use tracing::instrument;
 use utils::anytrace::*;
 use vec1::Vec1;
@@ -22,13 +23,14 @@ use vec1::Vec1;
 pub use crate::utils::{View, ViewInner};
 use crate::{
     data::{Leaf2, QuorumProposal2, VidDisperse, VidDisperseShare},
+    drb::{DrbOutput, DrbResult},
     error::HotShotError,
     event::{HotShotAction, LeafInfo},
     message::Proposal,
     simple_certificate::{DaCertificate, QuorumCertificate2},
     traits::{
         block_contents::BuilderFee,
-        metrics::{Counter, Gauge, Histogram, Metrics, NoMetrics},
+        metrics::{Counter, Gauge, GaugeFamily, Histogram, Metrics, NoMetrics},
         node_implementation::{ConsensusTime, NodeType},
         signature_key::SignatureKey,
         BlockPayload, ValidatedState,
@@ -36,7 +38,7 @@ use crate::{
     utils::{
         epoch_from_block_number, BuilderCommitment, LeafCommitment, StateAndDelta, Terminator,
     },
-    vid::VidCommitment,
+    vid::{VidCodeRate, VidCommitment},
     vote::{Certificate, HasViewNumber},
 };
 
@@ -268,6 +270,35 @@ impl<T: ConsensusTime> HotShotActionViews<T> {
         }
     }
 }
+/// A snapshot of the state of consensus on a node, for status and health queries
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ConsensusStatus<TYPES: NodeType> {
+    /// The view the node is currently in
+    pub cur_view: TYPES::View,
+    /// The epoch the node is currently in
+    pub cur_epoch: TYPES::Epoch,
+    /// The view of the last decided leaf
+    pub last_decided_view: TYPES::View,
+    /// The height of the last decided leaf
+    pub last_decided_height: u64,
+    /// The view of the locked QC
+    pub locked_view: TYPES::View,
+    /// The view of the highest QC the node has seen
+    pub high_qc_view: TYPES::View,
+    /// The number of views the node keeps validated state for, including the last decided one
+    pub undecided_views: usize,
+    /// The number of VID shares held for views after the last decided one
+    pub pending_vid_shares: usize,
+    /// The number of DA certificates held for views after the last decided one
+    pub pending_da_certificates: usize,
+    /// The upcoming views, up to [`LOOK_AHEAD`](crate::constants::LOOK_AHEAD) views ahead, in
+    /// which the node is the leader
+    pub upcoming_leader_views: Vec<TYPES::View>,
+    /// Whether the node's network is ready to send messages
+    pub network_ready: bool,
+}
+
 /// A reference to the consensus algorithm
 ///
 /// This will contain the state of all rounds.
@@ -322,6 +353,9 @@ pub struct Consensus<TYPES: NodeType> {
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// The DRB outputs we've computed, keyed by the epoch whose leaders they choose
+    drb_results: BTreeMap<TYPES::Epoch, DrbOutput>,
 }
 
 /// Contains several `ConsensusMetrics` that we're interested in from the consensus interfaces
@@ -353,8 +387,28 @@ pub struct ConsensusMetricsValue {
     pub number_of_timeouts_as_leader: Box<dyn Counter>,
     /// The number of empty blocks that have been proposed
     pub number_of_empty_blocks_proposed: Box<dyn Counter>,
+    /// Number of blocks proposed which were built locally because no builder delivered one
+    pub number_of_locally_built_blocks_proposed: Box<dyn Counter>,
     /// Number of events in the hotshot event queue
     pub internal_event_queue_len: Box<dyn Gauge>,
+    /// Current timeout for requests to peers, in milliseconds
+    pub request_timeout: Box<dyn Gauge>,
+    /// Round trip times of the requests peers answered, in seconds
+    pub request_round_trip_time: Box<dyn Histogram>,
+    /// Number of requests peers did not answer in time
+    pub failed_requests: Box<dyn Counter>,
+    /// Number of requests also sent to another peer because the first was slow to answer
+    pub hedged_requests: Box<dyn Counter>,
+    /// Average round trip time of the requests each peer answered, in milliseconds
+    pub peer_request_latency: Box<dyn GaugeFamily>,
+    /// Percentage of requests each peer answered in time, on average
+    pub peer_request_success_rate: Box<dyn GaugeFamily>,
+    /// Reputation score of each builder, as a percentage
+    pub builder_score: Box<dyn GaugeFamily>,
+    /// Average time each builder took to report its available blocks, in milliseconds
+    pub builder_latency: Box<dyn GaugeFamily>,
+    /// Number of builders temporarily excluded for their low score
+    pub excluded_builders: Box<dyn Gauge>,
 }
 
 impl ConsensusMetricsValue {
@@ -383,8 +437,35 @@ impl ConsensusMetricsValue {
                 .create_counter(String::from("number_of_timeouts_as_leader"), None),
             number_of_empty_blocks_proposed: metrics
                 .create_counter(String::from("number_of_empty_blocks_proposed"), None),
+            number_of_locally_built_blocks_proposed: metrics.create_counter(
+                String::from("number_of_locally_built_blocks_proposed"),
+                None,
+            ),
             internal_event_queue_len: metrics
                 .create_gauge(String::from("internal_event_queue_len"), None),
+            request_timeout: metrics
+                .create_gauge(String::from("request_timeout"), Some(String::from("ms"))),
+            request_round_trip_time: metrics.create_histogram(
+                String::from("request_round_trip_time"),
+                Some(String::from("s")),
+            ),
+            failed_requests: metrics.create_counter(String::from("failed_requests"), None),
+            hedged_requests: metrics.create_counter(String::from("hedged_requests"), None),
+            peer_request_latency: metrics.gauge_family(
+                String::from("peer_request_latency_ms"),
+                vec![String::from("peer")],
+            ),
+            peer_request_success_rate: metrics.gauge_family(
+                String::from("peer_request_success_rate"),
+                vec![String::from("peer")],
+            ),
+            builder_score: metrics
+                .gauge_family(String::from("builder_score"), vec![String::from("builder")]),
+            builder_latency: metrics.gauge_family(
+                String::from("builder_latency_ms"),
+                vec![String::from("builder")],
+            ),
+            excluded_builders: metrics.create_gauge(String::from("excluded_builders"), None),
         }
     }
 }
@@ -427,6 +508,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             high_qc,
             metrics,
             epoch_height,
+            drb_results: BTreeMap::new(),
         }
     }
 
@@ -717,6 +799,39 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         Ok(())
     }
 
+    /// Get the DRB result used to choose the leaders of `epoch`, if we've computed it.
+    pub fn drb_result(&self, epoch: TYPES::Epoch) -> Option<DrbResult> {
+        self.drb_results.get(&epoch).map(|output| output.result)
+    }
+
+    /// Get the DRB output, including its checkpoints, used to choose the leaders of `epoch`.
+    pub fn drb_output(&self, epoch: TYPES::Epoch) -> Option<&DrbOutput> {
+        self.drb_results.get(&epoch)
+    }
+
+    /// Save the DRB output used to choose the leaders of `epoch`, forgetting those of epochs
+    /// which are already over.
+    ///
+    /// # Errors
+    /// Can return an error when a different output for the same epoch already exists.
+    pub fn update_drb_result(&mut self, epoch: TYPES::Epoch, output: DrbOutput) -> Result<()> {
+        if let Some(existing) = self.drb_results.get(&epoch) {
+            ensure!(
+                existing.result == output.result,
+                error!("Conflicting DRB results for epoch {}", epoch)
+            );
+        }
+        self.drb_results.insert(epoch, output);
+        self.drb_results = self.drb_results.split_off(&self.cur_epoch);
+        Ok(())
+    }
+
+    /// Forget the DRB output used to choose the leaders of `epoch`, so that a different one can be
+    /// saved, e.g. because it turned out to be invalid.
+    pub fn remove_drb_result(&mut self, epoch: TYPES::Epoch) -> Option<DrbOutput> {
+        self.drb_results.remove(&epoch)
+    }
+
     /// Update the high QC if given a newer one.
     /// # Errors
     /// Can return an error when the provided high_qc is not newer than the existing entry.
@@ -889,7 +1004,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
 
     /// Associated helper function:
     /// Takes `LockedConsensusState` which will be updated; locks it for read and write accordingly.
-    /// Calculates `VidDisperse` based on the view, the txns and the membership,
+    /// Calculates `VidDisperse` based on the view, the txns, the membership and the code rate,
     /// and updates `vid_shares` map with the signed `VidDisperseShare` proposals.
     /// Returned `Option` indicates whether the update has actually happened or not.
     #[instrument(skip_all, target = "Consensus", fields(view = *view))]
@@ -899,9 +1014,12 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         membership: Arc<TYPES::Membership>,
         private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
         epoch: TYPES::Epoch,
+        code_rate: VidCodeRate,
     ) -> Option<()> {
         let txns = Arc::clone(consensus.read().await.saved_payloads().get(&view)?);
-        let vid = VidDisperse::calculate_vid_disperse(txns, &membership, view, epoch, None).await;
+        let vid =
+            VidDisperse::calculate_vid_disperse(txns, &membership, view, epoch, None, code_rate)
+                .await;
         let shares = VidDisperseShare::from_vid_disperse(vid);
         let mut consensus_writer = consensus.write().await;
         for share in shares {
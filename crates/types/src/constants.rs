@@ -35,6 +35,10 @@ pub const COMBINED_NETWORK_DELAY_DURATION: u64 = 5000;
 /// The default network data request delay in milliseconds
 pub const REQUEST_DATA_DELAY: u64 = 5000;
 
+/// The largest serialized response to a leaf, payload or quorum certificate request a node sends
+/// or accepts, in bytes
+pub const MAX_DATA_RESPONSE_SIZE: u64 = 20 * 1024 * 1024;
+
 /// Default channel size for consensus event sharing
 pub const EVENT_CHANNEL_SIZE: usize = 100_000;
 
@@ -48,7 +48,10 @@ use crate::{
         BlockPayload,
     },
     utils::bincode_opts,
-    vid::{vid_scheme, VidCommitment, VidCommon, VidPrecomputeData, VidSchemeType, VidShare},
+    vid::{
+        vid_scheme, VidCodeRate, VidCommitment, VidCommon, VidPrecomputeData, VidSchemeType,
+        VidShare,
+    },
     vote::{Certificate, HasViewNumber},
 };
 
@@ -200,8 +203,8 @@ impl<TYPES: NodeType> VidDisperse<TYPES> {
         }
     }
 
-    /// Calculate the vid disperse information from the payload given a view, epoch and membership,
-    /// optionally using precompute data from builder
+    /// Calculate the vid disperse information from the payload given a view, epoch, membership
+    /// and code rate, optionally using precompute data from builder
     ///
     /// # Panics
     /// Panics if the VID calculation fails, this should not happen.
@@ -212,16 +215,17 @@ impl<TYPES: NodeType> VidDisperse<TYPES> {
         view: TYPES::View,
         epoch: TYPES::Epoch,
         precompute_data: Option<VidPrecomputeData>,
+        code_rate: VidCodeRate,
     ) -> Self {
         let num_nodes = membership.total_nodes(epoch);
 
         let vid_disperse = spawn_blocking(move || {
             precompute_data
                 .map_or_else(
-                    || vid_scheme(num_nodes).disperse(Arc::clone(&txns)),
-                    |data| vid_scheme(num_nodes).disperse_precompute(Arc::clone(&txns), &data)
+                    || vid_scheme(num_nodes, code_rate).and_then(|mut vid| vid.disperse(Arc::clone(&txns))),
+                    |data| vid_scheme(num_nodes, code_rate).and_then(|vid| vid.disperse_precompute(Arc::clone(&txns), &data))
                 )
-                .unwrap_or_else(|err| panic!("VID disperse failure:(num_storage nodes,payload_byte_len)=({num_nodes},{}) error: {err}", txns.len()))
+                .unwrap_or_else(|err| panic!("VID disperse failure:(num_storage nodes,code_rate,payload_byte_len)=({num_nodes},{code_rate},{}) error: {err}", txns.len()))
         }).await;
         // Unwrap here will just propagate any panic from the spawned task, it's not a new place we can panic.
         let vid_disperse = vid_disperse.unwrap();
@@ -613,9 +617,10 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
         &mut self,
         block_payload: TYPES::BlockPayload,
         num_storage_nodes: usize,
+        code_rate: VidCodeRate,
     ) -> std::result::Result<(), BlockError> {
         let encoded_txns = block_payload.encode();
-        let commitment = vid_commitment(&encoded_txns, num_storage_nodes);
+        let commitment = vid_commitment(&encoded_txns, num_storage_nodes, code_rate);
         if commitment != self.block_header.payload_commitment() {
             return Err(BlockError::InconsistentPayloadCommitment);
         }
@@ -841,7 +846,11 @@ impl<TYPES: NodeType> Leaf<TYPES> {
         let builder_commitment = payload.builder_commitment(&metadata);
         let payload_bytes = payload.encode();
 
-        let payload_commitment = vid_commitment(&payload_bytes, GENESIS_VID_NUM_STORAGE_NODES);
+        let payload_commitment = vid_commitment(
+            &payload_bytes,
+            GENESIS_VID_NUM_STORAGE_NODES,
+            VidCodeRate::default(),
+        );
 
         let block_header = TYPES::BlockHeader::genesis(
             instance_state,
@@ -913,9 +922,10 @@ impl<TYPES: NodeType> Leaf<TYPES> {
         &mut self,
         block_payload: TYPES::BlockPayload,
         num_storage_nodes: usize,
+        code_rate: VidCodeRate,
     ) -> std::result::Result<(), BlockError> {
         let encoded_txns = block_payload.encode();
-        let commitment = vid_commitment(&encoded_txns, num_storage_nodes);
+        let commitment = vid_commitment(&encoded_txns, num_storage_nodes, code_rate);
         if commitment != self.block_header.payload_commitment() {
             return Err(BlockError::InconsistentPayloadCommitment);
         }
@@ -1136,19 +1146,20 @@ pub mod null_block {
             signature_key::BuilderSignatureKey,
             BlockPayload,
         },
-        vid::{vid_scheme, VidCommitment},
+        vid::{vid_scheme, VidCodeRate, VidCommitment},
     };
 
     /// The commitment for a null block payload.
     ///
-    /// Note: the commitment depends on the network (via `num_storage_nodes`),
+    /// Note: the commitment depends on the network (via `num_storage_nodes` and `code_rate`),
     /// and may change (albeit rarely) during execution.
     ///
     /// We memoize the result to avoid having to recalculate it.
     #[memoize(SharedCache, Capacity: 10)]
     #[must_use]
-    pub fn commitment(num_storage_nodes: usize) -> Option<VidCommitment> {
-        let vid_result = vid_scheme(num_storage_nodes).commit_only(Vec::new());
+    pub fn commitment(num_storage_nodes: usize, code_rate: VidCodeRate) -> Option<VidCommitment> {
+        let vid_result = vid_scheme(num_storage_nodes, code_rate)
+            .and_then(|mut vid| vid.commit_only(Vec::new()));
 
         match vid_result {
             Ok(r) => Some(r),
@@ -1160,6 +1171,7 @@ pub mod null_block {
     #[must_use]
     pub fn builder_fee<TYPES: NodeType, V: Versions>(
         num_storage_nodes: usize,
+        code_rate: VidCodeRate,
         version: vbs::version::Version,
         view_number: u64,
     ) -> Option<BuilderFee<TYPES>> {
@@ -1192,7 +1204,7 @@ pub mod null_block {
                 &priv_key,
                 FEE_AMOUNT,
                 &null_block_metadata,
-                &commitment(num_storage_nodes)?,
+                &commitment(num_storage_nodes, code_rate)?,
             ) {
                 Ok(sig) => Some(BuilderFee {
                     fee_amount: FEE_AMOUNT,
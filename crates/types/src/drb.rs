@@ -0,0 +1,254 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Distributed random beacon (DRB) used for leader election with epochs
+//!
+//! The seed for epoch `e` is derived from the signatures of a decided QC of epoch `e`, and the
+//! result is the seed hashed [`DrbConfig::difficulty`] times with SHA-256. Because the
+//! computation can't be sped up by parallelism, nobody can learn the result (and so the leaders
+//! of epoch `e + 2`, which use it) much sooner than anybody else, yet everyone has a whole epoch
+//! to compute it.
+
+use std::{num::NonZeroUsize, thread, time::Duration};
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    simple_certificate::QuorumCertificate2, traits::node_implementation::NodeType,
+    utils::bincode_opts,
+};
+
+/// The input to the DRB calculation, derived from a decided QC signature
+pub type DrbSeedInput = [u8; 32];
+
+/// The result of the DRB calculation
+pub type DrbResult = [u8; 32];
+
+/// The DRB result used for the first epochs, which come before any DRB result is computed
+pub const INITIAL_DRB_RESULT: DrbResult = [0u8; 32];
+
+/// The last epoch which uses [`INITIAL_DRB_RESULT`]. The first DRB result is computed from the last
+/// block of epoch 1, for epoch 3.
+pub const INITIAL_DRB_RESULT_EPOCHS: u64 = 2;
+
+/// A conservative rate of iterated SHA-256 hashing on a single core, in hashes per second.
+///
+/// Iterating [`Sha256`] over a 32-byte hash, as [`compute_drb_result`] does, runs at about 10.7M
+/// hashes per second on a Xeon core with the SHA extensions, and at about 1.7M with the portable
+/// implementation `sha2` falls back to without them. We round the slower rate down, so that
+/// hardware without SHA extensions still finishes within [`DRB_CALCULATION_TIME`]. The `drb`
+/// benchmark in `hotshot-testing` reports the rate of a machine, and how long
+/// [`DEFAULT_DRB_DIFFICULTY`] takes there.
+pub const DRB_HASHES_PER_SECOND: u64 = 1_000_000;
+
+/// How long the DRB calculation should take with the default difficulty. The result of epoch `e`
+/// is needed at the start of epoch `e + 2`, so this must stay well below the duration of an epoch.
+pub const DRB_CALCULATION_TIME: Duration = Duration::from_secs(5 * 60);
+
+/// The default number of times the seed is hashed
+pub const DEFAULT_DRB_DIFFICULTY: u64 = DRB_HASHES_PER_SECOND * DRB_CALCULATION_TIME.as_secs();
+
+/// The default number of hashes between checkpoints
+pub const DEFAULT_DRB_CHECKPOINT_INTERVAL: u64 = DEFAULT_DRB_DIFFICULTY / 64;
+
+/// How the DRB is computed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DrbConfig {
+    /// The number of times the seed is hashed
+    pub difficulty: u64,
+    /// The number of hashes between consecutive checkpoints, or zero to keep no checkpoints.
+    ///
+    /// Checkpoints let a node which didn't compute the result itself verify it in parallel.
+    pub checkpoint_interval: u64,
+}
+
+impl Default for DrbConfig {
+    fn default() -> Self {
+        Self {
+            difficulty: DEFAULT_DRB_DIFFICULTY,
+            checkpoint_interval: DEFAULT_DRB_CHECKPOINT_INTERVAL,
+        }
+    }
+}
+
+impl DrbConfig {
+    /// The number of checkpoints a computation with this config produces
+    #[must_use]
+    pub fn num_checkpoints(&self) -> u64 {
+        if self.checkpoint_interval == 0 || self.difficulty == 0 {
+            0
+        } else {
+            (self.difficulty - 1) / self.checkpoint_interval
+        }
+    }
+}
+
+/// The output of the DRB calculation
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DrbOutput {
+    /// The result of the calculation
+    pub result: DrbResult,
+    /// The intermediate hashes after every [`DrbConfig::checkpoint_interval`] iterations,
+    /// excluding the result itself
+    pub checkpoints: Vec<DrbResult>,
+}
+
+/// Derive the DRB seed from the signatures of a decided QC.
+///
+/// Returns `None` if the QC has no signatures, i.e. for the genesis QC.
+#[must_use]
+pub fn drb_seed_input<TYPES: NodeType>(qc: &QuorumCertificate2<TYPES>) -> Option<DrbSeedInput> {
+    let signatures = bincode_opts().serialize(qc.signatures.as_ref()?).ok()?;
+    Some(Sha256::digest(signatures).into())
+}
+
+/// Hash `hash` with SHA-256 `iterations` times
+fn iterate_hash(mut hash: DrbResult, iterations: u64) -> DrbResult {
+    for _ in 0..iterations {
+        hash = Sha256::digest(hash).into();
+    }
+    hash
+}
+
+/// Compute the DRB result for `drb_seed_input`.
+///
+/// This takes a long time by design, so it should be run on a blocking thread.
+#[must_use]
+pub fn compute_drb_result(drb_seed_input: DrbSeedInput, config: &DrbConfig) -> DrbOutput {
+    let mut hash = drb_seed_input;
+    let mut checkpoints = Vec::new();
+    let mut remaining = config.difficulty;
+    for _ in 0..config.num_checkpoints() {
+        hash = iterate_hash(hash, config.checkpoint_interval);
+        remaining -= config.checkpoint_interval;
+        checkpoints.push(hash);
+    }
+
+    DrbOutput {
+        result: iterate_hash(hash, remaining),
+        checkpoints,
+    }
+}
+
+/// Verify that `output` is the DRB result for `drb_seed_input`.
+///
+/// The segments between checkpoints are recomputed in parallel, so with checkpoints this is
+/// faster than computing the result from scratch.
+#[must_use]
+pub fn verify_drb_result(
+    drb_seed_input: DrbSeedInput,
+    output: &DrbOutput,
+    config: &DrbConfig,
+) -> bool {
+    let num_checkpoints = config.num_checkpoints();
+    if output.checkpoints.len() as u64 != num_checkpoints {
+        return false;
+    }
+
+    // Each segment is a start, an expected end, and the number of hashes in between
+    let starts = std::iter::once(&drb_seed_input).chain(&output.checkpoints);
+    let ends = output
+        .checkpoints
+        .iter()
+        .chain(std::iter::once(&output.result));
+    let last_segment = config.difficulty - num_checkpoints * config.checkpoint_interval;
+    let segments: Vec<_> = starts
+        .zip(ends)
+        .enumerate()
+        .map(|(i, (start, end))| {
+            let iterations = if i as u64 == num_checkpoints {
+                last_segment
+            } else {
+                config.checkpoint_interval
+            };
+            (*start, *end, iterations)
+        })
+        .collect();
+
+    let parallelism = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = segments.len().div_ceil(parallelism);
+    thread::scope(|scope| {
+        segments
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .all(|(start, end, iterations)| iterate_hash(*start, *iterations) == *end)
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .all(|handle| handle.join().unwrap_or(false))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checkpoints_match_the_result() {
+        let seed = [3u8; 32];
+        let plain = compute_drb_result(
+            seed,
+            &DrbConfig {
+                difficulty: 1_000,
+                checkpoint_interval: 0,
+            },
+        );
+        assert!(plain.checkpoints.is_empty());
+        assert_eq!(plain.result, iterate_hash(seed, 1_000));
+
+        for checkpoint_interval in [1, 7, 100, 999, 1_000, 5_000] {
+            let config = DrbConfig {
+                difficulty: 1_000,
+                checkpoint_interval,
+            };
+            let output = compute_drb_result(seed, &config);
+            assert_eq!(output.result, plain.result);
+            assert_eq!(output.checkpoints.len() as u64, config.num_checkpoints());
+            assert!(verify_drb_result(seed, &output, &config));
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_results() {
+        let seed = [5u8; 32];
+        let config = DrbConfig {
+            difficulty: 1_000,
+            checkpoint_interval: 100,
+        };
+        let output = compute_drb_result(seed, &config);
+
+        let mut wrong_result = output.clone();
+        wrong_result.result[0] ^= 1;
+        assert!(!verify_drb_result(seed, &wrong_result, &config));
+
+        let mut wrong_checkpoint = output.clone();
+        wrong_checkpoint.checkpoints[4][0] ^= 1;
+        assert!(!verify_drb_result(seed, &wrong_checkpoint, &config));
+
+        let mut missing_checkpoint = output.clone();
+        missing_checkpoint.checkpoints.pop();
+        assert!(!verify_drb_result(seed, &missing_checkpoint, &config));
+
+        assert!(!verify_drb_result([6u8; 32], &output, &config));
+    }
+
+    #[test]
+    fn zero_difficulty_is_the_seed() {
+        let config = DrbConfig {
+            difficulty: 0,
+            checkpoint_interval: 10,
+        };
+        let output = compute_drb_result([9u8; 32], &config);
+        assert_eq!(output.result, [9u8; 32]);
+        assert!(verify_drb_result([9u8; 32], &output, &config));
+    }
+}
@@ -23,6 +23,10 @@ pub enum HotShotError<TYPES: NodeType> {
     #[error("Invalid state: {0}")]
     InvalidState(String),
 
+    /// The config is invalid
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+
     /// Leaf was not present in storage
     #[error("Missing leaf with commitment: {0}")]
     MissingLeaf(Commitment<Leaf2<TYPES>>),
@@ -35,6 +39,10 @@ pub enum HotShotError<TYPES: NodeType> {
     #[error("Failed to deserialize: {0}")]
     FailedToDeserialize(String),
 
+    /// The transaction was not accepted into the mempool
+    #[error("Transaction rejected: {0}")]
+    TransactionRejected(String),
+
     /// The view timed out
     #[error("View {view_number} timed out: {state:?}")]
     ViewTimedOut {
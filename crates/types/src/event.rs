@@ -6,9 +6,10 @@
 
 //! Events that a `HotShot` instance can emit
 
-use std::sync::Arc;
+use std::{collections::BTreeSet, num::NonZeroUsize, sync::Arc};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::{
     data::{DaProposal, Leaf2, QuorumProposal2, UpgradeProposal, VidDisperseShare},
@@ -115,7 +116,13 @@ pub enum EventType<TYPES: NodeType> {
         /// This list is sorted in reverse view number order, with the newest (highest view number)
         /// block first in the list.
         ///
-        /// This list may be incomplete if the node is currently performing catchup.
+        /// Together with the previous `Decide` event, this list has no height gaps: leaves this
+        /// node missed are fetched from its peers first, and those which could not be recovered
+        /// are reported with a [`EventType::DecideGap`] just before this event.
+        ///
+        /// This node never executed the blocks of the leaves it fetched, so their state is a
+        /// placeholder built by [`ValidatedState::from_header`], which only holds what can be read
+        /// from the block header. Their delta is `None`.
         /// Vid Info for a decided view may be missing if this node never saw it's share.
         leaf_chain: Arc<LeafChain<TYPES>>,
         /// The QC signing the most recent leaf in `leaf_chain`.
@@ -126,6 +133,14 @@ pub enum EventType<TYPES: NodeType> {
         /// Optional information of the number of transactions in the block, for logging purposes.
         block_size: Option<u64>,
     },
+    /// Decided leaves which this node missed and could not recover from its peers, between the
+    /// previous `Decide` event and the next one
+    DecideGap {
+        /// Height of the first missing leaf
+        from_height: u64,
+        /// Height of the last missing leaf
+        to_height: u64,
+    },
     /// A replica task was canceled by a timeout interrupt
     ReplicaViewTimeout {
         /// The view that timed out
@@ -180,7 +195,168 @@ pub enum EventType<TYPES: NodeType> {
         data: Vec<u8>,
     },
 }
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+
+impl<TYPES: NodeType> EventType<TYPES> {
+    /// The kind of this event
+    #[must_use]
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::Error { .. } => EventKind::Error,
+            Self::Decide { .. } => EventKind::Decide,
+            Self::DecideGap { .. } => EventKind::DecideGap,
+            Self::ReplicaViewTimeout { .. } => EventKind::ReplicaViewTimeout,
+            Self::ViewFinished { .. } => EventKind::ViewFinished,
+            Self::ViewTimeout { .. } => EventKind::ViewTimeout,
+            Self::Transactions { .. } => EventKind::Transactions,
+            Self::DaProposal { .. } => EventKind::DaProposal,
+            Self::QuorumProposal { .. } => EventKind::QuorumProposal,
+            Self::UpgradeProposal { .. } => EventKind::UpgradeProposal,
+            Self::ExternalMessageReceived { .. } => EventKind::ExternalMessageReceived,
+        }
+    }
+
+    /// The node which sent the contents of this event, if it came from another node
+    #[must_use]
+    pub fn sender(&self) -> Option<&TYPES::SignatureKey> {
+        match self {
+            Self::DaProposal { sender, .. }
+            | Self::QuorumProposal { sender, .. }
+            | Self::UpgradeProposal { sender, .. }
+            | Self::ExternalMessageReceived { sender, .. } => Some(sender),
+            _ => None,
+        }
+    }
+}
+
+/// The variant of an [`EventType`], without its contents
+#[non_exhaustive]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    /// [`EventType::Error`]
+    Error,
+    /// [`EventType::Decide`]
+    Decide,
+    /// [`EventType::DecideGap`]
+    DecideGap,
+    /// [`EventType::ReplicaViewTimeout`]
+    ReplicaViewTimeout,
+    /// [`EventType::ViewFinished`]
+    ViewFinished,
+    /// [`EventType::ViewTimeout`]
+    ViewTimeout,
+    /// [`EventType::Transactions`]
+    Transactions,
+    /// [`EventType::DaProposal`]
+    DaProposal,
+    /// [`EventType::QuorumProposal`]
+    QuorumProposal,
+    /// [`EventType::UpgradeProposal`]
+    UpgradeProposal,
+    /// [`EventType::ExternalMessageReceived`]
+    ExternalMessageReceived,
+}
+
+/// Selects the events a subscriber of a filtered event stream receives.
+///
+/// Every criterion left as `None` matches all events, so [`EventFilter::default`] passes
+/// everything through.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = "TYPES: NodeType"))]
+pub struct EventFilter<TYPES: NodeType> {
+    /// The kinds of events to keep
+    pub kinds: Option<BTreeSet<EventKind>>,
+    /// The first view to keep events from
+    pub from_view: Option<TYPES::View>,
+    /// The last view to keep events from
+    pub to_view: Option<TYPES::View>,
+    /// Keep only events sent by one of these nodes. Events without a sender, such as `Decide`,
+    /// never match once this is set.
+    pub senders: Option<BTreeSet<TYPES::SignatureKey>>,
+    /// Buffer the matching events in a queue of their own with this capacity, instead of reading
+    /// them straight from the shared output channel. Once the queue is full the oldest events are
+    /// dropped, and the stream reports how many were missed.
+    pub capacity: Option<NonZeroUsize>,
+}
+
+impl<TYPES: NodeType> Default for EventFilter<TYPES> {
+    fn default() -> Self {
+        Self {
+            kinds: None,
+            from_view: None,
+            to_view: None,
+            senders: None,
+            capacity: None,
+        }
+    }
+}
+
+impl<TYPES: NodeType> EventFilter<TYPES> {
+    /// Keep only events of the given kinds
+    #[must_use]
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    /// Keep only events from `view` onwards
+    #[must_use]
+    pub fn from_view(mut self, view: TYPES::View) -> Self {
+        self.from_view = Some(view);
+        self
+    }
+
+    /// Keep only events up to and including `view`
+    #[must_use]
+    pub fn to_view(mut self, view: TYPES::View) -> Self {
+        self.to_view = Some(view);
+        self
+    }
+
+    /// Keep only events sent by one of `senders`
+    #[must_use]
+    pub fn senders(mut self, senders: impl IntoIterator<Item = TYPES::SignatureKey>) -> Self {
+        self.senders = Some(senders.into_iter().collect());
+        self
+    }
+
+    /// Buffer the matching events in a queue of `capacity` events
+    #[must_use]
+    pub fn bounded(mut self, capacity: NonZeroUsize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Whether `event` passes the filter
+    #[must_use]
+    pub fn matches(&self, event: &Event<TYPES>) -> bool {
+        self.kinds
+            .as_ref()
+            .map_or(true, |kinds| kinds.contains(&event.event.kind()))
+            && self
+                .from_view
+                .map_or(true, |from_view| event.view_number >= from_view)
+            && self
+                .to_view
+                .map_or(true, |to_view| event.view_number <= to_view)
+            && self.senders.as_ref().map_or(true, |senders| {
+                event
+                    .event
+                    .sender()
+                    .is_some_and(|sender| senders.contains(sender))
+            })
+    }
+}
+
+/// Yielded by a filtered event stream in place of the events a subscriber missed because it fell
+/// behind
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("Subscriber fell behind and missed {missed} events")]
+pub struct EventsLagged {
+    /// The number of events missed
+    pub missed: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// A list of actions that we track for nodes
 pub enum HotShotAction {
     /// A quorum vote was sent
@@ -10,8 +10,12 @@ use url::Url;
 use vec1::Vec1;
 
 use crate::{
-    constants::REQUEST_DATA_DELAY, traits::signature_key::SignatureKey,
-    upgrade_config::UpgradeConfig, HotShotConfig, PeerConfig, ValidatorConfig,
+    constants::REQUEST_DATA_DELAY,
+    drb::DrbConfig,
+    traits::{signature_key::SignatureKey, storage::RetentionPolicy},
+    upgrade_config::UpgradeConfig,
+    vid::VidCodeRate,
+    HotShotConfig, MempoolConfig, PeerConfig, ValidatorConfig,
 };
 
 /// Default builder URL, used as placeholder
@@ -55,6 +59,18 @@ pub struct HotShotConfigFile<KEY: SignatureKey> {
     pub upgrade: UpgradeConfig,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// DRB config
+    #[serde(default)]
+    pub drb: DrbConfig,
+    /// Mempool config, if the node should keep a local transaction pool
+    #[serde(default)]
+    pub mempool: Option<MempoolConfig>,
+    /// Erasure code rate of VID
+    #[serde(default)]
+    pub vid_code_rate: VidCodeRate,
+    /// Storage retention policy
+    #[serde(default)]
+    pub retention_policy: RetentionPolicy,
 }
 
 impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
@@ -83,6 +99,10 @@ impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
             start_voting_time: val.upgrade.start_voting_time,
             stop_voting_time: val.upgrade.stop_voting_time,
             epoch_height: val.epoch_height,
+            drb: val.drb,
+            mempool: val.mempool,
+            vid_code_rate: val.vid_code_rate,
+            retention_policy: val.retention_policy,
         }
     }
 }
@@ -128,6 +148,10 @@ impl<KEY: SignatureKey> HotShotConfigFile<KEY> {
             builder_urls: default_builder_urls(),
             upgrade: UpgradeConfig::default(),
             epoch_height: 0,
+            drb: DrbConfig::default(),
+            mempool: None,
+            vid_code_rate: VidCodeRate::default(),
+            retention_policy: RetentionPolicy::default(),
         }
     }
 }
@@ -9,17 +9,20 @@ use std::{fmt::Debug, future::Future, num::NonZeroUsize, pin::Pin, time::Duratio
 
 use bincode::Options;
 use displaydoc::Display;
+use drb::DrbConfig;
 use light_client::StateVerKey;
 use tracing::error;
 use traits::signature_key::SignatureKey;
 use url::Url;
 use vec1::Vec1;
 
-use crate::utils::bincode_opts;
+use crate::{traits::storage::RetentionPolicy, utils::bincode_opts, vid::VidCodeRate};
 pub mod bundle;
+pub mod compression;
 pub mod consensus;
 pub mod constants;
 pub mod data;
+pub mod drb;
 pub mod error;
 pub mod event;
 /// Holds the configuration file specification for a HotShot node.
@@ -34,6 +37,7 @@ pub mod request_response;
 pub mod signature_key;
 pub mod simple_certificate;
 pub mod simple_vote;
+pub mod slashing_protection;
 pub mod stake_table;
 pub mod traits;
 
@@ -158,6 +162,32 @@ impl<KEY: SignatureKey> Default for PeerConfig<KEY> {
     }
 }
 
+/// Limits of the local pool of transactions waiting to be included in a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct MempoolConfig {
+    /// The total size of the pending transactions, in bytes
+    pub max_bytes: u64,
+    /// The total size of the pending transactions delivered by any one peer, in bytes. The
+    /// node's own transactions are exempt.
+    pub max_bytes_per_sender: u64,
+    /// The total size of the pending transactions delivered by networks which don't tell who
+    /// by, such as the CDN, in bytes
+    pub max_bytes_unattributed: u64,
+    /// How long a transaction may stay pending before it is evicted
+    pub max_age: Duration,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024 * 1024,
+            max_bytes_per_sender: 4 * 1024 * 1024,
+            max_bytes_unattributed: 32 * 1024 * 1024,
+            max_age: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
 /// Holds configuration for a `HotShot`
 #[derive(Clone, derive_more::Debug, serde::Serialize, serde::Deserialize)]
 #[serde(bound(deserialize = ""))]
@@ -206,6 +236,23 @@ pub struct HotShotConfig<KEY: SignatureKey> {
     pub stop_voting_time: u64,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// How the DRB used for leader election with epochs is computed
+    #[serde(default)]
+    pub drb: DrbConfig,
+    /// Limits of the local transaction pool, or `None` to keep no pool
+    #[serde(default)]
+    pub mempool: Option<MempoolConfig>,
+    /// Erasure code rate of VID, from which the number of shares needed to recover a payload is
+    /// derived. Builders must use the same rate to compute payload commitments.
+    ///
+    /// ADVZ, the VID scheme, only takes a power of two of shares, so the number the rate gives is
+    /// rounded down to one, and the effective rate may be lower than this one; see
+    /// [`VidCodeRate::recovery_threshold`].
+    #[serde(default)]
+    pub vid_code_rate: VidCodeRate,
+    /// What storage keeps around once views are decided
+    #[serde(default)]
+    pub retention_policy: RetentionPolicy,
 }
 
 impl<KEY: SignatureKey> HotShotConfig<KEY> {
@@ -25,6 +25,7 @@ use vbs::{
 };
 
 use crate::{
+    compression::CompressionConfig,
     data::{
         DaProposal, Leaf, Leaf2, QuorumProposal, QuorumProposal2, UpgradeProposal, VidDisperseShare,
     },
@@ -156,6 +157,7 @@ impl<TYPES: NodeType> ViewMessage<TYPES> for MessageKind<TYPES> {
             MessageKind::Data(DataMessage::RequestData(msg)) => msg.view,
             MessageKind::Data(DataMessage::DataResponse(msg)) => match msg {
                 ResponseMessage::Found(m) => m.view_number(),
+                ResponseMessage::Data { data, .. } => data.view_number(),
                 ResponseMessage::NotFound | ResponseMessage::Denied => TYPES::View::new(1),
             },
             MessageKind::External(_) => TYPES::View::new(1),
@@ -403,6 +405,9 @@ pub struct UpgradeLock<TYPES: NodeType, V: Versions> {
 
     /// phantom data for the `Versions` trait
     pub _pd: PhantomData<V>,
+
+    /// how message bodies are compressed from `V::Compression` onwards
+    pub compression: CompressionConfig,
 }
 
 impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
@@ -412,6 +417,7 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
         Self {
             decided_upgrade_certificate: Arc::new(RwLock::new(None)),
             _pd: PhantomData::<V>,
+            compression: CompressionConfig::default(),
         }
     }
 
@@ -421,9 +427,17 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
         Self {
             decided_upgrade_certificate: Arc::new(RwLock::new(certificate.clone())),
             _pd: PhantomData::<V>,
+            compression: CompressionConfig::default(),
         }
     }
 
+    /// Use `compression` for messages from `V::Compression` onwards
+    #[must_use]
+    pub fn with_compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = compression;
+        self
+    }
+
     /// Calculate the version applied in a view, based on the provided upgrade lock.
     ///
     /// # Errors
@@ -489,9 +503,30 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
             }
         };
 
-        serialized_message
+        let serialized_message = serialized_message
+            .wrap()
+            .context(info!("Failed to serialize message!"))?;
+
+        if version < V::Compression::VERSION {
+            return Ok(serialized_message);
+        }
+
+        // Compress everything after the version prefix, which stays readable so the receiver
+        // knows whether to decompress
+        let (_, body) = Version::deserialize(&serialized_message)
             .wrap()
-            .context(info!("Failed to serialize message!"))
+            .context(info!("Failed to read message version!"))?;
+        let prefix_len = serialized_message.len() - body.len();
+        let compressed_body = self
+            .compression
+            .compress(body)
+            .context(info!("Failed to compress message!"))?;
+
+        let mut compressed_message = serialized_message;
+        compressed_message.truncate(prefix_len);
+        compressed_message.extend_from_slice(&compressed_body);
+
+        Ok(compressed_message)
     }
 
     /// Deserialize a message with a version number, using `message.view_number()` to determine the message's version. This function will fail on improperly versioned messages.
@@ -503,10 +538,23 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
         &self,
         message: &[u8],
     ) -> Result<M> {
-        let actual_version = Version::deserialize(message)
+        let (actual_version, body) = Version::deserialize(message)
             .wrap()
-            .context(info!("Failed to read message version!"))?
-            .0;
+            .context(info!("Failed to read message version!"))?;
+
+        // Undo the compression applied by `serialize`
+        let decompressed_message;
+        let message = if actual_version >= V::Compression::VERSION {
+            let prefix = &message[..message.len() - body.len()];
+            let decompressed_body = self
+                .compression
+                .decompress(body)
+                .context(info!("Failed to decompress message!"))?;
+            decompressed_message = [prefix, &decompressed_body].concat();
+            &decompressed_message[..]
+        } else {
+            message
+        };
 
         let deserialized_message: M = match actual_version {
             v if v == V::Base::VERSION => Serializer::<V::Base>::deserialize(message),
@@ -12,6 +12,7 @@ use multiaddr::Multiaddr;
 use serde_inline_default::serde_inline_default;
 use thiserror::Error;
 use tracing::error;
+use url::Url;
 
 use crate::{
     constants::{
@@ -29,6 +30,21 @@ use crate::{
 pub struct Libp2pConfig {
     /// The bootstrap nodes to connect to (multiaddress, serialized public key)
     pub bootstrap_nodes: Vec<(PeerId, Multiaddr)>,
+    /// The transport to run libp2p over. The bootstrap node addresses must be for this transport
+    #[serde(default)]
+    pub transport: Libp2pTransport,
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, Default, ValueEnum,
+)]
+/// the transport libp2p connections are made over
+pub enum Libp2pTransport {
+    #[default]
+    /// QUIC over UDP, which brings its own encryption and multiplexing
+    Quic,
+    /// TCP, secured with Noise and multiplexed with Yamux
+    Tcp,
 }
 
 /// configuration for combined network
@@ -106,6 +122,16 @@ impl Default for RandomBuilderConfig {
     }
 }
 
+/// Options for the builder a leader falls back to for a block of its own pending transactions,
+/// when no builder delivers one in time
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct LocalBuilderConfig {
+    /// Seed the builder key is generated from, indexed by the node index
+    pub seed: [u8; 32],
+    /// The total size of the transactions in a locally built block, in bytes
+    pub max_block_size: u64,
+}
+
 /// a network configuration
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 #[serde(bound(deserialize = ""))]
@@ -153,6 +179,10 @@ pub struct NetworkConfig<KEY: SignatureKey> {
     pub builder: BuilderType,
     /// random builder config
     pub random_builder: Option<RandomBuilderConfig>,
+    /// local builder config, if nodes should build blocks of their own mempool
+    pub local_builder: Option<LocalBuilderConfig>,
+    /// URL of the Solver to fetch the auction results from, if the marketplace is used
+    pub solver_url: Option<Url>,
     /// The list of public keys that are allowed to connect to the orchestrator
     pub public_keys: Vec<PeerConfigKeys<KEY>>,
 }
@@ -290,6 +320,8 @@ impl<K: SignatureKey> Default for NetworkConfig<K> {
             commit_sha: String::new(),
             builder: BuilderType::default(),
             random_builder: None,
+            local_builder: None,
+            solver_url: None,
             public_keys: vec![],
         }
     }
@@ -349,6 +381,12 @@ pub struct NetworkConfigFile<KEY: SignatureKey> {
     /// random builder configuration
     #[serde(default)]
     pub random_builder: Option<RandomBuilderConfig>,
+    /// local builder configuration, if nodes should build blocks of their own mempool
+    #[serde(default)]
+    pub local_builder: Option<LocalBuilderConfig>,
+    /// URL of the Solver to fetch the auction results from, if the marketplace is used
+    #[serde(default)]
+    pub solver_url: Option<Url>,
     /// The list of public keys that are allowed to connect to the orchestrator
     ///
     /// If nonempty, this list becomes the stake table and is used to determine DA membership (ignoring the node's request).
@@ -376,6 +414,7 @@ impl<K: SignatureKey> From<NetworkConfigFile<K>> for NetworkConfig<K> {
             transaction_size: val.transaction_size,
             libp2p_config: Some(Libp2pConfig {
                 bootstrap_nodes: Vec::new(),
+                transport: Libp2pTransport::default(),
             }),
             config: val.config.into(),
             key_type_name: std::any::type_name::<K>().to_string(),
@@ -384,6 +423,8 @@ impl<K: SignatureKey> From<NetworkConfigFile<K>> for NetworkConfig<K> {
             commit_sha: String::new(),
             builder: val.builder,
             random_builder: val.random_builder,
+            local_builder: val.local_builder,
+            solver_url: val.solver_url,
             public_keys: val.public_keys,
         }
     }
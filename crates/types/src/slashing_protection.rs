@@ -0,0 +1,371 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Slashing protection
+//!
+//! This module provides [`SigningHistory`], a record of every vote and proposal a node has
+//! signed, which is used to refuse signing two conflicting messages in the same view. The history
+//! can be exported to and imported from an [`Interchange`] file, whose JSON format is modelled on
+//! Ethereum's EIP-3076, so that a key can be moved between machines without losing its
+//! protection.
+
+use std::{collections::BTreeMap, fmt};
+
+use committable::{Commitment, Committable};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+    event::HotShotAction,
+    traits::node_implementation::{ConsensusTime, NodeType},
+};
+
+/// Version of the interchange format produced by [`SigningHistory::to_interchange`].
+pub const INTERCHANGE_FORMAT_VERSION: &str = "1";
+
+/// Returns whether signing `action` twice in the same view would be equivocation, and therefore
+/// whether it is tracked in a [`SigningHistory`].
+#[must_use]
+pub fn is_protected(action: HotShotAction) -> bool {
+    matches!(
+        action,
+        HotShotAction::Vote
+            | HotShotAction::Propose
+            | HotShotAction::DaVote
+            | HotShotAction::ViewSyncVote
+    )
+}
+
+/// Commitment to the data covered by a signature.
+///
+/// Signing the same data twice is harmless, so an action is only refused if it has a different
+/// signing root than what we previously signed for the same view.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SigningRoot([u8; 32]);
+
+impl<T: Committable> From<Commitment<T>> for SigningRoot {
+    fn from(commitment: Commitment<T>) -> Self {
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(commitment.as_ref());
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for SigningRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x")?;
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SigningRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::str::FromStr for SigningRoot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix("0x").unwrap_or(s);
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(format!("Invalid signing root: {s}"));
+        }
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+                .map_err(|e| format!("Invalid signing root {s}: {e}"))?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for SigningRoot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SigningRoot {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer)?
+                .parse()
+                .map_err(de::Error::custom)
+        } else {
+            Ok(Self(<[u8; 32]>::deserialize(deserializer)?))
+        }
+    }
+}
+
+/// Reasons for refusing to sign an action.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SlashingProtectionError {
+    /// We already signed something different for this action in this view
+    #[error("Already signed {action:?} in view {view} over {existing}, refusing to sign {new}")]
+    Conflict {
+        /// The view of the action
+        view: u64,
+        /// The action
+        action: HotShotAction,
+        /// What we signed before
+        existing: SigningRoot,
+        /// What we were asked to sign
+        new: SigningRoot,
+    },
+
+    /// The action is older than the history we have, so we cannot tell whether it conflicts
+    #[error(
+        "Refusing to sign {action:?} in view {view}, which is below the watermark {watermark}"
+    )]
+    BelowWatermark {
+        /// The view of the action
+        view: u64,
+        /// The action
+        action: HotShotAction,
+        /// The lowest view in which the action may be signed
+        watermark: u64,
+    },
+}
+
+/// The actions a node has signed, keyed by view.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct SigningHistory<TYPES: NodeType> {
+    /// What we signed for each action in each view
+    records: BTreeMap<(TYPES::View, HotShotAction), SigningRoot>,
+    /// Lowest view in which each action may still be signed. History below it was pruned or
+    /// imported without its details, so anything older is refused.
+    watermarks: BTreeMap<HotShotAction, TYPES::View>,
+}
+
+impl<TYPES: NodeType> Default for SigningHistory<TYPES> {
+    fn default() -> Self {
+        Self {
+            records: BTreeMap::new(),
+            watermarks: BTreeMap::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> SigningHistory<TYPES> {
+    /// Check whether `action` over `signing_root` may be signed in `view`.
+    ///
+    /// Returns `Ok(true)` if the action has to be recorded, and `Ok(false)` if we already signed
+    /// exactly this before.
+    ///
+    /// # Errors
+    /// Returns an error if signing would conflict with the history.
+    pub fn check(
+        &self,
+        view: TYPES::View,
+        action: HotShotAction,
+        signing_root: SigningRoot,
+    ) -> Result<bool, SlashingProtectionError> {
+        if !is_protected(action) {
+            return Ok(false);
+        }
+        if let Some(existing) = self.records.get(&(view, action)) {
+            if *existing == signing_root {
+                return Ok(false);
+            }
+            return Err(SlashingProtectionError::Conflict {
+                view: *view,
+                action,
+                existing: *existing,
+                new: signing_root,
+            });
+        }
+        if let Some(watermark) = self.watermarks.get(&action) {
+            if view < *watermark {
+                return Err(SlashingProtectionError::BelowWatermark {
+                    view: *view,
+                    action,
+                    watermark: **watermark,
+                });
+            }
+        }
+        Ok(true)
+    }
+
+    /// Check `action` like [`Self::check`], and record it if it may be signed.
+    ///
+    /// Returns whether the history changed.
+    ///
+    /// # Errors
+    /// Returns an error if signing would conflict with the history.
+    pub fn check_and_insert(
+        &mut self,
+        view: TYPES::View,
+        action: HotShotAction,
+        signing_root: SigningRoot,
+    ) -> Result<bool, SlashingProtectionError> {
+        let is_new = self.check(view, action, signing_root)?;
+        if is_new {
+            self.records.insert((view, action), signing_root);
+        }
+        Ok(is_new)
+    }
+
+    /// Iterate over the recorded actions, oldest first.
+    pub fn records(&self) -> impl Iterator<Item = (TYPES::View, HotShotAction, SigningRoot)> + '_ {
+        self.records
+            .iter()
+            .map(|((view, action), signing_root)| (*view, *action, *signing_root))
+    }
+
+    /// Drop the records of views before `view`, and refuse to sign anything before it from now on.
+    pub fn prune(&mut self, view: TYPES::View) {
+        let pruned = std::mem::take(&mut self.records);
+        for ((record_view, action), signing_root) in pruned {
+            if record_view < view {
+                self.raise_watermark(action, view);
+            } else {
+                self.records.insert((record_view, action), signing_root);
+            }
+        }
+    }
+
+    /// Merge another history into this one.
+    ///
+    /// Where the two histories disagree about a view, we keep our own record, and refuse to sign
+    /// that action again until a later view, since either of the two may have been broadcast.
+    pub fn merge(&mut self, other: Self) {
+        for (action, watermark) in other.watermarks {
+            self.raise_watermark(action, watermark);
+        }
+        for ((view, action), signing_root) in other.records {
+            match self.records.get(&(view, action)) {
+                Some(existing) if *existing != signing_root => {
+                    tracing::warn!(
+                        "Conflicting {:?} records for view {:?} while merging signing histories",
+                        action,
+                        view
+                    );
+                    self.raise_watermark(action, view + 1);
+                }
+                Some(_) => {}
+                None => {
+                    self.records.insert((view, action), signing_root);
+                }
+            }
+        }
+    }
+
+    /// Export the history of `pubkey` in the interchange format.
+    #[must_use]
+    pub fn to_interchange(&self, pubkey: TYPES::SignatureKey) -> Interchange<TYPES> {
+        Interchange {
+            metadata: InterchangeMetadata {
+                interchange_format_version: INTERCHANGE_FORMAT_VERSION.to_string(),
+            },
+            data: vec![InterchangeData {
+                pubkey,
+                signed_actions: self
+                    .records()
+                    .map(|(view, action, signing_root)| SignedAction {
+                        view: *view,
+                        action,
+                        signing_root,
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    /// Build the history of `pubkey` from an interchange file.
+    ///
+    /// Besides the individual records, the most recent view of each action becomes its watermark,
+    /// so that we never sign anything older than what was signed on the other machine.
+    ///
+    /// # Errors
+    /// Returns an error if the interchange format version is not supported.
+    pub fn from_interchange(
+        interchange: &Interchange<TYPES>,
+        pubkey: &TYPES::SignatureKey,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            interchange.metadata.interchange_format_version == INTERCHANGE_FORMAT_VERSION,
+            "Unsupported interchange format version {}",
+            interchange.metadata.interchange_format_version
+        );
+
+        let mut history = Self::default();
+        for data in interchange
+            .data
+            .iter()
+            .filter(|data| data.pubkey == *pubkey)
+        {
+            let mut imported = Self::default();
+            for signed in &data.signed_actions {
+                let view = TYPES::View::new(signed.view);
+                if imported
+                    .check_and_insert(view, signed.action, signed.signing_root)
+                    .is_err()
+                {
+                    imported.raise_watermark(signed.action, view + 1);
+                }
+                imported.raise_watermark(signed.action, view);
+            }
+            history.merge(imported);
+        }
+        Ok(history)
+    }
+
+    /// Make sure `action` is refused in any view before `view`.
+    fn raise_watermark(&mut self, action: HotShotAction, view: TYPES::View) {
+        let watermark = self.watermarks.entry(action).or_insert(view);
+        if view > *watermark {
+            *watermark = view;
+        }
+    }
+}
+
+/// A history of signed actions which can be moved between machines, serialized as JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct Interchange<TYPES: NodeType> {
+    /// Information about the file itself
+    pub metadata: InterchangeMetadata,
+    /// The history of each key in the file
+    pub data: Vec<InterchangeData<TYPES>>,
+}
+
+/// Information about an [`Interchange`] file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterchangeMetadata {
+    /// Version of the format, currently [`INTERCHANGE_FORMAT_VERSION`]
+    pub interchange_format_version: String,
+}
+
+/// The signing history of a single key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct InterchangeData<TYPES: NodeType> {
+    /// The key which signed the actions
+    pub pubkey: TYPES::SignatureKey,
+    /// The signed actions
+    pub signed_actions: Vec<SignedAction>,
+}
+
+/// A single signed action in an [`Interchange`] file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedAction {
+    /// The view the action was signed in
+    pub view: u64,
+    /// The action
+    pub action: HotShotAction,
+    /// Commitment to what was signed
+    pub signing_root: SigningRoot,
+}
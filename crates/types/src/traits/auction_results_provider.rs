@@ -18,7 +18,12 @@ use super::node_implementation::NodeType;
 /// type has the requisite fields available.
 #[async_trait]
 pub trait AuctionResultsProvider<TYPES: NodeType>: Send + Sync + Clone {
-    /// Fetches the auction result for a view. Does not cache the result,
+    /// Fetches the auction result for a view. Implementations may cache the result, otherwise
     /// subsequent calls will invoke additional wasted calls.
     async fn fetch_auction_result(&self, view_number: TYPES::View) -> Result<TYPES::AuctionResult>;
+
+    /// Starts fetching the auction result for a view in the background, so that a later call to
+    /// [`Self::fetch_auction_result`] for it doesn't have to wait for the Solver. Implementations
+    /// which don't cache results do nothing.
+    fn prefetch_auction_result(&self, _view_number: TYPES::View) {}
 }
@@ -28,7 +28,7 @@ use crate::{
     data::Leaf2,
     traits::{node_implementation::NodeType, states::InstanceState, ValidatedState},
     utils::BuilderCommitment,
-    vid::{vid_scheme, VidCommitment, VidCommon, VidSchemeType},
+    vid::{vid_scheme, VidCodeRate, VidCommitment, VidCommon, VidSchemeType},
 };
 
 /// Trait for structures that need to be unambiguously encoded as bytes.
@@ -46,6 +46,12 @@ pub trait Transaction:
     /// Since each new namespace adds overhead
     /// just ignore this parameter by default and use it when needed
     fn minimum_block_size(&self) -> u64;
+
+    /// How strongly the transaction should be preferred over others when they compete for space,
+    /// typically the fee it pays. Higher is preferred.
+    fn priority(&self) -> u64 {
+        0
+    }
 }
 
 /// Abstraction over the full contents of a block
@@ -148,9 +154,10 @@ pub trait TestableBlock<TYPES: NodeType>: BlockPayload<TYPES> + Debug {
 pub fn vid_commitment(
     encoded_transactions: &[u8],
     num_storage_nodes: usize,
+    code_rate: VidCodeRate,
 ) -> <VidSchemeType as VidScheme>::Commit {
     let encoded_tx_len = encoded_transactions.len();
-    vid_scheme(num_storage_nodes).commit_only(encoded_transactions).unwrap_or_else(|err| panic!("VidScheme::commit_only failure:(num_storage_nodes,payload_byte_len)=({num_storage_nodes},{encoded_tx_len}) error: {err}"))
+    vid_scheme(num_storage_nodes, code_rate).and_then(|mut vid| vid.commit_only(encoded_transactions)).unwrap_or_else(|err| panic!("VidScheme::commit_only failure:(num_storage_nodes,code_rate,payload_byte_len)=({num_storage_nodes},{code_rate},{encoded_tx_len}) error: {err}"))
 }
 
 /// Compute the VID payload commitment along with precompute data reducing time in VID Disperse
@@ -161,12 +168,13 @@ pub fn vid_commitment(
 pub fn precompute_vid_commitment(
     encoded_transactions: &[u8],
     num_storage_nodes: usize,
+    code_rate: VidCodeRate,
 ) -> (
     <VidSchemeType as VidScheme>::Commit,
     <VidSchemeType as Precomputable>::PrecomputeData,
 ) {
     let encoded_tx_len = encoded_transactions.len();
-    vid_scheme(num_storage_nodes).commit_only_precompute(encoded_transactions).unwrap_or_else(|err| panic!("VidScheme::commit_only failure:(num_storage_nodes,payload_byte_len)=({num_storage_nodes},{encoded_tx_len}) error: {err}"))
+    vid_scheme(num_storage_nodes, code_rate).and_then(|vid| vid.commit_only_precompute(encoded_transactions)).unwrap_or_else(|err| panic!("VidScheme::commit_only failure:(num_storage_nodes,code_rate,payload_byte_len)=({num_storage_nodes},{code_rate},{encoded_tx_len}) error: {err}"))
 }
 
 /// The number of storage nodes to use when computing the genesis VID commitment.
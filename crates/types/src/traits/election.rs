@@ -5,12 +5,17 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! The election trait, used to decide which node is the leader and determine if a vote is valid.
-use std::{collections::BTreeSet, fmt::Debug, num::NonZeroU64};
+use std::{collections::BTreeSet, fmt::Debug};
 
+use primitive_types::U256;
 use utils::anytrace::Result;
 
 use super::{network::Topic, node_implementation::NodeType};
-use crate::{traits::signature_key::SignatureKey, PeerConfig};
+use crate::{
+    drb::DrbResult,
+    traits::signature_key::{SignatureKey, StakeTableEntryType},
+    PeerConfig,
+};
 
 /// A protocol for determining membership in and participating in a committee.
 pub trait Membership<TYPES: NodeType>: Clone + Debug + Send + Sync {
@@ -88,12 +93,38 @@ pub trait Membership<TYPES: NodeType>: Clone + Debug + Send + Sync {
     /// Returns the number of total nodes in the committee in an epoch `epoch`
     fn total_nodes(&self, epoch: TYPES::Epoch) -> usize;
 
-    /// Returns the threshold for a specific `Membership` implementation
-    fn success_threshold(&self) -> NonZeroU64;
+    /// Returns the total stake of the committee in `epoch`
+    fn total_stake(&self, epoch: TYPES::Epoch) -> U256 {
+        self.stake_table(epoch)
+            .iter()
+            .fold(U256::zero(), |total, entry| {
+                total.saturating_add(entry.stake())
+            })
+    }
+
+    /// Returns the stake needed for a quorum in `epoch`, i.e. more than two thirds of the
+    /// total stake
+    fn success_threshold(&self, epoch: TYPES::Epoch) -> U256;
+
+    /// Returns the stake needed to be sure that at least one honest node is involved in `epoch`,
+    /// i.e. more than a third of the total stake
+    fn failure_threshold(&self, epoch: TYPES::Epoch) -> U256;
+
+    /// Returns the stake required to upgrade the network protocol in `epoch`
+    fn upgrade_threshold(&self, epoch: TYPES::Epoch) -> U256;
 
-    /// Returns the threshold for a specific `Membership` implementation
-    fn failure_threshold(&self) -> NonZeroU64;
+    /// Set the stake table of the committee from `epoch` onwards, as derived from decided state.
+    ///
+    /// Implementations whose committee never changes can ignore it, which is the default.
+    fn update_stake_table(
+        &self,
+        _epoch: TYPES::Epoch,
+        _stake_table: Vec<PeerConfig<TYPES::SignatureKey>>,
+    ) {
+    }
 
-    /// Returns the threshold required to upgrade the network protocol
-    fn upgrade_threshold(&self) -> NonZeroU64;
+    /// Provide the DRB result used to choose the leaders of `epoch`.
+    ///
+    /// Implementations which don't use the DRB can ignore it, which is the default.
+    fn add_drb_result(&self, _epoch: TYPES::Epoch, _drb_result: DrbResult) {}
 }
@@ -20,16 +20,24 @@ use std::{
 use async_trait::async_trait;
 use dyn_clone::DynClone;
 use futures::{future::join_all, Future};
+use libp2p_identity::PeerId;
 use rand::{
     distributions::{Bernoulli, Uniform},
     prelude::Distribution,
 };
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 use tokio::{sync::mpsc::error::TrySendError, time::sleep};
 
 use super::{node_implementation::NodeType, signature_key::SignatureKey};
-use crate::{data::ViewNumber, message::SequencingMessage, BoxSyncFuture};
+use crate::{
+    data::{Leaf2, ViewNumber},
+    message::SequencingMessage,
+    simple_certificate::QuorumCertificate2,
+    vote::HasViewNumber,
+    BoxSyncFuture,
+};
 
 /// Centralized server specific errors
 #[derive(Debug, Error, Serialize, Deserialize)]
@@ -141,6 +149,85 @@ pub enum RequestKind<TYPES: NodeType> {
     DaProposal(TYPES::View),
     /// Request for quorum proposal for a view
     Proposal(TYPES::View),
+    /// Request the decided leaf at a block height, with its payload
+    Leaf {
+        /// Height of the leaf
+        height: u64,
+    },
+    /// Request the payload of the leaf proposed in a view
+    Payload {
+        /// View of the leaf
+        view: TYPES::View,
+    },
+    /// Request the quorum certificate formed in a view
+    QuorumCertificate {
+        /// View of the certified leaf
+        view: TYPES::View,
+    },
+}
+
+/// Decided data served in response to [`RequestKind::Leaf`], [`RequestKind::Payload`] and
+/// [`RequestKind::QuorumCertificate`] requests
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub enum ResponseData<TYPES: NodeType> {
+    /// A decided leaf, with its payload if the responder has one
+    Leaf(Leaf2<TYPES>),
+    /// The payload of the leaf proposed in `view`
+    Payload {
+        /// View of the leaf
+        view: TYPES::View,
+        /// The payload
+        payload: TYPES::BlockPayload,
+    },
+    /// A quorum certificate
+    QuorumCertificate(QuorumCertificate2<TYPES>),
+}
+
+impl<TYPES: NodeType> ResponseData<TYPES> {
+    /// The view this data belongs to
+    #[must_use]
+    pub fn view_number(&self) -> TYPES::View {
+        match self {
+            Self::Leaf(leaf) => leaf.view_number(),
+            Self::Payload { view, .. } => *view,
+            Self::QuorumCertificate(qc) => qc.view_number(),
+        }
+    }
+
+    /// Whether this is the data `request` asked for
+    #[must_use]
+    pub fn answers(&self, request: &RequestKind<TYPES>) -> bool {
+        match (self, request) {
+            (Self::Leaf(leaf), RequestKind::Leaf { height }) => leaf.height() == *height,
+            (Self::Payload { .. }, RequestKind::Payload { view })
+            | (Self::QuorumCertificate(_), RequestKind::QuorumCertificate { view }) => {
+                self.view_number() == *view
+            }
+            _ => false,
+        }
+    }
+
+    /// Sign the hash of the serialized data, as the responder does before sending it. Returns
+    /// `None` if the data can't be serialized or signed.
+    #[must_use]
+    pub fn sign(
+        &self,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType> {
+        let data = bincode::serialize(self).ok()?;
+        TYPES::SignatureKey::sign(private_key, &Sha256::digest(data)).ok()
+    }
+
+    /// Whether `signature` is a signature of this data by `key`
+    #[must_use]
+    pub fn is_signed_by(
+        &self,
+        key: &TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> bool {
+        bincode::serialize(self).is_ok_and(|data| key.validate(signature, &Sha256::digest(data)))
+    }
 }
 
 /// A response for a request.  `SequencingMessage` is the same as other network messages
@@ -156,6 +243,81 @@ pub enum ResponseMessage<TYPES: NodeType> {
     NotFound,
     /// The Request was denied
     Denied,
+    /// Peer returned us decided data, signed with its key
+    Data {
+        /// The data, which fails to deserialize if it is larger than
+        /// [`MAX_DATA_RESPONSE_SIZE`](crate::constants::MAX_DATA_RESPONSE_SIZE)
+        #[serde(with = "size_limited_response_data")]
+        data: ResponseData<TYPES>,
+        /// Signature of the data by the peer
+        signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    },
+}
+
+/// Utilities for sending [`ResponseData`] as its bincode bytes, so that data larger than
+/// [`MAX_DATA_RESPONSE_SIZE`](crate::constants::MAX_DATA_RESPONSE_SIZE) is refused before any of
+/// it is decoded.
+pub mod size_limited_response_data {
+    use std::{fmt, marker::PhantomData};
+
+    use bincode::Options;
+    use serde::{
+        de::{self, Deserializer, Visitor},
+        ser::{self, Serializer},
+    };
+
+    use super::{NodeType, ResponseData};
+    use crate::constants::MAX_DATA_RESPONSE_SIZE;
+
+    /// Serialize `data` as the bytes `bincode::serialize` gives
+    ///
+    /// # Errors
+    /// Returns `Err` if the data can't be serialized, or the serializer fails.
+    pub fn serialize<S: Serializer, TYPES: NodeType>(
+        data: &ResponseData<TYPES>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let bytes = bincode::serialize(data).map_err(ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+
+    /// Deserialize data serialized by [`serialize`]
+    ///
+    /// # Errors
+    /// Returns `Err` if the data is larger than `MAX_DATA_RESPONSE_SIZE` or malformed.
+    pub fn deserialize<'de, D: Deserializer<'de>, TYPES: NodeType>(
+        deserializer: D,
+    ) -> Result<ResponseData<TYPES>, D::Error> {
+        deserializer.deserialize_bytes(LimitedVisitor(PhantomData))
+    }
+
+    /// Decodes the bytes of [`ResponseData`], up to `MAX_DATA_RESPONSE_SIZE` of them
+    struct LimitedVisitor<TYPES>(PhantomData<TYPES>);
+
+    impl<TYPES: NodeType> Visitor<'_> for LimitedVisitor<TYPES> {
+        type Value = ResponseData<TYPES>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "at most {MAX_DATA_RESPONSE_SIZE} bytes of response data"
+            )
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+            if bytes.len() as u64 > MAX_DATA_RESPONSE_SIZE {
+                return Err(E::invalid_length(bytes.len(), &self));
+            }
+            // The options of `bincode::serialize`, with the limit also bounding the lengths
+            // claimed inside the data
+            bincode::DefaultOptions::new()
+                .with_fixint_encoding()
+                .allow_trailing_bytes()
+                .with_limit(MAX_DATA_RESPONSE_SIZE)
+                .deserialize(bytes)
+                .map_err(E::custom)
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -169,6 +331,46 @@ pub enum BroadcastDelay {
     View(u64),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A way in which a peer was caught misbehaving, reported to the network with
+/// [`ConnectedNetwork::report_peer`].
+pub enum Misbehaviour {
+    /// Sent a proposal whose signature does not verify
+    InvalidProposal,
+    /// Sent a VID share whose signature does not verify
+    InvalidVidShare,
+    /// Sent a response to a data request whose signature does not verify
+    InvalidResponse,
+}
+
+impl Misbehaviour {
+    /// How much the misbehaviour counts against the peer. Networks ban a peer once the sum of its
+    /// (decaying) penalties reaches [`MISBEHAVIOUR_BAN_THRESHOLD`].
+    #[must_use]
+    pub fn penalty(self) -> f64 {
+        match self {
+            Self::InvalidProposal | Self::InvalidVidShare | Self::InvalidResponse => 25.0,
+        }
+    }
+}
+
+/// The total penalty at which networks ban a peer
+pub const MISBEHAVIOUR_BAN_THRESHOLD: f64 = 100.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The peer a message was received from, as authenticated by the transport.
+///
+/// This is who actually delivered the message, which is not necessarily the node named as the
+/// sender inside it: anyone can relay (or forge) a message claiming to be from someone else.
+/// Misbehaviour must only ever be attributed to this identity.
+pub enum AuthenticatedPeer<K> {
+    /// A libp2p peer. Gossip is attributed to its (signed) author, direct messages to the peer on
+    /// the other end of the connection.
+    Libp2p(PeerId),
+    /// A node whose key the transport authenticated
+    Key(K),
+}
+
 #[async_trait]
 /// represents a networking implmentration
 /// exposes low level API for interacting with a network
@@ -242,7 +444,18 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     ///
     /// # Errors
     /// If there is a network-related failure.
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError>;
+    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+        Ok(self.recv_message_from().await?.0)
+    }
+
+    /// Receive a message along with the peer that delivered it, if the network can authenticate
+    /// it.
+    ///
+    /// # Errors
+    /// If there is a network-related failure.
+    async fn recv_message_from(
+        &self,
+    ) -> Result<(Vec<u8>, Option<AuthenticatedPeer<K>>), NetworkError>;
 
     /// queues lookup of a node
     ///
@@ -272,6 +485,22 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     fn is_primary_down(&self) -> bool {
         false
     }
+
+    /// Whether the network is ready to send messages, without waiting for it like
+    /// [`wait_for_ready`](Self::wait_for_ready). Networks which don't track this are never
+    /// reported as ready.
+    fn is_ready(&self) -> bool {
+        false
+    }
+
+    /// Report that `peer` misbehaved, so the network can penalise it, and eventually ban it.
+    ///
+    /// `peer` must come from [`recv_message_from`](Self::recv_message_from), never from the
+    /// contents of a message, or a forger could get honest nodes banned.
+    fn report_peer(&self, peer: &AuthenticatedPeer<K>, misbehaviour: Misbehaviour);
+
+    /// Whether `peer` is currently banned, in which case its messages should be ignored
+    fn is_peer_banned(&self, peer: &AuthenticatedPeer<K>) -> bool;
 }
 
 /// A channel generator for types that need asynchronous execution
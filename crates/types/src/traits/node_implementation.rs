@@ -270,4 +270,7 @@ pub trait Versions: Clone + Copy + Debug + Send + Sync + 'static {
 
     /// The version at which to switch over to epochs logic
     type Epochs: StaticVersionType;
+
+    /// The version from which message bodies are compressed on the wire
+    type Compression: StaticVersionType;
 }
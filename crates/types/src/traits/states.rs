@@ -23,6 +23,7 @@ use crate::{
         BlockPayload,
     },
     vid::VidCommon,
+    PeerConfig,
 };
 
 /// Instance-level state, which allows us to fetch missing validated state.
@@ -87,6 +88,18 @@ pub trait ValidatedState<TYPES: NodeType>:
 
     /// Gets called to notify the persistence backend that this state has been committed
     fn on_commit(&self);
+
+    /// The stake table this state sets, if it changes it.
+    ///
+    /// This is only consulted for the decided state of the last block of each epoch `e`, and the
+    /// stake table it returns takes effect in epoch `e + 2`. Defaults to never changing the stake
+    /// table.
+    fn stake_table_update(
+        &self,
+        _instance: &Self::Instance,
+    ) -> Option<Vec<PeerConfig<TYPES::SignatureKey>>> {
+        None
+    }
 }
 
 /// extra functions required on state to be usable by hotshot-testing
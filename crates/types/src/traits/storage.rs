@@ -4,9 +4,10 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-//! Abstract storage type for storing DA proposals and VID shares
+//! Abstract storage type for storing DA proposals and VID shares, and loading decided data
 //!
-//! This modules provides the [`Storage`] trait.
+//! This modules provides the [`Storage`] trait, and the [`RetentionPolicy`] which governs what
+//! storage keeps around once views are decided.
 //!
 
 use std::collections::BTreeMap;
@@ -14,17 +15,45 @@ use std::collections::BTreeMap;
 use anyhow::Result;
 use async_trait::async_trait;
 use jf_vid::VidScheme;
+use serde::{Deserialize, Serialize};
 
 use super::node_implementation::NodeType;
 use crate::{
     consensus::{CommitmentMap, View},
     data::{DaProposal, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare},
-    event::HotShotAction,
+    drb::DrbOutput,
+    event::{HotShotAction, LeafInfo},
     message::Proposal,
     simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
+    slashing_protection::{SigningHistory, SigningRoot},
     vid::VidSchemeType,
+    PeerConfig,
 };
 
+/// Governs what a [`Storage`] keeps around once views are decided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Number of views before the last decided view for which proposals, DA proposals and VID
+    /// shares are kept, so that we can still serve them to peers which are catching up.
+    pub views_to_keep: u64,
+    /// Drop VID shares as soon as their view is decided, since a decide implies that the DA
+    /// committee has certified the availability of the payload.
+    pub prune_vid_on_decide: bool,
+    /// Keep every decided leaf in a separate archive which is never garbage collected.
+    pub archive_decided_leaves: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            views_to_keep: 100,
+            prune_vid_on_decide: false,
+            archive_decided_leaves: false,
+        }
+    }
+}
+
 /// Abstraction for storing a variety of consensus payload datum.
 #[async_trait]
 pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
@@ -48,6 +77,27 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
     ) -> Result<()>;
     /// Record a HotShotAction taken.
     async fn record_action(&self, view: TYPES::View, action: HotShotAction) -> Result<()>;
+    /// Check that signing `action` over `signing_root` in `view` does not conflict with anything
+    /// we signed before, and durably record it before returning. This must be atomic with respect
+    /// to concurrent calls, and must return an error if the action may not be signed.
+    ///
+    /// The default implementation records nothing, and so offers no slashing protection.
+    async fn record_signed_action(
+        &self,
+        _view: TYPES::View,
+        _action: HotShotAction,
+        _signing_root: SigningRoot,
+    ) -> Result<()> {
+        Ok(())
+    }
+    /// Load the history of signed actions, for slashing protection.
+    async fn load_signing_history(&self) -> Result<SigningHistory<TYPES>> {
+        Ok(SigningHistory::default())
+    }
+    /// Merge an imported history of signed actions into the one in storage.
+    async fn import_signing_history(&self, _history: SigningHistory<TYPES>) -> Result<()> {
+        Ok(())
+    }
     /// Update the current high QC in storage.
     async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()>;
     /// Update the current high QC in storage.
@@ -66,11 +116,49 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
         leafs: CommitmentMap<Leaf2<TYPES>>,
         state: BTreeMap<TYPES::View, View<TYPES>>,
     ) -> Result<()>;
+    /// Update the most recently decided leaf, along with its state, in storage.
+    async fn update_decided_leaf(&self, _leaf_info: &LeafInfo<TYPES>) -> Result<()> {
+        Ok(())
+    }
+    /// Garbage collect data which is no longer needed now that `decided_view` has been decided.
+    async fn gc(&self, _decided_view: TYPES::View) -> Result<()> {
+        Ok(())
+    }
+    /// Load the decided leaf at block `height`, with its payload if one was stored, to serve
+    /// peers once the leaf is no longer in memory.
+    async fn load_leaf_at_height(&self, _height: u64) -> Result<Option<Leaf2<TYPES>>> {
+        Ok(None)
+    }
+    /// Load the payload of the leaf proposed in `view`, to serve peers once it is no longer in
+    /// memory.
+    async fn load_payload(&self, _view: TYPES::View) -> Result<Option<TYPES::BlockPayload>> {
+        Ok(None)
+    }
+    /// Load the quorum certificate formed in `view`, to serve peers once it is no longer in
+    /// memory.
+    async fn load_quorum_certificate(
+        &self,
+        _view: TYPES::View,
+    ) -> Result<Option<QuorumCertificate2<TYPES>>> {
+        Ok(None)
+    }
     /// Upgrade the current decided upgrade certificate in storage.
     async fn update_decided_upgrade_certificate(
         &self,
         decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
     ) -> Result<()>;
+    /// Store the output of the DRB calculation which seeds the leader schedule of `epoch`.
+    async fn add_drb_result(&self, _epoch: TYPES::Epoch, _drb_output: &DrbOutput) -> Result<()> {
+        Ok(())
+    }
+    /// Store the stake table which decided state set for `epoch` onwards.
+    async fn add_stake_table(
+        &self,
+        _epoch: TYPES::Epoch,
+        _stake_table: &[PeerConfig<TYPES::SignatureKey>],
+    ) -> Result<()> {
+        Ok(())
+    }
     /// Migrate leaves from `Leaf` to `Leaf2`, and proposals from `QuorumProposal` to `QuorumProposal2`
     async fn migrate_consensus(
         &self,
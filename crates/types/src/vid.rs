@@ -9,13 +9,18 @@
 //!   VID scheme.
 //! - type aliases [`VidCommitment`], [`VidCommon`], [`VidShare`]
 //!   for [`VidScheme`] assoc types.
+//! - [`VidCodeRate`], the erasure code rate the recovery threshold of the
+//!   scheme is derived from.
 //!
 //! Purpose: the specific choice of VID scheme is an implementation detail.
 //! This crate and all downstream crates should talk to the VID scheme only
 //! via the traits exposed here.
 
 #![allow(missing_docs)]
-use std::{fmt::Debug, ops::Range};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    ops::Range,
+};
 
 use ark_bn254::Bn254;
 use jf_pcs::{
@@ -29,7 +34,7 @@ use jf_vid::{
     },
     payload_prover::{PayloadProver, Statement},
     precomputable::Precomputable,
-    VidDisperse, VidResult, VidScheme,
+    VidDisperse, VidError, VidResult, VidScheme,
 };
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
@@ -41,11 +46,102 @@ use crate::{
     message::Proposal,
 };
 
+/// Erasure code rate of the VID scheme: the fraction `numerator / denominator` of the storage
+/// nodes whose shares suffice to recover a payload.
+///
+/// The lower the rate, the more redundant the dispersal and the fewer storage nodes need to be
+/// available to recover a payload. The payload commitment depends on the rate, so all nodes, and
+/// the builders computing payload commitments for them, must use the same rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VidCodeRate {
+    /// Numerator of the rate
+    pub numerator: u32,
+    /// Denominator of the rate
+    pub denominator: u32,
+}
+
+impl VidCodeRate {
+    /// The rate `numerator / denominator`
+    #[must_use]
+    pub const fn new(numerator: u32, denominator: u32) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// `num_storage_nodes` times the rate, rounded up: the number of shares which would recover a
+    /// payload dispersed among `num_storage_nodes` storage nodes if the VID scheme took any
+    /// recovery threshold. See [`Self::recovery_threshold`] for the one it takes.
+    ///
+    /// # Errors
+    /// If there are no storage nodes, there are more than `u32::MAX` of them, or the rate is not
+    /// in `(0, 1]`.
+    pub fn exact_recovery_threshold(self, num_storage_nodes: usize) -> VidResult<u32> {
+        if self.numerator == 0 || self.numerator > self.denominator {
+            return Err(VidError::Argument(format!(
+                "code rate {self} should be in (0, 1]"
+            )));
+        }
+        let num_storage_nodes = u32::try_from(num_storage_nodes).map_err(|err| {
+            VidError::Argument(format!(
+                "num_storage_nodes {num_storage_nodes} should fit into u32; error: {err}"
+            ))
+        })?;
+        if num_storage_nodes == 0 {
+            return Err(VidError::Argument(
+                "num_storage_nodes should be positive".to_string(),
+            ));
+        }
+
+        // At least 1 and at most `num_storage_nodes`, as the rate is in (0, 1]
+        let threshold = (u64::from(num_storage_nodes) * u64::from(self.numerator))
+            .div_ceil(u64::from(self.denominator));
+
+        Ok(u32::try_from(threshold).unwrap_or(num_storage_nodes))
+    }
+
+    /// The number of shares needed to recover a payload dispersed among `num_storage_nodes`
+    /// storage nodes.
+    ///
+    /// This is [`Self::exact_recovery_threshold`] rounded down to a power of two, so the effective
+    /// rate may be lower than this one. ADVZ takes no other recovery threshold: in `jf-vid` at the
+    /// jellyfish tag pinned in the workspace `Cargo.toml`, `Advz::new` fails with "should be a
+    /// power of two" otherwise (`vid/src/advz.rs`, tracked upstream in
+    /// <https://github.com/EspressoSystems/jellyfish/issues/668>). The test
+    /// `test_advz_takes_only_power_of_two_recovery_thresholds` below checks that this still holds.
+    ///
+    /// # Errors
+    /// If there are no storage nodes, there are more than `u32::MAX` of them, or the rate is not
+    /// in `(0, 1]`.
+    pub fn recovery_threshold(self, num_storage_nodes: usize) -> VidResult<u32> {
+        let threshold = self.exact_recovery_threshold(num_storage_nodes)?;
+        Ok(1 << threshold.ilog2())
+    }
+}
+
+impl Default for VidCodeRate {
+    /// A rate of 1, which recovers payloads from the largest power of two not exceeding the
+    /// number of storage nodes
+    fn default() -> Self {
+        Self::new(1, 1)
+    }
+}
+
+impl Display for VidCodeRate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
 /// VID scheme constructor.
 ///
 /// Returns an opaque type that impls jellyfish traits:
 /// [`VidScheme`], [`PayloadProver`], [`Precomputable`].
 ///
+/// The recovery threshold of the scheme is derived from `code_rate`, see
+/// [`VidCodeRate::recovery_threshold`].
+///
 /// # Rust forbids naming impl Trait in return types
 ///
 /// Due to Rust limitations the return type of [`vid_scheme`] is a newtype
@@ -57,50 +153,66 @@ use crate::{
 /// - [Naming impl trait in return types - Impl trait initiative](https://rust-lang.github.io/impl-trait-initiative/explainer/rpit_names.html)
 /// - [RFC: Type alias impl trait (TAIT)](https://github.com/rust-lang/rfcs/blob/master/text/2515-type_alias_impl_trait.md)
 ///
-/// # Panics
-/// When the construction fails for the underlying VID scheme.
-#[must_use]
+/// # Errors
+/// When `num_storage_nodes` and `code_rate` don't give a valid recovery threshold, or the
+/// construction fails for the underlying VID scheme.
+pub fn vid_scheme(num_storage_nodes: usize, code_rate: VidCodeRate) -> VidResult<VidSchemeType> {
+    memoized_vid_scheme(num_storage_nodes, code_rate).map_err(VidError::Argument)
+}
+
+/// Memoized [`vid_scheme()`].
+///
+/// The error is a string because the memoized result must be `Clone`, which [`VidError`] isn't.
 #[memoize::memoize(SharedCache, Capacity: 10)]
-pub fn vid_scheme(num_storage_nodes: usize) -> VidSchemeType {
-    // recovery_threshold is currently num_storage_nodes rounded down to a power of two
-    // TODO recovery_threshold should be a function of the desired erasure code rate
-    // https://github.com/EspressoSystems/HotShot/issues/2152
-    let recovery_threshold = 1 << num_storage_nodes.ilog2();
-
-    #[allow(clippy::panic)]
-    let num_storage_nodes = u32::try_from(num_storage_nodes).unwrap_or_else(|err| {
-        panic!(
-            "num_storage_nodes {num_storage_nodes} should fit into u32; \
-                error: {err}"
-        )
-    });
-
-    // TODO panic, return `Result`, or make `new` infallible upstream (eg. by panicking)?
-    #[allow(clippy::panic)]
-    VidSchemeType(
-        Advz::new(num_storage_nodes, recovery_threshold, &*KZG_SRS).unwrap_or_else(|err| {
-              panic!("advz construction failure: (num_storage nodes,recovery_threshold)=({num_storage_nodes},{recovery_threshold}); \
-                      error: {err}")
-        })
-    )
+fn memoized_vid_scheme(
+    num_storage_nodes: usize,
+    code_rate: VidCodeRate,
+) -> Result<VidSchemeType, String> {
+    new_vid_scheme(num_storage_nodes, code_rate, &KZG_SRS)
 }
 
 /// Similar to [`vid_scheme()`], but with `KZG_SRS_TEST` for testing purpose only.
+///
+/// # Errors
+/// Same as [`vid_scheme()`].
+#[cfg(feature = "test-srs")]
+pub fn vid_scheme_for_test(
+    num_storage_nodes: usize,
+    code_rate: VidCodeRate,
+) -> VidResult<VidSchemeType> {
+    memoized_vid_scheme_for_test(num_storage_nodes, code_rate).map_err(VidError::Argument)
+}
+
+/// Memoized [`vid_scheme_for_test()`].
 #[cfg(feature = "test-srs")]
 #[memoize::memoize(SharedCache, Capacity: 10)]
-pub fn vid_scheme_for_test(num_storage_nodes: usize) -> VidSchemeType {
-    let recovery_threshold = 1 << num_storage_nodes.ilog2();
-    #[allow(clippy::panic)]
-    let num_storage_nodes = u32::try_from(num_storage_nodes).unwrap_or_else(|err| {
-        panic!("num_storage_nodes {num_storage_nodes} should fit into u32; error: {err}")
-    });
-    #[allow(clippy::panic)]
-    VidSchemeType(
-        Advz::new(num_storage_nodes, recovery_threshold, &*KZG_SRS_TEST).unwrap_or_else(|err| {
-           panic!("advz construction failure: (num_storage nodes,recovery_threshold)=({num_storage_nodes},{recovery_threshold});\
-                   error: {err}")
+fn memoized_vid_scheme_for_test(
+    num_storage_nodes: usize,
+    code_rate: VidCodeRate,
+) -> Result<VidSchemeType, String> {
+    new_vid_scheme(num_storage_nodes, code_rate, &KZG_SRS_TEST)
+}
+
+/// Construct the VID scheme for `num_storage_nodes` and `code_rate` from `srs`
+fn new_vid_scheme(
+    num_storage_nodes: usize,
+    code_rate: VidCodeRate,
+    srs: &'static UnivariateUniversalParams<E>,
+) -> Result<VidSchemeType, String> {
+    let recovery_threshold = code_rate
+        .recovery_threshold(num_storage_nodes)
+        .map_err(|err| err.to_string())?;
+    // `recovery_threshold` checked that `num_storage_nodes` fits into u32
+    let num_storage_nodes = u32::try_from(num_storage_nodes).map_err(|err| err.to_string())?;
+
+    Advz::new(num_storage_nodes, recovery_threshold, srs)
+        .map(VidSchemeType)
+        .map_err(|err| {
+            format!(
+                "advz construction failure: (num_storage_nodes,recovery_threshold)=({num_storage_nodes},{recovery_threshold}); \
+                 error: {err}"
+            )
         })
-    )
 }
 
 /// VID commitment type
@@ -342,3 +454,16 @@ fn stmt_conversion(stmt: Statement<'_, VidSchemeType>) -> Statement<'_, Advz> {
         common: stmt.common,
     }
 }
+
+#[cfg(all(test, feature = "test-srs"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advz_takes_only_power_of_two_recovery_thresholds() {
+        // If this fails, the VID scheme takes any recovery threshold, and
+        // `VidCodeRate::recovery_threshold` no longer needs to round down to a power of two
+        assert!(Advz::new(6, 3, &KZG_SRS_TEST).is_err());
+        assert!(Advz::new(6, 4, &KZG_SRS_TEST).is_ok());
+    }
+}
@@ -78,9 +78,12 @@ pub trait Certificate<TYPES: NodeType>: HasViewNumber<TYPES> {
         epoch: TYPES::Epoch,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> impl std::future::Future<Output = bool>;
-    /// Returns the amount of stake needed to create this certificate
+    /// Returns the amount of stake needed to create this certificate in `epoch`
     // TODO: Make this a static ratio of the total stake of `Membership`
-    fn threshold<MEMBERSHIP: Membership<TYPES>>(membership: &MEMBERSHIP) -> u64;
+    fn threshold<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        epoch: TYPES::Epoch,
+    ) -> u64;
     /// Get the commitment which was voted on
     fn data(&self) -> &Self::Voteable;
     /// Get the vote commitment which the votes commit to
@@ -195,16 +198,15 @@ impl<
         signers.set(vote_node_id, true);
         sig_list.push(original_signature);
 
-        // TODO: Get the stake from the stake table entry.
         *total_stake_casted += stake_table_entry.stake();
         total_vote_map.insert(key, (vote.signature(), vote_commitment));
 
-        if *total_stake_casted >= CERT::threshold(membership).into() {
+        if *total_stake_casted >= CERT::threshold(membership, epoch) {
             // Assemble QC
             let real_qc_pp: <<TYPES as NodeType>::SignatureKey as SignatureKey>::QcParams =
                 <TYPES::SignatureKey as SignatureKey>::public_parameter(
                     stake_table,
-                    U256::from(CERT::threshold(membership)),
+                    CERT::threshold(membership, epoch),
                 );
 
             let real_qc_sig = <TYPES::SignatureKey as SignatureKey>::assemble(